@@ -1,2 +1,3 @@
 pub mod llvm;
+pub mod trap;
 pub mod xtensa_esp32;
@@ -1 +1,2 @@
+#[allow(dead_code, clippy::upper_case_acronyms)]
 struct LLVM {}
@@ -0,0 +1,4 @@
+//! Trap codes passed to the runtime trap handler, shared with the IR so that
+//! both agree on their values.
+
+pub use ir::TrapCode;
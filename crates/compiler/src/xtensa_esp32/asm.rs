@@ -19,20 +19,21 @@ pub enum Operand {
     LiteralI32(i32),
 }
 
-impl ToString for Operand {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SP => "sp".to_string(),
-            RegA(i) => format!("a{}", i),
-            Imm(i) => format!("{}", i),
-            Symbol(s) => s.clone(),
-            LiteralI32(v) => format!("{}", v),
+            SP => write!(f, "sp"),
+            RegA(i) => write!(f, "a{}", i),
+            Imm(i) => write!(f, "{}", i),
+            Symbol(s) => write!(f, "{}", s),
+            LiteralI32(v) => write!(f, "{}", v),
         }
     }
 }
 
 pub use Operand::*;
 
+#[derive(Default)]
 pub struct AsmWriter {
     items: Vec<Item>,
 }
@@ -83,12 +84,7 @@ impl AsmWriter {
 
         let mut p = self.items.iter().peekable();
 
-        loop {
-            let item = match p.next() {
-                Some(item) => item,
-                None => break,
-            };
-
+        while let Some(item) = p.next() {
             match item {
                 Item::Comment(comment) if enable_comment => {
                     s.push_str(&format!("\t# {}", comment));
@@ -0,0 +1,544 @@
+use wasm_parser::{
+    decoder::{
+        const_expr::ConstValue,
+        instructions::{Instruction, MemArg},
+        types::{DataMode, ImportDesc, Limits},
+    },
+    parser::module::Module,
+};
+
+use super::{asm::*, pop_stack, push_stack, CompileError, Result, XtensaEsp32};
+use crate::trap::TrapCode;
+
+/// Size of a wasm page, in bytes.
+pub const PAGE_SIZE: u32 = 65536;
+
+/// Default for `XtensaEsp32::with_max_memory_pages`: 2 pages (128 KiB) leave
+/// most of the 520 KiB of ESP32 SRAM to the firmware.
+pub const DEFAULT_MAX_MEMORY_PAGES: u32 = 2;
+
+/// Symbol of the linear memory.
+const MEMORY_SYMBOL: &str = "wasmicon_memory";
+/// Symbol of the current size of the linear memory, in pages.
+const MEMORY_PAGES_SYMBOL: &str = "wasmicon_memory_pages";
+/// Symbol of a word telling whether memory accesses are bounds-checked, for
+/// the firmware to refuse running unchecked code.
+const BOUNDS_CHECKS_SYMBOL: &str = "wasmicon_memory_bounds_checked";
+
+/// Linear memory reserved for the module.
+#[derive(Debug, Clone)]
+pub(super) struct LinearMemory {
+    /// Number of pages reserved, the most `memory.grow` can reach.
+    pub(super) max_pages: u32,
+}
+
+impl XtensaEsp32 {
+    /// Sets how many pages the linear memory may use, reserved up front as
+    /// there is no allocator to grow it into.
+    pub fn with_max_memory_pages(mut self, pages: u32) -> Self {
+        self.max_memory_pages = pages;
+        self
+    }
+
+    /// Sets whether memory accesses are checked against the memory size,
+    /// trapping when out of bounds. Unchecked accesses are faster, but may
+    /// overwrite the firmware.
+    pub fn with_bounds_checks(mut self, enabled: bool) -> Self {
+        self.bounds_checks = enabled;
+        self
+    }
+
+    /// Reserves the linear memory of `module`, if it has one, with its active
+    /// data segments copied in, and emits whether accesses are bounds-checked.
+    /// `globals` are the values of the globals, for segment offsets.
+    pub(super) fn reserve_memory(
+        &mut self,
+        module: &Module,
+        globals: &[Option<ConstValue>],
+    ) -> Result<()> {
+        let imported = module
+            .imports
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::Memory(limits) => Some(limits),
+                _ => None,
+            });
+        let memories: Vec<&Limits> = imported
+            .chain(module.memories.iter().map(|memory| &memory.limits))
+            .collect();
+        match memories[..] {
+            [] => {}
+            [limits] => self.reserve_linear_memory(module, globals, limits)?,
+            _ => panic!("Unsupported multiple memories"),
+        }
+
+        // Emitted even without memory, so the firmware can always check it.
+        self.data
+            .op(".section", vec![symbol(".rodata")])
+            .op(".align", vec![Imm(4)])
+            .op(".global", vec![symbol(BOUNDS_CHECKS_SYMBOL)])
+            .label(BOUNDS_CHECKS_SYMBOL)
+            .op(".word", vec![Imm(self.bounds_checks as i32)]);
+
+        Ok(())
+    }
+
+    /// Reserves the memory with `limits`. Only the minimum size is reserved if
+    /// the module never grows its memory.
+    fn reserve_linear_memory(
+        &mut self,
+        module: &Module,
+        globals: &[Option<ConstValue>],
+        limits: &Limits,
+    ) -> Result<()> {
+        if limits.index64 {
+            panic!("Unsupported 64-bit memory");
+        }
+
+        if limits.min > self.max_memory_pages as u64 {
+            return Err(CompileError::MemoryTooLarge {
+                pages: limits.min,
+                max_pages: self.max_memory_pages,
+            });
+        }
+        let grows = module.functions.iter().any(|func| {
+            func.raw_body
+                .iter()
+                .flatten()
+                .any(|inst| matches!(inst, Instruction::MemoryGrow { .. }))
+        });
+        let max_pages = match grows {
+            true => limits.max.map_or(self.max_memory_pages, |max| {
+                max.min(self.max_memory_pages as u64) as u32
+            }),
+            false => limits.min as u32,
+        };
+
+        let image = data_image(module, globals, max_pages);
+        let section = match image.is_empty() {
+            true => ".bss",
+            false => ".data",
+        };
+        self.data
+            .op(".section", vec![symbol(section)])
+            .op(".align", vec![Imm(4)])
+            .op(".global", vec![symbol(MEMORY_SYMBOL)])
+            .label(MEMORY_SYMBOL)
+            .inline_comment(format!("{} pages", max_pages));
+        write_bytes(&mut self.data, &image);
+        self.data
+            .op(
+                ".space",
+                vec![Imm((max_pages * PAGE_SIZE) as i32 - image.len() as i32)],
+            )
+            .op(".section", vec![symbol(".data")])
+            .op(".align", vec![Imm(4)])
+            .op(".global", vec![symbol(MEMORY_PAGES_SYMBOL)])
+            .label(MEMORY_PAGES_SYMBOL)
+            .op(".word", vec![Imm(limits.min as i32)]);
+
+        self.memory = Some(LinearMemory { max_pages });
+
+        Ok(())
+    }
+
+    fn linear_memory(&self, memory_index: u32) -> LinearMemory {
+        if memory_index != 0 {
+            panic!("Unsupported memory index: {}", memory_index);
+        }
+        self.memory
+            .clone()
+            .expect("Memory instruction without memory")
+    }
+
+    /// Loads the address of the memory into `reg`.
+    fn load_memory_base(&mut self, insts_writer: &mut AsmWriter, reg: usize) {
+        let label = self.literal_symbol(MEMORY_SYMBOL);
+        insts_writer
+            .op("l32r", vec![RegA(reg), Symbol(label)])
+            .inline_comment(format!("a{} = linear memory", reg));
+    }
+
+    /// Loads the address of the current memory size into `reg`.
+    fn load_memory_pages(&mut self, insts_writer: &mut AsmWriter, reg: usize) {
+        let label = self.literal_symbol(MEMORY_PAGES_SYMBOL);
+        insts_writer.op("l32r", vec![RegA(reg), Symbol(label)]);
+    }
+
+    /// Traps unless the `len` bytes at the address in `address` are within
+    /// the memory, if bounds checks are enabled. Uses a3 and a6.
+    fn check_bounds(&mut self, insts_writer: &mut AsmWriter, address: usize, len: usize) {
+        if !self.bounds_checks {
+            return;
+        }
+
+        let trap_label = self.gen_symbol();
+        let ok_label = self.gen_symbol();
+        self.load_memory_pages(insts_writer, 3);
+        // Compares the address with the size minus the length, as their sum
+        // may overflow.
+        insts_writer
+            .op("l32i", vec![RegA(3), RegA(3), Imm(0)])
+            .op("slli", vec![RegA(3), RegA(3), Imm(16)])
+            .inline_comment("a3 = memory size in bytes")
+            .op("bltu", vec![RegA(3), RegA(len), Symbol(trap_label.clone())])
+            .op("sub", vec![RegA(6), RegA(3), RegA(len)])
+            .op(
+                "bgeu",
+                vec![RegA(6), RegA(address), Symbol(ok_label.clone())],
+            )
+            .inline_comment(format!(
+                "if a{} + a{} <= size then jump to {}",
+                address, len, ok_label
+            ))
+            .label(trap_label);
+        self.trap(insts_writer, TrapCode::MemoryOutOfBounds);
+        insts_writer.label(ok_label);
+    }
+
+    /// Pops an address into a2 and makes it absolute, checking that the
+    /// `width` bytes accessed are in bounds. Returns the immediate offset the
+    /// access instruction adds, at most `max_imm` and a multiple of `scale`;
+    /// the rest of `mem_arg.offset` is added to a2.
+    fn address(
+        &mut self,
+        insts_writer: &mut AsmWriter,
+        mem_arg: &MemArg,
+        width: u32,
+        max_imm: u64,
+        scale: u64,
+    ) -> i32 {
+        self.linear_memory(mem_arg.memory_index);
+        pop_stack(insts_writer, 2);
+        if self.bounds_checks {
+            match u32::try_from(mem_arg.offset + width as u64) {
+                Ok(len) => {
+                    let label = self.literal_i32(len as i32);
+                    insts_writer
+                        .op("l32r", vec![RegA(4), Symbol(label)])
+                        .inline_comment("a4 = offset + width");
+                    self.check_bounds(insts_writer, 2, 4);
+                }
+                Err(_) => self.trap(insts_writer, TrapCode::MemoryOutOfBounds),
+            }
+        }
+        self.load_memory_base(insts_writer, 3);
+        insts_writer.op("add", vec![RegA(2), RegA(2), RegA(3)]);
+
+        if mem_arg.offset <= max_imm && mem_arg.offset.is_multiple_of(scale) {
+            return mem_arg.offset as i32;
+        }
+        // Offsets of 32-bit memories fit u32, the add wraps like the address.
+        let offset = mem_arg.offset as u32 as i32;
+        let label = self.literal_i32(offset);
+        insts_writer
+            .op("l32r", vec![RegA(3), Symbol(label)])
+            .op("add", vec![RegA(2), RegA(2), RegA(3)])
+            .inline_comment(format!("a2 += offset {}", offset));
+        0
+    }
+
+    /// Loads `width` bytes from memory and pushes them, sign-extended if
+    /// `signed`. As unaligned loads raise an exception, accesses whose
+    /// alignment hint is below `width` are done byte by byte, and the others
+    /// fall back to bytes at runtime if the hint turns out wrong.
+    pub(super) fn load(
+        &mut self,
+        insts_writer: &mut AsmWriter,
+        mem_arg: &MemArg,
+        width: u32,
+        signed: bool,
+    ) {
+        if 1 << mem_arg.align < width {
+            let offset = self.address(insts_writer, mem_arg, width, 256 - width as u64, 1);
+            load_bytes(insts_writer, offset, width, signed);
+            push_stack(insts_writer, 5);
+            return;
+        }
+
+        let opcode = match (width, signed) {
+            (1, _) => "l8ui",
+            (2, false) => "l16ui",
+            (2, true) => "l16si",
+            _ => "l32i",
+        };
+        // Both paths take the offset as an immediate.
+        let offset = self.address(
+            insts_writer,
+            mem_arg,
+            width,
+            256 - width as u64,
+            width as u64,
+        );
+        if width == 1 {
+            insts_writer.op(opcode, vec![RegA(2), RegA(2), Imm(offset)]);
+            if signed {
+                insts_writer.op("sext", vec![RegA(2), RegA(2), Imm(7)]);
+            }
+            push_stack(insts_writer, 2);
+            return;
+        }
+
+        let unaligned_label = self.gen_symbol();
+        let end_label = self.gen_symbol();
+        branch_if_unaligned(insts_writer, width, &unaligned_label);
+        insts_writer
+            .op(opcode, vec![RegA(5), RegA(2), Imm(offset)])
+            .op("j", vec![Symbol(end_label.clone())])
+            .label(unaligned_label);
+        load_bytes(insts_writer, offset, width, signed);
+        insts_writer.label(end_label);
+        push_stack(insts_writer, 5);
+    }
+
+    /// Pops a value and stores its low `width` bytes to memory, byte by byte
+    /// if the alignment hint is below `width` or the address turns out
+    /// unaligned.
+    pub(super) fn store(&mut self, insts_writer: &mut AsmWriter, mem_arg: &MemArg, width: u32) {
+        pop_stack(insts_writer, 5);
+
+        if 1 << mem_arg.align < width {
+            let offset = self.address(insts_writer, mem_arg, width, 256 - width as u64, 1);
+            store_bytes(insts_writer, offset, width);
+            return;
+        }
+
+        let opcode = match width {
+            1 => "s8i",
+            2 => "s16i",
+            _ => "s32i",
+        };
+        let offset = self.address(
+            insts_writer,
+            mem_arg,
+            width,
+            256 - width as u64,
+            width as u64,
+        );
+        if width == 1 {
+            insts_writer.op(opcode, vec![RegA(5), RegA(2), Imm(offset)]);
+            return;
+        }
+
+        let unaligned_label = self.gen_symbol();
+        let end_label = self.gen_symbol();
+        branch_if_unaligned(insts_writer, width, &unaligned_label);
+        insts_writer
+            .op(opcode, vec![RegA(5), RegA(2), Imm(offset)])
+            .op("j", vec![Symbol(end_label.clone())])
+            .label(unaligned_label);
+        store_bytes(insts_writer, offset, width);
+        insts_writer.label(end_label);
+    }
+
+    pub(super) fn memory_size(&mut self, insts_writer: &mut AsmWriter, memory_index: u32) {
+        self.linear_memory(memory_index);
+        self.load_memory_pages(insts_writer, 3);
+        insts_writer.op("l32i", vec![RegA(2), RegA(3), Imm(0)]);
+        push_stack(insts_writer, 2);
+    }
+
+    /// Grows the memory by the popped number of pages, within the reserved
+    /// ones. Pushes the previous size, or -1 if there isn't enough room.
+    pub(super) fn memory_grow(&mut self, insts_writer: &mut AsmWriter, memory_index: u32) {
+        let memory = self.linear_memory(memory_index);
+        let fail_label = self.gen_symbol();
+        let end_label = self.gen_symbol();
+
+        pop_stack(insts_writer, 2);
+        self.load_memory_pages(insts_writer, 3);
+        insts_writer
+            .op("l32i", vec![RegA(4), RegA(3), Imm(0)])
+            .inline_comment("a4 = current pages")
+            .op("add", vec![RegA(5), RegA(4), RegA(2)])
+            .inline_comment("a5 = new pages")
+            .op("bltu", vec![RegA(5), RegA(4), Symbol(fail_label.clone())])
+            .inline_comment("overflow");
+        self.load_i32(insts_writer, 6, memory.max_pages as i32);
+        insts_writer
+            .op("bltu", vec![RegA(6), RegA(5), Symbol(fail_label.clone())])
+            .inline_comment(format!(
+                "if a5 > {} then jump to {}",
+                memory.max_pages, fail_label
+            ))
+            .op("s32i", vec![RegA(5), RegA(3), Imm(0)])
+            .op("mov", vec![RegA(2), RegA(4)])
+            .op("j", vec![Symbol(end_label.clone())])
+            .label(fail_label)
+            .op("movi", vec![RegA(2), Imm(-1)])
+            .label(end_label);
+        push_stack(insts_writer, 2);
+    }
+
+    /// Pops a count, a byte value and a destination, and fills the count bytes
+    /// at the destination with the value.
+    pub(super) fn memory_fill(&mut self, insts_writer: &mut AsmWriter, memory_index: u32) {
+        self.linear_memory(memory_index);
+        let loop_label = self.gen_symbol();
+        let end_label = self.gen_symbol();
+
+        pop_stack(insts_writer, 4); // count
+        pop_stack(insts_writer, 5); // value
+        pop_stack(insts_writer, 2); // destination
+        self.check_bounds(insts_writer, 2, 4);
+        self.load_memory_base(insts_writer, 3);
+        insts_writer
+            .op("add", vec![RegA(2), RegA(2), RegA(3)])
+            .label(loop_label.clone())
+            .op("beqz", vec![RegA(4), Symbol(end_label.clone())])
+            .op("s8i", vec![RegA(5), RegA(2), Imm(0)])
+            .op("addi", vec![RegA(2), RegA(2), Imm(1)])
+            .op("addi", vec![RegA(4), RegA(4), Imm(-1)])
+            .op("j", vec![Symbol(loop_label)])
+            .label(end_label);
+    }
+
+    /// Pops a count, a source and a destination, and copies the count bytes at
+    /// the source to the destination. The ranges may overlap.
+    pub(super) fn memory_copy(
+        &mut self,
+        insts_writer: &mut AsmWriter,
+        dst_index: u32,
+        src_index: u32,
+    ) {
+        self.linear_memory(dst_index);
+        self.linear_memory(src_index);
+        let backward_label = self.gen_symbol();
+        let forward_label = self.gen_symbol();
+        let end_label = self.gen_symbol();
+
+        pop_stack(insts_writer, 4); // count
+        pop_stack(insts_writer, 5); // source
+        pop_stack(insts_writer, 2); // destination
+        self.check_bounds(insts_writer, 5, 4);
+        self.check_bounds(insts_writer, 2, 4);
+        self.load_memory_base(insts_writer, 3);
+        insts_writer
+            .op("add", vec![RegA(2), RegA(2), RegA(3)])
+            .op("add", vec![RegA(5), RegA(5), RegA(3)])
+            .op(
+                "bltu",
+                vec![RegA(5), RegA(2), Symbol(backward_label.clone())],
+            )
+            .inline_comment("copy from the end if the source is below")
+            .label(forward_label.clone())
+            .op("beqz", vec![RegA(4), Symbol(end_label.clone())])
+            .op("l8ui", vec![RegA(6), RegA(5), Imm(0)])
+            .op("s8i", vec![RegA(6), RegA(2), Imm(0)])
+            .op("addi", vec![RegA(5), RegA(5), Imm(1)])
+            .op("addi", vec![RegA(2), RegA(2), Imm(1)])
+            .op("addi", vec![RegA(4), RegA(4), Imm(-1)])
+            .op("j", vec![Symbol(forward_label)])
+            .label(backward_label.clone())
+            .op("beqz", vec![RegA(4), Symbol(end_label.clone())])
+            .op("addi", vec![RegA(4), RegA(4), Imm(-1)])
+            .op("add", vec![RegA(6), RegA(5), RegA(4)])
+            .op("l8ui", vec![RegA(6), RegA(6), Imm(0)])
+            .op("add", vec![RegA(3), RegA(2), RegA(4)])
+            .op("s8i", vec![RegA(6), RegA(3), Imm(0)])
+            .op("j", vec![Symbol(backward_label)])
+            .label(end_label);
+    }
+}
+
+/// Jumps to `label` if the address in a2 isn't a multiple of `width`. The
+/// immediate offset is, so it doesn't change the alignment. Uses a3.
+fn branch_if_unaligned(insts_writer: &mut AsmWriter, width: u32, label: &str) {
+    insts_writer
+        .op(
+            "extui",
+            vec![RegA(3), RegA(2), Imm(0), Imm(width.trailing_zeros() as i32)],
+        )
+        .op("bnez", vec![RegA(3), Symbol(label.to_string())])
+        .inline_comment("alignment hint is wrong");
+}
+
+/// Loads the `width` bytes at a2 + `offset` into a5, byte by byte, least
+/// significant first. Uses a4.
+fn load_bytes(insts_writer: &mut AsmWriter, offset: i32, width: u32, signed: bool) {
+    for i in 0..width {
+        let byte_offset = Imm(offset + i as i32);
+        match i {
+            0 => insts_writer.op("l8ui", vec![RegA(5), RegA(2), byte_offset]),
+            _ => insts_writer
+                .op("l8ui", vec![RegA(4), RegA(2), byte_offset])
+                .op("slli", vec![RegA(4), RegA(4), Imm(8 * i as i32)])
+                .op("or", vec![RegA(5), RegA(5), RegA(4)]),
+        };
+    }
+    if signed {
+        insts_writer.op("sext", vec![RegA(5), RegA(5), Imm(8 * width as i32 - 1)]);
+    }
+}
+
+/// Stores the low `width` bytes of a5 at a2 + `offset`, byte by byte.
+fn store_bytes(insts_writer: &mut AsmWriter, offset: i32, width: u32) {
+    for i in 0..width {
+        if i > 0 {
+            insts_writer.op("srli", vec![RegA(5), RegA(5), Imm(8)]);
+        }
+        insts_writer.op("s8i", vec![RegA(5), RegA(2), Imm(offset + i as i32)]);
+    }
+}
+
+/// Initial contents of the memory up to the end of the last active data
+/// segment.
+fn data_image(module: &Module, globals: &[Option<ConstValue>], max_pages: u32) -> Vec<u8> {
+    let mut image = vec![];
+
+    for (index, data) in module.datas.iter().enumerate() {
+        let DataMode::Active {
+            memory_index,
+            offset,
+        } = &data.mode
+        else {
+            continue;
+        };
+        assert_eq!(*memory_index, 0, "Unsupported memory index");
+
+        let offset = match offset
+            .eval(|global_index| globals.get(global_index as usize).copied().flatten())
+        {
+            Ok(ConstValue::I32(offset)) => offset as u32 as usize,
+            other => panic!("Unsupported offset of data segment {}: {:?}", index, other),
+        };
+        let end = offset + data.bytes.len();
+        if end > (max_pages * PAGE_SIZE) as usize {
+            panic!(
+                "Data segment {} is out of the {} reserved pages",
+                index, max_pages
+            );
+        }
+
+        if image.len() < end {
+            image.resize(end, 0);
+        }
+        image[offset..end].copy_from_slice(&data.bytes);
+    }
+
+    image
+}
+
+/// Writes `bytes` as `.byte` lines, with runs of zeros as `.space`.
+fn write_bytes(writer: &mut AsmWriter, bytes: &[u8]) {
+    /// Shortest run of zeros written as `.space`.
+    const MIN_SPACE: usize = 16;
+    let zeros_at = |bytes: &[u8]| bytes.iter().take_while(|byte| **byte == 0).count();
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let zeros = zeros_at(rest);
+        if zeros >= MIN_SPACE || zeros == rest.len() {
+            writer.op(".space", vec![Imm(zeros as i32)]);
+            rest = &rest[zeros..];
+            continue;
+        }
+
+        let len = (1..rest.len().min(16))
+            .find(|start| zeros_at(&rest[*start..]) >= MIN_SPACE)
+            .unwrap_or(rest.len().min(16));
+        let line = rest[..len].iter().map(|byte| Imm(*byte as i32)).collect();
+        writer.op(".byte", line);
+        rest = &rest[len..];
+    }
+}
@@ -1,16 +1,30 @@
 mod asm;
+mod memory;
+mod result;
+mod trap;
 
 use std::collections::HashMap;
 
+use crate::trap::TrapCode;
 use asm::*;
+pub use memory::*;
+pub use result::*;
+pub use trap::*;
 use wasm_parser::{
     decoder::{
-        instructions::Instruction,
-        types::{GlobalType, Import, ImportDesc, ValueType},
+        const_expr::ConstValue,
+        instructions::{Block, BlockType, Instruction},
+        types::{FuncType, GlobalType, Import, ImportDesc, ValueType},
     },
-    parser::module::{Function, Module},
+    parser::module::{Function, Module, Node},
+    validator::{accessed_memory, operand_types},
 };
 
+/// Bytes the windowed ABI keeps at the top of a frame for spilled registers.
+const SAVE_AREA_SIZE: i32 = 32;
+/// Largest frame `entry` can allocate.
+const MAX_FRAME_SIZE: i32 = 32760;
+
 fn push_stack(insts_writer: &mut AsmWriter, reg: usize) {
     insts_writer
         .comment("### push stack")
@@ -29,6 +43,148 @@ fn pop_stack(insts_writer: &mut AsmWriter, reg: usize) {
         .inline_comment(format!("a{} = stack[wsp];", reg));
 }
 
+/// Pops the operands of a binary instruction: the right-hand side into a2, then
+/// the left-hand side into a3.
+fn pop_operands(insts_writer: &mut AsmWriter) {
+    pop_stack(insts_writer, 2);
+    pop_stack(insts_writer, 3);
+}
+
+/// Whether a branch in `nodes` targets the label `level` blocks out of them.
+fn is_branch_target(nodes: &[Node], level: u32) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Instruction(Instruction::Br { level: target })
+        | Node::Instruction(Instruction::BrIf { level: target }) => *target == level,
+        Node::Instruction(Instruction::BrTable {
+            label_indexes,
+            default_index,
+        }) => *default_index == level || label_indexes.contains(&level),
+        Node::Instruction(_) => false,
+        Node::Block { body, .. } | Node::Loop { body, .. } | Node::TryTable { body, .. } => {
+            is_branch_target(body, level + 1)
+        }
+        Node::If {
+            then_body,
+            else_body,
+            ..
+        } => {
+            is_branch_target(then_body, level + 1)
+                || else_body
+                    .as_ref()
+                    .is_some_and(|else_body| is_branch_target(else_body, level + 1))
+        }
+    })
+}
+
+/// Depth of the deepest block of `nodes` that is branched to, 0 if there is
+/// none. Every depth up to it gets a frame slot for the saved wsp.
+fn max_target_depth(nodes: &[Node]) -> u32 {
+    nodes
+        .iter()
+        .map(|node| {
+            let (depth, bodies): (u32, Vec<&Vec<Node>>) = match node {
+                Node::Instruction(_) => return 0,
+                Node::Block { depth, body, .. }
+                | Node::Loop { depth, body, .. }
+                | Node::TryTable { depth, body, .. } => (*depth, vec![body]),
+                Node::If {
+                    depth,
+                    then_body,
+                    else_body,
+                    ..
+                } => (
+                    *depth,
+                    [Some(then_body), else_body.as_ref()]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                ),
+            };
+            let own = match bodies.iter().any(|body| is_branch_target(body, 0)) {
+                true => depth,
+                false => 0,
+            };
+            bodies
+                .into_iter()
+                .map(|body| max_target_depth(body))
+                .fold(own, u32::max)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Number of values `instr` pops off and pushes onto the wasm stack, as
+/// lowered: calls always push a10.
+fn stack_effect(instr: &Instruction, func_types: &[FuncType]) -> (usize, usize) {
+    if let Some((params, results)) = operand_types(instr) {
+        let address = accessed_memory(instr).map_or(0, |_| 1);
+        return (address + params.len(), results.len());
+    }
+
+    match instr {
+        Instruction::LocalGet { .. }
+        | Instruction::GlobalGet { .. }
+        | Instruction::MemorySize { .. } => (0, 1),
+        Instruction::LocalSet { .. }
+        | Instruction::GlobalSet { .. }
+        | Instruction::Drop
+        | Instruction::BrIf { .. }
+        | Instruction::BrTable { .. } => (1, 0),
+        Instruction::LocalTee { .. } | Instruction::MemoryGrow { .. } => (1, 1),
+        Instruction::Select { .. } | Instruction::SelectResult { .. } => (3, 1),
+        Instruction::MemoryInit { .. }
+        | Instruction::MemoryCopy { .. }
+        | Instruction::MemoryFill { .. } => (3, 0),
+        Instruction::Call { func_index } => {
+            let func_type = &func_types[*func_index as usize];
+            (func_type.params.len(), func_type.results.len().max(1))
+        }
+        _ => (0, 0),
+    }
+}
+
+/// Upper bound of the number of values on the wasm stack while running
+/// `nodes`, entered with `height` values on it.
+fn max_stack_height(nodes: &[Node], mut height: usize, func_types: &[FuncType]) -> usize {
+    let mut max = height;
+
+    for node in nodes {
+        match node {
+            Node::Instruction(instr) => {
+                let (pops, pushes) = stack_effect(instr, func_types);
+                height = height.saturating_sub(pops) + pushes;
+            }
+            Node::Block {
+                block_type, body, ..
+            }
+            | Node::Loop {
+                block_type, body, ..
+            }
+            | Node::TryTable {
+                block_type, body, ..
+            } => {
+                max = max.max(max_stack_height(body, height, func_types));
+                height = height.saturating_sub(block_type.params.len()) + block_type.results.len();
+            }
+            Node::If {
+                block_type,
+                then_body,
+                else_body,
+                ..
+            } => {
+                height = height.saturating_sub(1); // condition
+                for body in [Some(then_body), else_body.as_ref()].into_iter().flatten() {
+                    max = max.max(max_stack_height(body, height, func_types));
+                }
+                height = height.saturating_sub(block_type.params.len()) + block_type.results.len();
+            }
+        }
+        max = max.max(height);
+    }
+
+    max
+}
+
 #[derive(Debug, Clone)]
 enum FuncDecl {
     Imported(Import),
@@ -38,15 +194,51 @@ enum FuncDecl {
 #[derive(Debug, Clone)]
 struct Global {
     label: String,
+    #[allow(dead_code)]
     global_type: GlobalType,
 }
 
+/// Branch target of an enclosing block.
+#[derive(Debug, Clone)]
+struct Label {
+    /// Assembly label branches jump to.
+    name: String,
+    /// Number of values a branch carries: the parameters of a loop, the
+    /// results of other blocks.
+    arity: usize,
+    /// Frame offset of the saved wsp below the block parameters, `None` if
+    /// nothing branches to the block.
+    wsp_offset: Option<i32>,
+}
+
 pub struct XtensaEsp32 {
     symbol_count: usize,
     asm: AsmWriter,
+    literals: AsmWriter,
+    /// Sections of the linear memory, after the code.
+    data: AsmWriter,
     literal_i32_map: HashMap<String, String>,
+    /// Literals holding the address of a symbol, by symbol.
+    literal_symbol_map: HashMap<String, String>,
     global_map: HashMap<usize, Global>,
     function_map: HashMap<u32, FuncDecl>,
+    /// Labels of the blocks enclosing the instruction being compiled, the
+    /// innermost last.
+    labels: Vec<Label>,
+    /// Frame offset of the wsp saved by the labels, by depth.
+    labels_offset: i32,
+    /// Index and number of results of the function being compiled.
+    func_index: usize,
+    result_count: usize,
+    max_memory_pages: u32,
+    bounds_checks: bool,
+    memory: Option<LinearMemory>,
+}
+
+impl Default for XtensaEsp32 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl XtensaEsp32 {
@@ -54,37 +246,75 @@ impl XtensaEsp32 {
         XtensaEsp32 {
             symbol_count: 0,
             asm: AsmWriter::new(),
+            literals: AsmWriter::new(),
+            data: AsmWriter::new(),
             literal_i32_map: HashMap::new(),
+            literal_symbol_map: HashMap::new(),
             function_map: HashMap::new(),
             global_map: HashMap::new(),
+            labels: vec![],
+            labels_offset: 0,
+            func_index: 0,
+            result_count: 0,
+            max_memory_pages: DEFAULT_MAX_MEMORY_PAGES,
+            bounds_checks: false,
+            memory: None,
         }
     }
 
-    pub fn compile(&mut self, module: Module) -> String {
-        let mut literals_writer = AsmWriter::new();
-        literals_writer.op(".literal_position", vec![]);
+    pub fn compile(&mut self, module: Module) -> Result<String> {
+        self.literals.op(".literal_position", vec![]);
+
+        // Global indices count imported globals first.
+        let imported_globals = module
+            .imports
+            .iter()
+            .filter(|import| matches!(import.desc, ImportDesc::Global(_)))
+            .count();
+        let mut global_values: Vec<ConstValue> = vec![];
 
-        let mut global_idx = 0;
-        for global in &module.globals {
+        for (idx, global) in module.globals.iter().enumerate() {
             let label = self.gen_symbol();
             self.global_map.insert(
-                global_idx,
+                imported_globals + idx,
                 Global {
                     label: label.clone(),
                     global_type: global.global_type.clone(),
                 },
             );
 
-            match &global.init_expr {
-                Instruction::I32Const { value } => {
-                    literals_writer.op(".literal", vec![Symbol(label), LiteralI32(*value)]);
+            let value = global
+                .init_expr
+                .eval(|global_index| {
+                    (global_index as usize)
+                        .checked_sub(imported_globals)
+                        .and_then(|idx| global_values.get(idx).copied())
+                })
+                .map_err(|err| CompileError::GlobalInit {
+                    index: imported_globals + idx,
+                    err,
+                })?;
+
+            match value {
+                ConstValue::I32(value) => {
+                    self.literals
+                        .op(".literal", vec![Symbol(label), LiteralI32(value)]);
+                }
+                ConstValue::F32(value) => {
+                    self.literals.op(
+                        ".literal",
+                        vec![Symbol(label), LiteralI32(value.to_bits() as i32)],
+                    );
                 }
                 _ => {
-                    panic!("Unsupported global init expression");
+                    return Err(CompileError::GlobalValue {
+                        index: imported_globals + idx,
+                        value,
+                    });
                 }
             }
 
-            global_idx += 1;
+            global_values.push(value);
         }
 
         let mut func_idx = 0;
@@ -106,24 +336,35 @@ impl XtensaEsp32 {
 
             for inst in insts {
                 if let Instruction::I32Const { value } = inst {
-                    let key = value.to_string();
-
-                    if self.literal_i32_map.contains_key(&key) {
-                        continue;
-                    }
-
-                    let label = self.gen_symbol();
-                    self.literal_i32_map.insert(key, label.clone());
-                    literals_writer.op(".literal", vec![Symbol(label.clone()), LiteralI32(*value)]);
+                    self.literal_i32(*value);
                 }
             }
         }
-        self.asm.extend(literals_writer);
+
+        let globals: Vec<Option<ConstValue>> = (0..imported_globals)
+            .map(|_| None)
+            .chain(global_values.into_iter().map(Some))
+            .collect();
+        self.reserve_memory(&module, &globals)?;
+
+        // Function indices count imported functions first.
+        let func_types: Vec<FuncType> = module
+            .imports
+            .iter()
+            .filter_map(|import| match import.desc {
+                ImportDesc::Func(type_index) => Some(module.types[type_index as usize].clone()),
+                _ => None,
+            })
+            .chain(module.functions.iter().map(|func| FuncType {
+                params: func.params.clone(),
+                results: func.results.clone(),
+            }))
+            .collect();
 
         for func in &module.functions {
-            let Some(insts) = &func.raw_body else {
+            if func.raw_body.is_none() {
                 continue;
-            };
+            }
 
             let func_label = func.label.clone();
             let name = func
@@ -139,8 +380,25 @@ impl XtensaEsp32 {
                     vec![Symbol(func_label.clone()), symbol("@function")],
                 )
                 .label(func_label.clone())
-                .inline_comment(name.clone())
-                .op("entry", vec![SP, Imm(64)]);
+                .inline_comment(name.clone());
+
+            // Frame layout from sp: params, locals, the wsp saved by each
+            // label depth, then the wasm stack, below the register save area.
+            let labels_offset = (func.params.len() + func.locals.len()) as i32 * 4;
+            let wsp_offset = labels_offset + max_target_depth(&func.body) as i32 * 4;
+            let stack_size = max_stack_height(&func.body, 0, &func_types) as i32 * 4;
+            let frame_size = (wsp_offset + stack_size + SAVE_AREA_SIZE + 15) & !15;
+            if frame_size > MAX_FRAME_SIZE {
+                panic!(
+                    "Function {} needs a {} byte frame, entry allows at most {}",
+                    name, frame_size, MAX_FRAME_SIZE
+                );
+            }
+            self.asm.op("entry", vec![SP, Imm(frame_size)]);
+            self.func_index = func.index;
+            let mut stack_check_writer = AsmWriter::new();
+            self.check_stack(&mut stack_check_writer);
+            self.asm.extend(stack_check_writer);
 
             let mut offset = 0;
             for (idx, _) in func.params.iter().enumerate() {
@@ -156,110 +414,195 @@ impl XtensaEsp32 {
                     .inline_comment(format!("local#{}", idx));
                 offset += 4; // 32bit
             }
+            self.labels_offset = labels_offset;
             self.asm
-                .op("addi", vec![RegA(7), SP, Imm(offset)])
+                .op("addi", vec![RegA(7), SP, Imm(wsp_offset)])
                 .inline_comment("a7 is wsp(wasm stack pointer)");
+            self.result_count = func.results.len();
 
             let mut insts_writer = AsmWriter::new();
-            if let Some(last) =
-                self.compile_instructions(&mut insts_writer, &mut insts.clone().into_iter())
-            {
-                match last {
-                    Instruction::End => {
-                        insts_writer.op("retw.n", vec![]);
-                    }
-                    _ => {}
-                }
-            }
+            self.compile_nodes(&mut insts_writer, &func.body)?;
+            self.epilogue(&mut insts_writer);
 
             self.asm.extend(insts_writer);
             self.asm.op(
                 ".size",
                 vec![
                     Symbol(func_label.clone()),
-                    symbol(format!(".-{}", func_label.to_string())),
+                    symbol(format!(".-{}", func_label)),
                 ],
             );
         }
 
-        // self.asm.write_to_string(true)
-        self.asm.write_to_string(false)
-    }
+        // Literals go first, as l32r only loads from lower addresses.
+        let mut asm = std::mem::take(&mut self.literals);
+        asm.extend(std::mem::take(&mut self.asm));
+        asm.extend(std::mem::take(&mut self.data));
 
-    fn compile_instructions(
-        &mut self,
-        insts_writer: &mut AsmWriter,
-        insts: &mut impl Iterator<Item = Instruction>,
-    ) -> Option<Instruction> {
-        loop {
-            let Some(inst) = insts.next() else {
-                break;
-            };
+        // asm.write_to_string(true)
+        Ok(asm.write_to_string(false))
+    }
 
-            match inst {
-                Instruction::If { block } => {
+    fn compile_nodes(&mut self, insts_writer: &mut AsmWriter, nodes: &[Node]) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::If {
+                    block_type,
+                    depth,
+                    then_body,
+                    else_body,
+                } => {
                     insts_writer.comment("if");
                     pop_stack(insts_writer, 2);
                     let falsy_case_label = self.gen_symbol();
+                    let end_label = match else_body {
+                        Some(_) => self.gen_symbol(),
+                        None => falsy_case_label.clone(),
+                    };
+                    let branched_to = is_branch_target(then_body, 0)
+                        || else_body
+                            .as_ref()
+                            .is_some_and(|else_body| is_branch_target(else_body, 0));
+                    self.push_label(
+                        insts_writer,
+                        end_label.clone(),
+                        block_type.results.len(),
+                        block_type,
+                        *depth,
+                        branched_to,
+                    );
                     insts_writer
                         .op("beqz", vec![RegA(2), Symbol(falsy_case_label.clone())])
                         .inline_comment(format!(
                             "if a2 == false then jump to {}(falsy case)",
-                            &falsy_case_label
+                            falsy_case_label
                         ));
                     insts_writer.comment("truthy case");
-                    if let Some(last) = self.compile_instructions(insts_writer, insts) {
-                        if matches!(last, Instruction::Else) {
-                            let end_label = self.gen_symbol();
-                            insts_writer.op("j", vec![Symbol(end_label.clone())]);
-                            insts_writer
-                                .label(falsy_case_label.clone())
-                                .inline_comment("else");
-                            self.compile_instructions(insts_writer, insts);
-
-                            insts_writer
-                                .label(end_label.clone())
-                                .inline_comment("end if");
-                        } else {
-                            insts_writer
-                                .label(falsy_case_label.clone())
-                                .inline_comment("end if");
-                        }
+                    self.compile_nodes(insts_writer, then_body)?;
+
+                    if let Some(else_body) = else_body {
+                        insts_writer.op("j", vec![Symbol(end_label.clone())]);
+                        insts_writer
+                            .label(falsy_case_label.clone())
+                            .inline_comment("else");
+                        self.compile_nodes(insts_writer, else_body)?;
+
+                        insts_writer
+                            .label(end_label.clone())
+                            .inline_comment("end if");
+                    } else {
+                        insts_writer
+                            .label(falsy_case_label.clone())
+                            .inline_comment("end if");
                     }
+                    self.labels.pop();
                 }
-                Instruction::Else => return Some(inst.clone()),
-                Instruction::End => return Some(inst.clone()),
-                Instruction::Return => {
-                    pop_stack(insts_writer, 2);
-                    insts_writer.comment("return").op("retw.n", vec![]);
+                Node::Block {
+                    block_type,
+                    depth,
+                    body,
+                } => {
+                    insts_writer.comment("block");
+                    let end_label = self.gen_symbol();
+                    let branched_to = is_branch_target(body, 0);
+                    self.push_label(
+                        insts_writer,
+                        end_label.clone(),
+                        block_type.results.len(),
+                        block_type,
+                        *depth,
+                        branched_to,
+                    );
+                    self.compile_nodes(insts_writer, body)?;
+                    self.labels.pop();
+                    insts_writer.label(end_label).inline_comment("end block");
                 }
-                _ => {
-                    self.compile_instruction(insts_writer, &inst);
+                Node::Loop {
+                    block_type,
+                    depth,
+                    body,
+                } => {
+                    let loop_label = self.gen_symbol();
+                    let branched_to = is_branch_target(body, 0);
+                    self.push_label(
+                        insts_writer,
+                        loop_label.clone(),
+                        block_type.params.len(),
+                        block_type,
+                        *depth,
+                        branched_to,
+                    );
+                    insts_writer.label(loop_label).inline_comment("loop");
+                    self.compile_nodes(insts_writer, body)?;
+                    self.labels.pop();
+                }
+                Node::TryTable {
+                    block_type,
+                    catches,
+                    ..
+                } => {
+                    // The node only keeps the resolved block type, which is
+                    // enough to tell which try_table isn't supported.
+                    let block_type = match block_type.results.as_slice() {
+                        [] if block_type.params.is_empty() => BlockType::Empty,
+                        results => BlockType::Value(results.to_vec()),
+                    };
+                    return Err(CompileError::Unsupported(Instruction::TryTable {
+                        block: Block { block_type },
+                        catches: catches.clone(),
+                    }));
+                }
+                Node::Instruction(Instruction::Return) => {
+                    insts_writer.comment("return");
+                    self.epilogue(insts_writer);
+                }
+                Node::Instruction(inst) => {
+                    self.compile_instruction(insts_writer, inst)?;
                 }
             }
         }
 
-        None
+        Ok(())
     }
 
-    fn compile_instruction(&mut self, insts_writer: &mut AsmWriter, inst: &Instruction) {
+    fn compile_instruction(
+        &mut self,
+        insts_writer: &mut AsmWriter,
+        inst: &Instruction,
+    ) -> Result<()> {
         match inst {
-            Instruction::Unreachable => todo!(),
-            Instruction::Nop => todo!(),
-            Instruction::Block { block } => todo!(),
-            Instruction::Loop { block } => todo!(),
-            Instruction::If { block } => {
-                // implemented in compile_instructions
-            }
-            Instruction::Else => todo!(),
-            Instruction::Br { level } => todo!(),
-            Instruction::BrIf { level } => todo!(),
+            Instruction::Unreachable => {
+                insts_writer.comment("unreachable");
+                self.trap(insts_writer, TrapCode::Unreachable);
+            }
+            Instruction::Nop => {}
+            Instruction::If { .. } => {
+                // implemented in compile_nodes
+            }
+            Instruction::Br { level } => {
+                insts_writer.comment(format!("br {}", level));
+                self.branch(insts_writer, *level);
+            }
+            Instruction::BrIf { level } => {
+                insts_writer.comment(format!("br_if {}", level));
+                pop_stack(insts_writer, 2);
+                let skip_label = self.gen_symbol();
+                insts_writer
+                    .op("beqz", vec![RegA(2), Symbol(skip_label.clone())])
+                    .inline_comment(format!("if a2 == false then jump to {}", skip_label));
+                self.branch(insts_writer, *level);
+                insts_writer.label(skip_label);
+            }
             Instruction::BrTable {
                 label_indexes,
                 default_index,
-            } => todo!(),
+            } => {
+                insts_writer.comment("br_table");
+                pop_stack(insts_writer, 2);
+                self.branch_table(insts_writer, label_indexes, *default_index);
+            }
             Instruction::Return => {
-                // implemented in compile_instructions
+                // implemented in compile_nodes
             }
             Instruction::Call { func_index } => {
                 let func = self.function_map.get(func_index).cloned().unwrap();
@@ -311,23 +654,14 @@ impl XtensaEsp32 {
             Instruction::End => {
                 // insts_writer.comment("____END____");
                 // insts_writer.op("retw.n", vec![]).inline_comment("end");
-                // implemented in compile_instructions.
-            }
-            Instruction::CallIndirect {
-                type_index,
-                table_index,
-            } => todo!(),
-            Instruction::RefNull { ref_type } => todo!(),
-            Instruction::RefIsNull => todo!(),
-            Instruction::RefFunc { func_index } => todo!(),
+                // implemented in compile_nodes.
+            }
             Instruction::Drop => {
                 insts_writer
                     .comment("drop")
                     .op("addi", vec![RegA(7), RegA(7), Imm(-4)])
                     .inline_comment("wsp -= 4;");
             }
-            Instruction::Select { result_types } => todo!(),
-            Instruction::SelectResult { result_type } => todo!(),
             Instruction::LocalGet { local_index } => {
                 insts_writer
                     .comment(format!("local.get {}", local_index))
@@ -348,7 +682,6 @@ impl XtensaEsp32 {
                     ));
                 pop_stack(insts_writer, 2);
             }
-            Instruction::LocalTee { local_index } => todo!(),
             Instruction::GlobalGet { global_index } => {
                 let idx = *global_index;
                 let global = self.global_map.get(&(idx as usize)).unwrap();
@@ -364,52 +697,68 @@ impl XtensaEsp32 {
                 //     .op("l32r", vec![RegA(2), Symbol(label.clone())]);
                 // push_stack(insts_writer, 2);
             }
-            Instruction::GlobalSet { global_index } => {
+            Instruction::GlobalSet { .. } => {
                 insts_writer.comment("global.set");
             }
-            Instruction::TableGet { table_index } => todo!(),
-            Instruction::TableSet { table_index } => todo!(),
-            Instruction::TableInit {
-                element_index,
-                table_index,
-            } => todo!(),
-            Instruction::ElemDrop { element_index } => todo!(),
-            Instruction::TableCopy {
-                dst_table_index,
-                src_table_index,
-            } => todo!(),
-            Instruction::TableGrow { table_index } => todo!(),
-            Instruction::TableSize { table_index } => todo!(),
-            Instruction::TableFill { table_index } => todo!(),
-            Instruction::I32Load { mem_arg } => todo!(),
-            Instruction::I64Load { mem_arg } => todo!(),
-            Instruction::F32Load { mem_arg } => todo!(),
-            Instruction::F64Load { mem_arg } => todo!(),
-            Instruction::I32Load8S { mem_arg } => todo!(),
-            Instruction::I32Load8U { mem_arg } => todo!(),
-            Instruction::I32Load16S { mem_arg } => todo!(),
-            Instruction::I32Load16U { mem_arg } => todo!(),
-            Instruction::I64Load8S { mem_arg } => todo!(),
-            Instruction::I64Load8U { mem_arg } => todo!(),
-            Instruction::I64Load16S { mem_arg } => todo!(),
-            Instruction::I64Load16U { mem_arg } => todo!(),
-            Instruction::I64Load32S { mem_arg } => todo!(),
-            Instruction::I64Load32U { mem_arg } => todo!(),
-            Instruction::I32Store { mem_arg } => todo!(),
-            Instruction::I64Store { mem_arg } => todo!(),
-            Instruction::F32Store { mem_arg } => todo!(),
-            Instruction::F64Store { mem_arg } => todo!(),
-            Instruction::I32Store8 { mem_arg } => todo!(),
-            Instruction::I32Store16 { mem_arg } => todo!(),
-            Instruction::I64Store8 { mem_arg } => todo!(),
-            Instruction::I64Store16 { mem_arg } => todo!(),
-            Instruction::I64Store32 { mem_arg } => todo!(),
-            Instruction::MemorySize => todo!(),
-            Instruction::MemoryGrow => todo!(),
-            Instruction::MemoryInit { data_index } => todo!(),
-            Instruction::DataDrop { data_index } => todo!(),
-            Instruction::MemoryCopy => todo!(),
-            Instruction::MemoryFill => todo!(),
+            Instruction::I32Load { mem_arg } => {
+                insts_writer.comment(format!("i32.load offset={}", mem_arg.offset));
+                self.load(insts_writer, mem_arg, 4, false);
+            }
+            Instruction::F32Load { mem_arg } => {
+                insts_writer.comment(format!("f32.load offset={}", mem_arg.offset));
+                self.load(insts_writer, mem_arg, 4, false);
+            }
+            Instruction::I32Load8S { mem_arg } => {
+                insts_writer.comment(format!("i32.load8_s offset={}", mem_arg.offset));
+                self.load(insts_writer, mem_arg, 1, true);
+            }
+            Instruction::I32Load8U { mem_arg } => {
+                insts_writer.comment(format!("i32.load8_u offset={}", mem_arg.offset));
+                self.load(insts_writer, mem_arg, 1, false);
+            }
+            Instruction::I32Load16S { mem_arg } => {
+                insts_writer.comment(format!("i32.load16_s offset={}", mem_arg.offset));
+                self.load(insts_writer, mem_arg, 2, true);
+            }
+            Instruction::I32Load16U { mem_arg } => {
+                insts_writer.comment(format!("i32.load16_u offset={}", mem_arg.offset));
+                self.load(insts_writer, mem_arg, 2, false);
+            }
+            Instruction::I32Store { mem_arg } => {
+                insts_writer.comment(format!("i32.store offset={}", mem_arg.offset));
+                self.store(insts_writer, mem_arg, 4);
+            }
+            Instruction::F32Store { mem_arg } => {
+                insts_writer.comment(format!("f32.store offset={}", mem_arg.offset));
+                self.store(insts_writer, mem_arg, 4);
+            }
+            Instruction::I32Store8 { mem_arg } => {
+                insts_writer.comment(format!("i32.store8 offset={}", mem_arg.offset));
+                self.store(insts_writer, mem_arg, 1);
+            }
+            Instruction::I32Store16 { mem_arg } => {
+                insts_writer.comment(format!("i32.store16 offset={}", mem_arg.offset));
+                self.store(insts_writer, mem_arg, 2);
+            }
+            Instruction::MemorySize { memory_index } => {
+                insts_writer.comment("memory.size");
+                self.memory_size(insts_writer, *memory_index);
+            }
+            Instruction::MemoryGrow { memory_index } => {
+                insts_writer.comment("memory.grow");
+                self.memory_grow(insts_writer, *memory_index);
+            }
+            Instruction::MemoryCopy {
+                dst_memory_index,
+                src_memory_index,
+            } => {
+                insts_writer.comment("memory.copy");
+                self.memory_copy(insts_writer, *dst_memory_index, *src_memory_index);
+            }
+            Instruction::MemoryFill { memory_index } => {
+                insts_writer.comment("memory.fill");
+                self.memory_fill(insts_writer, *memory_index);
+            }
             Instruction::I32Const { value } => {
                 let key = value.to_string();
                 let label = self.literal_i32_map.get(&key).unwrap();
@@ -418,62 +767,121 @@ impl XtensaEsp32 {
                     .op("l32r", vec![RegA(2), Symbol(label.clone())]);
                 push_stack(insts_writer, 2);
             }
-            Instruction::I64Const { value } => todo!(),
-            Instruction::F32Const { value } => todo!(),
-            Instruction::F64Const { value } => todo!(),
-            Instruction::I32Eqz => todo!(),
-            Instruction::I32Eq => todo!(),
-            Instruction::I32Ne => todo!(),
+            Instruction::I32Eqz => {
+                insts_writer.comment("i32.eqz");
+                pop_stack(insts_writer, 2);
+                self.set_if(insts_writer, "beqz", vec![RegA(2)], "a2 == 0");
+            }
+            Instruction::I32Eq => {
+                insts_writer.comment("i32.eq");
+                pop_operands(insts_writer);
+                self.set_if(insts_writer, "beq", vec![RegA(3), RegA(2)], "a3 == a2");
+            }
+            Instruction::I32Ne => {
+                insts_writer.comment("i32.ne");
+                pop_operands(insts_writer);
+                self.set_if(insts_writer, "bne", vec![RegA(3), RegA(2)], "a3 != a2");
+            }
             Instruction::I32LtS => {
-                insts_writer.comment("i32.lts");
+                insts_writer.comment("i32.lt_s");
+                pop_operands(insts_writer);
+                self.set_if(insts_writer, "blt", vec![RegA(3), RegA(2)], "a3 < a2");
+            }
+            Instruction::I32LtU => {
+                insts_writer.comment("i32.lt_u");
+                pop_operands(insts_writer);
+                self.set_if(
+                    insts_writer,
+                    "bltu",
+                    vec![RegA(3), RegA(2)],
+                    "a3 < a2 (unsigned)",
+                );
+            }
+            Instruction::I32GtS => {
+                insts_writer.comment("i32.gt_s");
+                pop_operands(insts_writer);
+                self.set_if(insts_writer, "blt", vec![RegA(2), RegA(3)], "a3 > a2");
+            }
+            Instruction::I32GtU => {
+                insts_writer.comment("i32.gt_u");
+                pop_operands(insts_writer);
+                self.set_if(
+                    insts_writer,
+                    "bltu",
+                    vec![RegA(2), RegA(3)],
+                    "a3 > a2 (unsigned)",
+                );
+            }
+            Instruction::I32LeS => {
+                insts_writer.comment("i32.le_s");
+                pop_operands(insts_writer);
+                self.set_if(insts_writer, "bge", vec![RegA(2), RegA(3)], "a3 <= a2");
+            }
+            Instruction::I32LeU => {
+                insts_writer.comment("i32.le_u");
+                pop_operands(insts_writer);
+                self.set_if(
+                    insts_writer,
+                    "bgeu",
+                    vec![RegA(2), RegA(3)],
+                    "a3 <= a2 (unsigned)",
+                );
+            }
+            Instruction::I32GeS => {
+                insts_writer.comment("i32.ge_s");
+                pop_operands(insts_writer);
+                self.set_if(insts_writer, "bge", vec![RegA(3), RegA(2)], "a3 >= a2");
+            }
+            Instruction::I32GeU => {
+                insts_writer.comment("i32.ge_u");
+                pop_operands(insts_writer);
+                self.set_if(
+                    insts_writer,
+                    "bgeu",
+                    vec![RegA(3), RegA(2)],
+                    "a3 >= a2 (unsigned)",
+                );
+            }
+            Instruction::I32Clz => {
+                insts_writer.comment("i32.clz");
                 pop_stack(insts_writer, 2);
-                pop_stack(insts_writer, 3);
-                let label = self.gen_symbol();
                 insts_writer
-                    .op("movi.n", vec![RegA(4), Imm(1)])
-                    .inline_comment("a4 = true(1)")
-                    .op("blt", vec![RegA(3), RegA(2), Symbol(label.clone())])
-                    .inline_comment(format!("if a3 < a2 then jump to {}", &label))
-                    .op("movi.n", vec![RegA(4), Imm(0)])
-                    .inline_comment("a4 = true(0)")
-                    .label(label);
-                push_stack(insts_writer, 4);
-            }
-            Instruction::I32LtU => todo!(),
-            Instruction::I32GtS => todo!(),
-            Instruction::I32GtU => todo!(),
-            Instruction::I32LeS => todo!(),
-            Instruction::I32LeU => todo!(),
-            Instruction::I32GeS => todo!(),
-            Instruction::I32GeU => todo!(),
-            Instruction::I64Eqz => todo!(),
-            Instruction::I64Eq => todo!(),
-            Instruction::I64Ne => todo!(),
-            Instruction::I64LtS => todo!(),
-            Instruction::I64LtU => todo!(),
-            Instruction::I64GtS => todo!(),
-            Instruction::I64GtU => todo!(),
-            Instruction::I64LeS => todo!(),
-            Instruction::I64LeU => todo!(),
-            Instruction::I64GeS => todo!(),
-            Instruction::I64GeU => todo!(),
-            Instruction::F32Eq => todo!(),
-            Instruction::F32Ne => todo!(),
-            Instruction::F32Lt => todo!(),
-            Instruction::F32Gt => todo!(),
-            Instruction::F32Le => todo!(),
-            Instruction::F32Ge => todo!(),
-            Instruction::F64Eq => todo!(),
-            Instruction::F64Ne => todo!(),
-            Instruction::F64Lt => todo!(),
-            Instruction::F64Gt => todo!(),
-            Instruction::F64Le => todo!(),
-            Instruction::F64Ge => todo!(),
-            Instruction::I32Clz => todo!(),
+                    .op("nsau", vec![RegA(2), RegA(2)])
+                    .inline_comment("number of leading zeros, 32 for 0");
+                push_stack(insts_writer, 2);
+            }
             Instruction::I32Ctz => {
-                insts_writer.unimplemented("i32.ctz");
+                insts_writer.comment("i32.ctz");
+                pop_stack(insts_writer, 2);
+                insts_writer
+                    .op("addi", vec![RegA(3), RegA(2), Imm(-1)])
+                    .op("movi.n", vec![RegA(4), Imm(-1)])
+                    .op("xor", vec![RegA(4), RegA(2), RegA(4)])
+                    .op("and", vec![RegA(3), RegA(3), RegA(4)])
+                    .inline_comment("a3 = mask of the trailing zeros")
+                    .op("nsau", vec![RegA(3), RegA(3)])
+                    .op("movi.n", vec![RegA(2), Imm(32)])
+                    .op("sub", vec![RegA(2), RegA(2), RegA(3)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32Popcnt => {
+                insts_writer.comment("i32.popcnt");
+                pop_stack(insts_writer, 2);
+                let loop_label = self.gen_symbol();
+                let end_label = self.gen_symbol();
+                insts_writer
+                    .op("movi.n", vec![RegA(3), Imm(0)])
+                    .inline_comment("a3 = count")
+                    .label(loop_label.clone())
+                    .op("beqz", vec![RegA(2), Symbol(end_label.clone())])
+                    .op("addi", vec![RegA(4), RegA(2), Imm(-1)])
+                    .op("and", vec![RegA(2), RegA(2), RegA(4)])
+                    .inline_comment("clear the lowest set bit")
+                    .op("addi.n", vec![RegA(3), RegA(3), Imm(1)])
+                    .op("j", vec![Symbol(loop_label)])
+                    .label(end_label);
+                push_stack(insts_writer, 3);
             }
-            Instruction::I32Popcnt => todo!(),
             Instruction::I32Add => {
                 insts_writer.comment("i32.add");
                 pop_stack(insts_writer, 2);
@@ -488,11 +896,45 @@ impl XtensaEsp32 {
                 insts_writer.op("sub", vec![RegA(2), RegA(2), RegA(3)]);
                 push_stack(insts_writer, 2);
             }
-            Instruction::I32Mul => todo!(),
-            Instruction::I32DivS => todo!(),
-            Instruction::I32DivU => todo!(),
-            Instruction::I32RemS => todo!(),
-            Instruction::I32RemU => todo!(),
+            Instruction::I32Mul => {
+                insts_writer.comment("i32.mul");
+                pop_operands(insts_writer);
+                insts_writer.op("mull", vec![RegA(2), RegA(3), RegA(2)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32DivS => {
+                insts_writer.comment("i32.div_s");
+                pop_operands(insts_writer);
+                self.check_divisor(insts_writer, true);
+                insts_writer.op("quos", vec![RegA(2), RegA(3), RegA(2)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32DivU => {
+                insts_writer.comment("i32.div_u");
+                pop_operands(insts_writer);
+                self.check_divisor(insts_writer, false);
+                insts_writer.op("quou", vec![RegA(2), RegA(3), RegA(2)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32RemS => {
+                insts_writer.comment("i32.rem_s");
+                pop_operands(insts_writer);
+                self.check_divisor(insts_writer, false);
+                insts_writer
+                    .op("addi", vec![RegA(4), RegA(2), Imm(1)])
+                    .inline_comment("a4 = 0 if a2 == -1")
+                    .op("rems", vec![RegA(2), RegA(3), RegA(2)])
+                    .op("moveqz", vec![RegA(2), RegA(4), RegA(4)])
+                    .inline_comment("x % -1 is 0, INT_MIN % -1 included");
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32RemU => {
+                insts_writer.comment("i32.rem_u");
+                pop_operands(insts_writer);
+                self.check_divisor(insts_writer, false);
+                insts_writer.op("remu", vec![RegA(2), RegA(3), RegA(2)]);
+                push_stack(insts_writer, 2);
+            }
             Instruction::I32And => {
                 pop_stack(insts_writer, 2);
                 pop_stack(insts_writer, 3);
@@ -527,95 +969,274 @@ impl XtensaEsp32 {
                     .op("sll", vec![RegA(2), RegA(3)]);
                 push_stack(insts_writer, 2);
             }
-            Instruction::I32ShrS => todo!(),
-            Instruction::I32ShrU => todo!(),
-            Instruction::I32Rotl => todo!(),
-            Instruction::I32Rotr => todo!(),
-            Instruction::I64Clz => todo!(),
-            Instruction::I64Ctz => todo!(),
-            Instruction::I64Popcnt => todo!(),
-            Instruction::I64Add => todo!(),
-            Instruction::I64Sub => todo!(),
-            Instruction::I64Mul => todo!(),
-            Instruction::I64DivS => todo!(),
-            Instruction::I64DivU => todo!(),
-            Instruction::I64RemS => todo!(),
-            Instruction::I64RemU => todo!(),
-            Instruction::I64And => todo!(),
-            Instruction::I64Or => todo!(),
-            Instruction::I64Xor => todo!(),
-            Instruction::I64Shl => todo!(),
-            Instruction::I64ShrS => todo!(),
-            Instruction::I64ShrU => todo!(),
-            Instruction::I64Rotl => todo!(),
-            Instruction::I64Rotr => todo!(),
-            Instruction::F32Abs => todo!(),
-            Instruction::F32Neg => todo!(),
-            Instruction::F32Ceil => todo!(),
-            Instruction::F32Floor => todo!(),
-            Instruction::F32Trunc => todo!(),
-            Instruction::F32Nearest => todo!(),
-            Instruction::F32Sqrt => todo!(),
-            Instruction::F32Add => todo!(),
-            Instruction::F32Sub => todo!(),
-            Instruction::F32Mul => todo!(),
-            Instruction::F32Div => todo!(),
-            Instruction::F32Min => todo!(),
-            Instruction::F32Max => todo!(),
-            Instruction::F32Copysign => todo!(),
-            Instruction::F64Abs => todo!(),
-            Instruction::F64Neg => todo!(),
-            Instruction::F64Ceil => todo!(),
-            Instruction::F64Floor => todo!(),
-            Instruction::F64Trunc => todo!(),
-            Instruction::F64Nearest => todo!(),
-            Instruction::F64Sqrt => todo!(),
-            Instruction::F64Add => todo!(),
-            Instruction::F64Sub => todo!(),
-            Instruction::F64Mul => todo!(),
-            Instruction::F64Div => todo!(),
-            Instruction::F64Min => todo!(),
-            Instruction::F64Max => todo!(),
-            Instruction::F64Copysign => todo!(),
-            Instruction::I32WrapI64 => todo!(),
-            Instruction::I32TruncF32S => todo!(),
-            Instruction::I32TruncF32U => todo!(),
-            Instruction::I32TruncF64S => todo!(),
-            Instruction::I32TruncF64U => todo!(),
-            Instruction::I64ExtendI32S => todo!(),
-            Instruction::I64ExtendI32U => todo!(),
-            Instruction::I64TruncF32S => todo!(),
-            Instruction::I64TruncF32U => todo!(),
-            Instruction::I64TruncF64S => todo!(),
-            Instruction::I64TruncF64U => todo!(),
-            Instruction::F32ConvertI32S => todo!(),
-            Instruction::F32ConvertI32U => todo!(),
-            Instruction::F32ConvertI64S => todo!(),
-            Instruction::F32ConvertI64U => todo!(),
-            Instruction::F32DemoteF64 => todo!(),
-            Instruction::F64ConvertI32S => todo!(),
-            Instruction::F64ConvertI32U => todo!(),
-            Instruction::F64ConvertI64S => todo!(),
-            Instruction::F64ConvertI64U => todo!(),
-            Instruction::F64PromoteF32 => todo!(),
-            Instruction::I32ReinterpretF32 => todo!(),
-            Instruction::I64ReinterpretF64 => todo!(),
-            Instruction::F32ReinterpretI32 => todo!(),
-            Instruction::F64ReinterpretI64 => todo!(),
-            Instruction::I32Extend8S => todo!(),
-            Instruction::I32Extend16S => todo!(),
-            Instruction::I64Extend8S => todo!(),
-            Instruction::I64Extend16S => todo!(),
-            Instruction::I64Extend32S => todo!(),
-            Instruction::I32TruncSatF32S => todo!(),
-            Instruction::I32TruncSatF32U => todo!(),
-            Instruction::I32TruncSatF64S => todo!(),
-            Instruction::I32TruncSatF64U => todo!(),
-            Instruction::I64TruncSatF32S => todo!(),
-            Instruction::I64TruncSatF32U => todo!(),
-            Instruction::I64TruncSatF64S => todo!(),
-            Instruction::I64TruncSatF64U => todo!(),
+            Instruction::I32ShrS => {
+                insts_writer.comment("i32.shr_s");
+                pop_operands(insts_writer);
+                insts_writer
+                    .op("ssr", vec![RegA(2)])
+                    .inline_comment("Sets Shift Amount Register(SAR)")
+                    .op("sra", vec![RegA(2), RegA(3)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32ShrU => {
+                insts_writer.comment("i32.shr_u");
+                pop_operands(insts_writer);
+                insts_writer
+                    .op("ssr", vec![RegA(2)])
+                    .inline_comment("Sets Shift Amount Register(SAR)")
+                    .op("srl", vec![RegA(2), RegA(3)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32Rotl => {
+                insts_writer.comment("i32.rotl");
+                pop_operands(insts_writer);
+                insts_writer
+                    .op("ssl", vec![RegA(2)])
+                    .inline_comment("Sets Shift Amount Register(SAR)")
+                    .op("src", vec![RegA(2), RegA(3), RegA(3)])
+                    .inline_comment("funnel shift of a3:a3");
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32Rotr => {
+                insts_writer.comment("i32.rotr");
+                pop_operands(insts_writer);
+                insts_writer
+                    .op("ssr", vec![RegA(2)])
+                    .inline_comment("Sets Shift Amount Register(SAR)")
+                    .op("src", vec![RegA(2), RegA(3), RegA(3)])
+                    .inline_comment("funnel shift of a3:a3");
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32Extend8S => {
+                insts_writer.comment("i32.extend8_s");
+                pop_stack(insts_writer, 2);
+                insts_writer.op("sext", vec![RegA(2), RegA(2), Imm(7)]);
+                push_stack(insts_writer, 2);
+            }
+            Instruction::I32Extend16S => {
+                insts_writer.comment("i32.extend16_s");
+                pop_stack(insts_writer, 2);
+                insts_writer.op("sext", vec![RegA(2), RegA(2), Imm(15)]);
+                push_stack(insts_writer, 2);
+            }
+            _ => return Err(CompileError::Unsupported(inst.clone())),
         }
+
+        Ok(())
+    }
+
+    /// Returns from the function, with its result, if any, in a2.
+    fn epilogue(&self, insts_writer: &mut AsmWriter) {
+        if self.result_count > 0 {
+            pop_stack(insts_writer, 2);
+        }
+        insts_writer.op("retw.n", vec![]);
+    }
+
+    /// Enters a block with `block_type`, whose branches carry `arity` values.
+    /// If something branches to it, saves the wsp below its parameters in the
+    /// frame slot of its depth.
+    fn push_label(
+        &mut self,
+        insts_writer: &mut AsmWriter,
+        name: String,
+        arity: usize,
+        block_type: &FuncType,
+        depth: u32,
+        branched_to: bool,
+    ) {
+        let wsp_offset = branched_to.then(|| {
+            let offset = self.labels_offset + (depth as i32 - 1) * 4;
+            let wsp = match block_type.params.len() {
+                0 => 7,
+                params => {
+                    insts_writer
+                        .op("addi", vec![RegA(4), RegA(7), Imm(-4 * params as i32)])
+                        .inline_comment("a4 = wsp below the block parameters");
+                    4
+                }
+            };
+            insts_writer.op("s32i", vec![RegA(wsp), SP, Imm(offset)]);
+            offset
+        });
+
+        self.labels.push(Label {
+            name,
+            arity,
+            wsp_offset,
+        });
+    }
+
+    /// Branches `level` labels out: moves the values the label expects from
+    /// the top of the wasm stack down to its wsp, then jumps to it.
+    fn branch(&mut self, insts_writer: &mut AsmWriter, level: u32) {
+        let depth = self.labels.len() - level as usize;
+        if depth == 0 {
+            self.epilogue(insts_writer);
+            return;
+        }
+
+        let label = &self.labels[depth - 1];
+        let offset = label.wsp_offset.unwrap();
+        if label.arity == 0 {
+            insts_writer
+                .op("l32i", vec![RegA(7), SP, Imm(offset)])
+                .inline_comment(format!("wsp = wsp of {}", label.name))
+                .op("j", vec![Symbol(label.name.clone())]);
+            return;
+        }
+
+        insts_writer
+            .op("l32i", vec![RegA(4), SP, Imm(offset)])
+            .inline_comment(format!("a4 = wsp of {}", label.name))
+            .op("addi", vec![RegA(5), RegA(7), Imm(-4 * label.arity as i32)])
+            .inline_comment("a5 = values carried by the branch");
+        // The destination is below the source, so copying upwards is safe.
+        for i in 0..label.arity {
+            let offset = Imm(4 * i as i32);
+            insts_writer
+                .op("l32i", vec![RegA(6), RegA(5), offset.clone()])
+                .op("s32i", vec![RegA(6), RegA(4), offset]);
+        }
+        insts_writer
+            .op("addi", vec![RegA(7), RegA(4), Imm(4 * label.arity as i32)])
+            .inline_comment("wsp = label wsp + carried values")
+            .op("j", vec![Symbol(label.name.clone())]);
+    }
+
+    /// Branches through a jump table indexed by a2, taking the default label
+    /// when a2 is out of bounds.
+    fn branch_table(&mut self, insts_writer: &mut AsmWriter, levels: &[u32], default: u32) {
+        let table_label = self.gen_symbol();
+        let table_literal = self.literal_symbol(&table_label);
+        let default_label = self.gen_symbol();
+
+        // One case per distinct label.
+        let mut cases: Vec<(u32, String)> = vec![];
+        for level in levels {
+            if !cases.iter().any(|(case_level, _)| case_level == level) {
+                cases.push((*level, self.gen_symbol()));
+            }
+        }
+
+        self.load_i32(insts_writer, 3, levels.len() as i32);
+        insts_writer
+            .op(
+                "bgeu",
+                vec![RegA(2), RegA(3), Symbol(default_label.clone())],
+            )
+            .inline_comment(format!(
+                "if a2 >= {} then jump to {}(default)",
+                levels.len(),
+                default_label
+            ))
+            .op("l32r", vec![RegA(3), Symbol(table_literal)])
+            .op("addx4", vec![RegA(3), RegA(2), RegA(3)])
+            .inline_comment("a3 = table + a2 * 4")
+            .op("l32i", vec![RegA(3), RegA(3), Imm(0)])
+            .op("jx", vec![RegA(3)])
+            .op(".align", vec![Imm(4)])
+            .label(table_label);
+        for level in levels {
+            let (_, case_label) = cases
+                .iter()
+                .find(|(case_level, _)| case_level == level)
+                .unwrap();
+            insts_writer.op(".word", vec![Symbol(case_label.clone())]);
+        }
+
+        for (level, case_label) in cases {
+            insts_writer.label(case_label);
+            self.branch(insts_writer, level);
+        }
+        insts_writer.label(default_label);
+        self.branch(insts_writer, default);
+    }
+
+    /// Pushes 1 if `branch` on `operands` is taken, 0 otherwise.
+    fn set_if(
+        &mut self,
+        insts_writer: &mut AsmWriter,
+        branch: &str,
+        mut operands: Vec<Operand>,
+        condition: &str,
+    ) {
+        let label = self.gen_symbol();
+        operands.push(Symbol(label.clone()));
+        insts_writer
+            .op("movi.n", vec![RegA(4), Imm(1)])
+            .inline_comment("a4 = true(1)")
+            .op(branch, operands)
+            .inline_comment(format!("if {} then jump to {}", condition, label))
+            .op("movi.n", vec![RegA(4), Imm(0)])
+            .inline_comment("a4 = false(0)")
+            .label(label);
+        push_stack(insts_writer, 4);
+    }
+
+    /// Traps if the divisor in a2 is zero and, for signed division, if the
+    /// dividend in a3 is INT_MIN and the divisor -1.
+    fn check_divisor(&mut self, insts_writer: &mut AsmWriter, signed: bool) {
+        let label = self.gen_symbol();
+        insts_writer
+            .op("bnez", vec![RegA(2), Symbol(label.clone())])
+            .inline_comment(format!("if a2 != 0 then jump to {}", label));
+        self.trap(insts_writer, TrapCode::IntegerDivideByZero);
+        insts_writer.label(label);
+
+        if signed {
+            let label = self.gen_symbol();
+            insts_writer
+                .op("addi", vec![RegA(4), RegA(2), Imm(1)])
+                .op("bnez", vec![RegA(4), Symbol(label.clone())])
+                .inline_comment(format!("if a2 != -1 then jump to {}", label))
+                .op("movi.n", vec![RegA(4), Imm(1)])
+                .op("slli", vec![RegA(4), RegA(4), Imm(31)])
+                .inline_comment("a4 = INT_MIN")
+                .op("bne", vec![RegA(3), RegA(4), Symbol(label.clone())])
+                .inline_comment(format!("if a3 != INT_MIN then jump to {}", label));
+            self.trap(insts_writer, TrapCode::IntegerOverflow);
+            insts_writer.label(label);
+        }
+    }
+
+    /// Loads `value` into `reg`, from the literal pool if it doesn't fit movi.
+    fn load_i32(&mut self, insts_writer: &mut AsmWriter, reg: usize, value: i32) {
+        if (-2048..2048).contains(&value) {
+            insts_writer.op("movi", vec![RegA(reg), Imm(value)]);
+        } else {
+            let label = self.literal_i32(value);
+            insts_writer.op("l32r", vec![RegA(reg), Symbol(label)]);
+        }
+    }
+
+    /// Label of the literal holding `value`, added to the pool if needed.
+    fn literal_i32(&mut self, value: i32) -> String {
+        let key = value.to_string();
+        if let Some(label) = self.literal_i32_map.get(&key) {
+            return label.clone();
+        }
+
+        let label = self.gen_symbol();
+        self.literal_i32_map.insert(key, label.clone());
+        self.literals
+            .op(".literal", vec![Symbol(label.clone()), LiteralI32(value)]);
+        label
+    }
+
+    /// Label of the literal holding the address of `name`, added to the pool
+    /// if needed.
+    fn literal_symbol(&mut self, name: &str) -> String {
+        if let Some(label) = self.literal_symbol_map.get(name) {
+            return label.clone();
+        }
+
+        let label = self.gen_symbol();
+        self.literal_symbol_map
+            .insert(name.to_string(), label.clone());
+        self.literals
+            .op(".literal", vec![Symbol(label.clone()), symbol(name)]);
+        label
     }
 
     fn gen_symbol(&mut self) -> String {
@@ -625,6 +1246,7 @@ impl XtensaEsp32 {
         s
     }
 
+    #[allow(dead_code)]
     fn get_value_type_byte_siize(&self, value_type: &ValueType) -> usize {
         match value_type {
             ValueType::I32 => 4,
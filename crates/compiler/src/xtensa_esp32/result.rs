@@ -0,0 +1,18 @@
+use wasm_parser::decoder::{
+    const_expr::{ConstExprError, ConstValue},
+    instructions::Instruction,
+};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum CompileError {
+    #[error("unsupported instruction: {0:?}")]
+    Unsupported(Instruction),
+    #[error("unsupported init expression of global {index}: {err}")]
+    GlobalInit { index: usize, err: ConstExprError },
+    #[error("unsupported value of global {index}: {value:?}")]
+    GlobalValue { index: usize, value: ConstValue },
+    #[error("memory needs {pages} pages, but at most {max_pages} are available")]
+    MemoryTooLarge { pages: u64, max_pages: u32 },
+}
+
+pub type Result<T> = std::result::Result<T, CompileError>;
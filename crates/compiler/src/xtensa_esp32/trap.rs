@@ -0,0 +1,97 @@
+use crate::trap::TrapCode;
+
+use super::{asm::*, XtensaEsp32};
+
+/// Symbol of the trap handler, `void wasmicon_trap(uint32_t code, uint32_t
+/// func_index, uint32_t pc)`. It must not return.
+pub const TRAP_HANDLER_SYMBOL: &str = "wasmicon_trap";
+/// Symbol of the lowest address the stack pointer may reach, checked when
+/// entering a function. 0 disables the check.
+pub const STACK_LIMIT_SYMBOL: &str = "wasmicon_stack_limit";
+
+impl XtensaEsp32 {
+    /// Calls the trap handler with `code`, the index of the function being
+    /// compiled and the address of the call.
+    pub(super) fn trap(&mut self, insts_writer: &mut AsmWriter, code: TrapCode) {
+        let site_label = self.gen_symbol();
+        let site_literal = self.literal_symbol(&site_label);
+        let handler_literal = self.literal_symbol(TRAP_HANDLER_SYMBOL);
+
+        insts_writer
+            .label(site_label)
+            .inline_comment(format!("trap: {}", code))
+            .op("movi", vec![RegA(10), Imm(code.code() as i32)]);
+        match i32::try_from(self.func_index) {
+            Ok(index) if index < 2048 => {
+                insts_writer.op("movi", vec![RegA(11), Imm(index)]);
+            }
+            _ => {
+                let label = self.literal_i32(self.func_index as i32);
+                insts_writer.op("l32r", vec![RegA(11), Symbol(label)]);
+            }
+        }
+        insts_writer
+            .op("l32r", vec![RegA(12), Symbol(site_literal)])
+            .inline_comment("pc")
+            .op("l32r", vec![RegA(8), Symbol(handler_literal)])
+            .op("callx8", vec![RegA(8)]);
+    }
+
+    /// Traps if the stack pointer is below the stack limit.
+    pub(super) fn check_stack(&mut self, insts_writer: &mut AsmWriter) {
+        let ok_label = self.gen_symbol();
+        let limit_literal = self.literal_symbol(STACK_LIMIT_SYMBOL);
+
+        insts_writer
+            .op("l32r", vec![RegA(8), Symbol(limit_literal)])
+            .op("l32i", vec![RegA(8), RegA(8), Imm(0)])
+            .op("bgeu", vec![SP, RegA(8), Symbol(ok_label.clone())])
+            .inline_comment(format!(
+                "if sp >= {} then jump to {}",
+                STACK_LIMIT_SYMBOL, ok_label
+            ));
+        self.trap(insts_writer, TrapCode::CallStackExhausted);
+        insts_writer.label(ok_label);
+    }
+}
+
+/// Default C implementation of the trap handler and of the stack limit,
+/// which prints the trap with the ROM `ets_printf` and halts. Both are weak,
+/// so the firmware can define its own.
+pub fn trap_runtime() -> String {
+    let messages: String = TrapCode::ALL
+        .iter()
+        .map(|code| format!("    [{}] = \"{}\",\n", code.code(), code.message()))
+        .collect();
+
+    format!(
+        r#"// Default trap handler for code compiled by wasmicon.
+#include <inttypes.h>
+#include <stdint.h>
+
+extern int ets_printf(const char *fmt, ...);
+
+// Lowest address the stack pointer of wasm functions may reach, 0 to disable
+// the check.
+__attribute__((weak)) uint32_t {limit} = 0;
+
+static const char *const messages[] = {{
+{messages}}};
+
+__attribute__((weak, noreturn)) void {handler}(uint32_t code, uint32_t func_index, uint32_t pc) {{
+    const char *message = "unknown trap";
+    if (code < sizeof(messages) / sizeof(messages[0]) && messages[code]) {{
+        message = messages[code];
+    }}
+
+    ets_printf("wasm trap: %s (code %" PRIu32 ") in func %" PRIu32 " at 0x%08" PRIx32 "\n",
+               message, code, func_index, pc);
+    for (;;) {{
+    }}
+}}
+"#,
+        limit = STACK_LIMIT_SYMBOL,
+        handler = TRAP_HANDLER_SYMBOL,
+        messages = messages,
+    )
+}
@@ -0,0 +1,500 @@
+use compiler::{
+    trap::TrapCode,
+    xtensa_esp32::{CompileError, XtensaEsp32},
+};
+use wasm_parser::{
+    decoder::{
+        instructions::{Block, BlockType, Instruction},
+        types::ValueType,
+    },
+    parser::Parser,
+    text::WatParser,
+};
+
+fn try_compile_wat(text: &str) -> Result<String, CompileError> {
+    let module = Parser::new(WatParser::new(text).parse().unwrap())
+        .parse()
+        .unwrap();
+    XtensaEsp32::new().compile(module)
+}
+
+fn compile_wat(text: &str) -> String {
+    try_compile_wat(text).unwrap()
+}
+
+/// Whether the literal pool holds `value`.
+fn has_literal(asm: &str, value: &str) -> bool {
+    asm.lines()
+        .any(|line| line.starts_with("\t.literal\t") && line.ends_with(&format!(", {value}")))
+}
+
+/// Number of calls to the trap handler with `code`.
+fn traps(asm: &str, code: TrapCode) -> usize {
+    asm.matches(&format!("movi\ta10, {}\n", code.code()))
+        .count()
+}
+
+/// The opcodes of the emitted instructions, without operands.
+fn opcodes(asm: &str) -> Vec<&str> {
+    asm.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|opcode| !opcode.ends_with(':') && !opcode.starts_with('.'))
+        .collect()
+}
+
+#[test]
+fn compiles_i32_instructions() {
+    let binary = [
+        "eq", "ne", "lt_s", "lt_u", "gt_s", "gt_u", "le_s", "le_u", "ge_s", "ge_u", "mul", "div_s",
+        "div_u", "rem_s", "rem_u", "shr_s", "shr_u", "rotl", "rotr",
+    ];
+    let unary = ["eqz", "clz", "ctz", "popcnt", "extend8_s", "extend16_s"];
+    let funcs: String = binary
+        .iter()
+        .map(|op| format!("(func (param i32 i32) (result i32) local.get 0 local.get 1 i32.{op})"))
+        .chain(
+            unary
+                .iter()
+                .map(|op| format!("(func (param i32) (result i32) local.get 0 i32.{op})")),
+        )
+        .collect();
+    let asm = compile_wat(&format!("(module {funcs})"));
+    let opcodes = opcodes(&asm);
+
+    for opcode in [
+        "beq", "bne", "blt", "bltu", "bge", "bgeu", "mull", "quos", "quou", "rems", "remu", "ssr",
+        "sra", "srl", "src", "nsau", "sext",
+    ] {
+        assert!(opcodes.contains(&opcode), "missing {opcode}:\n{asm}");
+    }
+}
+
+/// What `i32.{op}` compiles to, from after popping its operands into a2 and a3
+/// to pushing its result.
+fn binary_op(op: &str) -> String {
+    let asm = compile_wat(&format!(
+        "(module (func (param i32 i32) (result i32) local.get 0 local.get 1 i32.{op}))"
+    ));
+    let pop = "\tl32i.n\ta3, a7, 0\n";
+    let start = asm.find(pop).unwrap() + pop.len();
+    let end = asm.rfind("\taddi\ta7, a7, 4\n").unwrap();
+
+    asm[start..end].to_string()
+}
+
+#[test]
+fn compiles_i32_comparisons_and_shifts() {
+    // The left-hand side is in a3, the right-hand side in a2.
+    for (op, branch) in [
+        ("eq", "beq\ta3, a2"),
+        ("lt_s", "blt\ta3, a2"),
+        ("gt_s", "blt\ta2, a3"),
+        ("le_u", "bgeu\ta2, a3"),
+        ("ge_s", "bge\ta3, a2"),
+    ] {
+        assert_eq!(
+            binary_op(op),
+            format!(
+                "\tmovi.n\ta4, 1
+\t{branch}, L5
+\tmovi.n\ta4, 0
+L5:
+\ts32i.n\ta4, a7, 0
+"
+            ),
+            "{op}"
+        );
+    }
+
+    for (op, shift) in [
+        ("shl", "ssl\ta2\n\tsll\ta2, a3"),
+        ("shr_s", "ssr\ta2\n\tsra\ta2, a3"),
+        ("shr_u", "ssr\ta2\n\tsrl\ta2, a3"),
+    ] {
+        assert_eq!(
+            binary_op(op),
+            format!("\t{shift}\n\ts32i.n\ta2, a7, 0\n"),
+            "{op}"
+        );
+    }
+}
+
+#[test]
+fn compiles_i32_division_checks() {
+    let divide_by_zero = TrapCode::IntegerDivideByZero.code();
+    let overflow = TrapCode::IntegerOverflow.code();
+    // Traps if the divisor is zero.
+    let check_zero = format!(
+        "\tbnez\ta2, L5
+L6:
+\tmovi\ta10, {divide_by_zero}
+\tmovi\ta11, 0
+\tl32r\ta12, L7
+\tl32r\ta8, L4
+\tcallx8\ta8
+L5:
+"
+    );
+
+    // INT_MIN / -1 overflows.
+    assert_eq!(
+        binary_op("div_s"),
+        format!(
+            "{check_zero}\taddi\ta4, a2, 1
+\tbnez\ta4, L8
+\tmovi.n\ta4, 1
+\tslli\ta4, a4, 31
+\tbne\ta3, a4, L8
+L9:
+\tmovi\ta10, {overflow}
+\tmovi\ta11, 0
+\tl32r\ta12, L10
+\tl32r\ta8, L4
+\tcallx8\ta8
+L8:
+\tquos\ta2, a3, a2
+\ts32i.n\ta2, a7, 0
+"
+        )
+    );
+    assert_eq!(
+        binary_op("div_u"),
+        format!("{check_zero}\tquou\ta2, a3, a2\n\ts32i.n\ta2, a7, 0\n")
+    );
+    // INT_MIN % -1 is 0: the result is replaced by a2 + 1 when that is 0.
+    assert_eq!(
+        binary_op("rem_s"),
+        format!(
+            "{check_zero}\taddi\ta4, a2, 1
+\trems\ta2, a3, a2
+\tmoveqz\ta2, a4, a4
+\ts32i.n\ta2, a7, 0
+"
+        )
+    );
+    assert_eq!(
+        binary_op("rem_u"),
+        format!("{check_zero}\tremu\ta2, a3, a2\n\ts32i.n\ta2, a7, 0\n")
+    );
+}
+
+#[test]
+fn traps_on_invalid_division() {
+    let asm = compile_wat(
+        "(module (func (param i32 i32) (result i32) local.get 0 local.get 1 i32.div_s))",
+    );
+
+    assert_eq!(traps(&asm, TrapCode::IntegerDivideByZero), 1, "{asm}");
+    assert_eq!(traps(&asm, TrapCode::IntegerOverflow), 1, "{asm}");
+    assert!(asm.contains("slli\ta4, a4, 31"), "{asm}");
+}
+
+#[test]
+fn rejects_unsupported_instructions() {
+    assert_eq!(
+        try_compile_wat("(module (func i32.const 1 f32.convert_i32_s drop))"),
+        Err(CompileError::Unsupported(Instruction::F32ConvertI32S))
+    );
+}
+
+#[test]
+fn rejects_try_table() {
+    let err = try_compile_wat("(module (func (try_table (result i32) i32.const 1) drop))");
+    assert_eq!(
+        err,
+        Err(CompileError::Unsupported(Instruction::TryTable {
+            block: Block {
+                block_type: BlockType::Value(vec![ValueType::I32])
+            },
+            catches: vec![]
+        }))
+    );
+}
+
+#[test]
+fn compiles_nop_to_nothing() {
+    assert_eq!(
+        compile_wat("(module (func nop nop))"),
+        compile_wat("(module (func))")
+    );
+}
+
+#[test]
+fn compiles_branches() {
+    let asm = compile_wat(
+        r#"
+        (module
+          (func (param $n i32) (result i32)
+            (local $i i32)
+            block $done
+              loop $next
+                local.get $i
+                local.get $n
+                i32.ge_s
+                br_if $done
+                local.get $i
+                i32.const 1
+                i32.add
+                local.set $i
+                br $next
+              end
+            end
+            local.get $i)
+          (func (param i32) (result i32)
+            block (result i32)
+              i32.const 7
+              local.get 0
+              br_table 0 1 0
+            end)
+          (func
+            return))
+        "#,
+    );
+    let opcodes = opcodes(&asm);
+
+    // wsp is saved by the block and the loop of the first function, and the
+    // block of the second.
+    assert_eq!(asm.matches("s32i\ta7, sp, ").count(), 3, "{asm}");
+    for opcode in ["beqz", "j", "bgeu", "addx4", "jx"] {
+        assert!(opcodes.contains(&opcode), "missing {opcode}:\n{asm}");
+    }
+    // The jump table is bounds-checked against its two entries.
+    assert_eq!(asm.matches("\t.word\tL").count(), 2, "{asm}");
+    assert!(asm.contains("movi\ta3, 2"), "{asm}");
+
+    // Params, locals, label slots and the wasm stack, below the save area.
+    assert_eq!(asm.matches("\tentry\tsp, ").count(), 3, "{asm}");
+    assert!(asm.contains("\tentry\tsp, 64\n"), "{asm}");
+    assert!(asm.contains("\tentry\tsp, 48\n"), "{asm}");
+    assert!(asm.contains("\tentry\tsp, 32\n"), "{asm}");
+
+    // Falling off the end returns the result in a2, a bare return pops nothing.
+    assert!(
+        asm.contains(
+            "\
+\tl32i.n\ta6, sp, 4
+\ts32i.n\ta6, a7, 0
+\taddi\ta7, a7, 4
+\taddi\ta7, a7, -4
+\tl32i.n\ta2, a7, 0
+\tretw.n\t
+\t.size\tfunc_0, .-func_0
+"
+        ),
+        "{asm}"
+    );
+    assert!(
+        asm.contains(
+            "\
+\taddi\ta7, sp, 0
+\tretw.n\t
+\tretw.n\t
+\t.size\tfunc_2, .-func_2
+"
+        ),
+        "{asm}"
+    );
+}
+
+#[test]
+fn loads_large_br_table_bounds_from_literals() {
+    let levels = "0 ".repeat(3000);
+    let asm = compile_wat(&format!(
+        "(module (func (param i32) block local.get 0 br_table {levels} 0 end))"
+    ));
+
+    assert!(has_literal(&asm, "3000"), "{asm}");
+    assert!(!asm.contains("movi\ta3, 3000"), "{asm}");
+}
+
+#[test]
+fn sizes_frames_by_stack_height() {
+    let consts = "i32.const 1\n".repeat(100);
+    let adds = "i32.add\n".repeat(99);
+    let asm = compile_wat(&format!("(module (func (result i32) {consts} {adds}))"));
+
+    assert!(asm.contains("\tentry\tsp, 432\n"), "{asm}");
+}
+
+#[test]
+#[should_panic(expected = "entry allows at most 32760")]
+fn rejects_frames_entry_cannot_allocate() {
+    let consts = "i32.const 1\n".repeat(8192);
+    let drops = "drop\n".repeat(8192);
+    compile_wat(&format!("(module (func {consts} {drops}))"));
+}
+
+#[test]
+fn reserves_linear_memory() {
+    let wat = r#"
+        (module
+          (memory 1 4)
+          (data (i32.const 16) "hi")
+          (data (i32.const 4096) "\01")
+          (func (param i32) (result i32)
+            local.get 0
+            i32.load offset=2000
+            local.get 0
+            i32.load16_s offset=3 align=1
+            i32.add
+            local.get 0
+            local.get 0
+            i32.store8 offset=1
+            i32.const 1
+            memory.grow
+            i32.add))
+        "#;
+    let asm = compile_wat(wat);
+
+    // The offset doesn't fit the immediate, the aligned load checks the
+    // hint at runtime, the unaligned one is done byte by byte.
+    assert!(has_literal(&asm, "2000"), "{asm}");
+    assert!(asm.contains("extui\ta3, a2, 0, 2\n\tbnez\ta3, "), "{asm}");
+    assert!(asm.contains("l32i\ta5, a2, 0"), "{asm}");
+    assert_eq!(opcodes(&asm).iter().filter(|op| **op == "extui").count(), 1);
+    assert!(asm.contains("l8ui\ta4, a2, 4"), "{asm}");
+    assert!(asm.contains("s8i\ta5, a2, 1"), "{asm}");
+    assert!(asm.ends_with(
+        "\
+wasmicon_memory:
+\t.space\t16
+\t.byte\t104, 105
+\t.space\t4078
+\t.byte\t1
+\t.space\t126975
+\t.section\t.data
+\t.align\t4
+\t.global\twasmicon_memory_pages
+wasmicon_memory_pages:
+\t.word\t1
+\t.section\t.rodata
+\t.align\t4
+\t.global\twasmicon_memory_bounds_checked
+wasmicon_memory_bounds_checked:
+\t.word\t0
+"
+    ));
+
+    // memory.grow can reach the page budget, not the declared maximum.
+    let module = Parser::new(WatParser::new(wat).parse().unwrap())
+        .parse()
+        .unwrap();
+    let asm = XtensaEsp32::new()
+        .with_max_memory_pages(3)
+        .compile(module)
+        .unwrap();
+    assert!(asm.contains("movi\ta6, 3"), "{asm}");
+
+    // Without memory.grow, only the minimum is reserved.
+    let asm = compile_wat("(module (memory 1 4))");
+    assert!(asm.contains(".section\t.bss"), "{asm}");
+    assert!(asm.contains(".space\t65536"), "{asm}");
+}
+
+#[test]
+fn rejects_memories_above_the_page_budget() {
+    assert_eq!(
+        try_compile_wat("(module (memory 3))"),
+        Err(CompileError::MemoryTooLarge {
+            pages: 3,
+            max_pages: 2
+        })
+    );
+}
+
+#[test]
+fn compiles_offsets_above_i32_max() {
+    let asm = compile_wat(
+        r#"
+        (module
+          (memory 1)
+          (func (param i32) (result i32)
+            local.get 0
+            i32.load offset=4294967295))
+        "#,
+    );
+
+    assert!(has_literal(&asm, "-1"), "{asm}");
+}
+
+#[test]
+fn loads_large_page_limits_from_literals() {
+    let wat = "(module (memory 1) (func (result i32) i32.const 1 memory.grow))";
+    let module = Parser::new(WatParser::new(wat).parse().unwrap())
+        .parse()
+        .unwrap();
+    let asm = XtensaEsp32::new()
+        .with_max_memory_pages(3000)
+        .compile(module)
+        .unwrap();
+
+    assert!(has_literal(&asm, "3000"), "{asm}");
+    assert!(!asm.contains("movi\ta6, 3000"), "{asm}");
+}
+
+#[test]
+fn checks_memory_bounds() {
+    let wat = r#"
+        (module
+          (memory 1)
+          (func (param i32) (result i32)
+            local.get 0
+            i32.load8_u offset=7
+            local.get 0
+            i32.const 0
+            i32.const 16
+            memory.fill))
+        "#;
+    let compile = |bounds_checks| {
+        let module = Parser::new(WatParser::new(wat).parse().unwrap())
+            .parse()
+            .unwrap();
+        XtensaEsp32::new()
+            .with_bounds_checks(bounds_checks)
+            .compile(module)
+            .unwrap()
+    };
+
+    let asm = compile(false);
+    assert_eq!(traps(&asm, TrapCode::MemoryOutOfBounds), 0, "{asm}");
+    assert!(asm.ends_with("wasmicon_memory_bounds_checked:\n\t.word\t0\n"));
+
+    // One check for the load, covering its offset and width, one for the fill.
+    let asm = compile(true);
+    assert_eq!(traps(&asm, TrapCode::MemoryOutOfBounds), 2, "{asm}");
+    assert!(has_literal(&asm, "8"), "{asm}");
+    assert!(asm.ends_with("wasmicon_memory_bounds_checked:\n\t.word\t1\n"));
+
+    // The firmware can check modules without memory too.
+    let asm = compile_wat("(module (func))");
+    assert!(!asm.contains("wasmicon_memory:"), "{asm}");
+    assert!(asm.ends_with("wasmicon_memory_bounds_checked:\n\t.word\t0\n"));
+}
+
+#[test]
+fn calls_the_trap_handler() {
+    let asm = compile_wat(
+        r#"
+        (module
+          (import "wasmicon" "sleep_ms" (func (param i32)))
+          (func unreachable))
+        "#,
+    );
+
+    // The stack check on entry and `unreachable` both report the function
+    // index, counting imports.
+    assert_eq!(traps(&asm, TrapCode::CallStackExhausted), 1, "{asm}");
+    assert_eq!(traps(&asm, TrapCode::Unreachable), 1, "{asm}");
+    assert_eq!(asm.matches("movi\ta11, 1\n").count(), 2, "{asm}");
+    assert!(has_literal(&asm, "wasmicon_trap"), "{asm}");
+    assert!(has_literal(&asm, "wasmicon_stack_limit"), "{asm}");
+
+    let runtime = compiler::xtensa_esp32::trap_runtime();
+    for code in TrapCode::ALL {
+        assert_eq!(TrapCode::from_code(code.code()), Some(code));
+        assert!(runtime.contains(&format!("[{}] = \"{}\"", code.code(), code)));
+    }
+    // uint32_t isn't unsigned int on every target.
+    assert!(runtime.contains("#include <inttypes.h>\n"));
+    assert!(!runtime.contains("%u") && !runtime.contains("%08x"));
+}
@@ -0,0 +1,245 @@
+use wasm_parser::{
+    decoder::{
+        instructions::Instruction,
+        types::{FuncType, GlobalType, ImportDesc, Limits, RefType, TableType, ValueType},
+    },
+    parser::module::Module,
+    validator::{accessed_memory, operand_types},
+};
+
+use super::Op;
+
+/// Operand and result types of an operation.
+pub type Signature = (Vec<ValueType>, Vec<ValueType>);
+
+/// Index spaces of a module, imports included, that the operations refer to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleEnv {
+    pub types: Vec<FuncType>,
+    pub funcs: Vec<FuncType>,
+    pub tables: Vec<TableType>,
+    pub memories: Vec<Limits>,
+    pub globals: Vec<GlobalType>,
+}
+
+impl ModuleEnv {
+    pub fn new(module: &Module) -> Self {
+        let mut env = ModuleEnv {
+            types: module.types.clone(),
+            funcs: vec![],
+            tables: vec![],
+            memories: vec![],
+            globals: vec![],
+        };
+
+        for import in &module.imports {
+            match &import.desc {
+                ImportDesc::Func(type_index) => {
+                    env.funcs.push(module.types[*type_index as usize].clone())
+                }
+                ImportDesc::Table(table_type) => env.tables.push(table_type.clone()),
+                ImportDesc::Memory(limits) => env.memories.push(limits.clone()),
+                ImportDesc::Global(global_type) => env.globals.push(global_type.clone()),
+                ImportDesc::Tag(_) => {}
+            }
+        }
+        env.funcs
+            .extend(module.functions.iter().map(|func| FuncType {
+                params: func.params.clone(),
+                results: func.results.clone(),
+            }));
+        env.tables.extend(module.tables.iter().cloned());
+        env.memories
+            .extend(module.memories.iter().map(|memory| memory.limits.clone()));
+        env.globals.extend(
+            module
+                .globals
+                .iter()
+                .map(|global| global.global_type.clone()),
+        );
+
+        env
+    }
+
+    /// Returns the operand and result types of `op`, or `None` if it refers to
+    /// an index out of bounds or isn't an instruction of its kind.
+    pub fn signature(&self, op: &Op) -> Option<Signature> {
+        use ValueType::I32;
+
+        let signature = match op {
+            Op::Const(constant) => (vec![], vec![constant.value_type()]),
+            Op::Numeric(instr) => {
+                if accessed_memory(instr).is_some() {
+                    return None;
+                }
+                let (params, results) = operand_types(instr)?;
+                (params.to_vec(), results.to_vec())
+            }
+            Op::TrapIf(_) => (vec![I32], vec![]),
+            Op::Select(value_type) => (
+                vec![value_type.clone(), value_type.clone(), I32],
+                vec![value_type.clone()],
+            ),
+            Op::RefNull(ref_type) => (vec![], vec![ref_value_type(*ref_type)]),
+            Op::RefIsNull(ref_type) => (vec![ref_value_type(*ref_type)], vec![I32]),
+            Op::RefFunc(func) => {
+                self.funcs.get(*func as usize)?;
+                (vec![], vec![ValueType::FuncRef])
+            }
+            Op::GlobalGet(global) => (vec![], vec![self.global(*global)?.value_type.clone()]),
+            Op::GlobalSet(global) => (vec![self.global(*global)?.value_type.clone()], vec![]),
+
+            Op::Load(instr) | Op::Store(instr) | Op::MemoryAccess(instr) => {
+                if memory_op(instr).as_ref() != Some(op) {
+                    return None;
+                }
+                let (params, results) = operand_types(instr)?;
+
+                let mut operands = vec![self.memory_addr_type(accessed_memory(instr)?)?];
+                operands.extend_from_slice(params);
+                (operands, results.to_vec())
+            }
+            Op::AtomicFence => (vec![], vec![]),
+            Op::MemorySize(memory) => (vec![], vec![self.memory_addr_type(*memory)?]),
+            Op::MemoryGrow(memory) => {
+                let addr_type = self.memory_addr_type(*memory)?;
+                (vec![addr_type.clone()], vec![addr_type])
+            }
+            Op::MemoryFill(memory) => {
+                let addr_type = self.memory_addr_type(*memory)?;
+                (vec![addr_type.clone(), I32, addr_type], vec![])
+            }
+            Op::MemoryCopy { dst, src } => {
+                let dst = self.memory_addr_type(*dst)?;
+                let src = self.memory_addr_type(*src)?;
+                (copy_operands(dst, src), vec![])
+            }
+            Op::MemoryInit { memory, .. } => {
+                (vec![self.memory_addr_type(*memory)?, I32, I32], vec![])
+            }
+            Op::DataDrop(_) => (vec![], vec![]),
+
+            Op::TableGet(table) => {
+                let table = self.table(*table)?;
+                (
+                    vec![addr_type(&table.limits)],
+                    vec![ref_value_type(table.element_type)],
+                )
+            }
+            Op::TableSet(table) => {
+                let table = self.table(*table)?;
+                (
+                    vec![addr_type(&table.limits), ref_value_type(table.element_type)],
+                    vec![],
+                )
+            }
+            Op::TableSize(table) => (vec![], vec![addr_type(&self.table(*table)?.limits)]),
+            Op::TableGrow(table) => {
+                let table = self.table(*table)?;
+                let addr_type = addr_type(&table.limits);
+                (
+                    vec![ref_value_type(table.element_type), addr_type.clone()],
+                    vec![addr_type],
+                )
+            }
+            Op::TableFill(table) => {
+                let table = self.table(*table)?;
+                let addr_type = addr_type(&table.limits);
+                (
+                    vec![
+                        addr_type.clone(),
+                        ref_value_type(table.element_type),
+                        addr_type,
+                    ],
+                    vec![],
+                )
+            }
+            Op::TableCopy { dst, src } => {
+                let dst = addr_type(&self.table(*dst)?.limits);
+                let src = addr_type(&self.table(*src)?.limits);
+                (copy_operands(dst, src), vec![])
+            }
+            Op::TableInit { table, .. } => (
+                vec![addr_type(&self.table(*table)?.limits), I32, I32],
+                vec![],
+            ),
+            Op::ElemDrop(_) => (vec![], vec![]),
+
+            Op::Call(func) => {
+                let func_type = self.funcs.get(*func as usize)?;
+                (func_type.params.clone(), func_type.results.clone())
+            }
+            Op::CallIndirect { type_index, table } => {
+                let func_type = self.types.get(*type_index as usize)?;
+                let mut params = func_type.params.clone();
+                params.push(addr_type(&self.table(*table)?.limits));
+                (params, func_type.results.clone())
+            }
+        };
+
+        Some(signature)
+    }
+
+    fn table(&self, table: u32) -> Option<&TableType> {
+        self.tables.get(table as usize)
+    }
+
+    fn global(&self, global: u32) -> Option<&GlobalType> {
+        self.globals.get(global as usize)
+    }
+
+    fn memory_addr_type(&self, memory: u32) -> Option<ValueType> {
+        self.memories.get(memory as usize).map(addr_type)
+    }
+}
+
+/// Type of the addresses of a memory or table.
+fn addr_type(limits: &Limits) -> ValueType {
+    match limits.index64 {
+        true => ValueType::I64,
+        false => ValueType::I32,
+    }
+}
+
+/// Destination, source and length operands of `memory.copy` and `table.copy`.
+/// The length is 64-bit only if both are.
+fn copy_operands(dst: ValueType, src: ValueType) -> Vec<ValueType> {
+    let len = match (&dst, &src) {
+        (ValueType::I64, ValueType::I64) => ValueType::I64,
+        _ => ValueType::I32,
+    };
+
+    vec![dst, src, len]
+}
+
+/// The memory operation of a memory access instruction: a load, a store or
+/// another access.
+pub(crate) fn memory_op(instr: &Instruction) -> Option<Op> {
+    accessed_memory(instr)?;
+    let (params, results) = operand_types(instr)?;
+
+    let op = match (params.len(), results.len()) {
+        (0, 1) => Op::Load(instr.clone()),
+        (1, 0) => Op::Store(instr.clone()),
+        _ => Op::MemoryAccess(instr.clone()),
+    };
+
+    Some(op)
+}
+
+pub(crate) fn ref_type(value_type: &ValueType) -> RefType {
+    match value_type {
+        ValueType::FuncRef => RefType::FuncRef,
+        ValueType::ExternRef => RefType::ExternRef,
+        ValueType::ExnRef => RefType::ExnRef,
+        value_type => panic!("{:?} is not a reference type", value_type),
+    }
+}
+
+pub(crate) fn ref_value_type(ref_type: RefType) -> ValueType {
+    match ref_type {
+        RefType::FuncRef => ValueType::FuncRef,
+        RefType::ExternRef => ValueType::ExternRef,
+        RefType::ExnRef => ValueType::ExnRef,
+    }
+}
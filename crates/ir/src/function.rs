@@ -0,0 +1,159 @@
+use std::iter;
+
+use wasm_parser::decoder::types::ValueType;
+
+use super::{Inst, TrapCode};
+
+/// SSA value, defined once either as a block parameter or as the result of
+/// an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Value(pub u32);
+
+/// Basic block, an index into [`Function::blocks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Block(pub u32);
+
+/// Function in SSA form. Wasm locals and operand stack entries are values:
+/// where control flow joins, they are passed as block arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Function index, imported functions included.
+    pub index: u32,
+    /// Assembly label of the function.
+    pub name: String,
+    pub params: Vec<ValueType>,
+    pub results: Vec<ValueType>,
+    /// Basic blocks. The first one is the entry, whose parameters are the
+    /// function parameters, and it's never branched to.
+    pub blocks: Vec<BlockData>,
+    /// Type of every value, indexed by the value.
+    pub value_types: Vec<ValueType>,
+}
+
+impl Function {
+    pub fn block(&self, block: Block) -> &BlockData {
+        &self.blocks[block.0 as usize]
+    }
+
+    pub fn value_type(&self, value: Value) -> &ValueType {
+        &self.value_types[value.0 as usize]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockData {
+    pub params: Vec<Value>,
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+}
+
+/// Branch to a block, with the values of its parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCall {
+    pub block: Block,
+    pub args: Vec<Value>,
+}
+
+/// Last instruction of a basic block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockCall),
+    /// Jumps to `then_dest` if the `i32` condition isn't zero, to `else_dest`
+    /// otherwise.
+    Branch {
+        cond: Value,
+        then_dest: BlockCall,
+        else_dest: BlockCall,
+    },
+    /// Jumps to `targets[index]`, or to `default` if the `i32` index is out of
+    /// range.
+    Switch {
+        index: Value,
+        targets: Vec<BlockCall>,
+        default: BlockCall,
+    },
+    Return(Vec<Value>),
+    ReturnCall {
+        func: u32,
+        args: Vec<Value>,
+    },
+    ReturnCallIndirect {
+        type_index: u32,
+        table: u32,
+        args: Vec<Value>,
+        /// Index in the table.
+        callee: Value,
+    },
+    Trap(TrapCode),
+}
+
+impl Terminator {
+    /// Branches to other blocks.
+    pub fn dests(&self) -> Vec<&BlockCall> {
+        match self {
+            Terminator::Jump(dest) => vec![dest],
+            Terminator::Branch {
+                then_dest,
+                else_dest,
+                ..
+            } => vec![then_dest, else_dest],
+            Terminator::Switch {
+                targets, default, ..
+            } => targets.iter().chain([default]).collect(),
+            Terminator::Return(_)
+            | Terminator::ReturnCall { .. }
+            | Terminator::ReturnCallIndirect { .. }
+            | Terminator::Trap(_) => vec![],
+        }
+    }
+
+    pub fn dests_mut(&mut self) -> Vec<&mut BlockCall> {
+        match self {
+            Terminator::Jump(dest) => vec![dest],
+            Terminator::Branch {
+                then_dest,
+                else_dest,
+                ..
+            } => vec![then_dest, else_dest],
+            Terminator::Switch {
+                targets, default, ..
+            } => targets.iter_mut().chain([default]).collect(),
+            Terminator::Return(_)
+            | Terminator::ReturnCall { .. }
+            | Terminator::ReturnCallIndirect { .. }
+            | Terminator::Trap(_) => vec![],
+        }
+    }
+
+    /// Values used by the terminator, block arguments included.
+    pub fn args(&self) -> Vec<Value> {
+        match self {
+            Terminator::Jump(dest) => dest.args.clone(),
+            Terminator::Branch {
+                cond,
+                then_dest,
+                else_dest,
+            } => iter::once(cond)
+                .chain(&then_dest.args)
+                .chain(&else_dest.args)
+                .copied()
+                .collect(),
+            Terminator::Switch {
+                index,
+                targets,
+                default,
+            } => iter::once(index)
+                .chain(targets.iter().flat_map(|target| &target.args))
+                .chain(&default.args)
+                .copied()
+                .collect(),
+            Terminator::Return(values) | Terminator::ReturnCall { args: values, .. } => {
+                values.clone()
+            }
+            Terminator::ReturnCallIndirect { args, callee, .. } => {
+                args.iter().chain([callee]).copied().collect()
+            }
+            Terminator::Trap(_) => vec![],
+        }
+    }
+}
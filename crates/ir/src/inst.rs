@@ -0,0 +1,212 @@
+use wasm_parser::decoder::{
+    instructions::Instruction,
+    types::{RefType, ValueType},
+};
+
+use super::{TrapCode, Value};
+
+/// Instruction of a basic block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inst {
+    pub op: Op,
+    /// Operands, in the order they were on the wasm operand stack.
+    pub args: Vec<Value>,
+    pub results: Vec<Value>,
+}
+
+/// Operation of an instruction, with its immediates. The operand and result
+/// types of every operation are given by [`ModuleEnv::signature`](super::ModuleEnv::signature).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Const(Constant),
+    /// Numeric or vector instruction other than constants and memory accesses,
+    /// e.g. `i32.add`. It never traps: divisions and float to integer
+    /// truncations are preceded by `trap_if` checks.
+    Numeric(Instruction),
+    /// Traps if the `i32` operand isn't zero.
+    TrapIf(TrapCode),
+    /// Picks the first operand if the `i32` condition isn't zero, the second
+    /// otherwise.
+    Select(ValueType),
+
+    RefNull(RefType),
+    /// Operand type.
+    RefIsNull(RefType),
+    RefFunc(u32),
+
+    GlobalGet(u32),
+    GlobalSet(u32),
+
+    /// Load instruction, including atomic loads. The operand is the address.
+    Load(Instruction),
+    /// Store instruction, including atomic and lane stores. The operands are
+    /// the address and the stored value.
+    Store(Instruction),
+    /// Other memory accesses: lane loads, atomic read-modify-writes, waits and
+    /// notifies. The first operand is the address.
+    MemoryAccess(Instruction),
+    AtomicFence,
+    MemorySize(u32),
+    MemoryGrow(u32),
+    MemoryFill(u32),
+    MemoryCopy {
+        dst: u32,
+        src: u32,
+    },
+    MemoryInit {
+        data: u32,
+        memory: u32,
+    },
+    DataDrop(u32),
+
+    TableGet(u32),
+    TableSet(u32),
+    TableSize(u32),
+    TableGrow(u32),
+    TableFill(u32),
+    TableCopy {
+        dst: u32,
+        src: u32,
+    },
+    TableInit {
+        element: u32,
+        table: u32,
+    },
+    ElemDrop(u32),
+
+    Call(u32),
+    /// The operands are the arguments followed by the index in the table.
+    CallIndirect {
+        type_index: u32,
+        table: u32,
+    },
+}
+
+impl Op {
+    /// The wasm instruction of the operation, `None` for `trap_if`.
+    pub fn instruction(&self) -> Option<Instruction> {
+        let instr = match self {
+            Op::Const(constant) => constant.instruction(),
+            Op::Numeric(instr) | Op::Load(instr) | Op::Store(instr) | Op::MemoryAccess(instr) => {
+                instr.clone()
+            }
+            Op::TrapIf(_) => return None,
+            Op::Select(value_type) => Instruction::SelectResult {
+                result_type: value_type.clone(),
+            },
+            Op::RefNull(ref_type) => Instruction::RefNull {
+                ref_type: *ref_type,
+            },
+            Op::RefIsNull(_) => Instruction::RefIsNull,
+            Op::RefFunc(func_index) => Instruction::RefFunc {
+                func_index: *func_index,
+            },
+            Op::GlobalGet(global_index) => Instruction::GlobalGet {
+                global_index: *global_index,
+            },
+            Op::GlobalSet(global_index) => Instruction::GlobalSet {
+                global_index: *global_index,
+            },
+            Op::AtomicFence => Instruction::AtomicFence,
+            Op::MemorySize(memory_index) => Instruction::MemorySize {
+                memory_index: *memory_index,
+            },
+            Op::MemoryGrow(memory_index) => Instruction::MemoryGrow {
+                memory_index: *memory_index,
+            },
+            Op::MemoryFill(memory_index) => Instruction::MemoryFill {
+                memory_index: *memory_index,
+            },
+            Op::MemoryCopy { dst, src } => Instruction::MemoryCopy {
+                dst_memory_index: *dst,
+                src_memory_index: *src,
+            },
+            Op::MemoryInit { data, memory } => Instruction::MemoryInit {
+                data_index: *data,
+                memory_index: *memory,
+            },
+            Op::DataDrop(data_index) => Instruction::DataDrop {
+                data_index: *data_index,
+            },
+            Op::TableGet(table_index) => Instruction::TableGet {
+                table_index: *table_index,
+            },
+            Op::TableSet(table_index) => Instruction::TableSet {
+                table_index: *table_index,
+            },
+            Op::TableSize(table_index) => Instruction::TableSize {
+                table_index: *table_index,
+            },
+            Op::TableGrow(table_index) => Instruction::TableGrow {
+                table_index: *table_index,
+            },
+            Op::TableFill(table_index) => Instruction::TableFill {
+                table_index: *table_index,
+            },
+            Op::TableCopy { dst, src } => Instruction::TableCopy {
+                dst_table_index: *dst,
+                src_table_index: *src,
+            },
+            Op::TableInit { element, table } => Instruction::TableInit {
+                element_index: *element,
+                table_index: *table,
+            },
+            Op::ElemDrop(element_index) => Instruction::ElemDrop {
+                element_index: *element_index,
+            },
+            Op::Call(func_index) => Instruction::Call {
+                func_index: *func_index,
+            },
+            Op::CallIndirect { type_index, table } => Instruction::CallIndirect {
+                type_index: *type_index,
+                table_index: *table,
+            },
+        };
+
+        Some(instr)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+}
+
+impl Constant {
+    /// Zero of a numeric or vector type, the initial value of locals.
+    pub fn zero(value_type: &ValueType) -> Option<Self> {
+        match value_type {
+            ValueType::I32 => Some(Constant::I32(0)),
+            ValueType::I64 => Some(Constant::I64(0)),
+            ValueType::F32 => Some(Constant::F32(0.0)),
+            ValueType::F64 => Some(Constant::F64(0.0)),
+            ValueType::V128 => Some(Constant::V128(0)),
+            ValueType::FuncRef | ValueType::ExternRef | ValueType::ExnRef => None,
+        }
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Constant::I32(_) => ValueType::I32,
+            Constant::I64(_) => ValueType::I64,
+            Constant::F32(_) => ValueType::F32,
+            Constant::F64(_) => ValueType::F64,
+            Constant::V128(_) => ValueType::V128,
+        }
+    }
+
+    /// The `*.const` instruction of the constant.
+    pub fn instruction(&self) -> Instruction {
+        match *self {
+            Constant::I32(value) => Instruction::I32Const { value },
+            Constant::I64(value) => Instruction::I64Const { value },
+            Constant::F32(value) => Instruction::F32Const { value },
+            Constant::F64(value) => Instruction::F64Const { value },
+            Constant::V128(value) => Instruction::V128Const { value },
+        }
+    }
+}
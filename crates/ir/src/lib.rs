@@ -0,0 +1,20 @@
+//! Typed SSA intermediate representation between the parser and the
+//! backends: functions are basic blocks with parameters, and instructions
+//! operate on values instead of the wasm operand stack.
+
+mod env;
+mod function;
+mod inst;
+mod lower;
+mod print;
+mod result;
+mod trap;
+mod verifier;
+
+pub use env::{ModuleEnv, Signature};
+pub use function::*;
+pub use inst::*;
+pub use lower::*;
+pub use result::*;
+pub use trap::*;
+pub use verifier::*;
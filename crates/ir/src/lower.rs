@@ -0,0 +1,707 @@
+use std::{collections::HashMap, mem};
+
+use wasm_parser::{
+    decoder::{
+        instructions::Instruction,
+        types::{FuncType, ValueType},
+    },
+    parser::module::{self as parsed, Node},
+};
+
+use super::{
+    env::{memory_op, ref_type},
+    Block, BlockCall, BlockData, Constant, Function, Inst, LowerError, ModuleEnv, Op, Terminator,
+    TrapCode, Value,
+};
+
+/// Lowers the functions defined in `module`, which must be valid.
+pub fn lower_module(module: &parsed::Module) -> Result<Vec<Function>, LowerError> {
+    let env = ModuleEnv::new(module);
+
+    module
+        .functions
+        .iter()
+        .map(|func| lower_function(&env, func))
+        .collect()
+}
+
+/// Lowers a function of a valid module to SSA form.
+///
+/// Locals and operand stack entries become values. At the start of loops and
+/// at the end of blocks that are branched to, all of them are passed as block
+/// arguments, even if they don't change.
+pub fn lower_function(env: &ModuleEnv, func: &parsed::Function) -> Result<Function, LowerError> {
+    let mut lowerer = Lowerer {
+        env,
+        func_index: func.index as u32,
+        blocks: vec![],
+        layout: vec![],
+        value_types: vec![],
+        current: None,
+        local_types: func.params_locals.clone(),
+        locals: vec![],
+        stack: vec![],
+        frames: vec![],
+    };
+
+    let entry = lowerer.create_block(func.params.clone(), false);
+    lowerer.switch_to(entry);
+    lowerer.locals = mem::take(&mut lowerer.stack);
+    for local_type in &func.locals {
+        let op = match Constant::zero(local_type) {
+            Some(constant) => Op::Const(constant),
+            None => Op::RefNull(ref_type(local_type)),
+        };
+        let value = lowerer.emit(op, vec![])[0];
+        lowerer.locals.push(value);
+    }
+
+    lowerer.frames.push(Frame {
+        kind: FrameKind::Function,
+        label: None,
+        label_types: func.results.clone(),
+        height: 0,
+    });
+    lowerer.nodes(&func.body)?;
+    lowerer.end_frame();
+
+    Ok(Function {
+        index: func.index as u32,
+        name: func.label.clone(),
+        params: func.params.clone(),
+        results: func.results.clone(),
+        blocks: lowerer.finish(),
+        value_types: lowerer.value_types,
+    })
+}
+
+#[derive(Debug, Default)]
+struct PendingBlock {
+    param_types: Vec<ValueType>,
+    /// Whether the first parameters are the values of the locals.
+    carries_locals: bool,
+    params: Vec<Value>,
+    insts: Vec<Inst>,
+    terminator: Option<Terminator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Function,
+    /// `block` or `if`.
+    Block,
+    Loop,
+}
+
+/// Entry of the control stack.
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    /// Block that branches to the label go to. For blocks, it's created by the
+    /// first branch; for the function, it's only needed by conditional
+    /// branches, other branches return directly.
+    label: Option<Block>,
+    /// Types of the values passed to the label: the results, or the
+    /// parameters for loops.
+    label_types: Vec<ValueType>,
+    /// Height of the operand stack when the block was entered, without its
+    /// parameters.
+    height: usize,
+}
+
+struct Lowerer<'e> {
+    env: &'e ModuleEnv,
+    func_index: u32,
+    blocks: Vec<PendingBlock>,
+    /// Blocks in the order they were started, which becomes their final order.
+    layout: Vec<Block>,
+    value_types: Vec<ValueType>,
+    /// Block instructions are added to, `None` in unreachable code.
+    current: Option<Block>,
+    local_types: Vec<ValueType>,
+    /// Current value of every local, parameters included.
+    locals: Vec<Value>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+}
+
+impl Lowerer<'_> {
+    fn nodes(&mut self, nodes: &[Node]) -> Result<(), LowerError> {
+        for node in nodes {
+            // The rest of the block is unreachable.
+            if self.current.is_none() {
+                break;
+            }
+            self.node(node)?;
+        }
+
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), LowerError> {
+        match node {
+            Node::Instruction(instr) => self.instruction(instr)?,
+            Node::Block {
+                block_type, body, ..
+            } => {
+                self.push_frame(FrameKind::Block, block_type, None);
+                self.nodes(body)?;
+                self.end_frame();
+            }
+            Node::Loop {
+                block_type, body, ..
+            } => {
+                let mut param_types = self.local_types.clone();
+                param_types.extend_from_slice(&block_type.params);
+                let header = self.create_block(param_types, true);
+
+                let mut args = self.locals.clone();
+                args.extend(self.pop_n(block_type.params.len()));
+                self.terminate(Terminator::Jump(BlockCall {
+                    block: header,
+                    args,
+                }));
+                self.switch_to(header);
+
+                self.push_frame(FrameKind::Loop, block_type, Some(header));
+                self.nodes(body)?;
+                self.end_frame();
+            }
+            Node::If {
+                block_type,
+                then_body,
+                else_body,
+                ..
+            } => {
+                let cond = self.pop();
+                self.push_frame(FrameKind::Block, block_type, None);
+                let height = self.frames.last().unwrap().height;
+                let entry_locals = self.locals.clone();
+                let entry_params = self.stack[height..].to_vec();
+
+                let then_block = self.create_block(vec![], false);
+                // Without `else`, the parameters are passed on as the results.
+                let else_dest = match else_body {
+                    Some(_) => BlockCall {
+                        block: self.create_block(vec![], false),
+                        args: vec![],
+                    },
+                    None => self.branch_dest(0),
+                };
+                self.terminate(Terminator::Branch {
+                    cond,
+                    then_dest: BlockCall {
+                        block: then_block,
+                        args: vec![],
+                    },
+                    else_dest: else_dest.clone(),
+                });
+
+                self.switch_to(then_block);
+                self.nodes(then_body)?;
+
+                if let Some(else_body) = else_body {
+                    if self.current.is_some() {
+                        let dest = self.branch_dest(0);
+                        self.terminate(Terminator::Jump(dest));
+                    }
+
+                    self.locals = entry_locals;
+                    self.stack.truncate(height);
+                    self.stack.extend(entry_params);
+                    self.switch_to(else_dest.block);
+                    self.nodes(else_body)?;
+                }
+
+                self.end_frame();
+            }
+            Node::TryTable { .. } => return Err(self.unsupported("try_table")),
+        }
+
+        Ok(())
+    }
+
+    fn instruction(&mut self, instr: &Instruction) -> Result<(), LowerError> {
+        match instr {
+            Instruction::Unreachable => self.terminate(Terminator::Trap(TrapCode::Unreachable)),
+            Instruction::Nop => {}
+            Instruction::Block { .. }
+            | Instruction::Loop { .. }
+            | Instruction::If { .. }
+            | Instruction::TryTable { .. }
+            | Instruction::Else
+            | Instruction::End => unreachable!("blocks are nodes of the body"),
+            Instruction::Br { level } => self.br(*level),
+            Instruction::BrIf { level } => {
+                let cond = self.pop();
+                let then_dest = self.branch_dest(*level);
+                let next = self.create_block(vec![], false);
+                self.terminate(Terminator::Branch {
+                    cond,
+                    then_dest,
+                    else_dest: BlockCall {
+                        block: next,
+                        args: vec![],
+                    },
+                });
+                self.switch_to(next);
+            }
+            Instruction::BrTable {
+                label_indexes,
+                default_index,
+            } => {
+                let index = self.pop();
+                let targets = label_indexes
+                    .iter()
+                    .map(|level| self.branch_dest(*level))
+                    .collect();
+                let default = self.branch_dest(*default_index);
+                self.terminate(Terminator::Switch {
+                    index,
+                    targets,
+                    default,
+                });
+            }
+            Instruction::Return => self.br(self.frames.len() as u32 - 1),
+            Instruction::Call { func_index } => self.op(Op::Call(*func_index)),
+            Instruction::CallIndirect {
+                type_index,
+                table_index,
+            } => self.op(Op::CallIndirect {
+                type_index: *type_index,
+                table: *table_index,
+            }),
+            Instruction::ReturnCall { func_index } => {
+                let params = self.env.funcs[*func_index as usize].params.len();
+                let args = self.pop_n(params);
+                self.terminate(Terminator::ReturnCall {
+                    func: *func_index,
+                    args,
+                });
+            }
+            Instruction::ReturnCallIndirect {
+                type_index,
+                table_index,
+            } => {
+                let callee = self.pop();
+                let params = self.env.types[*type_index as usize].params.len();
+                let args = self.pop_n(params);
+                self.terminate(Terminator::ReturnCallIndirect {
+                    type_index: *type_index,
+                    table: *table_index,
+                    args,
+                    callee,
+                });
+            }
+            Instruction::Throw { .. } => return Err(self.unsupported("throw")),
+            Instruction::ThrowRef => return Err(self.unsupported("throw_ref")),
+
+            Instruction::RefNull { ref_type } => self.op(Op::RefNull(*ref_type)),
+            Instruction::RefIsNull => {
+                let operand_type = self.value_type(*self.stack.last().unwrap());
+                self.op(Op::RefIsNull(ref_type(operand_type)));
+            }
+            Instruction::RefFunc { func_index } => self.op(Op::RefFunc(*func_index)),
+            Instruction::Drop => {
+                self.pop();
+            }
+            Instruction::Select { .. } | Instruction::SelectResult { .. } => {
+                let value_type = self.value_type(self.stack[self.stack.len() - 3]).clone();
+                self.op(Op::Select(value_type));
+            }
+
+            Instruction::LocalGet { local_index } => {
+                self.stack.push(self.locals[*local_index as usize]);
+            }
+            Instruction::LocalSet { local_index } => {
+                self.locals[*local_index as usize] = self.pop();
+            }
+            Instruction::LocalTee { local_index } => {
+                self.locals[*local_index as usize] = *self.stack.last().unwrap();
+            }
+            Instruction::GlobalGet { global_index } => self.op(Op::GlobalGet(*global_index)),
+            Instruction::GlobalSet { global_index } => self.op(Op::GlobalSet(*global_index)),
+
+            Instruction::TableGet { table_index } => self.op(Op::TableGet(*table_index)),
+            Instruction::TableSet { table_index } => self.op(Op::TableSet(*table_index)),
+            Instruction::TableInit {
+                element_index,
+                table_index,
+            } => self.op(Op::TableInit {
+                element: *element_index,
+                table: *table_index,
+            }),
+            Instruction::ElemDrop { element_index } => self.op(Op::ElemDrop(*element_index)),
+            Instruction::TableCopy {
+                dst_table_index,
+                src_table_index,
+            } => self.op(Op::TableCopy {
+                dst: *dst_table_index,
+                src: *src_table_index,
+            }),
+            Instruction::TableGrow { table_index } => self.op(Op::TableGrow(*table_index)),
+            Instruction::TableSize { table_index } => self.op(Op::TableSize(*table_index)),
+            Instruction::TableFill { table_index } => self.op(Op::TableFill(*table_index)),
+
+            Instruction::MemorySize { memory_index } => self.op(Op::MemorySize(*memory_index)),
+            Instruction::MemoryGrow { memory_index } => self.op(Op::MemoryGrow(*memory_index)),
+            Instruction::MemoryInit {
+                data_index,
+                memory_index,
+            } => self.op(Op::MemoryInit {
+                data: *data_index,
+                memory: *memory_index,
+            }),
+            Instruction::DataDrop { data_index } => self.op(Op::DataDrop(*data_index)),
+            Instruction::MemoryCopy {
+                dst_memory_index,
+                src_memory_index,
+            } => self.op(Op::MemoryCopy {
+                dst: *dst_memory_index,
+                src: *src_memory_index,
+            }),
+            Instruction::MemoryFill { memory_index } => self.op(Op::MemoryFill(*memory_index)),
+            Instruction::AtomicFence => self.op(Op::AtomicFence),
+
+            Instruction::I32Const { value } => self.op(Op::Const(Constant::I32(*value))),
+            Instruction::I64Const { value } => self.op(Op::Const(Constant::I64(*value))),
+            Instruction::F32Const { value } => self.op(Op::Const(Constant::F32(*value))),
+            Instruction::F64Const { value } => self.op(Op::Const(Constant::F64(*value))),
+            Instruction::V128Const { value } => self.op(Op::Const(Constant::V128(*value))),
+
+            instr => match memory_op(instr) {
+                Some(op) => self.op(op),
+                None => {
+                    self.numeric_checks(instr);
+                    self.op(Op::Numeric(instr.clone()));
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Adds the `trap_if` checks that make the numeric instruction `instr`
+    /// trap-free.
+    fn numeric_checks(&mut self, instr: &Instruction) {
+        use Instruction::*;
+
+        let (eqz, eq) = match instr {
+            I32DivS | I32DivU | I32RemS | I32RemU => (I32Eqz, I32Eq),
+            I64DivS | I64DivU | I64RemS | I64RemU => (I64Eqz, I64Eq),
+            instr => {
+                if let Some(bounds) = conversion_bounds(instr) {
+                    let operand = *self.stack.last().unwrap();
+                    self.conversion_checks(operand, bounds);
+                }
+                return;
+            }
+        };
+
+        let lhs = self.stack[self.stack.len() - 2];
+        let rhs = self.stack[self.stack.len() - 1];
+        let is_zero = self.emit(Op::Numeric(eqz), vec![rhs])[0];
+        self.emit(Op::TrapIf(TrapCode::IntegerDivideByZero), vec![is_zero]);
+
+        // `MIN / -1` overflows, while `MIN % -1` is 0.
+        let (min, minus_one) = match instr {
+            I32DivS => (Constant::I32(i32::MIN), Constant::I32(-1)),
+            I64DivS => (Constant::I64(i64::MIN), Constant::I64(-1)),
+            _ => return,
+        };
+        let min = self.emit(Op::Const(min), vec![])[0];
+        let minus_one = self.emit(Op::Const(minus_one), vec![])[0];
+        let lhs_is_min = self.emit(Op::Numeric(eq.clone()), vec![lhs, min])[0];
+        let rhs_is_minus_one = self.emit(Op::Numeric(eq), vec![rhs, minus_one])[0];
+        let overflows = self.emit(Op::Numeric(I32And), vec![lhs_is_min, rhs_is_minus_one])[0];
+        self.emit(Op::TrapIf(TrapCode::IntegerOverflow), vec![overflows]);
+    }
+
+    /// Traps on NaN and on values that truncate to an integer out of range.
+    fn conversion_checks(&mut self, operand: Value, bounds: ConversionBounds) {
+        use Instruction::*;
+
+        let (ne, gt, ge, lt) = match self.value_type(operand) {
+            ValueType::F32 => (F32Ne, F32Gt, F32Ge, F32Lt),
+            _ => (F64Ne, F64Gt, F64Ge, F64Lt),
+        };
+        let constant = |value: f64| match self.value_type(operand) {
+            ValueType::F32 => Constant::F32(value as f32),
+            _ => Constant::F64(value),
+        };
+        let (lower, upper) = (constant(bounds.lower), constant(bounds.upper));
+
+        let is_nan = self.emit(Op::Numeric(ne), vec![operand, operand])[0];
+        self.emit(Op::TrapIf(TrapCode::InvalidConversion), vec![is_nan]);
+
+        let lower = self.emit(Op::Const(lower), vec![])[0];
+        let upper = self.emit(Op::Const(upper), vec![])[0];
+        let above_lower = match bounds.lower_inclusive {
+            true => ge,
+            false => gt,
+        };
+        let above_lower = self.emit(Op::Numeric(above_lower), vec![operand, lower])[0];
+        let below_upper = self.emit(Op::Numeric(lt), vec![operand, upper])[0];
+        let in_range = self.emit(Op::Numeric(I32And), vec![above_lower, below_upper])[0];
+        let out_of_range = self.emit(Op::Numeric(I32Eqz), vec![in_range])[0];
+        self.emit(Op::TrapIf(TrapCode::IntegerOverflow), vec![out_of_range]);
+    }
+
+    fn br(&mut self, level: u32) {
+        let frame = &self.frames[self.frames.len() - 1 - level as usize];
+
+        if frame.kind == FrameKind::Function {
+            let values = self.label_args(frame);
+            self.terminate(Terminator::Return(values));
+        } else {
+            let dest = self.branch_dest(level);
+            self.terminate(Terminator::Jump(dest));
+        }
+    }
+
+    /// Branch to the label of the frame `level` levels up, creating the
+    /// label's block if needed.
+    fn branch_dest(&mut self, level: u32) -> BlockCall {
+        let index = self.frames.len() - 1 - level as usize;
+        let block = match self.frames[index].label {
+            Some(block) => block,
+            None => {
+                let frame = &self.frames[index];
+                let block = match frame.kind {
+                    FrameKind::Function => self.create_block(frame.label_types.clone(), false),
+                    _ => {
+                        let mut param_types = self.local_types.clone();
+                        param_types.extend_from_slice(&frame.label_types);
+                        self.create_block(param_types, true)
+                    }
+                };
+                self.frames[index].label = Some(block);
+                block
+            }
+        };
+
+        BlockCall {
+            block,
+            args: self.label_args(&self.frames[index]),
+        }
+    }
+
+    /// Values passed to the label of `frame`, with the locals for blocks and
+    /// loops.
+    fn label_args(&self, frame: &Frame) -> Vec<Value> {
+        let mut args = match frame.kind {
+            FrameKind::Function => vec![],
+            _ => self.locals.clone(),
+        };
+        args.extend_from_slice(&self.stack[self.stack.len() - frame.label_types.len()..]);
+
+        args
+    }
+
+    fn push_frame(&mut self, kind: FrameKind, block_type: &FuncType, label: Option<Block>) {
+        let label_types = match kind {
+            FrameKind::Loop => block_type.params.clone(),
+            _ => block_type.results.clone(),
+        };
+
+        self.frames.push(Frame {
+            kind,
+            label,
+            label_types,
+            height: self.stack.len() - block_type.params.len(),
+        });
+    }
+
+    /// Pops the innermost frame at its `end`, continuing after it.
+    fn end_frame(&mut self) {
+        let frame = self.frames.pop().unwrap();
+
+        match frame.kind {
+            // Loops fall through, their label is at the start.
+            FrameKind::Loop => {}
+            FrameKind::Block => {
+                if let Some(label) = frame.label {
+                    if self.current.is_some() {
+                        let args = self.label_args(&frame);
+                        self.terminate(Terminator::Jump(BlockCall { block: label, args }));
+                    }
+                    self.stack.truncate(frame.height);
+                    self.switch_to(label);
+                }
+            }
+            FrameKind::Function => {
+                if self.current.is_some() {
+                    let args = self.label_args(&frame);
+                    let terminator = match frame.label {
+                        Some(label) => Terminator::Jump(BlockCall { block: label, args }),
+                        None => Terminator::Return(args),
+                    };
+                    self.terminate(terminator);
+                }
+                if let Some(label) = frame.label {
+                    self.stack.clear();
+                    self.switch_to(label);
+                    let values = mem::take(&mut self.stack);
+                    self.terminate(Terminator::Return(values));
+                }
+            }
+        }
+    }
+
+    fn create_block(&mut self, param_types: Vec<ValueType>, carries_locals: bool) -> Block {
+        self.blocks.push(PendingBlock {
+            param_types,
+            carries_locals,
+            ..PendingBlock::default()
+        });
+
+        Block(self.blocks.len() as u32 - 1)
+    }
+
+    /// Continues in `block`, whose parameters become the locals and the values
+    /// pushed on the stack.
+    fn switch_to(&mut self, block: Block) {
+        let pending = &self.blocks[block.0 as usize];
+        let param_types = pending.param_types.clone();
+        let carries_locals = pending.carries_locals;
+
+        let params: Vec<Value> = param_types
+            .into_iter()
+            .map(|value_type| self.new_value(value_type))
+            .collect();
+        self.blocks[block.0 as usize].params = params.clone();
+
+        if carries_locals {
+            let (locals, values) = params.split_at(self.local_types.len());
+            self.locals = locals.to_vec();
+            self.stack.extend_from_slice(values);
+        } else {
+            self.stack.extend(params);
+        }
+
+        self.current = Some(block);
+        self.layout.push(block);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.current.take().unwrap();
+        self.blocks[block.0 as usize].terminator = Some(terminator);
+    }
+
+    /// Adds an instruction taking its operands from the stack.
+    fn op(&mut self, op: Op) {
+        let (params, _) = self.signature(&op);
+        let args = self.pop_n(params.len());
+        let results = self.emit(op, args);
+        self.stack.extend(results);
+    }
+
+    /// Adds an instruction, returning its results.
+    fn emit(&mut self, op: Op, args: Vec<Value>) -> Vec<Value> {
+        let (_, result_types) = self.signature(&op);
+        let results: Vec<Value> = result_types
+            .into_iter()
+            .map(|value_type| self.new_value(value_type))
+            .collect();
+
+        let block = self.current.unwrap();
+        self.blocks[block.0 as usize].insts.push(Inst {
+            op,
+            args,
+            results: results.clone(),
+        });
+
+        results
+    }
+
+    fn signature(&self, op: &Op) -> (Vec<ValueType>, Vec<ValueType>) {
+        self.env
+            .signature(op)
+            .unwrap_or_else(|| panic!("invalid operation {:?}", op))
+    }
+
+    fn new_value(&mut self, value_type: ValueType) -> Value {
+        self.value_types.push(value_type);
+        Value(self.value_types.len() as u32 - 1)
+    }
+
+    fn value_type(&self, value: Value) -> &ValueType {
+        &self.value_types[value.0 as usize]
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap()
+    }
+
+    fn pop_n(&mut self, n: usize) -> Vec<Value> {
+        self.stack.split_off(self.stack.len() - n)
+    }
+
+    fn unsupported(&self, instr: &'static str) -> LowerError {
+        LowerError::Unsupported {
+            func_index: self.func_index,
+            instr,
+        }
+    }
+
+    /// Takes the blocks in layout order, renumbering them.
+    fn finish(&mut self) -> Vec<BlockData> {
+        let order: HashMap<Block, Block> = self
+            .layout
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (*block, Block(index as u32)))
+            .collect();
+
+        self.layout
+            .iter()
+            .map(|block| {
+                let pending = mem::take(&mut self.blocks[block.0 as usize]);
+                let mut terminator = pending.terminator.expect("block without terminator");
+                for dest in terminator.dests_mut() {
+                    dest.block = order[&dest.block];
+                }
+
+                BlockData {
+                    params: pending.params,
+                    insts: pending.insts,
+                    terminator,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Range of the floats a truncation instruction accepts.
+struct ConversionBounds {
+    lower: f64,
+    lower_inclusive: bool,
+    /// Exclusive.
+    upper: f64,
+}
+
+fn conversion_bounds(instr: &Instruction) -> Option<ConversionBounds> {
+    const I32_MIN: f64 = -2147483648.0;
+    const I32_LIMIT: f64 = 2147483648.0;
+    const U32_LIMIT: f64 = 4294967296.0;
+    const I64_MIN: f64 = -9223372036854775808.0;
+    const I64_LIMIT: f64 = 9223372036854775808.0;
+    const U64_LIMIT: f64 = 18446744073709551616.0;
+
+    // Unsigned truncations accept (-1, limit). Signed ones accept [min, limit),
+    // except from f64 to i32 where (min - 1, limit) is representable.
+    let (lower, lower_inclusive, upper) = match instr {
+        Instruction::I32TruncF32S => (I32_MIN, true, I32_LIMIT),
+        Instruction::I32TruncF64S => (I32_MIN - 1.0, false, I32_LIMIT),
+        Instruction::I32TruncF32U | Instruction::I32TruncF64U => (-1.0, false, U32_LIMIT),
+        Instruction::I64TruncF32S | Instruction::I64TruncF64S => (I64_MIN, true, I64_LIMIT),
+        Instruction::I64TruncF32U | Instruction::I64TruncF64U => (-1.0, false, U64_LIMIT),
+        _ => return None,
+    };
+
+    Some(ConversionBounds {
+        lower,
+        lower_inclusive,
+        upper,
+    })
+}
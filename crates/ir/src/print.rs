@@ -0,0 +1,189 @@
+//! Text dump of the IR:
+//!
+//! ```text
+//! function add(i32, i32) -> i32 {
+//! block0(v0: i32, v1: i32):
+//!     v2 = i32.add v0, v1
+//!     return v2
+//! }
+//! ```
+
+use std::fmt;
+
+use wasm_parser::{
+    decoder::types::ValueType,
+    text::{print_instruction, value_type_name},
+};
+
+use super::{Block, BlockCall, Function, Inst, Op, Terminator, TrapCode, Value};
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block{}", self.0)
+    }
+}
+
+/// Name of a trap code in the text dump.
+fn trap_name(code: TrapCode) -> &'static str {
+    match code {
+        TrapCode::Unreachable => "unreachable",
+        TrapCode::IntegerDivideByZero => "integer_divide_by_zero",
+        TrapCode::IntegerOverflow => "integer_overflow",
+        TrapCode::InvalidConversion => "invalid_conversion",
+        TrapCode::MemoryOutOfBounds => "memory_out_of_bounds",
+        TrapCode::TableOutOfBounds => "table_out_of_bounds",
+        TrapCode::IndirectCallTypeMismatch => "indirect_call_type_mismatch",
+        TrapCode::CallStackExhausted => "call_stack_exhausted",
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self, self.instruction()) {
+            (Op::TrapIf(code), _) => write!(f, "trap_if {}", trap_name(*code)),
+            (_, Some(instr)) => f.write_str(&print_instruction(&instr)),
+            (_, None) => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.results.is_empty() {
+            write!(f, "{} = ", Values(&self.results))?;
+        }
+        write!(f, "{}", self.op)?;
+        if !self.args.is_empty() {
+            write!(f, " {}", Values(&self.args))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BlockCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.block)?;
+        if !self.args.is_empty() {
+            write!(f, "({})", Values(&self.args))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(dest) => write!(f, "jump {}", dest),
+            Terminator::Branch {
+                cond,
+                then_dest,
+                else_dest,
+            } => write!(f, "br_if {}, {}, {}", cond, then_dest, else_dest),
+            Terminator::Switch {
+                index,
+                targets,
+                default,
+            } => {
+                write!(f, "br_table {}, [", index)?;
+                for (i, target) in targets.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", target)?;
+                }
+                write!(f, "], {}", default)
+            }
+            Terminator::Return(values) if values.is_empty() => f.write_str("return"),
+            Terminator::Return(values) => write!(f, "return {}", Values(values)),
+            Terminator::ReturnCall { func, args } => {
+                write!(f, "return_call {}", func)?;
+                if !args.is_empty() {
+                    write!(f, " {}", Values(args))?;
+                }
+                Ok(())
+            }
+            Terminator::ReturnCallIndirect {
+                type_index,
+                table,
+                args,
+                callee,
+            } => {
+                write!(f, "return_call_indirect {} (type {}) ", table, type_index)?;
+                for arg in args {
+                    write!(f, "{}, ", arg)?;
+                }
+                write!(f, "{}", callee)
+            }
+            Terminator::Trap(code) => write!(f, "trap {}", trap_name(*code)),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "function {}({})", self.name, Types(&self.params))?;
+        if !self.results.is_empty() {
+            write!(f, " -> {}", Types(&self.results))?;
+        }
+        writeln!(f, " {{")?;
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            write!(f, "{}", Block(index as u32))?;
+            if !block.params.is_empty() {
+                f.write_str("(")?;
+                for (i, param) in block.params.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    let value_type = self.value_types.get(param.0 as usize);
+                    write!(f, "{}: {}", param, value_type.map_or("?", value_type_name))?;
+                }
+                f.write_str(")")?;
+            }
+            writeln!(f, ":")?;
+
+            for inst in &block.insts {
+                writeln!(f, "    {}", inst)?;
+            }
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+/// Comma-separated values.
+struct Values<'a>(&'a [Value]);
+
+impl fmt::Display for Values<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, value) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Comma-separated value types.
+struct Types<'a>(&'a [ValueType]);
+
+impl fmt::Display for Types<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, value_type) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(value_type_name(value_type))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+use wasm_parser::decoder::types::ValueType;
+
+use super::{Block, Value};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum LowerError {
+    #[error("func {func_index}: `{instr}` is not supported")]
+    Unsupported {
+        func_index: u32,
+        instr: &'static str,
+    },
+}
+
+/// Error returned by the verifier, together with where in the function it
+/// happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierError {
+    pub block: Option<Block>,
+    /// Index of the instruction within the block, `None` for its parameters
+    /// and terminator.
+    pub inst: Option<usize>,
+    pub kind: VerifierErrorKind,
+}
+
+impl fmt::Display for VerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(block) = self.block {
+            write!(f, "{}", block)?;
+            if let Some(inst) = self.inst {
+                write!(f, ", inst {}", inst)?;
+            }
+            f.write_str(": ")?;
+        }
+
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for VerifierError {}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerifierErrorKind {
+    #[error("function has no blocks")]
+    NoBlocks,
+    #[error("entry block parameters don't match the function parameters")]
+    EntryParamsMismatch,
+    #[error("branch to the entry block")]
+    BranchToEntry,
+    #[error("unknown {0}")]
+    UnknownBlock(Block),
+    #[error("unknown value {0}")]
+    UnknownValue(Value),
+    #[error("{0} is defined more than once")]
+    Redefinition(Value),
+    #[error("{0} is used where it isn't defined")]
+    NotDominated(Value),
+    #[error("operation refers to an unknown index or has the wrong kind")]
+    InvalidOperation,
+    #[error("expected {expected} operands, found {actual}")]
+    ArgCountMismatch { expected: usize, actual: usize },
+    #[error("expected {expected} results, found {actual}")]
+    ResultCountMismatch { expected: usize, actual: usize },
+    #[error("tail call results don't match the results of the function")]
+    ReturnCallMismatch,
+    #[error("type mismatch for {value}: expected {expected:?}, found {actual:?}")]
+    TypeMismatch {
+        value: Value,
+        expected: ValueType,
+        actual: ValueType,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, VerifierError>;
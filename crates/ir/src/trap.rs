@@ -0,0 +1,64 @@
+use std::fmt;
+
+/// Reason of a trap, raised explicitly by the IR or passed by compiled code to
+/// the runtime trap handler. 0 is never a trap code.
+///
+/// The codes are fixed, so that firmware can rely on them. Some are reserved
+/// for instructions the backends don't lower yet, and never emitted.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/exec/runtime.html#syntax-trap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrapCode {
+    Unreachable = 1,
+    IntegerDivideByZero = 2,
+    IntegerOverflow = 3,
+    /// Reserved for the float to integer truncations.
+    InvalidConversion = 4,
+    MemoryOutOfBounds = 5,
+    /// Reserved for the table instructions and `call_indirect`.
+    TableOutOfBounds = 6,
+    /// Reserved for `call_indirect`.
+    IndirectCallTypeMismatch = 7,
+    CallStackExhausted = 8,
+}
+
+impl TrapCode {
+    pub const ALL: [TrapCode; 8] = [
+        TrapCode::Unreachable,
+        TrapCode::IntegerDivideByZero,
+        TrapCode::IntegerOverflow,
+        TrapCode::InvalidConversion,
+        TrapCode::MemoryOutOfBounds,
+        TrapCode::TableOutOfBounds,
+        TrapCode::IndirectCallTypeMismatch,
+        TrapCode::CallStackExhausted,
+    ];
+
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<TrapCode> {
+        TrapCode::ALL.into_iter().find(|trap| trap.code() == code)
+    }
+
+    /// Message of the trap, as in the wasm spec test suite.
+    pub fn message(self) -> &'static str {
+        match self {
+            TrapCode::Unreachable => "unreachable",
+            TrapCode::IntegerDivideByZero => "integer divide by zero",
+            TrapCode::IntegerOverflow => "integer overflow",
+            TrapCode::InvalidConversion => "invalid conversion to integer",
+            TrapCode::MemoryOutOfBounds => "out of bounds memory access",
+            TrapCode::TableOutOfBounds => "out of bounds table access",
+            TrapCode::IndirectCallTypeMismatch => "indirect call type mismatch",
+            TrapCode::CallStackExhausted => "call stack exhausted",
+        }
+    }
+}
+
+impl fmt::Display for TrapCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use wasm_parser::decoder::types::ValueType;
+
+use super::{
+    Block, Function, ModuleEnv, Op, Result, Terminator, Value, VerifierError, VerifierErrorKind,
+};
+
+type KindResult<T> = std::result::Result<T, VerifierErrorKind>;
+
+/// Checks that `func` is well-formed SSA: blocks and values exist, every value
+/// is defined once and before its uses (in dominance order), and operands,
+/// results and block arguments have the types their operations expect.
+pub fn verify(env: &ModuleEnv, func: &Function) -> Result<()> {
+    if func.blocks.is_empty() {
+        return Err(VerifierError {
+            block: None,
+            inst: None,
+            kind: VerifierErrorKind::NoBlocks,
+        });
+    }
+
+    let mut verifier = Verifier {
+        env,
+        func,
+        defs: HashMap::new(),
+        dominators: Dominators::new(func),
+    };
+    verifier.collect_defs()?;
+
+    let entry = Block(0);
+    let entry_types = func.blocks[0]
+        .params
+        .iter()
+        .map(|param| func.value_type(*param));
+    if !entry_types.eq(&func.params) {
+        return Err(error(entry, None, VerifierErrorKind::EntryParamsMismatch));
+    }
+
+    for (index, data) in func.blocks.iter().enumerate() {
+        let block = Block(index as u32);
+
+        for position in 0..data.insts.len() {
+            verifier
+                .inst(block, position)
+                .map_err(|kind| error(block, Some(position), kind))?;
+        }
+        verifier
+            .terminator(block, &data.terminator)
+            .map_err(|kind| error(block, None, kind))?;
+    }
+
+    Ok(())
+}
+
+fn error(block: Block, inst: Option<usize>, kind: VerifierErrorKind) -> VerifierError {
+    VerifierError {
+        block: Some(block),
+        inst,
+        kind,
+    }
+}
+
+/// Where a value is defined: block parameters are at position 0, and the
+/// results of instruction `i` at position `i + 1`.
+#[derive(Debug, Clone, Copy)]
+struct Def {
+    block: Block,
+    position: usize,
+}
+
+struct Verifier<'a> {
+    env: &'a ModuleEnv,
+    func: &'a Function,
+    defs: HashMap<Value, Def>,
+    dominators: Dominators,
+}
+
+impl Verifier<'_> {
+    fn collect_defs(&mut self) -> Result<()> {
+        for (index, data) in self.func.blocks.iter().enumerate() {
+            let block = Block(index as u32);
+            let params = data.params.iter().map(|param| (None, param, 0));
+            let results = data.insts.iter().enumerate().flat_map(|(position, inst)| {
+                inst.results
+                    .iter()
+                    .map(move |result| (Some(position), result, position + 1))
+            });
+
+            for (inst, value, position) in params.chain(results) {
+                if value.0 as usize >= self.func.value_types.len() {
+                    return Err(error(block, inst, VerifierErrorKind::UnknownValue(*value)));
+                }
+                if self.defs.insert(*value, Def { block, position }).is_some() {
+                    return Err(error(block, inst, VerifierErrorKind::Redefinition(*value)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn inst(&self, block: Block, position: usize) -> KindResult<()> {
+        let inst = &self.func.block(block).insts[position];
+        let (params, results) = self
+            .env
+            .signature(&inst.op)
+            .ok_or(VerifierErrorKind::InvalidOperation)?;
+
+        self.uses(block, position + 1, &inst.args)?;
+        self.check_types(&inst.args, &params)?;
+
+        if inst.results.len() != results.len() {
+            return Err(VerifierErrorKind::ResultCountMismatch {
+                expected: results.len(),
+                actual: inst.results.len(),
+            });
+        }
+        self.check_types(&inst.results, &results)
+    }
+
+    fn terminator(&self, block: Block, terminator: &Terminator) -> KindResult<()> {
+        let position = self.func.block(block).insts.len() + 1;
+        self.uses(block, position, &terminator.args())?;
+
+        for dest in terminator.dests() {
+            if dest.block.0 == 0 {
+                return Err(VerifierErrorKind::BranchToEntry);
+            }
+            let target = self
+                .func
+                .blocks
+                .get(dest.block.0 as usize)
+                .ok_or(VerifierErrorKind::UnknownBlock(dest.block))?;
+            let param_types: Vec<_> = target
+                .params
+                .iter()
+                .map(|param| self.func.value_type(*param).clone())
+                .collect();
+            self.check_types(&dest.args, &param_types)?;
+        }
+
+        match terminator {
+            Terminator::Branch { cond, .. } => self.check_types(&[*cond], &[ValueType::I32]),
+            Terminator::Switch { index, .. } => self.check_types(&[*index], &[ValueType::I32]),
+            Terminator::Return(values) => self.check_types(values, &self.func.results),
+            Terminator::ReturnCall { func, args } => self.tail_call(&Op::Call(*func), args),
+            Terminator::ReturnCallIndirect {
+                type_index,
+                table,
+                args,
+                callee,
+            } => {
+                let mut args = args.clone();
+                args.push(*callee);
+                let op = Op::CallIndirect {
+                    type_index: *type_index,
+                    table: *table,
+                };
+                self.tail_call(&op, &args)
+            }
+            Terminator::Jump(_) | Terminator::Trap(_) => Ok(()),
+        }
+    }
+
+    fn tail_call(&self, call: &Op, args: &[Value]) -> KindResult<()> {
+        let (params, results) = self
+            .env
+            .signature(call)
+            .ok_or(VerifierErrorKind::InvalidOperation)?;
+        self.check_types(args, &params)?;
+
+        match results == self.func.results {
+            true => Ok(()),
+            false => Err(VerifierErrorKind::ReturnCallMismatch),
+        }
+    }
+
+    /// Checks that the values used at `position` in `block` are defined before.
+    fn uses(&self, block: Block, position: usize, values: &[Value]) -> KindResult<()> {
+        for value in values {
+            let def = self
+                .defs
+                .get(value)
+                .ok_or(VerifierErrorKind::UnknownValue(*value))?;
+
+            let dominates = match def.block == block {
+                true => def.position < position,
+                false => self.dominators.dominates(def.block, block),
+            };
+            if !dominates {
+                return Err(VerifierErrorKind::NotDominated(*value));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_types(&self, values: &[Value], expected: &[ValueType]) -> KindResult<()> {
+        if values.len() != expected.len() {
+            return Err(VerifierErrorKind::ArgCountMismatch {
+                expected: expected.len(),
+                actual: values.len(),
+            });
+        }
+
+        for (value, expected) in values.iter().zip(expected) {
+            let actual = self.func.value_type(*value);
+            if actual != expected {
+                return Err(VerifierErrorKind::TypeMismatch {
+                    value: *value,
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Dominator tree of the blocks reachable from the entry.
+///
+/// Cooper, Harvey and Kennedy, "A Simple, Fast Dominance Algorithm".
+struct Dominators {
+    /// Immediate dominator of every reachable block, the entry being its own.
+    idoms: Vec<Option<usize>>,
+    /// Position of every reachable block in reverse postorder.
+    rpo_numbers: Vec<Option<usize>>,
+}
+
+impl Dominators {
+    fn new(func: &Function) -> Self {
+        let len = func.blocks.len();
+        let successors = |block: usize| -> Vec<usize> {
+            func.blocks[block]
+                .terminator
+                .dests()
+                .iter()
+                .map(|dest| dest.block.0 as usize)
+                .filter(|successor| *successor < len)
+                .collect()
+        };
+
+        // Iterative depth-first search for the postorder.
+        let mut postorder = vec![];
+        let mut visited = vec![false; len];
+        let mut stack = vec![(0, successors(0))];
+        visited[0] = true;
+        while let Some((block, remaining)) = stack.last_mut() {
+            match remaining.pop() {
+                Some(successor) if !visited[successor] => {
+                    visited[successor] = true;
+                    stack.push((successor, successors(successor)));
+                }
+                Some(_) => {}
+                None => {
+                    postorder.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+
+        let mut rpo_numbers = vec![None; len];
+        for (number, block) in postorder.iter().rev().enumerate() {
+            rpo_numbers[*block] = Some(number);
+        }
+
+        let mut predecessors = vec![vec![]; len];
+        for &block in &postorder {
+            for successor in successors(block) {
+                predecessors[successor].push(block);
+            }
+        }
+
+        let mut idoms = vec![None; len];
+        idoms[0] = Some(0);
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for &block in postorder.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in &predecessors[block] {
+                    if idoms[predecessor].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(other) => intersect(&idoms, &rpo_numbers, predecessor, other),
+                    });
+                }
+
+                if new_idom.is_some() && idoms[block] != new_idom {
+                    idoms[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators { idoms, rpo_numbers }
+    }
+
+    /// Whether `a` dominates `b`. Unreachable blocks are dominated by every
+    /// block, as they can't observe undefined values.
+    fn dominates(&self, a: Block, b: Block) -> bool {
+        let (a, mut b) = (a.0 as usize, b.0 as usize);
+        if self.rpo_numbers.get(b).copied().flatten().is_none() {
+            return true;
+        }
+
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idoms[b] {
+                Some(idom) if idom != b => b = idom,
+                _ => return false,
+            }
+        }
+    }
+}
+
+fn intersect(
+    idoms: &[Option<usize>],
+    rpo_numbers: &[Option<usize>],
+    mut a: usize,
+    mut b: usize,
+) -> usize {
+    while a != b {
+        while rpo_numbers[a] > rpo_numbers[b] {
+            a = idoms[a].unwrap();
+        }
+        while rpo_numbers[b] > rpo_numbers[a] {
+            b = idoms[b].unwrap();
+        }
+    }
+
+    a
+}
@@ -0,0 +1,293 @@
+use std::{fs, path::PathBuf};
+
+#[path = "../../wasm_parser/tests/common/mod.rs"]
+mod common;
+
+use ir::{
+    lower_module, verify, Block, BlockCall, BlockData, Function, ModuleEnv, Op, Terminator, Value,
+    VerifierErrorKind,
+};
+use wasm_parser::{
+    decoder::{instructions::Instruction, types::ValueType, Decoder},
+    parser::{module::Module, Parser},
+    text::WatParser,
+};
+
+/// Number of random modules checked by `lowers_generated_modules`.
+const GENERATED_MODULES: u64 = 200;
+
+fn parse_wat(text: &str) -> Module {
+    Parser::new(WatParser::new(text).parse().unwrap())
+        .parse()
+        .unwrap()
+}
+
+/// Lowers every function of `module`, checking that the result verifies.
+fn lower_and_verify(module: &Module) -> Vec<Function> {
+    let env = ModuleEnv::new(module);
+    let functions = lower_module(module).unwrap();
+
+    for func in &functions {
+        verify(&env, func).unwrap_or_else(|err| panic!("{}\n{}", err, func));
+    }
+
+    functions
+}
+
+#[test]
+fn lowers_examples() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "wasm") {
+            continue;
+        }
+
+        let module = Decoder::new(&fs::read(&path).unwrap()).decode().unwrap();
+        lower_and_verify(&Parser::new(module).parse().unwrap());
+    }
+}
+
+#[test]
+fn lowers_generated_modules() {
+    for seed in 0..GENERATED_MODULES {
+        let Some(bytes) = common::generate_module(seed, common::wasm_smith_config()) else {
+            continue;
+        };
+
+        let module = Decoder::new(&bytes).decode().unwrap();
+        lower_and_verify(&Parser::new(module).parse().unwrap());
+    }
+}
+
+#[test]
+fn passes_locals_as_block_arguments() {
+    let module = parse_wat(
+        r#"
+        (module
+          (func $sum (param $n i32) (result i32)
+            (local $i i32) (local $acc i32)
+            block $done
+              loop $next
+                local.get $i
+                local.get $n
+                i32.ge_s
+                br_if $done
+                local.get $acc
+                local.get $i
+                i32.add
+                local.set $acc
+                local.get $i
+                i32.const 1
+                i32.add
+                local.set $i
+                br $next
+              end
+            end
+            local.get $acc)
+          (func $pick (param i32) (result i32)
+            block (result i32)
+              i32.const 7
+              local.get 0
+              br_table 0 1
+            end
+            i32.const 1
+            i32.add))
+        "#,
+    );
+    let functions = lower_and_verify(&module);
+
+    assert_eq!(
+        functions[0].to_string(),
+        "\
+function sum(i32) -> i32 {
+block0(v0: i32):
+    v1 = i32.const 0
+    v2 = i32.const 0
+    jump block1(v0, v1, v2)
+block1(v3: i32, v4: i32, v5: i32):
+    v6 = i32.ge_s v4, v3
+    br_if v6, block3(v3, v4, v5), block2
+block2:
+    v7 = i32.add v5, v4
+    v8 = i32.const 1
+    v9 = i32.add v4, v8
+    jump block1(v3, v9, v7)
+block3(v10: i32, v11: i32, v12: i32):
+    return v12
+}
+"
+    );
+    assert_eq!(
+        functions[1].to_string(),
+        "\
+function pick(i32) -> i32 {
+block0(v0: i32):
+    v1 = i32.const 7
+    br_table v0, [block1(v0, v1)], block2(v1)
+block1(v2: i32, v3: i32):
+    v4 = i32.const 1
+    v5 = i32.add v3, v4
+    jump block2(v5)
+block2(v6: i32):
+    return v6
+}
+"
+    );
+}
+
+#[test]
+fn makes_traps_explicit() {
+    let module = parse_wat(
+        r#"
+        (module
+          (func $div (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.div_s)
+          (func $trunc (param f64) (result i32)
+            local.get 0
+            i32.trunc_f64_u)
+          (func $never
+            unreachable))
+        "#,
+    );
+    let functions = lower_and_verify(&module);
+
+    assert_eq!(
+        functions[0].to_string(),
+        "\
+function div(i32, i32) -> i32 {
+block0(v0: i32, v1: i32):
+    v2 = i32.eqz v1
+    trap_if integer_divide_by_zero v2
+    v3 = i32.const -2147483648
+    v4 = i32.const -1
+    v5 = i32.eq v0, v3
+    v6 = i32.eq v1, v4
+    v7 = i32.and v5, v6
+    trap_if integer_overflow v7
+    v8 = i32.div_s v0, v1
+    return v8
+}
+"
+    );
+    assert_eq!(
+        functions[1].to_string(),
+        "\
+function trunc(f64) -> i32 {
+block0(v0: f64):
+    v1 = f64.ne v0, v0
+    trap_if invalid_conversion v1
+    v2 = f64.const -1.0
+    v3 = f64.const 4294967296.0
+    v4 = f64.gt v0, v2
+    v5 = f64.lt v0, v3
+    v6 = i32.and v4, v5
+    v7 = i32.eqz v6
+    trap_if integer_overflow v7
+    v8 = i32.trunc_f64_u v0
+    return v8
+}
+"
+    );
+    assert_eq!(
+        functions[2].to_string(),
+        "function never() {\nblock0:\n    trap unreachable\n}\n"
+    );
+}
+
+#[test]
+fn rejects_malformed_functions() {
+    let module = parse_wat("(module (func (param i32) (result i32) local.get 0))");
+    let env = ModuleEnv::new(&module);
+    let func = lower_module(&module).unwrap().remove(0);
+
+    let add = |args| ir::Inst {
+        op: Op::Numeric(Instruction::I32Add),
+        args,
+        results: vec![Value(1)],
+    };
+    let jump = |block, args| {
+        Terminator::Jump(BlockCall {
+            block: Block(block),
+            args,
+        })
+    };
+    let cases = [
+        (
+            vec![BlockData {
+                params: vec![Value(0)],
+                insts: vec![add(vec![Value(0), Value(2)])],
+                terminator: Terminator::Return(vec![Value(1)]),
+            }],
+            VerifierErrorKind::UnknownValue(Value(2)),
+        ),
+        (
+            vec![BlockData {
+                params: vec![Value(0)],
+                insts: vec![add(vec![Value(0), Value(1)])],
+                terminator: Terminator::Return(vec![Value(1)]),
+            }],
+            VerifierErrorKind::NotDominated(Value(1)),
+        ),
+        (
+            vec![BlockData {
+                params: vec![Value(0)],
+                insts: vec![add(vec![Value(0)])],
+                terminator: Terminator::Return(vec![Value(1)]),
+            }],
+            VerifierErrorKind::ArgCountMismatch {
+                expected: 2,
+                actual: 1,
+            },
+        ),
+        (
+            vec![BlockData {
+                params: vec![Value(0)],
+                insts: vec![],
+                terminator: jump(0, vec![Value(0)]),
+            }],
+            VerifierErrorKind::BranchToEntry,
+        ),
+        (
+            vec![
+                BlockData {
+                    params: vec![Value(0)],
+                    insts: vec![],
+                    terminator: jump(1, vec![]),
+                },
+                BlockData {
+                    params: vec![Value(1)],
+                    insts: vec![],
+                    terminator: Terminator::Return(vec![Value(1)]),
+                },
+            ],
+            VerifierErrorKind::ArgCountMismatch {
+                expected: 1,
+                actual: 0,
+            },
+        ),
+    ];
+
+    for (blocks, kind) in cases {
+        let func = Function {
+            blocks,
+            value_types: vec![ValueType::I32, ValueType::I32],
+            ..func.clone()
+        };
+        assert_eq!(verify(&env, &func).unwrap_err().kind, kind, "{}", func);
+    }
+
+    let func = Function {
+        value_types: vec![ValueType::I64],
+        ..func
+    };
+    let err = verify(&env, &func).unwrap_err();
+    assert_eq!(err.kind, VerifierErrorKind::EntryParamsMismatch);
+    assert_eq!(
+        err.to_string(),
+        "block0: entry block parameters don't match the function parameters"
+    );
+}
@@ -0,0 +1,99 @@
+use super::{instructions::Instruction, types::RefType};
+
+/// Constant expression, as used for global initializers and segment offsets.
+/// The terminating `end` is not included.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstExpr {
+    pub instructions: Vec<Instruction>,
+}
+
+/// Value produced by evaluating a constant expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    RefNull(RefType),
+    RefFunc(u32),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ConstExprError {
+    #[error("instruction is not constant: {0:?}")]
+    NonConstantInstruction(Instruction),
+    #[error("value of global {0} is not known")]
+    UnknownGlobal(u32),
+    #[error("type mismatch")]
+    TypeMismatch,
+    #[error("expression must produce exactly one value, got {0}")]
+    InvalidResultCount(usize),
+}
+
+impl ConstExpr {
+    /// Evaluates the expression. `global` returns the value of an (imported or
+    /// previously defined) global, or `None` if it isn't known.
+    ///
+    /// Supports the extended-const proposal (`i32`/`i64` `add`, `sub` and `mul`).
+    pub fn eval(
+        &self,
+        global: impl Fn(u32) -> Option<ConstValue>,
+    ) -> Result<ConstValue, ConstExprError> {
+        let mut stack = vec![];
+
+        for instr in &self.instructions {
+            let value = match instr {
+                Instruction::I32Const { value } => ConstValue::I32(*value),
+                Instruction::I64Const { value } => ConstValue::I64(*value),
+                Instruction::F32Const { value } => ConstValue::F32(*value),
+                Instruction::F64Const { value } => ConstValue::F64(*value),
+                Instruction::V128Const { value } => ConstValue::V128(*value),
+                Instruction::RefNull { ref_type } => ConstValue::RefNull(*ref_type),
+                Instruction::RefFunc { func_index } => ConstValue::RefFunc(*func_index),
+                Instruction::GlobalGet { global_index } => {
+                    global(*global_index).ok_or(ConstExprError::UnknownGlobal(*global_index))?
+                }
+                Instruction::I32Add | Instruction::I32Sub | Instruction::I32Mul => {
+                    let (ConstValue::I32(lhs), ConstValue::I32(rhs)) = pop2(&mut stack)? else {
+                        return Err(ConstExprError::TypeMismatch);
+                    };
+
+                    ConstValue::I32(match instr {
+                        Instruction::I32Add => lhs.wrapping_add(rhs),
+                        Instruction::I32Sub => lhs.wrapping_sub(rhs),
+                        _ => lhs.wrapping_mul(rhs),
+                    })
+                }
+                Instruction::I64Add | Instruction::I64Sub | Instruction::I64Mul => {
+                    let (ConstValue::I64(lhs), ConstValue::I64(rhs)) = pop2(&mut stack)? else {
+                        return Err(ConstExprError::TypeMismatch);
+                    };
+
+                    ConstValue::I64(match instr {
+                        Instruction::I64Add => lhs.wrapping_add(rhs),
+                        Instruction::I64Sub => lhs.wrapping_sub(rhs),
+                        _ => lhs.wrapping_mul(rhs),
+                    })
+                }
+                instr => return Err(ConstExprError::NonConstantInstruction(instr.clone())),
+            };
+
+            stack.push(value);
+        }
+
+        match stack.as_slice() {
+            [value] => Ok(*value),
+            values => Err(ConstExprError::InvalidResultCount(values.len())),
+        }
+    }
+}
+
+fn pop2(stack: &mut Vec<ConstValue>) -> Result<(ConstValue, ConstValue), ConstExprError> {
+    let rhs = stack.pop().ok_or(ConstExprError::TypeMismatch)?;
+    let lhs = stack.pop().ok_or(ConstExprError::TypeMismatch)?;
+
+    Ok((lhs, rhs))
+}
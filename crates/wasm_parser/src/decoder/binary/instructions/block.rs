@@ -1,10 +1,13 @@
-use crate::decoder::binary::types::ValueType;
+use crate::decoder::binary::types::{FuncType, ValueType};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block {
     pub block_type: BlockType,
 }
 
+/// Block type.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/syntax/instructions.html#syntax-blocktype
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlockType {
     Empty,
@@ -13,11 +16,36 @@ pub enum BlockType {
 }
 
 impl BlockType {
-    pub fn result_count(&self) -> usize {
+    /// Resolves the block type to the function type it stands for, looking up
+    /// type indices in `types` (the module's type section).
+    ///
+    /// Returns `None` if the type index is out of bounds.
+    pub fn func_type(&self, types: &[FuncType]) -> Option<FuncType> {
         match self {
-            Self::Empty => 0,
-            Self::Value(types) => types.len(),
-            Self::TypeIndex(_) => 1,
+            Self::Empty => Some(FuncType {
+                params: vec![],
+                results: vec![],
+            }),
+            Self::Value(value_types) => Some(FuncType {
+                params: vec![],
+                results: value_types.clone(),
+            }),
+            Self::TypeIndex(index) => types.get(*index as usize).cloned(),
+        }
+    }
+
+    pub fn param_count(&self, types: &[FuncType]) -> Option<usize> {
+        match self {
+            Self::Empty | Self::Value(_) => Some(0),
+            Self::TypeIndex(index) => types.get(*index as usize).map(|ty| ty.params.len()),
+        }
+    }
+
+    pub fn result_count(&self, types: &[FuncType]) -> Option<usize> {
+        match self {
+            Self::Empty => Some(0),
+            Self::Value(value_types) => Some(value_types.len()),
+            Self::TypeIndex(index) => types.get(*index as usize).map(|ty| ty.results.len()),
         }
     }
 }
@@ -2,10 +2,25 @@ use crate::decoder::binary::types::{RefType, ValueType};
 
 use super::Block;
 
+/// Bit of a memarg's alignment field that says an explicit memory index follows.
+pub(crate) const MEM_ARG_HAS_MEMORY_INDEX: u32 = 1 << 6;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MemArg {
     pub align: u32,
-    pub offset: u32,
+    pub offset: u64,
+    pub memory_index: u32,
+}
+
+/// Catch clause of a `try_table` (exception handling proposal).
+///
+/// WebAssembly specification: https://webassembly.github.io/exception-handling/core/binary/instructions.html#control-instructions
+#[derive(Debug, Clone, PartialEq)]
+pub enum Catch {
+    Catch { tag_index: u32, label: u32 },
+    CatchRef { tag_index: u32, label: u32 },
+    CatchAll { label: u32 },
+    CatchAllRef { label: u32 },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -42,6 +57,21 @@ pub enum Instruction {
         type_index: u32,
         table_index: u32,
     },
+    ReturnCall {
+        func_index: u32,
+    },
+    ReturnCallIndirect {
+        type_index: u32,
+        table_index: u32,
+    },
+    TryTable {
+        block: Block,
+        catches: Vec<Catch>,
+    },
+    Throw {
+        tag_index: u32,
+    },
+    ThrowRef,
     /* References */
     RefNull {
         ref_type: RefType,
@@ -171,16 +201,26 @@ pub enum Instruction {
     I64Store32 {
         mem_arg: MemArg,
     },
-    MemorySize,
-    MemoryGrow,
+    MemorySize {
+        memory_index: u32,
+    },
+    MemoryGrow {
+        memory_index: u32,
+    },
     MemoryInit {
         data_index: u32,
+        memory_index: u32,
     },
     DataDrop {
         data_index: u32,
     },
-    MemoryCopy,
-    MemoryFill,
+    MemoryCopy {
+        dst_memory_index: u32,
+        src_memory_index: u32,
+    },
+    MemoryFill {
+        memory_index: u32,
+    },
     /* Numerics */
     I32Const {
         value: i32,
@@ -331,4 +371,524 @@ pub enum Instruction {
     I64TruncSatF64S,
     I64TruncSatF64U,
     /* Vectors */
+    V128Load {
+        mem_arg: MemArg,
+    },
+    V128Load8x8S {
+        mem_arg: MemArg,
+    },
+    V128Load8x8U {
+        mem_arg: MemArg,
+    },
+    V128Load16x4S {
+        mem_arg: MemArg,
+    },
+    V128Load16x4U {
+        mem_arg: MemArg,
+    },
+    V128Load32x2S {
+        mem_arg: MemArg,
+    },
+    V128Load32x2U {
+        mem_arg: MemArg,
+    },
+    V128Load8Splat {
+        mem_arg: MemArg,
+    },
+    V128Load16Splat {
+        mem_arg: MemArg,
+    },
+    V128Load32Splat {
+        mem_arg: MemArg,
+    },
+    V128Load64Splat {
+        mem_arg: MemArg,
+    },
+    V128Store {
+        mem_arg: MemArg,
+    },
+    V128Const {
+        value: u128,
+    },
+    I8x16Shuffle {
+        lanes: [u8; 16],
+    },
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS {
+        lane: u8,
+    },
+    I8x16ExtractLaneU {
+        lane: u8,
+    },
+    I8x16ReplaceLane {
+        lane: u8,
+    },
+    I16x8ExtractLaneS {
+        lane: u8,
+    },
+    I16x8ExtractLaneU {
+        lane: u8,
+    },
+    I16x8ReplaceLane {
+        lane: u8,
+    },
+    I32x4ExtractLane {
+        lane: u8,
+    },
+    I32x4ReplaceLane {
+        lane: u8,
+    },
+    I64x2ExtractLane {
+        lane: u8,
+    },
+    I64x2ReplaceLane {
+        lane: u8,
+    },
+    F32x4ExtractLane {
+        lane: u8,
+    },
+    F32x4ReplaceLane {
+        lane: u8,
+    },
+    F64x2ExtractLane {
+        lane: u8,
+    },
+    F64x2ReplaceLane {
+        lane: u8,
+    },
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Load16Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Load32Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Load64Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Store8Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Store16Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Store32Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Store64Lane {
+        mem_arg: MemArg,
+        lane: u8,
+    },
+    V128Load32Zero {
+        mem_arg: MemArg,
+    },
+    V128Load64Zero {
+        mem_arg: MemArg,
+    },
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+    /* Atomics */
+    MemoryAtomicNotify {
+        mem_arg: MemArg,
+    },
+    MemoryAtomicWait32 {
+        mem_arg: MemArg,
+    },
+    MemoryAtomicWait64 {
+        mem_arg: MemArg,
+    },
+    AtomicFence,
+    I32AtomicLoad {
+        mem_arg: MemArg,
+    },
+    I64AtomicLoad {
+        mem_arg: MemArg,
+    },
+    I32AtomicLoad8U {
+        mem_arg: MemArg,
+    },
+    I32AtomicLoad16U {
+        mem_arg: MemArg,
+    },
+    I64AtomicLoad8U {
+        mem_arg: MemArg,
+    },
+    I64AtomicLoad16U {
+        mem_arg: MemArg,
+    },
+    I64AtomicLoad32U {
+        mem_arg: MemArg,
+    },
+    I32AtomicStore {
+        mem_arg: MemArg,
+    },
+    I64AtomicStore {
+        mem_arg: MemArg,
+    },
+    I32AtomicStore8 {
+        mem_arg: MemArg,
+    },
+    I32AtomicStore16 {
+        mem_arg: MemArg,
+    },
+    I64AtomicStore8 {
+        mem_arg: MemArg,
+    },
+    I64AtomicStore16 {
+        mem_arg: MemArg,
+    },
+    I64AtomicStore32 {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwAdd {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwAdd {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8AddU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16AddU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8AddU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16AddU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32AddU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwSub {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwSub {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8SubU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16SubU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8SubU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16SubU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32SubU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwAnd {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwAnd {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8AndU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16AndU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8AndU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16AndU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32AndU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwOr {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwOr {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8OrU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16OrU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8OrU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16OrU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32OrU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwXor {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwXor {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8XorU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16XorU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8XorU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16XorU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32XorU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwXchg {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwXchg {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8XchgU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16XchgU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8XchgU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16XchgU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32XchgU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmwCmpxchg {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmwCmpxchg {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw8CmpxchgU {
+        mem_arg: MemArg,
+    },
+    I32AtomicRmw16CmpxchgU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw8CmpxchgU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw16CmpxchgU {
+        mem_arg: MemArg,
+    },
+    I64AtomicRmw32CmpxchgU {
+        mem_arg: MemArg,
+    },
 }
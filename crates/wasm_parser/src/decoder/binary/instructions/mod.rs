@@ -1,4 +1,5 @@
 mod block;
+#[allow(clippy::module_inception)]
 mod instructions;
 
 pub use block::*;
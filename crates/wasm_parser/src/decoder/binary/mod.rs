@@ -1,3 +1,4 @@
+pub mod const_expr;
 pub mod instructions;
 pub mod module;
 pub mod section;
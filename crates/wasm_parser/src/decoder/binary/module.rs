@@ -1,17 +1,25 @@
+use crate::decoder::Result;
+
 use super::{
-    section::Code,
-    types::{Data, Element, Export, FuncType, Global, Import, MemoryType, TableType},
+    section::{Code, CustomSection, CustomSectionRef, FunctionBody, NameSection},
+    types::{
+        Data, DataRef, Element, Export, ExportRef, FuncType, Global, Import, ImportRef, MemoryType,
+        TableType, TagType,
+    },
 };
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Module {
     pub version: u32,
-    pub custom_section: Option<()>,
+    pub custom_section: Vec<CustomSection>,
+    /// Parsed contents of the `name` custom section, if present and well-formed.
+    pub name_section: Option<NameSection>,
     pub type_section: Vec<FuncType>,
     pub import_section: Vec<Import>,
     pub function_section: Vec<u32>,
     pub table_section: Vec<TableType>,
     pub memory_section: Vec<MemoryType>,
+    pub tag_section: Vec<TagType>,
     pub global_section: Vec<Global>,
     pub export_section: Vec<Export>,
     pub start_section: Option<u32>,
@@ -21,23 +29,52 @@ pub struct Module {
     pub data_count_section: Option<u32>,
 }
 
-impl Default for Module {
-    fn default() -> Self {
-        Module {
-            version: 0,
-            custom_section: None,
-            type_section: vec![],
-            import_section: vec![],
-            function_section: vec![],
-            table_section: vec![],
-            memory_section: vec![],
-            global_section: vec![],
-            export_section: vec![],
-            start_section: None,
-            element_section: vec![],
-            code_section: vec![],
-            data_section: vec![],
-            data_count_section: None,
-        }
+/// Module whose names and byte contents borrow from the module binary, and
+/// whose function bodies are only decoded on demand.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BorrowedModule<'a> {
+    pub version: u32,
+    pub custom_section: Vec<CustomSectionRef<'a>>,
+    pub name_section: Option<NameSection>,
+    pub type_section: Vec<FuncType>,
+    pub import_section: Vec<ImportRef<'a>>,
+    pub function_section: Vec<u32>,
+    pub table_section: Vec<TableType>,
+    pub memory_section: Vec<MemoryType>,
+    pub tag_section: Vec<TagType>,
+    pub global_section: Vec<Global>,
+    pub export_section: Vec<ExportRef<'a>>,
+    pub start_section: Option<u32>,
+    pub element_section: Vec<Element>,
+    pub code_section: Vec<FunctionBody<'a>>,
+    pub data_section: Vec<DataRef<'a>>,
+    pub data_count_section: Option<u32>,
+}
+
+impl BorrowedModule<'_> {
+    /// Copies the module into an owned `Module`, decoding every function body.
+    pub fn into_module(self) -> Result<Module> {
+        Ok(Module {
+            version: self.version,
+            custom_section: self.custom_section.into_iter().map(Into::into).collect(),
+            name_section: self.name_section,
+            type_section: self.type_section,
+            import_section: self.import_section.into_iter().map(Into::into).collect(),
+            function_section: self.function_section,
+            table_section: self.table_section,
+            memory_section: self.memory_section,
+            tag_section: self.tag_section,
+            global_section: self.global_section,
+            export_section: self.export_section.into_iter().map(Into::into).collect(),
+            start_section: self.start_section,
+            element_section: self.element_section,
+            code_section: self
+                .code_section
+                .iter()
+                .map(FunctionBody::to_code)
+                .collect::<Result<_>>()?,
+            data_section: self.data_section.into_iter().map(Into::into).collect(),
+            data_count_section: self.data_count_section,
+        })
     }
 }
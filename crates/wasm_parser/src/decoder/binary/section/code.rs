@@ -1,7 +1,45 @@
-use crate::decoder::binary::{instructions::Instruction, types::ValueType};
+use crate::decoder::{
+    binary::{instructions::Instruction, types::ValueType},
+    Decoder, InstructionIter, Result,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Code {
     pub locals: Vec<ValueType>,
     pub code: Vec<Instruction>,
 }
+
+/// Function body that hasn't been decoded yet. It borrows its bytes from the
+/// module binary and decodes them only when asked to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionBody<'a> {
+    /// Function index, imported functions included.
+    pub func_index: u32,
+    /// Offset of `bytes` in the module binary.
+    pub offset: usize,
+    /// Locals declarations followed by the instructions.
+    pub bytes: &'a [u8],
+}
+
+impl<'a> FunctionBody<'a> {
+    pub fn locals(&self) -> Result<Vec<ValueType>> {
+        Decoder::for_function_body(self).read_locals()
+    }
+
+    /// Returns an iterator decoding the instructions one at a time.
+    pub fn instructions(&self) -> Result<InstructionIter<'a>> {
+        let mut decoder = Decoder::for_function_body(self);
+        decoder.read_locals()?;
+
+        Ok(InstructionIter::new(decoder))
+    }
+
+    /// Decodes the whole body.
+    pub fn to_code(&self) -> Result<Code> {
+        let mut decoder = Decoder::for_function_body(self);
+        let locals = decoder.read_locals()?;
+        let code = InstructionIter::new(decoder).collect::<Result<_>>()?;
+
+        Ok(Code { locals, code })
+    }
+}
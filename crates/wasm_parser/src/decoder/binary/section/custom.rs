@@ -0,0 +1,24 @@
+/// Custom section.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/binary/modules.html#custom-section
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomSection {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Custom section whose name and contents borrow from the module binary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomSectionRef<'a> {
+    pub name: &'a str,
+    pub bytes: &'a [u8],
+}
+
+impl From<CustomSectionRef<'_>> for CustomSection {
+    fn from(custom: CustomSectionRef<'_>) -> Self {
+        CustomSection {
+            name: custom.name.to_owned(),
+            bytes: custom.bytes.to_vec(),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum SectionId {
     Custom,
@@ -13,6 +15,7 @@ pub enum SectionId {
     Code,
     Data,
     DataCount,
+    Tag,
     Unknown(u8),
 }
 
@@ -32,16 +35,81 @@ impl From<u8> for SectionId {
             10 => SectionId::Code,
             11 => SectionId::Data,
             12 => SectionId::DataCount,
+            13 => SectionId::Tag,
             _ => SectionId::Unknown(value),
         }
     }
 }
 
+impl From<SectionId> for u8 {
+    fn from(id: SectionId) -> Self {
+        match id {
+            SectionId::Custom => 0,
+            SectionId::Type => 1,
+            SectionId::Import => 2,
+            SectionId::Function => 3,
+            SectionId::Table => 4,
+            SectionId::Memory => 5,
+            SectionId::Global => 6,
+            SectionId::Export => 7,
+            SectionId::Start => 8,
+            SectionId::Element => 9,
+            SectionId::Code => 10,
+            SectionId::Data => 11,
+            SectionId::DataCount => 12,
+            SectionId::Tag => 13,
+            SectionId::Unknown(id) => id,
+        }
+    }
+}
+
+impl fmt::Display for SectionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SectionId::Custom => write!(f, "custom"),
+            SectionId::Type => write!(f, "type"),
+            SectionId::Import => write!(f, "import"),
+            SectionId::Function => write!(f, "function"),
+            SectionId::Table => write!(f, "table"),
+            SectionId::Memory => write!(f, "memory"),
+            SectionId::Global => write!(f, "global"),
+            SectionId::Export => write!(f, "export"),
+            SectionId::Start => write!(f, "start"),
+            SectionId::Element => write!(f, "element"),
+            SectionId::Code => write!(f, "code"),
+            SectionId::Data => write!(f, "data"),
+            SectionId::DataCount => write!(f, "data count"),
+            SectionId::Tag => write!(f, "tag"),
+            SectionId::Unknown(id) => write!(f, "unknown({:#04x})", id),
+        }
+    }
+}
+
 impl SectionId {
     pub fn is_unknown(&self) -> bool {
+        matches!(self, SectionId::Unknown(_))
+    }
+
+    /// Position of the section in the order required by the spec.
+    /// Custom sections may appear anywhere and have no position.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/spec/core/binary/modules.html#binary-module
+    pub fn order(&self) -> Option<u8> {
         match self {
-            SectionId::Unknown(_) => true,
-            _ => false,
+            SectionId::Custom | SectionId::Unknown(_) => None,
+            SectionId::Type => Some(1),
+            SectionId::Import => Some(2),
+            SectionId::Function => Some(3),
+            SectionId::Table => Some(4),
+            SectionId::Memory => Some(5),
+            SectionId::Tag => Some(6),
+            SectionId::Global => Some(7),
+            SectionId::Export => Some(8),
+            SectionId::Start => Some(9),
+            SectionId::Element => Some(10),
+            SectionId::DataCount => Some(11),
+            SectionId::Code => Some(12),
+            SectionId::Data => Some(13),
         }
     }
 }
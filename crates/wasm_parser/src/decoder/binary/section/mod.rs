@@ -1,5 +1,9 @@
 mod code;
+mod custom;
 mod id;
+mod name;
 
 pub use code::*;
+pub use custom::*;
 pub use id::*;
+pub use name::*;
@@ -0,0 +1,26 @@
+use std::collections::BTreeMap;
+
+/// Name section, the custom section named `name`.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NameSection {
+    pub module_name: Option<String>,
+    /// Function index to function name.
+    pub function_names: BTreeMap<u32, String>,
+    /// Function index to local index to local name.
+    pub local_names: BTreeMap<u32, BTreeMap<u32, String>>,
+}
+
+impl NameSection {
+    pub fn function_name(&self, func_index: u32) -> Option<&str> {
+        self.function_names.get(&func_index).map(String::as_str)
+    }
+
+    pub fn local_name(&self, func_index: u32, local_index: u32) -> Option<&str> {
+        self.local_names
+            .get(&func_index)
+            .and_then(|locals| locals.get(&local_index))
+            .map(String::as_str)
+    }
+}
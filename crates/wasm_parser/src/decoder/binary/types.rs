@@ -1,4 +1,4 @@
-use super::instructions::Instruction;
+use super::const_expr::ConstExpr;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ValueType {
@@ -30,6 +30,10 @@ pub enum ValueType {
     ///
     /// `0x6F`
     ExternRef,
+    /// Reference to an exception (exception handling proposal).
+    ///
+    /// `0x69`
+    ExnRef,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,10 +42,11 @@ pub struct FuncType {
     pub results: Vec<ValueType>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RefType {
     FuncRef,
     ExternRef,
+    ExnRef,
 }
 
 /// Limits.
@@ -49,8 +54,13 @@ pub enum RefType {
 /// WebAssembly specification: https://webassembly.github.io/spec/core/syntax/types.html#limits
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Limits {
-    pub min: u32,
-    pub max: Option<u32>,
+    /// Minimum size, in pages for memories and in elements for tables.
+    pub min: u64,
+    pub max: Option<u64>,
+    /// Whether the memory is shared between threads (threads proposal).
+    pub shared: bool,
+    /// Whether the memory is indexed with `i64` addresses (memory64 proposal).
+    pub index64: bool,
 }
 
 /// Table type.
@@ -67,6 +77,14 @@ pub struct MemoryType {
     pub limits: Limits,
 }
 
+/// Tag type, the signature of an exception (exception handling proposal).
+///
+/// WebAssembly specification: https://webassembly.github.io/exception-handling/core/binary/types.html#tag-types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagType {
+    pub type_index: u32,
+}
+
 /// Global type.
 ///
 /// WebAssembly specification: https://webassembly.github.io/spec/core/syntax/types.html#global-types
@@ -82,7 +100,7 @@ pub struct GlobalType {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Global {
     pub global_type: GlobalType,
-    pub init_expr: Instruction,
+    pub init_expr: ConstExpr,
 }
 
 /// Import description.
@@ -94,6 +112,7 @@ pub enum ImportDesc {
     Table(TableType),
     Memory(Limits),
     Global(GlobalType),
+    Tag(TagType),
 }
 
 /// Import.
@@ -106,6 +125,24 @@ pub struct Import {
     pub desc: ImportDesc,
 }
 
+/// Import whose names borrow from the module binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportRef<'a> {
+    pub module: &'a str,
+    pub field: &'a str,
+    pub desc: ImportDesc,
+}
+
+impl From<ImportRef<'_>> for Import {
+    fn from(import: ImportRef<'_>) -> Self {
+        Import {
+            module: import.module.to_owned(),
+            field: import.field.to_owned(),
+            desc: import.desc,
+        }
+    }
+}
+
 /// Export.
 ///
 /// WebAssembly specification: https://webassembly.github.io/spec/core/binary/modules.html#binary-export
@@ -115,6 +152,22 @@ pub struct Export {
     pub desc: ExportDesc,
 }
 
+/// Export whose name borrows from the module binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportRef<'a> {
+    pub name: &'a str,
+    pub desc: ExportDesc,
+}
+
+impl From<ExportRef<'_>> for Export {
+    fn from(export: ExportRef<'_>) -> Self {
+        Export {
+            name: export.name.to_owned(),
+            desc: export.desc,
+        }
+    }
+}
+
 /// Export description.
 ///
 /// WebAssembly specification: https://webassembly.github.io/spec/core/binary/modules.html#binary-exportdesc
@@ -124,22 +177,31 @@ pub enum ExportDesc {
     Table(u32),
     Mem(u32),
     Global(u32),
+    Tag(u32),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Element {
     pub mode: ElementMode,
     pub ref_type: RefType,
-    pub init: Vec<u32>,
+    pub init: ElementInit,
+}
+
+/// Initial values of an element segment.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/binary/modules.html#element-section
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementInit {
+    /// Function indices (prefixes 0 to 3).
+    Functions(Vec<u32>),
+    /// Constant expressions (prefixes 4 to 7).
+    Expressions(Vec<ConstExpr>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ElementMode {
     Passive,
-    Active {
-        table_index: u32,
-        offset: Instruction,
-    },
+    Active { table_index: u32, offset: ConstExpr },
     Declarative,
 }
 
@@ -149,11 +211,27 @@ pub struct Data {
     pub bytes: Vec<u8>,
 }
 
+/// Data segment whose bytes borrow from the module binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataRef<'a> {
+    pub mode: DataMode,
+    pub bytes: &'a [u8],
+}
+
+impl From<DataRef<'_>> for Data {
+    fn from(data: DataRef<'_>) -> Self {
+        Data {
+            mode: data.mode,
+            bytes: data.bytes.to_vec(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum DataMode {
     Passive,
     Active {
         memory_index: u32,
-        offset: Instruction,
+        offset: ConstExpr,
     },
 }
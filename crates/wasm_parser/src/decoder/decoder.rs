@@ -1,169 +1,390 @@
-use anyhow::{bail, Context};
-use std::io::{BufReader, Read};
+use std::collections::BTreeMap;
 
 use crate::decoder::binary::types::ValueType;
 
 use super::{
     binary::{
-        instructions::{Block, BlockType, Instruction, MemArg},
-        module::Module,
-        section::{Code, SectionId},
+        const_expr::ConstExpr,
+        instructions::{Block, BlockType, Catch, Instruction, MemArg, MEM_ARG_HAS_MEMORY_INDEX},
+        module::{BorrowedModule, Module},
+        section::{CustomSectionRef, FunctionBody, NameSection, SectionId},
         types::{
-            Element, ElementMode, Export, ExportDesc, FuncType, Global, GlobalType, Import,
-            ImportDesc, Limits, MemoryType, RefType, TableType,
+            DataRef, Element, ElementInit, ElementMode, ExportDesc, ExportRef, FuncType, Global,
+            GlobalType, ImportDesc, ImportRef, Limits, MemoryType, RefType, TableType, TagType,
         },
     },
-    result::{DecodeError, Result},
-    types::{Data, DataMode},
+    result::{DecodeError, DecodeErrorKind, Result},
+    types::DataMode,
 };
 
-pub struct Decoder<R> {
-    reader: BufReader<R>,
+/// Upper bound on the number of locals a single function may declare.
+///
+/// Same limit as the major engines use; it keeps a malicious local count from
+/// exhausting memory.
+const MAX_LOCALS: u32 = 50_000;
+
+/// Upper bound on how many items `read_vec` and the streaming decoder reserve
+/// before reading them.
+pub(super) const MAX_VEC_PREALLOCATION: usize = 1024;
+
+/// Decoder over an in-memory module binary.
+///
+/// Names and byte contents are borrowed from the input rather than copied.
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Offset of `bytes` in the module binary.
+    base_offset: usize,
+    section: Option<SectionId>,
+    func_index: Option<u32>,
+    instr_index: Option<u32>,
 }
 
-impl<R: Read> Decoder<R> {
-    pub fn new(reader: R) -> Self {
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_offset(bytes, 0)
+    }
+
+    /// Creates a decoder for bytes that start at `offset` in the module binary.
+    pub(crate) fn with_offset(bytes: &'a [u8], offset: usize) -> Self {
         Decoder {
-            reader: BufReader::new(reader),
+            bytes,
+            pos: 0,
+            base_offset: offset,
+            section: None,
+            func_index: None,
+            instr_index: None,
         }
     }
 
-    fn is_empty(&mut self) -> bool {
-        // self.reader.fill_buf().unwrap().is_empty()
-        self.reader.buffer().is_empty()
+    /// Creates a decoder over `bytes`, which start at `offset` in the module
+    /// binary, reporting errors in the same section and function as `self`.
+    fn sub_decoder(&self, bytes: &'a [u8], offset: usize) -> Decoder<'a> {
+        Decoder {
+            section: self.section,
+            func_index: self.func_index,
+            ..Decoder::with_offset(bytes, offset)
+        }
     }
 
-    pub fn decode(&mut self) -> Result<Module> {
-        self.validate_magic_header()?;
-        let version = self.decode_version()?;
+    /// Creates a decoder for the contents of a section, which start at
+    /// `offset` in the module binary.
+    pub(crate) fn for_section(id: SectionId, bytes: &'a [u8], offset: usize) -> Self {
+        Decoder {
+            section: Some(id),
+            ..Decoder::with_offset(bytes, offset)
+        }
+    }
 
-        if version != 1 {
-            bail!(DecodeError::InvalidVersion);
+    pub(crate) fn for_function_body(body: &FunctionBody<'a>) -> Self {
+        Decoder {
+            section: Some(SectionId::Code),
+            func_index: Some(body.func_index),
+            ..Decoder::with_offset(body.bytes, body.offset)
+        }
+    }
+
+    /// Absolute offset of the next byte to be read.
+    pub(crate) fn offset(&self) -> usize {
+        self.base_offset + self.pos
+    }
+
+    fn error(&self, kind: DecodeErrorKind) -> DecodeError {
+        self.error_at(self.offset(), kind)
+    }
+
+    pub(crate) fn error_at(&self, offset: usize, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            offset,
+            section: self.section,
+            func_index: self.func_index,
+            instr_index: self.instr_index,
+            kind,
         }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    /// Decodes the whole module into an owned `Module`.
+    pub fn decode(&mut self) -> Result<Module> {
+        self.decode_borrowed()?.into_module()
+    }
 
-        let mut module = Module::default();
+    /// Decodes the module without copying names and byte contents, and without
+    /// decoding function bodies.
+    pub fn decode_borrowed(&mut self) -> Result<BorrowedModule<'a>> {
+        let version = self.decode_header()?;
+
+        let mut module = BorrowedModule {
+            version,
+            ..BorrowedModule::default()
+        };
+        let mut last_order = 0;
 
         loop {
             if self.is_empty() {
                 break;
             }
 
-            let id = match self.read_section() {
-                Ok((id, _)) => id,
-                Err(e) => return Err(e),
-            };
+            let section_offset = self.offset();
+            let (id, size) = self.read_section()?;
+            self.check_section_order(id, section_offset, &mut last_order)?;
+            let mut d = self.read_section_contents(id, size)?;
 
             match id {
-                SectionId::Custom => {}
+                SectionId::Custom => {
+                    let custom = d.decode_custom_section()?;
+                    if module.name_section.is_none() {
+                        module.name_section = d.decode_names(&custom);
+                    }
+
+                    module.custom_section.push(custom);
+                }
                 SectionId::Type => {
-                    module.type_section =
-                        self.decode_type_section().context("decode type section")?;
+                    module.type_section = d.decode_type_section()?;
                 }
                 SectionId::Import => {
-                    module.import_section = self
-                        .decode_import_section()
-                        .context("decode import section")?;
+                    module.import_section = d.decode_import_section()?;
                 }
                 SectionId::Function => {
-                    module.function_section = self
-                        .decode_function_section()
-                        .context("decode function section")?;
+                    module.function_section = d.decode_function_section()?;
                 }
                 SectionId::Table => {
-                    module.table_section = self
-                        .decode_table_section()
-                        .context("decode table section")?;
+                    module.table_section = d.decode_table_section()?;
                 }
                 SectionId::Memory => {
-                    module.memory_section = self
-                        .decode_memory_section()
-                        .context("decode memory section")?;
+                    module.memory_section = d.decode_memory_section()?;
+                }
+                SectionId::Tag => {
+                    module.tag_section = d.decode_tag_section()?;
                 }
                 SectionId::Global => {
-                    module.global_section = self
-                        .decode_global_section()
-                        .context("decode global section")?;
+                    module.global_section = d.decode_global_section()?;
                 }
                 SectionId::Export => {
-                    module.export_section = self
-                        .decode_export_section()
-                        .context("decode export section")?;
+                    module.export_section = d.decode_export_section()?;
                 }
                 SectionId::Start => {
-                    module.start_section = self
-                        .decode_start_section()
-                        .context("decode start section")?;
+                    module.start_section = Some(d.decode_start_section()?);
                 }
                 SectionId::Element => {
-                    module.element_section = self
-                        .decode_element_section()
-                        .context("decode element section")?;
+                    module.element_section = d.decode_element_section()?;
                 }
                 SectionId::Code => {
-                    module.code_section =
-                        self.decode_code_section().context("decode code section")?
+                    let imported_funcs = module
+                        .import_section
+                        .iter()
+                        .filter(|import| matches!(import.desc, ImportDesc::Func(_)))
+                        .count() as u32;
+                    module.code_section = d.decode_code_section(imported_funcs)?;
                 }
                 SectionId::Data => {
-                    module.data_section =
-                        self.decode_data_section().context("decode data section")?
+                    module.data_section = d.decode_data_section()?;
                 }
                 SectionId::DataCount => {
-                    module.data_count_section = Some(
-                        self.decode_data_count_section()
-                            .context("decode data count section")?,
-                    );
+                    module.data_count_section = Some(d.decode_data_count_section()?);
                 }
-                _ => {
-                    unimplemented!("[decode] {:?} is not implemented", id)
+                SectionId::Unknown(_) => {
+                    return Err(self.error(DecodeErrorKind::InvalidSectionId(id)))
                 }
             }
+
+            d.check_section_end(size)?;
         }
 
+        self.check_counts(
+            module.function_section.len(),
+            module.code_section.len(),
+            module.data_count_section,
+            module.data_section.len(),
+        )?;
+
         Ok(module)
     }
 
+    /// Decodes the magic header and the version.
+    pub(crate) fn decode_header(&mut self) -> Result<u32> {
+        self.validate_magic_header()?;
+        let version = self.decode_version()?;
+
+        if version != 1 {
+            return Err(self.error_at(4, DecodeErrorKind::InvalidVersion));
+        }
+
+        Ok(version)
+    }
+
     fn validate_magic_header(&mut self) -> Result<()> {
-        let mut magic = [0; 4];
-        self.reader.read_exact(&mut magic).unwrap();
+        let Some(magic) = self.read_array::<4>() else {
+            return Err(self.error(DecodeErrorKind::TruncatedHeader));
+        };
 
         match magic {
             [0x00, 0x61, 0x73, 0x6d] => Ok(()),
-            _ => bail!(DecodeError::InvalidMagicHeader),
+            _ => Err(self.error_at(0, DecodeErrorKind::InvalidMagicHeader)),
         }
     }
 
     fn decode_version(&mut self) -> Result<u32> {
-        let mut version = [0; 4];
-        self.reader.read_exact(&mut version).unwrap();
+        let Some(version) = self.read_array::<4>() else {
+            return Err(self.error(DecodeErrorKind::TruncatedHeader));
+        };
 
         Ok(u32::from_le_bytes(version))
     }
 
-    fn decode_type_section(&mut self) -> Result<Vec<FuncType>> {
+    /// Checks that the section starting at `offset` comes after the previous
+    /// non-custom section, whose order is `last_order`.
+    pub(crate) fn check_section_order(
+        &self,
+        id: SectionId,
+        offset: usize,
+        last_order: &mut u8,
+    ) -> Result<()> {
+        if let Some(order) = id.order() {
+            if order == *last_order {
+                return Err(self.error_at(offset, DecodeErrorKind::DuplicateSection(id)));
+            }
+            if order < *last_order {
+                return Err(self.error_at(offset, DecodeErrorKind::SectionOutOfOrder(id)));
+            }
+            *last_order = order;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the contents of a section and returns a decoder for them.
+    ///
+    /// Each section is decoded from its own bounded buffer so that a wrong size
+    /// can't make it read into the next section.
+    pub(crate) fn read_section_contents(
+        &mut self,
+        id: SectionId,
+        size: u32,
+    ) -> Result<Decoder<'a>> {
+        let offset = self.offset();
+        let bytes = self.read_bytes(size as usize)?;
+
+        Ok(Decoder::for_section(id, bytes, offset))
+    }
+
+    /// Checks that the whole section was decoded.
+    pub(crate) fn check_section_end(&self, size: u32) -> Result<()> {
+        if !self.is_empty() {
+            return Err(self.error(DecodeErrorKind::SectionSizeMismatch {
+                expected: size,
+                actual: self.pos,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the counts that can only be compared once all sections are decoded.
+    pub(crate) fn check_counts(
+        &self,
+        functions: usize,
+        bodies: usize,
+        data_count: Option<u32>,
+        data: usize,
+    ) -> Result<()> {
+        if functions != bodies {
+            return Err(self.error(DecodeErrorKind::FunctionCodeMismatch { functions, bodies }));
+        }
+
+        if let Some(count) = data_count {
+            if count as usize != data {
+                return Err(self.error(DecodeErrorKind::DataCountMismatch {
+                    expected: count,
+                    actual: data,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes a custom section, which takes up the rest of the decoder's bytes.
+    pub(crate) fn decode_custom_section(&mut self) -> Result<CustomSectionRef<'a>> {
+        let name = self.read_name()?;
+        let bytes = self.read_bytes(self.bytes.len() - self.pos)?;
+
+        Ok(CustomSectionRef { name, bytes })
+    }
+
+    /// Decodes `custom` as a name section if it is one.
+    ///
+    /// Malformed name sections are ignored rather than rejected, as recommended
+    /// by the spec.
+    pub(crate) fn decode_names(&self, custom: &CustomSectionRef<'a>) -> Option<NameSection> {
+        if custom.name != "name" {
+            return None;
+        }
+
+        // The custom section's bytes run to the end of the section.
+        let offset = self.base_offset + self.bytes.len() - custom.bytes.len();
+        self.sub_decoder(custom.bytes, offset)
+            .decode_name_section(offset + custom.bytes.len())
+            .ok()
+    }
+
+    /// Decodes the contents of the `name` custom section, which end at offset `end`.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+    fn decode_name_section(&mut self, end: usize) -> Result<NameSection> {
+        let mut names = NameSection::default();
+
+        while self.offset() < end {
+            let id = self.read_u8()?;
+            let size = self.read_size()?;
+            let offset = self.offset();
+            let bytes = self.read_bytes(size as usize)?;
+            let mut d = self.sub_decoder(bytes, offset);
+
+            match id {
+                0x00 => names.module_name = Some(d.read_name()?.to_owned()),
+                0x01 => names.function_names = d.read_name_map()?,
+                0x02 => {
+                    names.local_names = d
+                        .read_vec(|d| Ok((d.read_size()?, d.read_name_map()?)))?
+                        .into_iter()
+                        .collect();
+                }
+                // Other subsections (labels, types, globals, ...) are not used yet.
+                _ => {}
+            }
+        }
+
+        Ok(names)
+    }
+
+    pub(crate) fn decode_type_section(&mut self) -> Result<Vec<FuncType>> {
         self.read_vec(|d| {
+            let offset = d.offset();
             let kind = d.read_u8()?;
 
             // functype starts with `0x60`.
             // Ref: https://webassembly.github.io/spec/core/binary/types.html#function-types
             if kind != 0x60 {
-                bail!(DecodeError::InvalidTypeKind);
+                return Err(d.error_at(offset, DecodeErrorKind::InvalidTypeKind));
             }
 
-            let params = d.read_vec(|d| d.read_value_type()).context("read params")?;
+            let params = d.read_vec(|d| d.read_value_type())?;
 
-            let results = d
-                .read_vec(|d| d.read_value_type())
-                .context("read results")?;
+            let results = d.read_vec(|d| d.read_value_type())?;
 
             Ok(FuncType { params, results })
         })
-        .context("read type section vector")
     }
 
-    fn decode_import_section(&mut self) -> Result<Vec<Import>> {
+    pub(crate) fn decode_import_section(&mut self) -> Result<Vec<ImportRef<'a>>> {
         self.read_vec(|d| {
             let module = d.read_name()?;
             let field = d.read_name()?;
+            let offset = d.offset();
             let desc_id = d.read_u8()?;
 
             let desc = match desc_id {
@@ -183,10 +404,14 @@ impl<R: Read> Decoder<R> {
                     let global = d.read_global_type()?;
                     ImportDesc::Global(global)
                 }
-                id => bail!(DecodeError::InvalidImportDescription(id)),
+                0x04 => {
+                    let tag = d.read_tag_type()?;
+                    ImportDesc::Tag(tag)
+                }
+                id => return Err(d.error_at(offset, DecodeErrorKind::InvalidImportDescription(id))),
             };
 
-            Ok(Import {
+            Ok(ImportRef {
                 module,
                 field,
                 desc,
@@ -194,20 +419,15 @@ impl<R: Read> Decoder<R> {
         })
     }
 
-    fn decode_function_section(&mut self) -> Result<Vec<u32>> {
+    pub(crate) fn decode_function_section(&mut self) -> Result<Vec<u32>> {
         self.read_vec(|d| d.read_size())
     }
 
-    fn decode_table_section(&mut self) -> Result<Vec<TableType>> {
-        self.read_vec(|d| {
-            Ok(TableType {
-                element_type: d.read_reference_type()?,
-                limits: d.read_limits()?,
-            })
-        })
+    pub(crate) fn decode_table_section(&mut self) -> Result<Vec<TableType>> {
+        self.read_vec(|d| d.read_table_type())
     }
 
-    fn decode_memory_section(&mut self) -> Result<Vec<MemoryType>> {
+    pub(crate) fn decode_memory_section(&mut self) -> Result<Vec<MemoryType>> {
         self.read_vec(|d| {
             Ok(MemoryType {
                 limits: d.read_limits()?,
@@ -215,12 +435,16 @@ impl<R: Read> Decoder<R> {
         })
     }
 
-    fn decode_global_section(&mut self) -> Result<Vec<Global>> {
+    pub(crate) fn decode_tag_section(&mut self) -> Result<Vec<TagType>> {
+        self.read_vec(|d| d.read_tag_type())
+    }
+
+    pub(crate) fn decode_global_section(&mut self) -> Result<Vec<Global>> {
         self.read_vec(|d| {
             let value_type = d.read_value_type()?;
             let mutable = d.read_u8()? == 0x01;
 
-            let init_expr = d.read_instruction_and_end()?;
+            let init_expr = d.read_const_expr()?;
 
             Ok(Global {
                 global_type: GlobalType {
@@ -232,9 +456,10 @@ impl<R: Read> Decoder<R> {
         })
     }
 
-    fn decode_export_section(&mut self) -> Result<Vec<Export>> {
+    pub(crate) fn decode_export_section(&mut self) -> Result<Vec<ExportRef<'a>>> {
         self.read_vec(|d| {
             let name = d.read_name()?;
+            let offset = d.offset();
             let desc_kind = d.read_u8()?;
 
             let desc = match desc_kind {
@@ -242,123 +467,150 @@ impl<R: Read> Decoder<R> {
                 0x01 => ExportDesc::Table(d.read_size()?),
                 0x02 => ExportDesc::Mem(d.read_size()?),
                 0x03 => ExportDesc::Global(d.read_size()?),
-                _ => bail!(DecodeError::InvalidExportDescription),
+                0x04 => ExportDesc::Tag(d.read_size()?),
+                _ => return Err(d.error_at(offset, DecodeErrorKind::InvalidExportDescription)),
             };
 
-            Ok(Export { name, desc })
+            Ok(ExportRef { name, desc })
         })
     }
 
-    fn decode_start_section(&mut self) -> Result<Option<u32>> {
-        let index = self.read_size()?;
-        Ok(Some(index))
+    pub(crate) fn decode_start_section(&mut self) -> Result<u32> {
+        self.read_size()
     }
 
-    fn decode_element_section(&mut self) -> Result<Vec<Element>> {
+    pub(crate) fn decode_element_section(&mut self) -> Result<Vec<Element>> {
         self.read_vec(|d| {
+            let offset = d.offset();
             let prefix = d.read_size()?;
 
-            let element = match prefix {
-                0 => {
-                    let offset = d.read_instruction_and_end()?;
-                    let init = d.read_vec(|d| d.read_size())?;
-
-                    Element {
-                        ref_type: RefType::FuncRef,
-                        init,
-                        mode: ElementMode::Active {
-                            table_index: 0,
-                            offset,
-                        },
-                    }
+            if prefix > 7 {
+                return Err(d.error_at(offset, DecodeErrorKind::UnsupportedElementPrefix));
+            }
+
+            // The prefix is a bit field:
+            // bit 0: passive or declarative, bit 1: explicit table index (active) or
+            // declarative (otherwise), bit 2: initial values are expressions.
+            // Ref: https://webassembly.github.io/spec/core/binary/modules.html#element-section
+            let mode = if prefix & 0b001 == 0 {
+                let table_index = if prefix & 0b010 != 0 {
+                    d.read_size()?
+                } else {
+                    0
+                };
+                let offset = d.read_const_expr()?;
+
+                ElementMode::Active {
+                    table_index,
+                    offset,
                 }
-                _ => bail!(DecodeError::UnsupportedElementPrefix),
+            } else if prefix & 0b010 == 0 {
+                ElementMode::Passive
+            } else {
+                ElementMode::Declarative
             };
 
-            Ok(element)
+            let ref_type = match prefix {
+                0 | 4 => RefType::FuncRef,
+                1..=3 => d.read_element_kind()?,
+                _ => d.read_reference_type()?,
+            };
+
+            let init = if prefix & 0b100 == 0 {
+                ElementInit::Functions(d.read_vec(|d| d.read_size())?)
+            } else {
+                ElementInit::Expressions(d.read_vec(|d| d.read_const_expr())?)
+            };
+
+            Ok(Element {
+                mode,
+                ref_type,
+                init,
+            })
         })
     }
 
-    fn decode_code_section(&mut self) -> Result<Vec<Code>> {
+    /// Decodes the code section, leaving the function bodies undecoded.
+    /// `imported_funcs` is the number of imported functions, which come first
+    /// in the function index space.
+    pub(crate) fn decode_code_section(
+        &mut self,
+        imported_funcs: u32,
+    ) -> Result<Vec<FunctionBody<'a>>> {
+        let mut func_index = imported_funcs;
+
         self.read_vec(|d| {
-            let body_size = d.read_size()?;
-            let bytes = d.read_bytes(body_size as usize)?;
-            let mut d = Decoder::new(bytes.as_slice());
-
-            let locals: Vec<ValueType> = d
-                .read_vec(|d| {
-                    let mut locals = vec![];
-                    let local_count = d.read_size()?;
-                    let ty = d.read_value_type()?;
-
-                    for _ in 0..local_count {
-                        locals.push(ty.clone());
-                    }
+            let body = d.read_function_body(func_index)?;
+            func_index += 1;
 
-                    Ok(locals)
-                })?
-                .into_iter()
-                .flatten()
-                .collect();
+            Ok(body)
+        })
+    }
 
-            let body = d.read_instructions()?;
+    /// Reads the size and bytes of a function body without decoding it.
+    pub(crate) fn read_function_body(&mut self, func_index: u32) -> Result<FunctionBody<'a>> {
+        let body_size = self.read_size()?;
+        let offset = self.offset();
+        let bytes = self.read_bytes(body_size as usize)?;
 
-            Ok(Code { locals, code: body })
+        Ok(FunctionBody {
+            func_index,
+            offset,
+            bytes,
         })
     }
 
-    fn decode_data_section(&mut self) -> Result<Vec<Data>> {
+    pub(crate) fn decode_data_section(&mut self) -> Result<Vec<DataRef<'a>>> {
         self.read_vec(|d| {
+            let offset = d.offset();
             let prefix = d.read_size()?;
 
-            let data = match prefix {
-                0 => {
-                    let expr = d.read_instruction_and_end()?;
-                    let size = d.read_size()?;
-                    let bytes = d.read_bytes(size as usize)?;
-
-                    Data {
-                        bytes,
-                        mode: DataMode::Active {
-                            memory_index: 0,
-                            offset: expr,
-                        },
-                    }
-                }
-                p => bail!(DecodeError::UnsupportedDataPrefix(p)),
+            let mode = match prefix {
+                0 => DataMode::Active {
+                    memory_index: 0,
+                    offset: d.read_const_expr()?,
+                },
+                1 => DataMode::Passive,
+                2 => DataMode::Active {
+                    memory_index: d.read_size()?,
+                    offset: d.read_const_expr()?,
+                },
+                p => return Err(d.error_at(offset, DecodeErrorKind::UnsupportedDataPrefix(p))),
             };
 
-            Ok(data)
+            let size = d.read_size()?;
+            let bytes = d.read_bytes(size as usize)?;
+
+            Ok(DataRef { bytes, mode })
         })
     }
 
-    fn decode_data_count_section(&mut self) -> Result<u32> {
+    pub(crate) fn decode_data_count_section(&mut self) -> Result<u32> {
         self.read_size()
     }
 
-    fn read_instructions(&mut self) -> Result<Vec<Instruction>> {
-        let mut instructions = Vec::new();
-
-        loop {
-            if self.is_empty() {
-                break;
-            }
-
-            let instr = self.read_instruction()?;
-
-            instructions.push(instr);
+    /// Reads the locals declarations at the start of a function body.
+    pub(crate) fn read_locals(&mut self) -> Result<Vec<ValueType>> {
+        let offset = self.offset();
+        let local_groups = self.read_vec(|d| Ok((d.read_size()?, d.read_value_type()?)))?;
+
+        let local_count = local_groups
+            .iter()
+            .try_fold(0u32, |total, (count, _)| total.checked_add(*count));
+        match local_count {
+            Some(count) if count <= MAX_LOCALS => {}
+            _ => return Err(self.error_at(offset, DecodeErrorKind::TooManyLocals)),
         }
 
-        Ok(instructions)
+        Ok(local_groups
+            .into_iter()
+            .flat_map(|(count, ty)| std::iter::repeat_n(ty, count as usize))
+            .collect())
     }
 
     fn read_instruction(&mut self) -> Result<Instruction> {
-        let opcode = match self.read_u8() {
-            Ok(opcode) => opcode,
-            Err(err) => {
-                return Err(err);
-            }
-        };
+        let offset = self.offset();
+        let opcode = self.read_u8()?;
 
         let instr = match opcode {
             // control instructions
@@ -366,20 +618,24 @@ impl<R: Read> Decoder<R> {
             0x01 => Instruction::Nop,
             0x02 => Instruction::Block {
                 block: Block {
-                    block_type: self.read_block_type().context("instruction block")?,
+                    block_type: self.read_block_type()?,
                 },
             },
             0x03 => Instruction::Loop {
                 block: Block {
-                    block_type: self.read_block_type().context("instruction loop block")?,
+                    block_type: self.read_block_type()?,
                 },
             },
             0x04 => Instruction::If {
                 block: Block {
-                    block_type: self.read_block_type().context("instruction if block")?,
+                    block_type: self.read_block_type()?,
                 },
             },
             0x05 => Instruction::Else,
+            0x08 => Instruction::Throw {
+                tag_index: self.read_size()?,
+            },
+            0x0A => Instruction::ThrowRef,
             0x0B => Instruction::End,
             0x0C => Instruction::Br {
                 level: self.read_size()?,
@@ -399,6 +655,19 @@ impl<R: Read> Decoder<R> {
                 type_index: self.read_size()?,
                 table_index: self.read_size()?,
             },
+            0x12 => Instruction::ReturnCall {
+                func_index: self.read_size()?,
+            },
+            0x13 => Instruction::ReturnCallIndirect {
+                type_index: self.read_size()?,
+                table_index: self.read_size()?,
+            },
+            0x1F => Instruction::TryTable {
+                block: Block {
+                    block_type: self.read_block_type()?,
+                },
+                catches: self.read_vec(|d| d.read_catch())?,
+            },
             // reference instructions
             0xD0 => Instruction::RefNull {
                 ref_type: self.read_reference_type()?,
@@ -448,33 +717,20 @@ impl<R: Read> Decoder<R> {
                     0x05 => Instruction::I64TruncSatF32U,
                     0x06 => Instruction::I64TruncSatF64S,
                     0x07 => Instruction::I64TruncSatF64U,
-                    0x08 => {
-                        let inst = Instruction::MemoryInit {
-                            data_index: self.read_size()?,
-                        };
-
-                        self.read_u8()?; // 0x00
-
-                        inst
-                    }
+                    0x08 => Instruction::MemoryInit {
+                        data_index: self.read_size()?,
+                        memory_index: self.read_size()?,
+                    },
                     0x09 => Instruction::DataDrop {
                         data_index: self.read_size()?,
                     },
-                    0x0A => {
-                        let inst = Instruction::MemoryCopy;
-
-                        self.read_u8()?; // 0x00
-                        self.read_u8()?; // 0x00
-
-                        inst
-                    }
-                    0x0B => {
-                        let inst = Instruction::MemoryFill;
-
-                        self.read_u8()?; // 0x00
-
-                        inst
-                    }
+                    0x0A => Instruction::MemoryCopy {
+                        dst_memory_index: self.read_size()?,
+                        src_memory_index: self.read_size()?,
+                    },
+                    0x0B => Instruction::MemoryFill {
+                        memory_index: self.read_size()?,
+                    },
                     0x0C => Instruction::TableInit {
                         element_index: self.read_size()?,
                         table_index: self.read_size()?,
@@ -495,9 +751,15 @@ impl<R: Read> Decoder<R> {
                     0x11 => Instruction::TableFill {
                         table_index: self.read_size()?,
                     },
-                    v => bail!(DecodeError::InvalidSubInstructionId(v)),
+                    v => {
+                        return Err(
+                            self.error_at(offset, DecodeErrorKind::InvalidSubInstructionId(v))
+                        )
+                    }
                 }
             }
+            0xFD => self.read_vector_instruction(offset)?,
+            0xFE => self.read_atomic_instruction(offset)?,
             // memory instructions
             0x28 => Instruction::I32Load {
                 mem_arg: self.read_mem_arg()?,
@@ -568,14 +830,12 @@ impl<R: Read> Decoder<R> {
             0x3E => Instruction::I64Store32 {
                 mem_arg: self.read_mem_arg()?,
             },
-            0x3F => {
-                self.read_u8()?;
-                Instruction::MemorySize
-            }
-            0x40 => {
-                self.read_u8()?;
-                Instruction::MemoryGrow
-            }
+            0x3F => Instruction::MemorySize {
+                memory_index: self.read_size()?,
+            },
+            0x40 => Instruction::MemoryGrow {
+                memory_index: self.read_size()?,
+            },
             /* numerics */
             0x41 => Instruction::I32Const {
                 value: self.read_i32()?,
@@ -717,25 +977,579 @@ impl<R: Read> Decoder<R> {
             0xC2 => Instruction::I64Extend8S,
             0xC3 => Instruction::I64Extend16S,
             0xC4 => Instruction::I64Extend32S,
-            _ => unimplemented!("Opcode 0x{:02x} is not implemented", opcode),
+            _ => return Err(self.error_at(offset, DecodeErrorKind::UnknownOpcode(opcode))),
         };
 
         Ok(instr)
     }
 
-    fn read_instruction_and_end(&mut self) -> Result<Instruction> {
-        let instr = self.read_instruction()?;
-        let end = self.read_size()?;
-        if end != 0x0B {
-            bail!(DecodeError::Expected("0x0B".to_owned()));
-        }
+    /// Reads a vector instruction, whose `0xFD` prefix at `offset` has already
+    /// been read.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/spec/core/binary/instructions.html#vector-instructions
+    fn read_vector_instruction(&mut self, offset: usize) -> Result<Instruction> {
+        let id = self.read_size()?;
+
+        let instr = match id {
+            0x00 => Instruction::V128Load {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x01 => Instruction::V128Load8x8S {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x02 => Instruction::V128Load8x8U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x03 => Instruction::V128Load16x4S {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x04 => Instruction::V128Load16x4U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x05 => Instruction::V128Load32x2S {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x06 => Instruction::V128Load32x2U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x07 => Instruction::V128Load8Splat {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x08 => Instruction::V128Load16Splat {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x09 => Instruction::V128Load32Splat {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x0A => Instruction::V128Load64Splat {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x0B => Instruction::V128Store {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x0C => Instruction::V128Const {
+                value: self.read_v128()?,
+            },
+            0x0D => Instruction::I8x16Shuffle {
+                lanes: self.read_lanes()?,
+            },
+            0x0E => Instruction::I8x16Swizzle,
+            0x0F => Instruction::I8x16Splat,
+            0x10 => Instruction::I16x8Splat,
+            0x11 => Instruction::I32x4Splat,
+            0x12 => Instruction::I64x2Splat,
+            0x13 => Instruction::F32x4Splat,
+            0x14 => Instruction::F64x2Splat,
+            0x15 => Instruction::I8x16ExtractLaneS {
+                lane: self.read_u8()?,
+            },
+            0x16 => Instruction::I8x16ExtractLaneU {
+                lane: self.read_u8()?,
+            },
+            0x17 => Instruction::I8x16ReplaceLane {
+                lane: self.read_u8()?,
+            },
+            0x18 => Instruction::I16x8ExtractLaneS {
+                lane: self.read_u8()?,
+            },
+            0x19 => Instruction::I16x8ExtractLaneU {
+                lane: self.read_u8()?,
+            },
+            0x1A => Instruction::I16x8ReplaceLane {
+                lane: self.read_u8()?,
+            },
+            0x1B => Instruction::I32x4ExtractLane {
+                lane: self.read_u8()?,
+            },
+            0x1C => Instruction::I32x4ReplaceLane {
+                lane: self.read_u8()?,
+            },
+            0x1D => Instruction::I64x2ExtractLane {
+                lane: self.read_u8()?,
+            },
+            0x1E => Instruction::I64x2ReplaceLane {
+                lane: self.read_u8()?,
+            },
+            0x1F => Instruction::F32x4ExtractLane {
+                lane: self.read_u8()?,
+            },
+            0x20 => Instruction::F32x4ReplaceLane {
+                lane: self.read_u8()?,
+            },
+            0x21 => Instruction::F64x2ExtractLane {
+                lane: self.read_u8()?,
+            },
+            0x22 => Instruction::F64x2ReplaceLane {
+                lane: self.read_u8()?,
+            },
+            0x23 => Instruction::I8x16Eq,
+            0x24 => Instruction::I8x16Ne,
+            0x25 => Instruction::I8x16LtS,
+            0x26 => Instruction::I8x16LtU,
+            0x27 => Instruction::I8x16GtS,
+            0x28 => Instruction::I8x16GtU,
+            0x29 => Instruction::I8x16LeS,
+            0x2A => Instruction::I8x16LeU,
+            0x2B => Instruction::I8x16GeS,
+            0x2C => Instruction::I8x16GeU,
+            0x2D => Instruction::I16x8Eq,
+            0x2E => Instruction::I16x8Ne,
+            0x2F => Instruction::I16x8LtS,
+            0x30 => Instruction::I16x8LtU,
+            0x31 => Instruction::I16x8GtS,
+            0x32 => Instruction::I16x8GtU,
+            0x33 => Instruction::I16x8LeS,
+            0x34 => Instruction::I16x8LeU,
+            0x35 => Instruction::I16x8GeS,
+            0x36 => Instruction::I16x8GeU,
+            0x37 => Instruction::I32x4Eq,
+            0x38 => Instruction::I32x4Ne,
+            0x39 => Instruction::I32x4LtS,
+            0x3A => Instruction::I32x4LtU,
+            0x3B => Instruction::I32x4GtS,
+            0x3C => Instruction::I32x4GtU,
+            0x3D => Instruction::I32x4LeS,
+            0x3E => Instruction::I32x4LeU,
+            0x3F => Instruction::I32x4GeS,
+            0x40 => Instruction::I32x4GeU,
+            0x41 => Instruction::F32x4Eq,
+            0x42 => Instruction::F32x4Ne,
+            0x43 => Instruction::F32x4Lt,
+            0x44 => Instruction::F32x4Gt,
+            0x45 => Instruction::F32x4Le,
+            0x46 => Instruction::F32x4Ge,
+            0x47 => Instruction::F64x2Eq,
+            0x48 => Instruction::F64x2Ne,
+            0x49 => Instruction::F64x2Lt,
+            0x4A => Instruction::F64x2Gt,
+            0x4B => Instruction::F64x2Le,
+            0x4C => Instruction::F64x2Ge,
+            0x4D => Instruction::V128Not,
+            0x4E => Instruction::V128And,
+            0x4F => Instruction::V128Andnot,
+            0x50 => Instruction::V128Or,
+            0x51 => Instruction::V128Xor,
+            0x52 => Instruction::V128Bitselect,
+            0x53 => Instruction::V128AnyTrue,
+            0x54 => Instruction::V128Load8Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x55 => Instruction::V128Load16Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x56 => Instruction::V128Load32Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x57 => Instruction::V128Load64Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x58 => Instruction::V128Store8Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x59 => Instruction::V128Store16Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x5A => Instruction::V128Store32Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x5B => Instruction::V128Store64Lane {
+                mem_arg: self.read_mem_arg()?,
+                lane: self.read_u8()?,
+            },
+            0x5C => Instruction::V128Load32Zero {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x5D => Instruction::V128Load64Zero {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x5E => Instruction::F32x4DemoteF64x2Zero,
+            0x5F => Instruction::F64x2PromoteLowF32x4,
+            0x60 => Instruction::I8x16Abs,
+            0x61 => Instruction::I8x16Neg,
+            0x62 => Instruction::I8x16Popcnt,
+            0x63 => Instruction::I8x16AllTrue,
+            0x64 => Instruction::I8x16Bitmask,
+            0x65 => Instruction::I8x16NarrowI16x8S,
+            0x66 => Instruction::I8x16NarrowI16x8U,
+            0x67 => Instruction::F32x4Ceil,
+            0x68 => Instruction::F32x4Floor,
+            0x69 => Instruction::F32x4Trunc,
+            0x6A => Instruction::F32x4Nearest,
+            0x6B => Instruction::I8x16Shl,
+            0x6C => Instruction::I8x16ShrS,
+            0x6D => Instruction::I8x16ShrU,
+            0x6E => Instruction::I8x16Add,
+            0x6F => Instruction::I8x16AddSatS,
+            0x70 => Instruction::I8x16AddSatU,
+            0x71 => Instruction::I8x16Sub,
+            0x72 => Instruction::I8x16SubSatS,
+            0x73 => Instruction::I8x16SubSatU,
+            0x74 => Instruction::F64x2Ceil,
+            0x75 => Instruction::F64x2Floor,
+            0x76 => Instruction::I8x16MinS,
+            0x77 => Instruction::I8x16MinU,
+            0x78 => Instruction::I8x16MaxS,
+            0x79 => Instruction::I8x16MaxU,
+            0x7A => Instruction::F64x2Trunc,
+            0x7B => Instruction::I8x16AvgrU,
+            0x7C => Instruction::I16x8ExtaddPairwiseI8x16S,
+            0x7D => Instruction::I16x8ExtaddPairwiseI8x16U,
+            0x7E => Instruction::I32x4ExtaddPairwiseI16x8S,
+            0x7F => Instruction::I32x4ExtaddPairwiseI16x8U,
+            0x80 => Instruction::I16x8Abs,
+            0x81 => Instruction::I16x8Neg,
+            0x82 => Instruction::I16x8Q15mulrSatS,
+            0x83 => Instruction::I16x8AllTrue,
+            0x84 => Instruction::I16x8Bitmask,
+            0x85 => Instruction::I16x8NarrowI32x4S,
+            0x86 => Instruction::I16x8NarrowI32x4U,
+            0x87 => Instruction::I16x8ExtendLowI8x16S,
+            0x88 => Instruction::I16x8ExtendHighI8x16S,
+            0x89 => Instruction::I16x8ExtendLowI8x16U,
+            0x8A => Instruction::I16x8ExtendHighI8x16U,
+            0x8B => Instruction::I16x8Shl,
+            0x8C => Instruction::I16x8ShrS,
+            0x8D => Instruction::I16x8ShrU,
+            0x8E => Instruction::I16x8Add,
+            0x8F => Instruction::I16x8AddSatS,
+            0x90 => Instruction::I16x8AddSatU,
+            0x91 => Instruction::I16x8Sub,
+            0x92 => Instruction::I16x8SubSatS,
+            0x93 => Instruction::I16x8SubSatU,
+            0x94 => Instruction::F64x2Nearest,
+            0x95 => Instruction::I16x8Mul,
+            0x96 => Instruction::I16x8MinS,
+            0x97 => Instruction::I16x8MinU,
+            0x98 => Instruction::I16x8MaxS,
+            0x99 => Instruction::I16x8MaxU,
+            0x9B => Instruction::I16x8AvgrU,
+            0x9C => Instruction::I16x8ExtmulLowI8x16S,
+            0x9D => Instruction::I16x8ExtmulHighI8x16S,
+            0x9E => Instruction::I16x8ExtmulLowI8x16U,
+            0x9F => Instruction::I16x8ExtmulHighI8x16U,
+            0xA0 => Instruction::I32x4Abs,
+            0xA1 => Instruction::I32x4Neg,
+            0xA3 => Instruction::I32x4AllTrue,
+            0xA4 => Instruction::I32x4Bitmask,
+            0xA7 => Instruction::I32x4ExtendLowI16x8S,
+            0xA8 => Instruction::I32x4ExtendHighI16x8S,
+            0xA9 => Instruction::I32x4ExtendLowI16x8U,
+            0xAA => Instruction::I32x4ExtendHighI16x8U,
+            0xAB => Instruction::I32x4Shl,
+            0xAC => Instruction::I32x4ShrS,
+            0xAD => Instruction::I32x4ShrU,
+            0xAE => Instruction::I32x4Add,
+            0xB1 => Instruction::I32x4Sub,
+            0xB5 => Instruction::I32x4Mul,
+            0xB6 => Instruction::I32x4MinS,
+            0xB7 => Instruction::I32x4MinU,
+            0xB8 => Instruction::I32x4MaxS,
+            0xB9 => Instruction::I32x4MaxU,
+            0xBA => Instruction::I32x4DotI16x8S,
+            0xBC => Instruction::I32x4ExtmulLowI16x8S,
+            0xBD => Instruction::I32x4ExtmulHighI16x8S,
+            0xBE => Instruction::I32x4ExtmulLowI16x8U,
+            0xBF => Instruction::I32x4ExtmulHighI16x8U,
+            0xC0 => Instruction::I64x2Abs,
+            0xC1 => Instruction::I64x2Neg,
+            0xC3 => Instruction::I64x2AllTrue,
+            0xC4 => Instruction::I64x2Bitmask,
+            0xC7 => Instruction::I64x2ExtendLowI32x4S,
+            0xC8 => Instruction::I64x2ExtendHighI32x4S,
+            0xC9 => Instruction::I64x2ExtendLowI32x4U,
+            0xCA => Instruction::I64x2ExtendHighI32x4U,
+            0xCB => Instruction::I64x2Shl,
+            0xCC => Instruction::I64x2ShrS,
+            0xCD => Instruction::I64x2ShrU,
+            0xCE => Instruction::I64x2Add,
+            0xD1 => Instruction::I64x2Sub,
+            0xD5 => Instruction::I64x2Mul,
+            0xD6 => Instruction::I64x2Eq,
+            0xD7 => Instruction::I64x2Ne,
+            0xD8 => Instruction::I64x2LtS,
+            0xD9 => Instruction::I64x2GtS,
+            0xDA => Instruction::I64x2LeS,
+            0xDB => Instruction::I64x2GeS,
+            0xDC => Instruction::I64x2ExtmulLowI32x4S,
+            0xDD => Instruction::I64x2ExtmulHighI32x4S,
+            0xDE => Instruction::I64x2ExtmulLowI32x4U,
+            0xDF => Instruction::I64x2ExtmulHighI32x4U,
+            0xE0 => Instruction::F32x4Abs,
+            0xE1 => Instruction::F32x4Neg,
+            0xE3 => Instruction::F32x4Sqrt,
+            0xE4 => Instruction::F32x4Add,
+            0xE5 => Instruction::F32x4Sub,
+            0xE6 => Instruction::F32x4Mul,
+            0xE7 => Instruction::F32x4Div,
+            0xE8 => Instruction::F32x4Min,
+            0xE9 => Instruction::F32x4Max,
+            0xEA => Instruction::F32x4Pmin,
+            0xEB => Instruction::F32x4Pmax,
+            0xEC => Instruction::F64x2Abs,
+            0xED => Instruction::F64x2Neg,
+            0xEF => Instruction::F64x2Sqrt,
+            0xF0 => Instruction::F64x2Add,
+            0xF1 => Instruction::F64x2Sub,
+            0xF2 => Instruction::F64x2Mul,
+            0xF3 => Instruction::F64x2Div,
+            0xF4 => Instruction::F64x2Min,
+            0xF5 => Instruction::F64x2Max,
+            0xF6 => Instruction::F64x2Pmin,
+            0xF7 => Instruction::F64x2Pmax,
+            0xF8 => Instruction::I32x4TruncSatF32x4S,
+            0xF9 => Instruction::I32x4TruncSatF32x4U,
+            0xFA => Instruction::F32x4ConvertI32x4S,
+            0xFB => Instruction::F32x4ConvertI32x4U,
+            0xFC => Instruction::I32x4TruncSatF64x2SZero,
+            0xFD => Instruction::I32x4TruncSatF64x2UZero,
+            0xFE => Instruction::F64x2ConvertLowI32x4S,
+            0xFF => Instruction::F64x2ConvertLowI32x4U,
+            v => return Err(self.error_at(offset, DecodeErrorKind::InvalidSubInstructionId(v))),
+        };
+
+        Ok(instr)
+    }
+
+    /// Reads an atomic memory instruction, whose `0xFE` prefix at `offset` has
+    /// already been read.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/threads/core/binary/instructions.html#atomic-memory-instructions
+    fn read_atomic_instruction(&mut self, offset: usize) -> Result<Instruction> {
+        let id = self.read_size()?;
+
+        let instr = match id {
+            0x00 => Instruction::MemoryAtomicNotify {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x01 => Instruction::MemoryAtomicWait32 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x02 => Instruction::MemoryAtomicWait64 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x03 => {
+                self.read_u8()?; // 0x00
+
+                Instruction::AtomicFence
+            }
+            0x10 => Instruction::I32AtomicLoad {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x11 => Instruction::I64AtomicLoad {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x12 => Instruction::I32AtomicLoad8U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x13 => Instruction::I32AtomicLoad16U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x14 => Instruction::I64AtomicLoad8U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x15 => Instruction::I64AtomicLoad16U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x16 => Instruction::I64AtomicLoad32U {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x17 => Instruction::I32AtomicStore {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x18 => Instruction::I64AtomicStore {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x19 => Instruction::I32AtomicStore8 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x1A => Instruction::I32AtomicStore16 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x1B => Instruction::I64AtomicStore8 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x1C => Instruction::I64AtomicStore16 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x1D => Instruction::I64AtomicStore32 {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x1E => Instruction::I32AtomicRmwAdd {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x1F => Instruction::I64AtomicRmwAdd {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x20 => Instruction::I32AtomicRmw8AddU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x21 => Instruction::I32AtomicRmw16AddU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x22 => Instruction::I64AtomicRmw8AddU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x23 => Instruction::I64AtomicRmw16AddU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x24 => Instruction::I64AtomicRmw32AddU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x25 => Instruction::I32AtomicRmwSub {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x26 => Instruction::I64AtomicRmwSub {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x27 => Instruction::I32AtomicRmw8SubU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x28 => Instruction::I32AtomicRmw16SubU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x29 => Instruction::I64AtomicRmw8SubU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x2A => Instruction::I64AtomicRmw16SubU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x2B => Instruction::I64AtomicRmw32SubU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x2C => Instruction::I32AtomicRmwAnd {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x2D => Instruction::I64AtomicRmwAnd {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x2E => Instruction::I32AtomicRmw8AndU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x2F => Instruction::I32AtomicRmw16AndU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x30 => Instruction::I64AtomicRmw8AndU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x31 => Instruction::I64AtomicRmw16AndU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x32 => Instruction::I64AtomicRmw32AndU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x33 => Instruction::I32AtomicRmwOr {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x34 => Instruction::I64AtomicRmwOr {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x35 => Instruction::I32AtomicRmw8OrU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x36 => Instruction::I32AtomicRmw16OrU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x37 => Instruction::I64AtomicRmw8OrU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x38 => Instruction::I64AtomicRmw16OrU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x39 => Instruction::I64AtomicRmw32OrU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x3A => Instruction::I32AtomicRmwXor {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x3B => Instruction::I64AtomicRmwXor {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x3C => Instruction::I32AtomicRmw8XorU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x3D => Instruction::I32AtomicRmw16XorU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x3E => Instruction::I64AtomicRmw8XorU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x3F => Instruction::I64AtomicRmw16XorU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x40 => Instruction::I64AtomicRmw32XorU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x41 => Instruction::I32AtomicRmwXchg {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x42 => Instruction::I64AtomicRmwXchg {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x43 => Instruction::I32AtomicRmw8XchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x44 => Instruction::I32AtomicRmw16XchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x45 => Instruction::I64AtomicRmw8XchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x46 => Instruction::I64AtomicRmw16XchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x47 => Instruction::I64AtomicRmw32XchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x48 => Instruction::I32AtomicRmwCmpxchg {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x49 => Instruction::I64AtomicRmwCmpxchg {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x4A => Instruction::I32AtomicRmw8CmpxchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x4B => Instruction::I32AtomicRmw16CmpxchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x4C => Instruction::I64AtomicRmw8CmpxchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x4D => Instruction::I64AtomicRmw16CmpxchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            0x4E => Instruction::I64AtomicRmw32CmpxchgU {
+                mem_arg: self.read_mem_arg()?,
+            },
+            v => return Err(self.error_at(offset, DecodeErrorKind::InvalidSubInstructionId(v))),
+        };
 
         Ok(instr)
     }
 
-    fn read_vec<T>(&mut self, decode_fn: impl Fn(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+    fn read_const_expr(&mut self) -> Result<ConstExpr> {
+        let mut instructions = vec![];
+
+        loop {
+            match self.read_instruction()? {
+                Instruction::End => break,
+                instr => instructions.push(instr),
+            }
+        }
+
+        Ok(ConstExpr { instructions })
+    }
+
+    fn read_vec<T>(&mut self, mut decode_fn: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
         let size = self.read_size()?;
-        let mut items = Vec::with_capacity(size as usize);
+        let mut items = Vec::with_capacity((size as usize).min(MAX_VEC_PREALLOCATION));
 
         for _ in 0..size {
             items.push(decode_fn(self)?);
@@ -745,122 +1559,238 @@ impl<R: Read> Decoder<R> {
     }
 
     fn read_u8(&mut self) -> Result<u8> {
-        let mut buf = [0; 1];
-        let result = self.reader.read_exact(&mut buf);
-
-        match result {
-            Ok(_) => Ok(buf[0]),
-            Err(_) => bail!(DecodeError::Expected("u8".to_owned())),
+        match self.bytes.get(self.pos) {
+            Some(byte) => {
+                self.pos += 1;
+                Ok(*byte)
+            }
+            None => Err(self.error(DecodeErrorKind::Expected("u8".to_owned()))),
         }
     }
 
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let array = self.bytes.get(self.pos..)?.first_chunk::<N>()?;
+        self.pos += N;
+
+        Some(*array)
+    }
+
     fn read_i32(&mut self) -> Result<i32> {
-        let size_result = leb128::read::signed(&mut self.reader);
+        let offset = self.offset();
+        let size_result = self.read_leb128(leb128::read::signed);
 
         match size_result {
             Ok(size) => Ok(size as i32),
-            Err(_) => bail!(DecodeError::Expected("i32".to_owned())),
+            Err(_) => Err(self.error_at(offset, DecodeErrorKind::Expected("i32".to_owned()))),
         }
     }
 
     fn read_i64(&mut self) -> Result<i64> {
-        let size_result = leb128::read::signed(&mut self.reader);
+        let offset = self.offset();
+        let size_result = self.read_leb128(leb128::read::signed);
 
         match size_result {
             Ok(size) => Ok(size),
-            Err(_) => bail!(DecodeError::Expected("i64".to_owned())),
+            Err(_) => Err(self.error_at(offset, DecodeErrorKind::Expected("i64".to_owned()))),
         }
     }
 
     fn read_f32(&mut self) -> Result<f32> {
-        let mut buf = [0; 4];
-        let result = self.reader.read_exact(&mut buf);
-
-        match result {
-            Ok(_) => Ok(f32::from_le_bytes(buf)),
-            Err(_) => bail!(DecodeError::Expected("f32".to_owned())),
+        match self.read_array() {
+            Some(bytes) => Ok(f32::from_le_bytes(bytes)),
+            None => Err(self.error(DecodeErrorKind::Expected("f32".to_owned()))),
         }
     }
 
     fn read_f64(&mut self) -> Result<f64> {
-        let mut buf = [0; 8];
-        let result = self.reader.read_exact(&mut buf);
-
-        match result {
-            Ok(_) => Ok(f64::from_le_bytes(buf)),
-            Err(_) => bail!(DecodeError::Expected("f64".to_owned())),
+        match self.read_array() {
+            Some(bytes) => Ok(f64::from_le_bytes(bytes)),
+            None => Err(self.error(DecodeErrorKind::Expected("f64".to_owned()))),
         }
     }
 
-    fn read_section(&mut self) -> Result<(SectionId, u32)> {
-        let id = self.read_u8().context("read section id")?;
+    /// Runs one of the `leb128::read` functions on the remaining bytes.
+    fn read_leb128<T>(
+        &mut self,
+        read: impl FnOnce(&mut &'a [u8]) -> std::result::Result<T, leb128::read::Error>,
+    ) -> std::result::Result<T, leb128::read::Error> {
+        let mut rest = &self.bytes[self.pos..];
+        let result = read(&mut rest);
+        self.pos = self.bytes.len() - rest.len();
+
+        result
+    }
+
+    pub(crate) fn read_section(&mut self) -> Result<(SectionId, u32)> {
+        let offset = self.offset();
+        let id = self.read_u8()?;
         let id = SectionId::from(id);
         if id.is_unknown() {
-            bail!(DecodeError::InvalidSectionId(id));
+            return Err(self.error_at(offset, DecodeErrorKind::InvalidSectionId(id)));
         }
 
-        let size = self.read_size().context("read section size")?;
+        let size = self.read_size()?;
 
         Ok((id, size))
     }
 
-    fn read_size(&mut self) -> Result<u32> {
-        let size_result = leb128::read::unsigned(&mut self.reader);
+    fn read_u64(&mut self) -> Result<u64> {
+        let offset = self.offset();
+        let size_result = self.read_leb128(leb128::read::unsigned);
 
         match size_result {
-            Ok(size) => Ok(size as u32),
-            Err(_) => bail!(DecodeError::Expected("size(leb128 unsigned)".to_owned())),
+            Ok(size) => Ok(size),
+            Err(_) => Err(self.error_at(offset, DecodeErrorKind::Expected("u64".to_owned()))),
+        }
+    }
+
+    pub(crate) fn read_size(&mut self) -> Result<u32> {
+        let offset = self.offset();
+        let size_result = self.read_leb128(leb128::read::unsigned);
+
+        match size_result.ok().and_then(|size| u32::try_from(size).ok()) {
+            Some(size) => Ok(size),
+            None => Err(self.error_at(
+                offset,
+                DecodeErrorKind::Expected("size(leb128 unsigned)".to_owned()),
+            )),
         }
     }
 
-    fn read_bytes(&mut self, size: usize) -> Result<Vec<u8>> {
-        let mut bytes = vec![0; size];
-        let result = self.reader.read_exact(&mut bytes);
+    fn read_bytes(&mut self, size: usize) -> Result<&'a [u8]> {
+        let bytes: &'a [u8] = self.bytes;
+
+        match bytes.get(self.pos..).and_then(|rest| rest.get(..size)) {
+            Some(read) => {
+                self.pos += size;
+                Ok(read)
+            }
+            None => Err(self.error(DecodeErrorKind::Expected(format!("{} bytes", size)))),
+        }
+    }
+
+    fn read_v128(&mut self) -> Result<u128> {
+        match self.read_array() {
+            Some(bytes) => Ok(u128::from_le_bytes(bytes)),
+            None => Err(self.error(DecodeErrorKind::Expected("v128".to_owned()))),
+        }
+    }
 
-        match result {
-            Ok(_) => Ok(bytes),
-            Err(_) => bail!(DecodeError::Expected(format!("{} bytes", size))),
+    fn read_lanes(&mut self) -> Result<[u8; 16]> {
+        match self.read_array() {
+            Some(lanes) => Ok(lanes),
+            None => Err(self.error(DecodeErrorKind::Expected("16 lane indices".to_owned()))),
         }
     }
 
+    /// Reads a memarg. Bit 6 of the alignment field says whether an explicit
+    /// memory index follows (multi-memory proposal).
     fn read_mem_arg(&mut self) -> Result<MemArg> {
+        let align = self.read_size()?;
+        let memory_index = match align & MEM_ARG_HAS_MEMORY_INDEX {
+            0 => 0,
+            _ => self.read_size()?,
+        };
+
         Ok(MemArg {
-            align: self.read_size()?,
-            offset: self.read_size()?,
+            align: align & !MEM_ARG_HAS_MEMORY_INDEX,
+            offset: self.read_u64()?,
+            memory_index,
         })
     }
 
     fn read_reference_type(&mut self) -> Result<RefType> {
+        let offset = self.offset();
         let type_id = self.read_u8()?;
 
         match type_id {
             0x70 => Ok(RefType::FuncRef),
             0x6F => Ok(RefType::ExternRef),
-            _ => bail!(DecodeError::InvalidRefType),
+            0x69 => Ok(RefType::ExnRef),
+            0x63 => self.read_nullable_ref_type(),
+            _ => Err(self.error_at(offset, DecodeErrorKind::InvalidRefType)),
+        }
+    }
+
+    /// Reads the heap type of a `ref null` (`0x63`) reference type, which is the
+    /// long form of `funcref`, `externref` and `exnref`.
+    fn read_nullable_ref_type(&mut self) -> Result<RefType> {
+        let offset = self.offset();
+
+        match self.read_u8()? {
+            0x70 => Ok(RefType::FuncRef),
+            0x6F => Ok(RefType::ExternRef),
+            0x69 => Ok(RefType::ExnRef),
+            _ => Err(self.error_at(offset, DecodeErrorKind::InvalidRefType)),
+        }
+    }
+
+    /// Reads an `elemkind`, which only allows `funcref` (0x00).
+    fn read_element_kind(&mut self) -> Result<RefType> {
+        let offset = self.offset();
+
+        match self.read_u8()? {
+            0x00 => Ok(RefType::FuncRef),
+            _ => Err(self.error_at(offset, DecodeErrorKind::InvalidElementKind)),
         }
     }
 
+    /// Reads a block type, which is either `0x40` (empty), a value type, or a
+    /// type index encoded as a non-negative s33.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/spec/core/binary/instructions.html#binary-blocktype
     fn read_block_type(&mut self) -> Result<BlockType> {
+        let offset = self.offset();
+
         match self.read_value_type() {
             Ok(value_type) => Ok(BlockType::Value(vec![value_type])),
-            Err(err) => match err.downcast_ref::<DecodeError>() {
-                Some(err) => {
-                    if let DecodeError::InvalidValueType(v) = err {
-                        if *v == 0x40 {
-                            Ok(BlockType::Empty)
-                        } else {
-                            Ok(BlockType::TypeIndex((*v).into()))
-                        }
-                    } else {
-                        Err(err.clone().into())
-                    }
+            Err(DecodeError {
+                kind: DecodeErrorKind::InvalidValueType(0x40),
+                ..
+            }) => Ok(BlockType::Empty),
+            Err(DecodeError {
+                kind: DecodeErrorKind::InvalidValueType(first),
+                ..
+            }) => {
+                let index = self.read_s33(first)?;
+
+                match u32::try_from(index) {
+                    Ok(index) => Ok(BlockType::TypeIndex(index)),
+                    Err(_) => Err(self.error_at(offset, DecodeErrorKind::InvalidBlockType)),
                 }
-                None => Err(err),
-            },
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads a signed LEB128 33-bit integer whose first byte, `first`, has
+    /// already been read.
+    fn read_s33(&mut self, first: u8) -> Result<i64> {
+        let offset = self.offset() - 1;
+        let mut result = (first & 0x7F) as i64;
+        let mut shift = 7;
+        let mut byte = first;
+
+        while byte & 0x80 != 0 {
+            // 33 bits fit in at most 5 bytes.
+            if shift >= 35 {
+                return Err(self.error_at(offset, DecodeErrorKind::Expected("s33".to_owned())));
+            }
+
+            byte = self.read_u8()?;
+            result |= ((byte & 0x7F) as i64) << shift;
+            shift += 7;
         }
+
+        if byte & 0x40 != 0 {
+            result |= -1 << shift;
+        }
+
+        Ok(result)
     }
 
     fn read_value_type(&mut self) -> Result<ValueType> {
+        let offset = self.offset();
         let type_id = self.read_u8()?;
 
         let ty = match type_id {
@@ -871,35 +1801,105 @@ impl<R: Read> Decoder<R> {
             0x7B => ValueType::V128,
             0x70 => ValueType::FuncRef,
             0x6F => ValueType::ExternRef,
-            v => bail!(DecodeError::InvalidValueType(v)),
+            0x69 => ValueType::ExnRef,
+            0x63 => match self.read_nullable_ref_type()? {
+                RefType::FuncRef => ValueType::FuncRef,
+                RefType::ExternRef => ValueType::ExternRef,
+                RefType::ExnRef => ValueType::ExnRef,
+            },
+            v => return Err(self.error_at(offset, DecodeErrorKind::InvalidValueType(v))),
         };
 
         Ok(ty)
     }
 
+    /// Reads limits. The kind is a bitfield: bit 0 says whether a maximum is
+    /// present, bit 1 whether the memory is shared (threads proposal) and bit 2
+    /// whether it is 64-bit (memory64 proposal).
     fn read_limits(&mut self) -> Result<Limits> {
-        match self.read_u8()? {
-            0x00 => Ok(Limits {
-                min: self.read_size()?,
-                max: None,
-            }),
-            0x01 => Ok(Limits {
-                min: self.read_size()?,
-                max: Some(self.read_size()?),
-            }),
-            _ => bail!(DecodeError::InvalidLimitsKind),
+        let offset = self.offset();
+        let kind = self.read_u8()?;
+        if kind > 0x07 {
+            return Err(self.error_at(offset, DecodeErrorKind::InvalidLimitsKind));
+        }
+
+        let index64 = kind & 0x04 != 0;
+
+        Ok(Limits {
+            min: self.read_limit(index64)?,
+            max: match kind & 0x01 {
+                0 => None,
+                _ => Some(self.read_limit(index64)?),
+            },
+            shared: kind & 0x02 != 0,
+            index64,
+        })
+    }
+
+    fn read_limit(&mut self, index64: bool) -> Result<u64> {
+        if index64 {
+            self.read_u64()
+        } else {
+            self.read_size().map(u64::from)
         }
     }
 
-    fn read_name(&mut self) -> Result<String> {
+    fn read_name(&mut self) -> Result<&'a str> {
+        let offset = self.offset();
         let size = self.read_size()?;
-        let mut buf = vec![0; size as usize];
-        let result = self.reader.read_exact(&mut buf);
+        let bytes = self.read_bytes(size as usize).map_err(|err| DecodeError {
+            kind: DecodeErrorKind::Expected("name".to_owned()),
+            ..err
+        })?;
+
+        match std::str::from_utf8(bytes) {
+            Ok(name) => Ok(name),
+            Err(_) => Err(self.error_at(offset, DecodeErrorKind::InvalidUtf8Name)),
+        }
+    }
 
-        match result {
-            Ok(_) => Ok(String::from_utf8(buf).unwrap()),
-            Err(_) => bail!(DecodeError::Expected("name".to_owned())),
+    fn read_name_map(&mut self) -> Result<BTreeMap<u32, String>> {
+        Ok(self
+            .read_vec(|d| Ok((d.read_size()?, d.read_name()?.to_owned())))?
+            .into_iter()
+            .collect())
+    }
+
+    fn read_catch(&mut self) -> Result<Catch> {
+        let offset = self.offset();
+
+        let catch = match self.read_u8()? {
+            0x00 => Catch::Catch {
+                tag_index: self.read_size()?,
+                label: self.read_size()?,
+            },
+            0x01 => Catch::CatchRef {
+                tag_index: self.read_size()?,
+                label: self.read_size()?,
+            },
+            0x02 => Catch::CatchAll {
+                label: self.read_size()?,
+            },
+            0x03 => Catch::CatchAllRef {
+                label: self.read_size()?,
+            },
+            kind => return Err(self.error_at(offset, DecodeErrorKind::InvalidCatchKind(kind))),
+        };
+
+        Ok(catch)
+    }
+
+    /// Reads a tag type. The attribute byte must be 0 (exception).
+    fn read_tag_type(&mut self) -> Result<TagType> {
+        let offset = self.offset();
+        let attribute = self.read_u8()?;
+        if attribute != 0x00 {
+            return Err(self.error_at(offset, DecodeErrorKind::InvalidTagAttribute(attribute)));
         }
+
+        Ok(TagType {
+            type_index: self.read_size()?,
+        })
     }
 
     fn read_global_type(&mut self) -> Result<GlobalType> {
@@ -914,11 +1914,57 @@ impl<R: Read> Decoder<R> {
 
     fn read_table_type(&mut self) -> Result<TableType> {
         let element_type = self.read_reference_type()?;
+        let offset = self.offset();
         let limits = self.read_limits()?;
 
+        // Only memories can be shared.
+        if limits.shared {
+            return Err(self.error_at(offset, DecodeErrorKind::InvalidLimitsKind));
+        }
+
         Ok(TableType {
             element_type,
             limits,
         })
     }
 }
+
+/// Iterator decoding the instructions of a function body one at a time.
+pub struct InstructionIter<'a> {
+    decoder: Decoder<'a>,
+    index: u32,
+    failed: bool,
+}
+
+impl<'a> InstructionIter<'a> {
+    pub(crate) fn new(decoder: Decoder<'a>) -> Self {
+        InstructionIter {
+            decoder,
+            index: 0,
+            failed: false,
+        }
+    }
+
+    /// Offset in the module binary of the next instruction.
+    pub fn offset(&self) -> usize {
+        self.decoder.offset()
+    }
+}
+
+impl Iterator for InstructionIter<'_> {
+    type Item = Result<Instruction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.decoder.is_empty() {
+            return None;
+        }
+
+        self.decoder.instr_index = Some(self.index);
+        self.index += 1;
+
+        let result = self.decoder.read_instruction();
+        self.failed = result.is_err();
+
+        Some(result)
+    }
+}
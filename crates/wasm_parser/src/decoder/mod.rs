@@ -1,7 +1,10 @@
 mod binary;
+#[allow(clippy::module_inception)]
 mod decoder;
 mod result;
+mod streaming;
 
 pub use binary::*;
 pub use decoder::*;
 pub use result::*;
+pub use streaming::*;
@@ -1,12 +1,50 @@
+use std::{fmt, io};
+
 use super::binary::section::SectionId;
 
+/// Error returned by the decoder, together with where in the module it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// Absolute byte offset in the module binary.
+    pub offset: usize,
+    /// Section being decoded, if any.
+    pub section: Option<SectionId>,
+    /// Function index (imported functions included) of the body being decoded.
+    pub func_index: Option<u32>,
+    /// Index of the instruction within the function body.
+    pub instr_index: Option<u32>,
+    pub kind: DecodeErrorKind,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset {:#x}", self.offset)?;
+
+        if let Some(section) = self.section {
+            write!(f, ", {} section", section)?;
+        }
+        if let Some(func_index) = self.func_index {
+            write!(f, ", func {}", func_index)?;
+        }
+        if let Some(instr_index) = self.instr_index {
+            write!(f, ", instr {}", instr_index)?;
+        }
+
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
-pub enum DecodeError {
+pub enum DecodeErrorKind {
     #[error("invalid magic header")]
     InvalidMagicHeader,
+    #[error("truncated module header")]
+    TruncatedHeader,
     #[error("invalid version")]
     InvalidVersion,
-    #[error("invalid section id: {0:?}")]
+    #[error("invalid section id: {0}")]
     InvalidSectionId(SectionId),
     #[error("invalid reference type")]
     InvalidRefType,
@@ -14,7 +52,7 @@ pub enum DecodeError {
     InvalidTableInstructionId,
     #[error("invalid sub instruction id: {0:#x}")]
     InvalidSubInstructionId(u32), // 0xFC xx
-    #[error("unxpected EOF")]
+    #[error("unexpected EOF")]
     UnexpectedEof,
     #[error("expected {0}")]
     Expected(String),
@@ -39,6 +77,10 @@ pub enum DecodeError {
     InvalidLimitsKind,
     #[error("invalid element kind")]
     InvalidElementKind,
+    #[error("invalid tag attribute: {0:#04x}")]
+    InvalidTagAttribute(u8),
+    #[error("invalid catch clause kind: {0:#04x}")]
+    InvalidCatchKind(u8),
 
     #[error("expected const expression")]
     ExpectedConstExpression,
@@ -47,6 +89,27 @@ pub enum DecodeError {
 
     #[error("unsupported data prefix")]
     UnsupportedDataPrefix(u32),
+
+    #[error("unknown opcode {0:#04x}")]
+    UnknownOpcode(u8),
+    #[error("invalid UTF-8 in name")]
+    InvalidUtf8Name,
+    #[error("too many locals")]
+    TooManyLocals,
+
+    #[error("duplicate {0} section")]
+    DuplicateSection(SectionId),
+    #[error("{0} section out of order")]
+    SectionOutOfOrder(SectionId),
+    #[error("section size mismatch: declared {expected} bytes, decoded {actual} bytes")]
+    SectionSizeMismatch { expected: u32, actual: usize },
+    #[error("function and code section have inconsistent lengths: {functions} functions, {bodies} bodies")]
+    FunctionCodeMismatch { functions: usize, bodies: usize },
+    #[error("data count section says {expected} segments, data section has {actual}")]
+    DataCountMismatch { expected: u32, actual: usize },
+
+    #[error("I/O error: {0}")]
+    Io(io::ErrorKind),
 }
 
-pub type Result<T> = anyhow::Result<T>;
+pub type Result<T> = std::result::Result<T, DecodeError>;
@@ -0,0 +1,399 @@
+use std::io::{self, Read};
+
+use super::{
+    binary::{
+        module::Module,
+        section::{Code, CustomSection, NameSection, SectionId},
+        types::{
+            Data, Element, Export, FuncType, Global, Import, ImportDesc, MemoryType, TableType,
+            TagType,
+        },
+    },
+    decoder::MAX_VEC_PREALLOCATION,
+    DecodeError, DecodeErrorKind, Decoder, Result,
+};
+
+/// Length of the magic header and version.
+const HEADER_LEN: usize = 8;
+
+/// How many bytes `decode_reader` asks for at a time.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Longest LEB128 number the decoder reads (a 64-bit integer).
+const MAX_LEB128_LEN: usize = 10;
+
+/// Part of a module, emitted by `StreamingDecoder` as soon as it is complete.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Header {
+        version: u32,
+    },
+    CustomSection(CustomSection),
+    /// Contents of the first well-formed `name` custom section, emitted right
+    /// after its `CustomSection`.
+    NameSection(NameSection),
+    TypeSection(Vec<FuncType>),
+    ImportSection(Vec<Import>),
+    FunctionSection(Vec<u32>),
+    TableSection(Vec<TableType>),
+    MemorySection(Vec<MemoryType>),
+    TagSection(Vec<TagType>),
+    GlobalSection(Vec<Global>),
+    ExportSection(Vec<Export>),
+    StartSection(u32),
+    ElementSection(Vec<Element>),
+    DataCountSection(u32),
+    /// Start of the code section, followed by `count` `FunctionBody` payloads.
+    CodeSectionStart {
+        count: u32,
+    },
+    FunctionBody {
+        func_index: u32,
+        code: Code,
+    },
+    DataSection(Vec<Data>),
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Header,
+    Sections,
+    FunctionBodies {
+        remaining: u32,
+        next_index: u32,
+        /// Offset of the end of the code section.
+        end: usize,
+        size: u32,
+    },
+}
+
+/// Push-style decoder for modules that arrive in chunks, e.g. over a serial
+/// link.
+///
+/// Sections are emitted once all their bytes have arrived, except for the code
+/// section, whose function bodies are emitted one by one.
+#[derive(Debug, Default)]
+pub struct StreamingDecoder {
+    /// Bytes received but not decoded yet.
+    buffer: Vec<u8>,
+    /// Offset of `buffer[0]` in the module binary.
+    buffer_offset: usize,
+    state: State,
+    last_order: u8,
+    has_name_section: bool,
+    imported_funcs: u32,
+    functions: usize,
+    bodies: usize,
+    data_count: Option<u32>,
+    data: usize,
+}
+
+impl StreamingDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the next chunk of the module binary and returns the payloads it
+    /// completed.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Payload>> {
+        self.buffer.extend_from_slice(chunk);
+        self.decode_available(false)
+    }
+
+    /// Signals the end of the module binary, returning the last payloads and
+    /// failing if the module is truncated.
+    pub fn finish(mut self) -> Result<Vec<Payload>> {
+        let payloads = self.decode_available(true)?;
+
+        Decoder::with_offset(&[], self.buffer_offset).check_counts(
+            self.functions,
+            self.bodies,
+            self.data_count,
+            self.data,
+        )?;
+
+        Ok(payloads)
+    }
+
+    /// Decodes a module from `reader` as its bytes arrive.
+    ///
+    /// The module ends when `read` returns 0, however few bytes each call returns.
+    pub fn decode_reader(mut reader: impl Read) -> Result<Module> {
+        let mut decoder = StreamingDecoder::new();
+        let mut module = Module::default();
+        let mut chunk = [0; READ_CHUNK_SIZE];
+
+        loop {
+            let len = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    return Err(DecodeError {
+                        offset: decoder.buffer_offset + decoder.buffer.len(),
+                        section: None,
+                        func_index: None,
+                        instr_index: None,
+                        kind: DecodeErrorKind::Io(err.kind()),
+                    })
+                }
+            };
+
+            for payload in decoder.push(&chunk[..len])? {
+                add_payload(&mut module, payload);
+            }
+        }
+
+        for payload in decoder.finish()? {
+            add_payload(&mut module, payload);
+        }
+
+        Ok(module)
+    }
+
+    /// Decodes as many payloads as the buffered bytes allow. With `eof`, bytes
+    /// are not expected to arrive anymore, so incomplete payloads are errors.
+    fn decode_available(&mut self, eof: bool) -> Result<Vec<Payload>> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let mut payloads = vec![];
+        let mut pos = 0;
+
+        let result = loop {
+            match self.step(&buffer[pos..], self.buffer_offset + pos, eof, &mut payloads) {
+                Ok(Some(consumed)) => pos += consumed,
+                Ok(None) => break Ok(payloads),
+                Err(err) => break Err(err),
+            }
+        };
+
+        self.buffer = buffer;
+        self.buffer.drain(..pos);
+        self.buffer_offset += pos;
+
+        result
+    }
+
+    /// Decodes the next payload from `bytes`, which start at `offset` in the
+    /// module binary. Returns how many bytes were consumed, or `None` if more
+    /// bytes are needed.
+    fn step(
+        &mut self,
+        bytes: &[u8],
+        offset: usize,
+        eof: bool,
+        payloads: &mut Vec<Payload>,
+    ) -> Result<Option<usize>> {
+        let mut d = Decoder::with_offset(bytes, offset);
+
+        match self.state {
+            State::Header => {
+                if bytes.len() < HEADER_LEN && !eof {
+                    return Ok(None);
+                }
+
+                let version = d.decode_header()?;
+                self.state = State::Sections;
+                payloads.push(Payload::Header { version });
+            }
+            State::Sections => {
+                if bytes.is_empty() || (!eof && !has_leb128(&bytes[1..])) {
+                    return Ok(None);
+                }
+
+                let (id, size) = d.read_section()?;
+                let mut last_order = self.last_order;
+                d.check_section_order(id, offset, &mut last_order)?;
+
+                let contents_offset = d.offset();
+                let available = &bytes[contents_offset - offset..];
+
+                if id == SectionId::Code {
+                    let available = &available[..available.len().min(size as usize)];
+                    if available.len() < size as usize && !eof && !has_leb128(available) {
+                        return Ok(None);
+                    }
+
+                    let mut contents = Decoder::for_section(id, available, contents_offset);
+                    let count = contents.read_size()?;
+
+                    self.last_order = last_order;
+                    self.state = State::FunctionBodies {
+                        remaining: count,
+                        next_index: self.imported_funcs,
+                        end: contents_offset + size as usize,
+                        size,
+                    };
+                    payloads.push(Payload::CodeSectionStart { count });
+
+                    return Ok(Some(contents.offset() - offset));
+                }
+
+                if available.len() < size as usize && !eof {
+                    return Ok(None);
+                }
+
+                let mut contents = d.read_section_contents(id, size)?;
+                let payload = match id {
+                    SectionId::Custom => {
+                        let custom = contents.decode_custom_section()?;
+                        let names = match self.has_name_section {
+                            true => None,
+                            false => contents.decode_names(&custom),
+                        };
+
+                        contents.check_section_end(size)?;
+                        payloads.push(Payload::CustomSection(custom.into()));
+                        if let Some(names) = names {
+                            self.has_name_section = true;
+                            payloads.push(Payload::NameSection(names));
+                        }
+
+                        return Ok(Some(d.offset() - offset));
+                    }
+                    SectionId::Type => Payload::TypeSection(contents.decode_type_section()?),
+                    SectionId::Import => {
+                        let imports = contents.decode_import_section()?;
+                        self.imported_funcs = imports
+                            .iter()
+                            .filter(|import| matches!(import.desc, ImportDesc::Func(_)))
+                            .count() as u32;
+
+                        Payload::ImportSection(imports.into_iter().map(Into::into).collect())
+                    }
+                    SectionId::Function => {
+                        let functions = contents.decode_function_section()?;
+                        self.functions = functions.len();
+
+                        Payload::FunctionSection(functions)
+                    }
+                    SectionId::Table => Payload::TableSection(contents.decode_table_section()?),
+                    SectionId::Memory => Payload::MemorySection(contents.decode_memory_section()?),
+                    SectionId::Tag => Payload::TagSection(contents.decode_tag_section()?),
+                    SectionId::Global => Payload::GlobalSection(contents.decode_global_section()?),
+                    SectionId::Export => Payload::ExportSection(
+                        contents
+                            .decode_export_section()?
+                            .into_iter()
+                            .map(Into::into)
+                            .collect(),
+                    ),
+                    SectionId::Start => Payload::StartSection(contents.decode_start_section()?),
+                    SectionId::Element => {
+                        Payload::ElementSection(contents.decode_element_section()?)
+                    }
+                    SectionId::Data => {
+                        let data: Vec<Data> = contents
+                            .decode_data_section()?
+                            .into_iter()
+                            .map(Into::into)
+                            .collect();
+                        self.data = data.len();
+
+                        Payload::DataSection(data)
+                    }
+                    SectionId::DataCount => {
+                        let count = contents.decode_data_count_section()?;
+                        self.data_count = Some(count);
+
+                        Payload::DataCountSection(count)
+                    }
+                    // The code section is handled above and `read_section` rejects
+                    // unknown ids.
+                    SectionId::Code | SectionId::Unknown(_) => unreachable!(),
+                };
+
+                contents.check_section_end(size)?;
+                self.last_order = last_order;
+                payloads.push(payload);
+            }
+            State::FunctionBodies {
+                remaining,
+                next_index,
+                end,
+                size,
+            } => {
+                let available = &bytes[..bytes.len().min(end - offset)];
+
+                if remaining == 0 {
+                    if offset != end {
+                        return Err(d.error_at(
+                            offset,
+                            DecodeErrorKind::SectionSizeMismatch {
+                                expected: size,
+                                actual: size as usize - (end - offset),
+                            },
+                        ));
+                    }
+
+                    self.state = State::Sections;
+                    return Ok(Some(0));
+                }
+
+                if available.len() < end - offset && !eof {
+                    if !has_leb128(available) {
+                        return Ok(None);
+                    }
+
+                    let mut probe = Decoder::for_section(SectionId::Code, available, offset);
+                    let body_size = probe.read_size()?;
+                    if available.len() - (probe.offset() - offset) < body_size as usize {
+                        return Ok(None);
+                    }
+                }
+
+                let mut contents = Decoder::for_section(SectionId::Code, available, offset);
+                let body = contents.read_function_body(next_index)?;
+                let code = body.to_code()?;
+
+                self.bodies += 1;
+                self.state = State::FunctionBodies {
+                    remaining: remaining - 1,
+                    next_index: next_index + 1,
+                    end,
+                    size,
+                };
+                payloads.push(Payload::FunctionBody {
+                    func_index: next_index,
+                    code,
+                });
+
+                return Ok(Some(contents.offset() - offset));
+            }
+        }
+
+        Ok(Some(d.offset() - offset))
+    }
+}
+
+fn add_payload(module: &mut Module, payload: Payload) {
+    match payload {
+        Payload::Header { version } => module.version = version,
+        Payload::CustomSection(custom) => module.custom_section.push(custom),
+        Payload::NameSection(names) => module.name_section = Some(names),
+        Payload::TypeSection(types) => module.type_section = types,
+        Payload::ImportSection(imports) => module.import_section = imports,
+        Payload::FunctionSection(functions) => module.function_section = functions,
+        Payload::TableSection(tables) => module.table_section = tables,
+        Payload::MemorySection(memories) => module.memory_section = memories,
+        Payload::TagSection(tags) => module.tag_section = tags,
+        Payload::GlobalSection(globals) => module.global_section = globals,
+        Payload::ExportSection(exports) => module.export_section = exports,
+        Payload::StartSection(start) => module.start_section = Some(start),
+        Payload::ElementSection(elements) => module.element_section = elements,
+        Payload::DataCountSection(count) => module.data_count_section = Some(count),
+        Payload::CodeSectionStart { count } => {
+            module
+                .code_section
+                .reserve((count as usize).min(MAX_VEC_PREALLOCATION));
+        }
+        Payload::FunctionBody { code, .. } => module.code_section.push(code),
+        Payload::DataSection(data) => module.data_section = data,
+    }
+}
+
+/// Returns whether `bytes` start with a whole LEB128 number, or with enough
+/// bytes for the decoder to reject it.
+fn has_leb128(bytes: &[u8]) -> bool {
+    bytes.len() >= MAX_LEB128_LEN || bytes.iter().any(|byte| byte & 0x80 == 0)
+}
@@ -0,0 +1,1536 @@
+use std::collections::BTreeMap;
+
+use crate::decoder::{
+    const_expr::ConstExpr,
+    instructions::{BlockType, Catch, Instruction, MemArg, MEM_ARG_HAS_MEMORY_INDEX},
+    module::Module,
+    section::{Code, NameSection, SectionId},
+    types::{
+        Data, DataMode, Element, ElementInit, ElementMode, ExportDesc, FuncType, GlobalType,
+        ImportDesc, Limits, RefType, TableType, TagType, ValueType,
+    },
+};
+
+/// `\0asm`.
+const MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+
+/// Encodes a `Module` into the binary format.
+///
+/// Custom sections are written after all other sections, so they don't keep
+/// their original position. The `name_section` is only encoded when there is no
+/// raw `name` custom section, which takes precedence.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/binary/index.html
+pub struct Encoder<'a> {
+    module: &'a Module,
+    bytes: Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(module: &'a Module) -> Self {
+        Encoder {
+            module,
+            bytes: vec![],
+        }
+    }
+
+    pub fn encode(mut self) -> Vec<u8> {
+        let module = self.module;
+
+        self.write_bytes(&MAGIC);
+        self.write_bytes(&module.version.to_le_bytes());
+
+        if !module.type_section.is_empty() {
+            self.write_section(SectionId::Type, |e| {
+                e.write_vec(&module.type_section, Self::write_func_type)
+            });
+        }
+        if !module.import_section.is_empty() {
+            self.write_section(SectionId::Import, |e| {
+                e.write_vec(&module.import_section, |e, import| {
+                    e.write_name(&import.module);
+                    e.write_name(&import.field);
+                    e.write_import_desc(&import.desc);
+                })
+            });
+        }
+        if !module.function_section.is_empty() {
+            self.write_section(SectionId::Function, |e| {
+                e.write_vec(&module.function_section, |e, type_index| {
+                    e.write_u32(*type_index)
+                })
+            });
+        }
+        if !module.table_section.is_empty() {
+            self.write_section(SectionId::Table, |e| {
+                e.write_vec(&module.table_section, Self::write_table_type)
+            });
+        }
+        if !module.memory_section.is_empty() {
+            self.write_section(SectionId::Memory, |e| {
+                e.write_vec(&module.memory_section, |e, memory| {
+                    e.write_limits(&memory.limits)
+                })
+            });
+        }
+        if !module.tag_section.is_empty() {
+            self.write_section(SectionId::Tag, |e| {
+                e.write_vec(&module.tag_section, Self::write_tag_type)
+            });
+        }
+        if !module.global_section.is_empty() {
+            self.write_section(SectionId::Global, |e| {
+                e.write_vec(&module.global_section, |e, global| {
+                    e.write_global_type(&global.global_type);
+                    e.write_const_expr(&global.init_expr);
+                })
+            });
+        }
+        if !module.export_section.is_empty() {
+            self.write_section(SectionId::Export, |e| {
+                e.write_vec(&module.export_section, |e, export| {
+                    e.write_name(&export.name);
+                    e.write_export_desc(&export.desc);
+                })
+            });
+        }
+        if let Some(start) = module.start_section {
+            self.write_section(SectionId::Start, |e| e.write_u32(start));
+        }
+        if !module.element_section.is_empty() {
+            self.write_section(SectionId::Element, |e| {
+                e.write_vec(&module.element_section, Self::write_element)
+            });
+        }
+        if let Some(count) = module.data_count_section {
+            self.write_section(SectionId::DataCount, |e| e.write_u32(count));
+        }
+        if !module.code_section.is_empty() {
+            self.write_section(SectionId::Code, |e| {
+                e.write_vec(&module.code_section, Self::write_code)
+            });
+        }
+        if !module.data_section.is_empty() {
+            self.write_section(SectionId::Data, |e| {
+                e.write_vec(&module.data_section, Self::write_data)
+            });
+        }
+
+        for custom in &module.custom_section {
+            self.write_section(SectionId::Custom, |e| {
+                e.write_name(&custom.name);
+                e.write_bytes(&custom.bytes);
+            });
+        }
+        if let Some(names) = &module.name_section {
+            if !module
+                .custom_section
+                .iter()
+                .any(|custom| custom.name == "name")
+            {
+                self.write_section(SectionId::Custom, |e| {
+                    e.write_name("name");
+                    e.write_name_section(names);
+                });
+            }
+        }
+
+        self.bytes
+    }
+
+    fn sub_encoder(&self) -> Encoder<'a> {
+        Encoder::new(self.module)
+    }
+
+    /// Writes a section whose contents are written by `write_contents`,
+    /// prefixed with its size.
+    fn write_section(&mut self, id: SectionId, write_contents: impl FnOnce(&mut Self)) {
+        self.write_u8(id.into());
+        self.write_sized(write_contents);
+    }
+
+    /// Writes whatever `write_contents` writes, prefixed with its size.
+    fn write_sized(&mut self, write_contents: impl FnOnce(&mut Self)) {
+        let mut e = self.sub_encoder();
+        write_contents(&mut e);
+
+        self.write_u32(e.bytes.len() as u32);
+        self.write_bytes(&e.bytes);
+    }
+
+    /// Writes the contents of the `name` custom section.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+    fn write_name_section(&mut self, names: &NameSection) {
+        if let Some(module_name) = &names.module_name {
+            self.write_u8(0x00);
+            self.write_sized(|e| e.write_name(module_name));
+        }
+        if !names.function_names.is_empty() {
+            self.write_u8(0x01);
+            self.write_sized(|e| e.write_name_map(&names.function_names));
+        }
+        if !names.local_names.is_empty() {
+            self.write_u8(0x02);
+            self.write_sized(|e| {
+                e.write_u32(names.local_names.len() as u32);
+                for (func_index, locals) in &names.local_names {
+                    e.write_u32(*func_index);
+                    e.write_name_map(locals);
+                }
+            });
+        }
+    }
+
+    fn write_name_map(&mut self, names: &BTreeMap<u32, String>) {
+        self.write_u32(names.len() as u32);
+        for (index, name) in names {
+            self.write_u32(*index);
+            self.write_name(name);
+        }
+    }
+
+    fn write_func_type(&mut self, func_type: &FuncType) {
+        self.write_u8(0x60);
+        self.write_vec(&func_type.params, Self::write_value_type);
+        self.write_vec(&func_type.results, Self::write_value_type);
+    }
+
+    fn write_import_desc(&mut self, desc: &ImportDesc) {
+        match desc {
+            ImportDesc::Func(type_index) => {
+                self.write_u8(0x00);
+                self.write_u32(*type_index);
+            }
+            ImportDesc::Table(table_type) => {
+                self.write_u8(0x01);
+                self.write_table_type(table_type);
+            }
+            ImportDesc::Memory(limits) => {
+                self.write_u8(0x02);
+                self.write_limits(limits);
+            }
+            ImportDesc::Global(global_type) => {
+                self.write_u8(0x03);
+                self.write_global_type(global_type);
+            }
+            ImportDesc::Tag(tag_type) => {
+                self.write_u8(0x04);
+                self.write_tag_type(tag_type);
+            }
+        }
+    }
+
+    fn write_export_desc(&mut self, desc: &ExportDesc) {
+        let (kind, index) = match desc {
+            ExportDesc::Func(index) => (0x00, index),
+            ExportDesc::Table(index) => (0x01, index),
+            ExportDesc::Mem(index) => (0x02, index),
+            ExportDesc::Global(index) => (0x03, index),
+            ExportDesc::Tag(index) => (0x04, index),
+        };
+
+        self.write_u8(kind);
+        self.write_u32(*index);
+    }
+
+    /// Writes an element segment, choosing the shortest of the eight encodings.
+    ///
+    /// WebAssembly specification: https://webassembly.github.io/spec/core/binary/modules.html#element-section
+    fn write_element(&mut self, element: &Element) {
+        let uses_expressions = matches!(element.init, ElementInit::Expressions(_));
+        let implicit_type = element.ref_type == RefType::FuncRef;
+
+        match &element.mode {
+            ElementMode::Active {
+                table_index: 0,
+                offset,
+            } if implicit_type => {
+                self.write_u32(if uses_expressions { 4 } else { 0 });
+                self.write_const_expr(offset);
+            }
+            ElementMode::Passive => {
+                self.write_u32(if uses_expressions { 5 } else { 1 });
+                self.write_element_type(element);
+            }
+            ElementMode::Active {
+                table_index,
+                offset,
+            } => {
+                self.write_u32(if uses_expressions { 6 } else { 2 });
+                self.write_u32(*table_index);
+                self.write_const_expr(offset);
+                self.write_element_type(element);
+            }
+            ElementMode::Declarative => {
+                self.write_u32(if uses_expressions { 7 } else { 3 });
+                self.write_element_type(element);
+            }
+        }
+
+        match &element.init {
+            ElementInit::Functions(func_indices) => {
+                self.write_vec(func_indices, |e, func_index| e.write_u32(*func_index))
+            }
+            ElementInit::Expressions(exprs) => self.write_vec(exprs, Self::write_const_expr),
+        }
+    }
+
+    /// Writes the `elemkind` (for function indices) or the reference type (for
+    /// expressions) of an element segment.
+    fn write_element_type(&mut self, element: &Element) {
+        match element.init {
+            ElementInit::Functions(_) => self.write_u8(0x00),
+            ElementInit::Expressions(_) => self.write_ref_type(element.ref_type),
+        }
+    }
+
+    fn write_code(&mut self, code: &Code) {
+        self.write_sized(|e| {
+            let local_groups: Vec<&[ValueType]> = code.locals.chunk_by(|a, b| a == b).collect();
+            e.write_vec(&local_groups, |e, group| {
+                e.write_u32(group.len() as u32);
+                e.write_value_type(&group[0]);
+            });
+
+            for instr in &code.code {
+                e.write_instruction(instr);
+            }
+        });
+    }
+
+    fn write_data(&mut self, data: &Data) {
+        match &data.mode {
+            DataMode::Active {
+                memory_index: 0,
+                offset,
+            } => {
+                self.write_u32(0);
+                self.write_const_expr(offset);
+            }
+            DataMode::Passive => self.write_u32(1),
+            DataMode::Active {
+                memory_index,
+                offset,
+            } => {
+                self.write_u32(2);
+                self.write_u32(*memory_index);
+                self.write_const_expr(offset);
+            }
+        }
+
+        self.write_u32(data.bytes.len() as u32);
+        self.write_bytes(&data.bytes);
+    }
+
+    fn write_const_expr(&mut self, expr: &ConstExpr) {
+        for instr in &expr.instructions {
+            self.write_instruction(instr);
+        }
+        self.write_instruction(&Instruction::End);
+    }
+
+    fn write_instruction(&mut self, instr: &Instruction) {
+        match instr {
+            Instruction::Block { block } => {
+                self.write_u8(0x02);
+                self.write_block_type(&block.block_type);
+            }
+            Instruction::Loop { block } => {
+                self.write_u8(0x03);
+                self.write_block_type(&block.block_type);
+            }
+            Instruction::If { block } => {
+                self.write_u8(0x04);
+                self.write_block_type(&block.block_type);
+            }
+            Instruction::TryTable { block, catches } => {
+                self.write_u8(0x1F);
+                self.write_block_type(&block.block_type);
+                self.write_vec(catches, Self::write_catch);
+            }
+            Instruction::BrTable {
+                label_indexes,
+                default_index,
+            } => {
+                self.write_u8(0x0E);
+                self.write_vec(label_indexes, |e, label| e.write_u32(*label));
+                self.write_u32(*default_index);
+            }
+            Instruction::Select { result_types: None } => self.write_u8(0x1B),
+            Instruction::Select {
+                result_types: Some(result_types),
+            } => {
+                self.write_u8(0x1C);
+                self.write_vec(result_types, Self::write_value_type);
+            }
+            Instruction::SelectResult { result_type } => {
+                self.write_u8(0x1C);
+                self.write_vec(std::slice::from_ref(result_type), Self::write_value_type);
+            }
+            Instruction::AtomicFence => {
+                self.write_prefixed(0xFE, 0x03);
+                self.write_u8(0x00);
+            }
+            Instruction::Unreachable => self.write_u8(0x00),
+            Instruction::Nop => self.write_u8(0x01),
+            Instruction::Else => self.write_u8(0x05),
+            Instruction::Throw { tag_index } => {
+                self.write_u8(0x08);
+                self.write_u32(*tag_index);
+            }
+            Instruction::ThrowRef => self.write_u8(0x0A),
+            Instruction::End => self.write_u8(0x0B),
+            Instruction::Br { level } => {
+                self.write_u8(0x0C);
+                self.write_u32(*level);
+            }
+            Instruction::BrIf { level } => {
+                self.write_u8(0x0D);
+                self.write_u32(*level);
+            }
+            Instruction::Return => self.write_u8(0x0F),
+            Instruction::Call { func_index } => {
+                self.write_u8(0x10);
+                self.write_u32(*func_index);
+            }
+            Instruction::CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                self.write_u8(0x11);
+                self.write_u32(*type_index);
+                self.write_u32(*table_index);
+            }
+            Instruction::ReturnCall { func_index } => {
+                self.write_u8(0x12);
+                self.write_u32(*func_index);
+            }
+            Instruction::ReturnCallIndirect {
+                type_index,
+                table_index,
+            } => {
+                self.write_u8(0x13);
+                self.write_u32(*type_index);
+                self.write_u32(*table_index);
+            }
+            Instruction::RefNull { ref_type } => {
+                self.write_u8(0xD0);
+                self.write_ref_type(*ref_type);
+            }
+            Instruction::RefIsNull => self.write_u8(0xD1),
+            Instruction::RefFunc { func_index } => {
+                self.write_u8(0xD2);
+                self.write_u32(*func_index);
+            }
+            Instruction::Drop => self.write_u8(0x1A),
+            Instruction::LocalGet { local_index } => {
+                self.write_u8(0x20);
+                self.write_u32(*local_index);
+            }
+            Instruction::LocalSet { local_index } => {
+                self.write_u8(0x21);
+                self.write_u32(*local_index);
+            }
+            Instruction::LocalTee { local_index } => {
+                self.write_u8(0x22);
+                self.write_u32(*local_index);
+            }
+            Instruction::GlobalGet { global_index } => {
+                self.write_u8(0x23);
+                self.write_u32(*global_index);
+            }
+            Instruction::GlobalSet { global_index } => {
+                self.write_u8(0x24);
+                self.write_u32(*global_index);
+            }
+            Instruction::TableGet { table_index } => {
+                self.write_u8(0x25);
+                self.write_u32(*table_index);
+            }
+            Instruction::TableSet { table_index } => {
+                self.write_u8(0x26);
+                self.write_u32(*table_index);
+            }
+            Instruction::I32Load { mem_arg } => {
+                self.write_u8(0x28);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load { mem_arg } => {
+                self.write_u8(0x29);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::F32Load { mem_arg } => {
+                self.write_u8(0x2A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::F64Load { mem_arg } => {
+                self.write_u8(0x2B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Load8S { mem_arg } => {
+                self.write_u8(0x2C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Load8U { mem_arg } => {
+                self.write_u8(0x2D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Load16S { mem_arg } => {
+                self.write_u8(0x2E);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Load16U { mem_arg } => {
+                self.write_u8(0x2F);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load8S { mem_arg } => {
+                self.write_u8(0x30);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load8U { mem_arg } => {
+                self.write_u8(0x31);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load16S { mem_arg } => {
+                self.write_u8(0x32);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load16U { mem_arg } => {
+                self.write_u8(0x33);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load32S { mem_arg } => {
+                self.write_u8(0x34);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Load32U { mem_arg } => {
+                self.write_u8(0x35);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Store { mem_arg } => {
+                self.write_u8(0x36);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Store { mem_arg } => {
+                self.write_u8(0x37);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::F32Store { mem_arg } => {
+                self.write_u8(0x38);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::F64Store { mem_arg } => {
+                self.write_u8(0x39);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Store8 { mem_arg } => {
+                self.write_u8(0x3A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32Store16 { mem_arg } => {
+                self.write_u8(0x3B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Store8 { mem_arg } => {
+                self.write_u8(0x3C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Store16 { mem_arg } => {
+                self.write_u8(0x3D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64Store32 { mem_arg } => {
+                self.write_u8(0x3E);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::MemorySize { memory_index } => {
+                self.write_u8(0x3F);
+                self.write_u32(*memory_index);
+            }
+            Instruction::MemoryGrow { memory_index } => {
+                self.write_u8(0x40);
+                self.write_u32(*memory_index);
+            }
+            Instruction::I32Const { value } => {
+                self.write_u8(0x41);
+                self.write_i32(*value);
+            }
+            Instruction::I64Const { value } => {
+                self.write_u8(0x42);
+                self.write_i64(*value);
+            }
+            Instruction::F32Const { value } => {
+                self.write_u8(0x43);
+                self.write_f32(*value);
+            }
+            Instruction::F64Const { value } => {
+                self.write_u8(0x44);
+                self.write_f64(*value);
+            }
+            Instruction::I32Eqz => self.write_u8(0x45),
+            Instruction::I32Eq => self.write_u8(0x46),
+            Instruction::I32Ne => self.write_u8(0x47),
+            Instruction::I32LtS => self.write_u8(0x48),
+            Instruction::I32LtU => self.write_u8(0x49),
+            Instruction::I32GtS => self.write_u8(0x4A),
+            Instruction::I32GtU => self.write_u8(0x4B),
+            Instruction::I32LeS => self.write_u8(0x4C),
+            Instruction::I32LeU => self.write_u8(0x4D),
+            Instruction::I32GeS => self.write_u8(0x4E),
+            Instruction::I32GeU => self.write_u8(0x4F),
+            Instruction::I64Eqz => self.write_u8(0x50),
+            Instruction::I64Eq => self.write_u8(0x51),
+            Instruction::I64Ne => self.write_u8(0x52),
+            Instruction::I64LtS => self.write_u8(0x53),
+            Instruction::I64LtU => self.write_u8(0x54),
+            Instruction::I64GtS => self.write_u8(0x55),
+            Instruction::I64GtU => self.write_u8(0x56),
+            Instruction::I64LeS => self.write_u8(0x57),
+            Instruction::I64LeU => self.write_u8(0x58),
+            Instruction::I64GeS => self.write_u8(0x59),
+            Instruction::I64GeU => self.write_u8(0x5A),
+            Instruction::F32Eq => self.write_u8(0x5B),
+            Instruction::F32Ne => self.write_u8(0x5C),
+            Instruction::F32Lt => self.write_u8(0x5D),
+            Instruction::F32Gt => self.write_u8(0x5E),
+            Instruction::F32Le => self.write_u8(0x5F),
+            Instruction::F32Ge => self.write_u8(0x60),
+            Instruction::F64Eq => self.write_u8(0x61),
+            Instruction::F64Ne => self.write_u8(0x62),
+            Instruction::F64Lt => self.write_u8(0x63),
+            Instruction::F64Gt => self.write_u8(0x64),
+            Instruction::F64Le => self.write_u8(0x65),
+            Instruction::F64Ge => self.write_u8(0x66),
+            Instruction::I32Clz => self.write_u8(0x67),
+            Instruction::I32Ctz => self.write_u8(0x68),
+            Instruction::I32Popcnt => self.write_u8(0x69),
+            Instruction::I32Add => self.write_u8(0x6A),
+            Instruction::I32Sub => self.write_u8(0x6B),
+            Instruction::I32Mul => self.write_u8(0x6C),
+            Instruction::I32DivS => self.write_u8(0x6D),
+            Instruction::I32DivU => self.write_u8(0x6E),
+            Instruction::I32RemS => self.write_u8(0x6F),
+            Instruction::I32RemU => self.write_u8(0x70),
+            Instruction::I32And => self.write_u8(0x71),
+            Instruction::I32Or => self.write_u8(0x72),
+            Instruction::I32Xor => self.write_u8(0x73),
+            Instruction::I32Shl => self.write_u8(0x74),
+            Instruction::I32ShrS => self.write_u8(0x75),
+            Instruction::I32ShrU => self.write_u8(0x76),
+            Instruction::I32Rotl => self.write_u8(0x77),
+            Instruction::I32Rotr => self.write_u8(0x78),
+            Instruction::I64Clz => self.write_u8(0x79),
+            Instruction::I64Ctz => self.write_u8(0x7A),
+            Instruction::I64Popcnt => self.write_u8(0x7B),
+            Instruction::I64Add => self.write_u8(0x7C),
+            Instruction::I64Sub => self.write_u8(0x7D),
+            Instruction::I64Mul => self.write_u8(0x7E),
+            Instruction::I64DivS => self.write_u8(0x7F),
+            Instruction::I64DivU => self.write_u8(0x80),
+            Instruction::I64RemS => self.write_u8(0x81),
+            Instruction::I64RemU => self.write_u8(0x82),
+            Instruction::I64And => self.write_u8(0x83),
+            Instruction::I64Or => self.write_u8(0x84),
+            Instruction::I64Xor => self.write_u8(0x85),
+            Instruction::I64Shl => self.write_u8(0x86),
+            Instruction::I64ShrS => self.write_u8(0x87),
+            Instruction::I64ShrU => self.write_u8(0x88),
+            Instruction::I64Rotl => self.write_u8(0x89),
+            Instruction::I64Rotr => self.write_u8(0x8A),
+            Instruction::F32Abs => self.write_u8(0x8B),
+            Instruction::F32Neg => self.write_u8(0x8C),
+            Instruction::F32Ceil => self.write_u8(0x8D),
+            Instruction::F32Floor => self.write_u8(0x8E),
+            Instruction::F32Trunc => self.write_u8(0x8F),
+            Instruction::F32Nearest => self.write_u8(0x90),
+            Instruction::F32Sqrt => self.write_u8(0x91),
+            Instruction::F32Add => self.write_u8(0x92),
+            Instruction::F32Sub => self.write_u8(0x93),
+            Instruction::F32Mul => self.write_u8(0x94),
+            Instruction::F32Div => self.write_u8(0x95),
+            Instruction::F32Min => self.write_u8(0x96),
+            Instruction::F32Max => self.write_u8(0x97),
+            Instruction::F32Copysign => self.write_u8(0x98),
+            Instruction::F64Abs => self.write_u8(0x99),
+            Instruction::F64Neg => self.write_u8(0x9A),
+            Instruction::F64Ceil => self.write_u8(0x9B),
+            Instruction::F64Floor => self.write_u8(0x9C),
+            Instruction::F64Trunc => self.write_u8(0x9D),
+            Instruction::F64Nearest => self.write_u8(0x9E),
+            Instruction::F64Sqrt => self.write_u8(0x9F),
+            Instruction::F64Add => self.write_u8(0xA0),
+            Instruction::F64Sub => self.write_u8(0xA1),
+            Instruction::F64Mul => self.write_u8(0xA2),
+            Instruction::F64Div => self.write_u8(0xA3),
+            Instruction::F64Min => self.write_u8(0xA4),
+            Instruction::F64Max => self.write_u8(0xA5),
+            Instruction::F64Copysign => self.write_u8(0xA6),
+            Instruction::I32WrapI64 => self.write_u8(0xA7),
+            Instruction::I32TruncF32S => self.write_u8(0xA8),
+            Instruction::I32TruncF32U => self.write_u8(0xA9),
+            Instruction::I32TruncF64S => self.write_u8(0xAA),
+            Instruction::I32TruncF64U => self.write_u8(0xAB),
+            Instruction::I64ExtendI32S => self.write_u8(0xAC),
+            Instruction::I64ExtendI32U => self.write_u8(0xAD),
+            Instruction::I64TruncF32S => self.write_u8(0xAE),
+            Instruction::I64TruncF32U => self.write_u8(0xAF),
+            Instruction::I64TruncF64S => self.write_u8(0xB0),
+            Instruction::I64TruncF64U => self.write_u8(0xB1),
+            Instruction::F32ConvertI32S => self.write_u8(0xB2),
+            Instruction::F32ConvertI32U => self.write_u8(0xB3),
+            Instruction::F32ConvertI64S => self.write_u8(0xB4),
+            Instruction::F32ConvertI64U => self.write_u8(0xB5),
+            Instruction::F32DemoteF64 => self.write_u8(0xB6),
+            Instruction::F64ConvertI32S => self.write_u8(0xB7),
+            Instruction::F64ConvertI32U => self.write_u8(0xB8),
+            Instruction::F64ConvertI64S => self.write_u8(0xB9),
+            Instruction::F64ConvertI64U => self.write_u8(0xBA),
+            Instruction::F64PromoteF32 => self.write_u8(0xBB),
+            Instruction::I32ReinterpretF32 => self.write_u8(0xBC),
+            Instruction::I64ReinterpretF64 => self.write_u8(0xBD),
+            Instruction::F32ReinterpretI32 => self.write_u8(0xBE),
+            Instruction::F64ReinterpretI64 => self.write_u8(0xBF),
+            Instruction::I32Extend8S => self.write_u8(0xC0),
+            Instruction::I32Extend16S => self.write_u8(0xC1),
+            Instruction::I64Extend8S => self.write_u8(0xC2),
+            Instruction::I64Extend16S => self.write_u8(0xC3),
+            Instruction::I64Extend32S => self.write_u8(0xC4),
+            Instruction::I32TruncSatF32S => self.write_prefixed(0xFC, 0x00),
+            Instruction::I32TruncSatF32U => self.write_prefixed(0xFC, 0x01),
+            Instruction::I32TruncSatF64S => self.write_prefixed(0xFC, 0x02),
+            Instruction::I32TruncSatF64U => self.write_prefixed(0xFC, 0x03),
+            Instruction::I64TruncSatF32S => self.write_prefixed(0xFC, 0x04),
+            Instruction::I64TruncSatF32U => self.write_prefixed(0xFC, 0x05),
+            Instruction::I64TruncSatF64S => self.write_prefixed(0xFC, 0x06),
+            Instruction::I64TruncSatF64U => self.write_prefixed(0xFC, 0x07),
+            Instruction::MemoryInit {
+                data_index,
+                memory_index,
+            } => {
+                self.write_prefixed(0xFC, 0x08);
+                self.write_u32(*data_index);
+                self.write_u32(*memory_index);
+            }
+            Instruction::DataDrop { data_index } => {
+                self.write_prefixed(0xFC, 0x09);
+                self.write_u32(*data_index);
+            }
+            Instruction::MemoryCopy {
+                dst_memory_index,
+                src_memory_index,
+            } => {
+                self.write_prefixed(0xFC, 0x0A);
+                self.write_u32(*dst_memory_index);
+                self.write_u32(*src_memory_index);
+            }
+            Instruction::MemoryFill { memory_index } => {
+                self.write_prefixed(0xFC, 0x0B);
+                self.write_u32(*memory_index);
+            }
+            Instruction::TableInit {
+                element_index,
+                table_index,
+            } => {
+                self.write_prefixed(0xFC, 0x0C);
+                self.write_u32(*element_index);
+                self.write_u32(*table_index);
+            }
+            Instruction::ElemDrop { element_index } => {
+                self.write_prefixed(0xFC, 0x0D);
+                self.write_u32(*element_index);
+            }
+            Instruction::TableCopy {
+                dst_table_index,
+                src_table_index,
+            } => {
+                self.write_prefixed(0xFC, 0x0E);
+                self.write_u32(*dst_table_index);
+                self.write_u32(*src_table_index);
+            }
+            Instruction::TableGrow { table_index } => {
+                self.write_prefixed(0xFC, 0x0F);
+                self.write_u32(*table_index);
+            }
+            Instruction::TableSize { table_index } => {
+                self.write_prefixed(0xFC, 0x10);
+                self.write_u32(*table_index);
+            }
+            Instruction::TableFill { table_index } => {
+                self.write_prefixed(0xFC, 0x11);
+                self.write_u32(*table_index);
+            }
+            Instruction::V128Load { mem_arg } => {
+                self.write_prefixed(0xFD, 0x00);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load8x8S { mem_arg } => {
+                self.write_prefixed(0xFD, 0x01);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load8x8U { mem_arg } => {
+                self.write_prefixed(0xFD, 0x02);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load16x4S { mem_arg } => {
+                self.write_prefixed(0xFD, 0x03);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load16x4U { mem_arg } => {
+                self.write_prefixed(0xFD, 0x04);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load32x2S { mem_arg } => {
+                self.write_prefixed(0xFD, 0x05);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load32x2U { mem_arg } => {
+                self.write_prefixed(0xFD, 0x06);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load8Splat { mem_arg } => {
+                self.write_prefixed(0xFD, 0x07);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load16Splat { mem_arg } => {
+                self.write_prefixed(0xFD, 0x08);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load32Splat { mem_arg } => {
+                self.write_prefixed(0xFD, 0x09);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load64Splat { mem_arg } => {
+                self.write_prefixed(0xFD, 0x0A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Store { mem_arg } => {
+                self.write_prefixed(0xFD, 0x0B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Const { value } => {
+                self.write_prefixed(0xFD, 0x0C);
+                self.write_v128(*value);
+            }
+            Instruction::I8x16Shuffle { lanes } => {
+                self.write_prefixed(0xFD, 0x0D);
+                self.write_bytes(lanes);
+            }
+            Instruction::I8x16Swizzle => self.write_prefixed(0xFD, 0x0E),
+            Instruction::I8x16Splat => self.write_prefixed(0xFD, 0x0F),
+            Instruction::I16x8Splat => self.write_prefixed(0xFD, 0x10),
+            Instruction::I32x4Splat => self.write_prefixed(0xFD, 0x11),
+            Instruction::I64x2Splat => self.write_prefixed(0xFD, 0x12),
+            Instruction::F32x4Splat => self.write_prefixed(0xFD, 0x13),
+            Instruction::F64x2Splat => self.write_prefixed(0xFD, 0x14),
+            Instruction::I8x16ExtractLaneS { lane } => {
+                self.write_prefixed(0xFD, 0x15);
+                self.write_u8(*lane);
+            }
+            Instruction::I8x16ExtractLaneU { lane } => {
+                self.write_prefixed(0xFD, 0x16);
+                self.write_u8(*lane);
+            }
+            Instruction::I8x16ReplaceLane { lane } => {
+                self.write_prefixed(0xFD, 0x17);
+                self.write_u8(*lane);
+            }
+            Instruction::I16x8ExtractLaneS { lane } => {
+                self.write_prefixed(0xFD, 0x18);
+                self.write_u8(*lane);
+            }
+            Instruction::I16x8ExtractLaneU { lane } => {
+                self.write_prefixed(0xFD, 0x19);
+                self.write_u8(*lane);
+            }
+            Instruction::I16x8ReplaceLane { lane } => {
+                self.write_prefixed(0xFD, 0x1A);
+                self.write_u8(*lane);
+            }
+            Instruction::I32x4ExtractLane { lane } => {
+                self.write_prefixed(0xFD, 0x1B);
+                self.write_u8(*lane);
+            }
+            Instruction::I32x4ReplaceLane { lane } => {
+                self.write_prefixed(0xFD, 0x1C);
+                self.write_u8(*lane);
+            }
+            Instruction::I64x2ExtractLane { lane } => {
+                self.write_prefixed(0xFD, 0x1D);
+                self.write_u8(*lane);
+            }
+            Instruction::I64x2ReplaceLane { lane } => {
+                self.write_prefixed(0xFD, 0x1E);
+                self.write_u8(*lane);
+            }
+            Instruction::F32x4ExtractLane { lane } => {
+                self.write_prefixed(0xFD, 0x1F);
+                self.write_u8(*lane);
+            }
+            Instruction::F32x4ReplaceLane { lane } => {
+                self.write_prefixed(0xFD, 0x20);
+                self.write_u8(*lane);
+            }
+            Instruction::F64x2ExtractLane { lane } => {
+                self.write_prefixed(0xFD, 0x21);
+                self.write_u8(*lane);
+            }
+            Instruction::F64x2ReplaceLane { lane } => {
+                self.write_prefixed(0xFD, 0x22);
+                self.write_u8(*lane);
+            }
+            Instruction::I8x16Eq => self.write_prefixed(0xFD, 0x23),
+            Instruction::I8x16Ne => self.write_prefixed(0xFD, 0x24),
+            Instruction::I8x16LtS => self.write_prefixed(0xFD, 0x25),
+            Instruction::I8x16LtU => self.write_prefixed(0xFD, 0x26),
+            Instruction::I8x16GtS => self.write_prefixed(0xFD, 0x27),
+            Instruction::I8x16GtU => self.write_prefixed(0xFD, 0x28),
+            Instruction::I8x16LeS => self.write_prefixed(0xFD, 0x29),
+            Instruction::I8x16LeU => self.write_prefixed(0xFD, 0x2A),
+            Instruction::I8x16GeS => self.write_prefixed(0xFD, 0x2B),
+            Instruction::I8x16GeU => self.write_prefixed(0xFD, 0x2C),
+            Instruction::I16x8Eq => self.write_prefixed(0xFD, 0x2D),
+            Instruction::I16x8Ne => self.write_prefixed(0xFD, 0x2E),
+            Instruction::I16x8LtS => self.write_prefixed(0xFD, 0x2F),
+            Instruction::I16x8LtU => self.write_prefixed(0xFD, 0x30),
+            Instruction::I16x8GtS => self.write_prefixed(0xFD, 0x31),
+            Instruction::I16x8GtU => self.write_prefixed(0xFD, 0x32),
+            Instruction::I16x8LeS => self.write_prefixed(0xFD, 0x33),
+            Instruction::I16x8LeU => self.write_prefixed(0xFD, 0x34),
+            Instruction::I16x8GeS => self.write_prefixed(0xFD, 0x35),
+            Instruction::I16x8GeU => self.write_prefixed(0xFD, 0x36),
+            Instruction::I32x4Eq => self.write_prefixed(0xFD, 0x37),
+            Instruction::I32x4Ne => self.write_prefixed(0xFD, 0x38),
+            Instruction::I32x4LtS => self.write_prefixed(0xFD, 0x39),
+            Instruction::I32x4LtU => self.write_prefixed(0xFD, 0x3A),
+            Instruction::I32x4GtS => self.write_prefixed(0xFD, 0x3B),
+            Instruction::I32x4GtU => self.write_prefixed(0xFD, 0x3C),
+            Instruction::I32x4LeS => self.write_prefixed(0xFD, 0x3D),
+            Instruction::I32x4LeU => self.write_prefixed(0xFD, 0x3E),
+            Instruction::I32x4GeS => self.write_prefixed(0xFD, 0x3F),
+            Instruction::I32x4GeU => self.write_prefixed(0xFD, 0x40),
+            Instruction::F32x4Eq => self.write_prefixed(0xFD, 0x41),
+            Instruction::F32x4Ne => self.write_prefixed(0xFD, 0x42),
+            Instruction::F32x4Lt => self.write_prefixed(0xFD, 0x43),
+            Instruction::F32x4Gt => self.write_prefixed(0xFD, 0x44),
+            Instruction::F32x4Le => self.write_prefixed(0xFD, 0x45),
+            Instruction::F32x4Ge => self.write_prefixed(0xFD, 0x46),
+            Instruction::F64x2Eq => self.write_prefixed(0xFD, 0x47),
+            Instruction::F64x2Ne => self.write_prefixed(0xFD, 0x48),
+            Instruction::F64x2Lt => self.write_prefixed(0xFD, 0x49),
+            Instruction::F64x2Gt => self.write_prefixed(0xFD, 0x4A),
+            Instruction::F64x2Le => self.write_prefixed(0xFD, 0x4B),
+            Instruction::F64x2Ge => self.write_prefixed(0xFD, 0x4C),
+            Instruction::V128Not => self.write_prefixed(0xFD, 0x4D),
+            Instruction::V128And => self.write_prefixed(0xFD, 0x4E),
+            Instruction::V128Andnot => self.write_prefixed(0xFD, 0x4F),
+            Instruction::V128Or => self.write_prefixed(0xFD, 0x50),
+            Instruction::V128Xor => self.write_prefixed(0xFD, 0x51),
+            Instruction::V128Bitselect => self.write_prefixed(0xFD, 0x52),
+            Instruction::V128AnyTrue => self.write_prefixed(0xFD, 0x53),
+            Instruction::V128Load8Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x54);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Load16Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x55);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Load32Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x56);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Load64Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x57);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Store8Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x58);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Store16Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x59);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Store32Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x5A);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Store64Lane { mem_arg, lane } => {
+                self.write_prefixed(0xFD, 0x5B);
+                self.write_mem_arg(mem_arg);
+                self.write_u8(*lane);
+            }
+            Instruction::V128Load32Zero { mem_arg } => {
+                self.write_prefixed(0xFD, 0x5C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::V128Load64Zero { mem_arg } => {
+                self.write_prefixed(0xFD, 0x5D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::F32x4DemoteF64x2Zero => self.write_prefixed(0xFD, 0x5E),
+            Instruction::F64x2PromoteLowF32x4 => self.write_prefixed(0xFD, 0x5F),
+            Instruction::I8x16Abs => self.write_prefixed(0xFD, 0x60),
+            Instruction::I8x16Neg => self.write_prefixed(0xFD, 0x61),
+            Instruction::I8x16Popcnt => self.write_prefixed(0xFD, 0x62),
+            Instruction::I8x16AllTrue => self.write_prefixed(0xFD, 0x63),
+            Instruction::I8x16Bitmask => self.write_prefixed(0xFD, 0x64),
+            Instruction::I8x16NarrowI16x8S => self.write_prefixed(0xFD, 0x65),
+            Instruction::I8x16NarrowI16x8U => self.write_prefixed(0xFD, 0x66),
+            Instruction::F32x4Ceil => self.write_prefixed(0xFD, 0x67),
+            Instruction::F32x4Floor => self.write_prefixed(0xFD, 0x68),
+            Instruction::F32x4Trunc => self.write_prefixed(0xFD, 0x69),
+            Instruction::F32x4Nearest => self.write_prefixed(0xFD, 0x6A),
+            Instruction::I8x16Shl => self.write_prefixed(0xFD, 0x6B),
+            Instruction::I8x16ShrS => self.write_prefixed(0xFD, 0x6C),
+            Instruction::I8x16ShrU => self.write_prefixed(0xFD, 0x6D),
+            Instruction::I8x16Add => self.write_prefixed(0xFD, 0x6E),
+            Instruction::I8x16AddSatS => self.write_prefixed(0xFD, 0x6F),
+            Instruction::I8x16AddSatU => self.write_prefixed(0xFD, 0x70),
+            Instruction::I8x16Sub => self.write_prefixed(0xFD, 0x71),
+            Instruction::I8x16SubSatS => self.write_prefixed(0xFD, 0x72),
+            Instruction::I8x16SubSatU => self.write_prefixed(0xFD, 0x73),
+            Instruction::F64x2Ceil => self.write_prefixed(0xFD, 0x74),
+            Instruction::F64x2Floor => self.write_prefixed(0xFD, 0x75),
+            Instruction::I8x16MinS => self.write_prefixed(0xFD, 0x76),
+            Instruction::I8x16MinU => self.write_prefixed(0xFD, 0x77),
+            Instruction::I8x16MaxS => self.write_prefixed(0xFD, 0x78),
+            Instruction::I8x16MaxU => self.write_prefixed(0xFD, 0x79),
+            Instruction::F64x2Trunc => self.write_prefixed(0xFD, 0x7A),
+            Instruction::I8x16AvgrU => self.write_prefixed(0xFD, 0x7B),
+            Instruction::I16x8ExtaddPairwiseI8x16S => self.write_prefixed(0xFD, 0x7C),
+            Instruction::I16x8ExtaddPairwiseI8x16U => self.write_prefixed(0xFD, 0x7D),
+            Instruction::I32x4ExtaddPairwiseI16x8S => self.write_prefixed(0xFD, 0x7E),
+            Instruction::I32x4ExtaddPairwiseI16x8U => self.write_prefixed(0xFD, 0x7F),
+            Instruction::I16x8Abs => self.write_prefixed(0xFD, 0x80),
+            Instruction::I16x8Neg => self.write_prefixed(0xFD, 0x81),
+            Instruction::I16x8Q15mulrSatS => self.write_prefixed(0xFD, 0x82),
+            Instruction::I16x8AllTrue => self.write_prefixed(0xFD, 0x83),
+            Instruction::I16x8Bitmask => self.write_prefixed(0xFD, 0x84),
+            Instruction::I16x8NarrowI32x4S => self.write_prefixed(0xFD, 0x85),
+            Instruction::I16x8NarrowI32x4U => self.write_prefixed(0xFD, 0x86),
+            Instruction::I16x8ExtendLowI8x16S => self.write_prefixed(0xFD, 0x87),
+            Instruction::I16x8ExtendHighI8x16S => self.write_prefixed(0xFD, 0x88),
+            Instruction::I16x8ExtendLowI8x16U => self.write_prefixed(0xFD, 0x89),
+            Instruction::I16x8ExtendHighI8x16U => self.write_prefixed(0xFD, 0x8A),
+            Instruction::I16x8Shl => self.write_prefixed(0xFD, 0x8B),
+            Instruction::I16x8ShrS => self.write_prefixed(0xFD, 0x8C),
+            Instruction::I16x8ShrU => self.write_prefixed(0xFD, 0x8D),
+            Instruction::I16x8Add => self.write_prefixed(0xFD, 0x8E),
+            Instruction::I16x8AddSatS => self.write_prefixed(0xFD, 0x8F),
+            Instruction::I16x8AddSatU => self.write_prefixed(0xFD, 0x90),
+            Instruction::I16x8Sub => self.write_prefixed(0xFD, 0x91),
+            Instruction::I16x8SubSatS => self.write_prefixed(0xFD, 0x92),
+            Instruction::I16x8SubSatU => self.write_prefixed(0xFD, 0x93),
+            Instruction::F64x2Nearest => self.write_prefixed(0xFD, 0x94),
+            Instruction::I16x8Mul => self.write_prefixed(0xFD, 0x95),
+            Instruction::I16x8MinS => self.write_prefixed(0xFD, 0x96),
+            Instruction::I16x8MinU => self.write_prefixed(0xFD, 0x97),
+            Instruction::I16x8MaxS => self.write_prefixed(0xFD, 0x98),
+            Instruction::I16x8MaxU => self.write_prefixed(0xFD, 0x99),
+            Instruction::I16x8AvgrU => self.write_prefixed(0xFD, 0x9B),
+            Instruction::I16x8ExtmulLowI8x16S => self.write_prefixed(0xFD, 0x9C),
+            Instruction::I16x8ExtmulHighI8x16S => self.write_prefixed(0xFD, 0x9D),
+            Instruction::I16x8ExtmulLowI8x16U => self.write_prefixed(0xFD, 0x9E),
+            Instruction::I16x8ExtmulHighI8x16U => self.write_prefixed(0xFD, 0x9F),
+            Instruction::I32x4Abs => self.write_prefixed(0xFD, 0xA0),
+            Instruction::I32x4Neg => self.write_prefixed(0xFD, 0xA1),
+            Instruction::I32x4AllTrue => self.write_prefixed(0xFD, 0xA3),
+            Instruction::I32x4Bitmask => self.write_prefixed(0xFD, 0xA4),
+            Instruction::I32x4ExtendLowI16x8S => self.write_prefixed(0xFD, 0xA7),
+            Instruction::I32x4ExtendHighI16x8S => self.write_prefixed(0xFD, 0xA8),
+            Instruction::I32x4ExtendLowI16x8U => self.write_prefixed(0xFD, 0xA9),
+            Instruction::I32x4ExtendHighI16x8U => self.write_prefixed(0xFD, 0xAA),
+            Instruction::I32x4Shl => self.write_prefixed(0xFD, 0xAB),
+            Instruction::I32x4ShrS => self.write_prefixed(0xFD, 0xAC),
+            Instruction::I32x4ShrU => self.write_prefixed(0xFD, 0xAD),
+            Instruction::I32x4Add => self.write_prefixed(0xFD, 0xAE),
+            Instruction::I32x4Sub => self.write_prefixed(0xFD, 0xB1),
+            Instruction::I32x4Mul => self.write_prefixed(0xFD, 0xB5),
+            Instruction::I32x4MinS => self.write_prefixed(0xFD, 0xB6),
+            Instruction::I32x4MinU => self.write_prefixed(0xFD, 0xB7),
+            Instruction::I32x4MaxS => self.write_prefixed(0xFD, 0xB8),
+            Instruction::I32x4MaxU => self.write_prefixed(0xFD, 0xB9),
+            Instruction::I32x4DotI16x8S => self.write_prefixed(0xFD, 0xBA),
+            Instruction::I32x4ExtmulLowI16x8S => self.write_prefixed(0xFD, 0xBC),
+            Instruction::I32x4ExtmulHighI16x8S => self.write_prefixed(0xFD, 0xBD),
+            Instruction::I32x4ExtmulLowI16x8U => self.write_prefixed(0xFD, 0xBE),
+            Instruction::I32x4ExtmulHighI16x8U => self.write_prefixed(0xFD, 0xBF),
+            Instruction::I64x2Abs => self.write_prefixed(0xFD, 0xC0),
+            Instruction::I64x2Neg => self.write_prefixed(0xFD, 0xC1),
+            Instruction::I64x2AllTrue => self.write_prefixed(0xFD, 0xC3),
+            Instruction::I64x2Bitmask => self.write_prefixed(0xFD, 0xC4),
+            Instruction::I64x2ExtendLowI32x4S => self.write_prefixed(0xFD, 0xC7),
+            Instruction::I64x2ExtendHighI32x4S => self.write_prefixed(0xFD, 0xC8),
+            Instruction::I64x2ExtendLowI32x4U => self.write_prefixed(0xFD, 0xC9),
+            Instruction::I64x2ExtendHighI32x4U => self.write_prefixed(0xFD, 0xCA),
+            Instruction::I64x2Shl => self.write_prefixed(0xFD, 0xCB),
+            Instruction::I64x2ShrS => self.write_prefixed(0xFD, 0xCC),
+            Instruction::I64x2ShrU => self.write_prefixed(0xFD, 0xCD),
+            Instruction::I64x2Add => self.write_prefixed(0xFD, 0xCE),
+            Instruction::I64x2Sub => self.write_prefixed(0xFD, 0xD1),
+            Instruction::I64x2Mul => self.write_prefixed(0xFD, 0xD5),
+            Instruction::I64x2Eq => self.write_prefixed(0xFD, 0xD6),
+            Instruction::I64x2Ne => self.write_prefixed(0xFD, 0xD7),
+            Instruction::I64x2LtS => self.write_prefixed(0xFD, 0xD8),
+            Instruction::I64x2GtS => self.write_prefixed(0xFD, 0xD9),
+            Instruction::I64x2LeS => self.write_prefixed(0xFD, 0xDA),
+            Instruction::I64x2GeS => self.write_prefixed(0xFD, 0xDB),
+            Instruction::I64x2ExtmulLowI32x4S => self.write_prefixed(0xFD, 0xDC),
+            Instruction::I64x2ExtmulHighI32x4S => self.write_prefixed(0xFD, 0xDD),
+            Instruction::I64x2ExtmulLowI32x4U => self.write_prefixed(0xFD, 0xDE),
+            Instruction::I64x2ExtmulHighI32x4U => self.write_prefixed(0xFD, 0xDF),
+            Instruction::F32x4Abs => self.write_prefixed(0xFD, 0xE0),
+            Instruction::F32x4Neg => self.write_prefixed(0xFD, 0xE1),
+            Instruction::F32x4Sqrt => self.write_prefixed(0xFD, 0xE3),
+            Instruction::F32x4Add => self.write_prefixed(0xFD, 0xE4),
+            Instruction::F32x4Sub => self.write_prefixed(0xFD, 0xE5),
+            Instruction::F32x4Mul => self.write_prefixed(0xFD, 0xE6),
+            Instruction::F32x4Div => self.write_prefixed(0xFD, 0xE7),
+            Instruction::F32x4Min => self.write_prefixed(0xFD, 0xE8),
+            Instruction::F32x4Max => self.write_prefixed(0xFD, 0xE9),
+            Instruction::F32x4Pmin => self.write_prefixed(0xFD, 0xEA),
+            Instruction::F32x4Pmax => self.write_prefixed(0xFD, 0xEB),
+            Instruction::F64x2Abs => self.write_prefixed(0xFD, 0xEC),
+            Instruction::F64x2Neg => self.write_prefixed(0xFD, 0xED),
+            Instruction::F64x2Sqrt => self.write_prefixed(0xFD, 0xEF),
+            Instruction::F64x2Add => self.write_prefixed(0xFD, 0xF0),
+            Instruction::F64x2Sub => self.write_prefixed(0xFD, 0xF1),
+            Instruction::F64x2Mul => self.write_prefixed(0xFD, 0xF2),
+            Instruction::F64x2Div => self.write_prefixed(0xFD, 0xF3),
+            Instruction::F64x2Min => self.write_prefixed(0xFD, 0xF4),
+            Instruction::F64x2Max => self.write_prefixed(0xFD, 0xF5),
+            Instruction::F64x2Pmin => self.write_prefixed(0xFD, 0xF6),
+            Instruction::F64x2Pmax => self.write_prefixed(0xFD, 0xF7),
+            Instruction::I32x4TruncSatF32x4S => self.write_prefixed(0xFD, 0xF8),
+            Instruction::I32x4TruncSatF32x4U => self.write_prefixed(0xFD, 0xF9),
+            Instruction::F32x4ConvertI32x4S => self.write_prefixed(0xFD, 0xFA),
+            Instruction::F32x4ConvertI32x4U => self.write_prefixed(0xFD, 0xFB),
+            Instruction::I32x4TruncSatF64x2SZero => self.write_prefixed(0xFD, 0xFC),
+            Instruction::I32x4TruncSatF64x2UZero => self.write_prefixed(0xFD, 0xFD),
+            Instruction::F64x2ConvertLowI32x4S => self.write_prefixed(0xFD, 0xFE),
+            Instruction::F64x2ConvertLowI32x4U => self.write_prefixed(0xFD, 0xFF),
+            Instruction::MemoryAtomicNotify { mem_arg } => {
+                self.write_prefixed(0xFE, 0x00);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::MemoryAtomicWait32 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x01);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::MemoryAtomicWait64 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x02);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicLoad { mem_arg } => {
+                self.write_prefixed(0xFE, 0x10);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicLoad { mem_arg } => {
+                self.write_prefixed(0xFE, 0x11);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicLoad8U { mem_arg } => {
+                self.write_prefixed(0xFE, 0x12);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicLoad16U { mem_arg } => {
+                self.write_prefixed(0xFE, 0x13);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicLoad8U { mem_arg } => {
+                self.write_prefixed(0xFE, 0x14);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicLoad16U { mem_arg } => {
+                self.write_prefixed(0xFE, 0x15);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicLoad32U { mem_arg } => {
+                self.write_prefixed(0xFE, 0x16);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicStore { mem_arg } => {
+                self.write_prefixed(0xFE, 0x17);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicStore { mem_arg } => {
+                self.write_prefixed(0xFE, 0x18);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicStore8 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x19);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicStore16 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x1A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicStore8 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x1B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicStore16 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x1C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicStore32 { mem_arg } => {
+                self.write_prefixed(0xFE, 0x1D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwAdd { mem_arg } => {
+                self.write_prefixed(0xFE, 0x1E);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwAdd { mem_arg } => {
+                self.write_prefixed(0xFE, 0x1F);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8AddU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x20);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16AddU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x21);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8AddU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x22);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16AddU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x23);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32AddU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x24);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwSub { mem_arg } => {
+                self.write_prefixed(0xFE, 0x25);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwSub { mem_arg } => {
+                self.write_prefixed(0xFE, 0x26);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8SubU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x27);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16SubU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x28);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8SubU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x29);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16SubU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x2A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32SubU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x2B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwAnd { mem_arg } => {
+                self.write_prefixed(0xFE, 0x2C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwAnd { mem_arg } => {
+                self.write_prefixed(0xFE, 0x2D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8AndU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x2E);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16AndU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x2F);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8AndU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x30);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16AndU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x31);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32AndU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x32);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwOr { mem_arg } => {
+                self.write_prefixed(0xFE, 0x33);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwOr { mem_arg } => {
+                self.write_prefixed(0xFE, 0x34);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8OrU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x35);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16OrU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x36);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8OrU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x37);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16OrU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x38);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32OrU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x39);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwXor { mem_arg } => {
+                self.write_prefixed(0xFE, 0x3A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwXor { mem_arg } => {
+                self.write_prefixed(0xFE, 0x3B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8XorU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x3C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16XorU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x3D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8XorU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x3E);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16XorU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x3F);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32XorU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x40);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwXchg { mem_arg } => {
+                self.write_prefixed(0xFE, 0x41);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwXchg { mem_arg } => {
+                self.write_prefixed(0xFE, 0x42);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8XchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x43);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16XchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x44);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8XchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x45);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16XchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x46);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32XchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x47);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmwCmpxchg { mem_arg } => {
+                self.write_prefixed(0xFE, 0x48);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmwCmpxchg { mem_arg } => {
+                self.write_prefixed(0xFE, 0x49);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw8CmpxchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x4A);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I32AtomicRmw16CmpxchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x4B);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw8CmpxchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x4C);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw16CmpxchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x4D);
+                self.write_mem_arg(mem_arg);
+            }
+            Instruction::I64AtomicRmw32CmpxchgU { mem_arg } => {
+                self.write_prefixed(0xFE, 0x4E);
+                self.write_mem_arg(mem_arg);
+            }
+        }
+    }
+
+    fn write_catch(&mut self, catch: &Catch) {
+        match catch {
+            Catch::Catch { tag_index, label } => {
+                self.write_u8(0x00);
+                self.write_u32(*tag_index);
+                self.write_u32(*label);
+            }
+            Catch::CatchRef { tag_index, label } => {
+                self.write_u8(0x01);
+                self.write_u32(*tag_index);
+                self.write_u32(*label);
+            }
+            Catch::CatchAll { label } => {
+                self.write_u8(0x02);
+                self.write_u32(*label);
+            }
+            Catch::CatchAllRef { label } => {
+                self.write_u8(0x03);
+                self.write_u32(*label);
+            }
+        }
+    }
+
+    /// Writes a block type. Blocks with several results must refer to a type,
+    /// so `BlockType::Value` may hold at most one value type.
+    fn write_block_type(&mut self, block_type: &BlockType) {
+        match block_type {
+            BlockType::Empty => self.write_u8(0x40),
+            BlockType::Value(value_types) => match value_types.as_slice() {
+                [] => self.write_u8(0x40),
+                [value_type] => self.write_value_type(value_type),
+                _ => panic!("block types with several results must use a type index"),
+            },
+            BlockType::TypeIndex(type_index) => self.write_i64(i64::from(*type_index)),
+        }
+    }
+
+    fn write_mem_arg(&mut self, mem_arg: &MemArg) {
+        if mem_arg.memory_index == 0 {
+            self.write_u32(mem_arg.align);
+        } else {
+            self.write_u32(mem_arg.align | MEM_ARG_HAS_MEMORY_INDEX);
+            self.write_u32(mem_arg.memory_index);
+        }
+        self.write_u64(mem_arg.offset);
+    }
+
+    fn write_value_type(&mut self, value_type: &ValueType) {
+        self.write_u8(match value_type {
+            ValueType::I32 => 0x7F,
+            ValueType::I64 => 0x7E,
+            ValueType::F32 => 0x7D,
+            ValueType::F64 => 0x7C,
+            ValueType::V128 => 0x7B,
+            ValueType::FuncRef => 0x70,
+            ValueType::ExternRef => 0x6F,
+            ValueType::ExnRef => 0x69,
+        });
+    }
+
+    fn write_ref_type(&mut self, ref_type: RefType) {
+        self.write_u8(match ref_type {
+            RefType::FuncRef => 0x70,
+            RefType::ExternRef => 0x6F,
+            RefType::ExnRef => 0x69,
+        });
+    }
+
+    fn write_table_type(&mut self, table_type: &TableType) {
+        self.write_ref_type(table_type.element_type);
+        self.write_limits(&table_type.limits);
+    }
+
+    fn write_global_type(&mut self, global_type: &GlobalType) {
+        self.write_value_type(&global_type.value_type);
+        self.write_u8(global_type.mutable as u8);
+    }
+
+    fn write_tag_type(&mut self, tag_type: &TagType) {
+        self.write_u8(0x00);
+        self.write_u32(tag_type.type_index);
+    }
+
+    fn write_limits(&mut self, limits: &Limits) {
+        let kind =
+            limits.max.is_some() as u8 | (limits.shared as u8) << 1 | (limits.index64 as u8) << 2;
+
+        self.write_u8(kind);
+        self.write_u64(limits.min);
+        if let Some(max) = limits.max {
+            self.write_u64(max);
+        }
+    }
+
+    fn write_vec<T>(&mut self, items: &[T], mut encode_fn: impl FnMut(&mut Self, &T)) {
+        self.write_u32(items.len() as u32);
+        for item in items {
+            encode_fn(self, item);
+        }
+    }
+
+    fn write_name(&mut self, name: &str) {
+        self.write_u32(name.len() as u32);
+        self.write_bytes(name.as_bytes());
+    }
+
+    /// Writes the opcode of a prefixed (`0xFC`, `0xFD` or `0xFE`) instruction.
+    fn write_prefixed(&mut self, prefix: u8, id: u32) {
+        self.write_u8(prefix);
+        self.write_u32(id);
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write_u64(u64::from(value));
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        // Writing to a `Vec` can't fail.
+        let _ = leb128::write::unsigned(&mut self.bytes, value);
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.write_i64(i64::from(value));
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        let _ = leb128::write::signed(&mut self.bytes, value);
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_f64(&mut self, value: f64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_v128(&mut self, value: u128) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+}
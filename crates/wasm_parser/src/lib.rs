@@ -1,2 +1,5 @@
 pub mod decoder;
+pub mod encoder;
 pub mod parser;
+pub mod text;
+pub mod validator;
@@ -0,0 +1,115 @@
+use std::{iter::Enumerate, slice::Iter};
+
+use crate::{
+    decoder::{
+        instructions::{Block, BlockType, Instruction},
+        section::SectionId,
+        types::FuncType,
+    },
+    validator::{ValidationError, ValidationErrorKind},
+};
+
+use super::module::Node;
+
+/// Builds the tree of the body of function `func_index` from its flat
+/// instructions.
+pub(crate) fn build_body(
+    code: &[Instruction],
+    types: &[FuncType],
+    func_index: u32,
+) -> Result<Vec<Node>, ValidationError> {
+    Ok(BodyBuilder {
+        instrs: code.iter().enumerate(),
+        types,
+        func_index,
+    }
+    .nodes(1)?
+    .0)
+}
+
+struct BodyBuilder<'a> {
+    instrs: Enumerate<Iter<'a, Instruction>>,
+    types: &'a [FuncType],
+    func_index: u32,
+}
+
+impl<'a> BodyBuilder<'a> {
+    /// Consumes instructions up to the `else` or `end` closing the current
+    /// block, returning the nodes and that instruction. `depth` is the label
+    /// depth of blocks started here.
+    fn nodes(
+        &mut self,
+        depth: u32,
+    ) -> Result<(Vec<Node>, Option<&'a Instruction>), ValidationError> {
+        let mut nodes = vec![];
+
+        while let Some((index, instr)) = self.instrs.next() {
+            let node = match instr {
+                Instruction::Block { block } => Node::Block {
+                    block_type: self.block_type(index, block)?,
+                    depth,
+                    body: self.nodes(depth + 1)?.0,
+                },
+                Instruction::Loop { block } => Node::Loop {
+                    block_type: self.block_type(index, block)?,
+                    depth,
+                    body: self.nodes(depth + 1)?.0,
+                },
+                Instruction::If { block } => {
+                    let (then_body, last) = self.nodes(depth + 1)?;
+                    let else_body = match last {
+                        Some(Instruction::Else) => Some(self.nodes(depth + 1)?.0),
+                        _ => None,
+                    };
+
+                    Node::If {
+                        block_type: self.block_type(index, block)?,
+                        depth,
+                        then_body,
+                        else_body,
+                    }
+                }
+                Instruction::TryTable { block, catches } => Node::TryTable {
+                    block_type: self.block_type(index, block)?,
+                    depth,
+                    catches: catches.clone(),
+                    body: self.nodes(depth + 1)?.0,
+                },
+                Instruction::Else | Instruction::End => return Ok((nodes, Some(instr))),
+                instr => Node::Instruction(instr.clone()),
+            };
+
+            nodes.push(node);
+        }
+
+        Ok((nodes, None))
+    }
+
+    /// Type of the block started by the instruction at `index`.
+    fn block_type(&self, index: usize, block: &Block) -> Result<FuncType, ValidationError> {
+        let results = match &block.block_type {
+            BlockType::Empty => vec![],
+            BlockType::Value(value_types) => value_types.clone(),
+            BlockType::TypeIndex(type_index) => {
+                return self
+                    .types
+                    .get(*type_index as usize)
+                    .cloned()
+                    .ok_or(ValidationError {
+                        section: Some(SectionId::Code),
+                        func_index: Some(self.func_index),
+                        instr_index: Some(index as u32),
+                        kind: ValidationErrorKind::Unknown {
+                            space: "type",
+                            index: *type_index,
+                        },
+                    });
+            }
+        };
+
+        Ok(FuncType {
+            params: vec![],
+            results,
+        })
+    }
+}
@@ -1,4 +1,6 @@
+mod body;
 pub mod module;
+#[allow(clippy::module_inception)]
 mod parser;
 
 pub use parser::*;
@@ -0,0 +1,41 @@
+use crate::decoder::{
+    instructions::{Catch, Instruction},
+    types::FuncType,
+};
+
+/// Node of a structured function body. Blocks hold their nested instructions
+/// instead of being delimited by `else` and `end`.
+///
+/// Every block has a label depth, counting the function body as 0: a branch
+/// `br level` directly inside a block of depth `depth` targets the enclosing
+/// block of depth `depth - level`, or returns if that is 0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// Any instruction other than `block`, `loop`, `if`, `try_table`, `else`
+    /// and `end`.
+    Instruction(Instruction),
+    Block {
+        block_type: FuncType,
+        depth: u32,
+        body: Vec<Node>,
+    },
+    Loop {
+        block_type: FuncType,
+        depth: u32,
+        body: Vec<Node>,
+    },
+    If {
+        block_type: FuncType,
+        depth: u32,
+        then_body: Vec<Node>,
+        /// `None` if the `if` has no `else`.
+        else_body: Option<Vec<Node>>,
+    },
+    TryTable {
+        block_type: FuncType,
+        depth: u32,
+        /// Labels are relative to the `try_table`, which doesn't count itself.
+        catches: Vec<Catch>,
+        body: Vec<Node>,
+    },
+}
@@ -1,3 +1,6 @@
+mod body;
+#[allow(clippy::module_inception)]
 mod module;
 
+pub use body::*;
 pub use module::*;
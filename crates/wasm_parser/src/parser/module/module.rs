@@ -1,8 +1,10 @@
 use crate::decoder::{
     instructions::Instruction,
-    types::{Global, Import, ValueType},
+    types::{Data, FuncType, Global, Import, MemoryType, TableType, ValueType},
 };
 
+use super::Node;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDecl {
     index: usize,
@@ -13,16 +15,26 @@ pub struct Function {
     pub index: usize,
     pub label: String,
     pub export_name: Option<String>,
+    /// Name from the name section, if any.
+    pub name: Option<String>,
     pub params: Vec<ValueType>,
     pub results: Vec<ValueType>,
     pub params_locals: Vec<ValueType>,
     pub locals: Vec<ValueType>,
     pub raw_body: Option<Vec<Instruction>>,
+    /// `raw_body` as a tree of blocks, without the final `end`.
+    pub body: Vec<Node>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Module {
+    /// Type section, used to resolve `BlockType::TypeIndex` block types.
+    pub types: Vec<FuncType>,
     pub functions: Vec<Function>,
     pub imports: Vec<Import>,
+    pub tables: Vec<TableType>,
+    pub memories: Vec<MemoryType>,
     pub globals: Vec<Global>,
+    /// Data segments, in data index order.
+    pub datas: Vec<Data>,
 }
@@ -1,8 +1,14 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
-use crate::decoder::{self, types::ExportDesc};
+use crate::{
+    decoder::{self, types::ExportDesc},
+    validator::{validate, ValidationError},
+};
 
-use super::module::{Function, Module};
+use super::{
+    body::build_body,
+    module::{Function, Module},
+};
 
 pub struct Parser {
     pub module_binary: decoder::module::Module,
@@ -13,15 +19,22 @@ impl Parser {
         Parser { module_binary }
     }
 
-    pub fn parse(&mut self) -> Module {
-        Module {
-            functions: self.parse_functions(),
+    /// Builds the module the backends compile, after validating it.
+    pub fn parse(&mut self) -> Result<Module, ValidationError> {
+        validate(&self.module_binary)?;
+
+        Ok(Module {
+            types: self.module_binary.type_section.clone(),
+            functions: self.parse_functions()?,
             imports: self.module_binary.import_section.clone(),
+            tables: self.module_binary.table_section.clone(),
+            memories: self.module_binary.memory_section.clone(),
             globals: self.module_binary.global_section.clone(),
-        }
+            datas: self.module_binary.data_section.clone(),
+        })
     }
 
-    fn parse_functions(&mut self) -> Vec<Function> {
+    fn parse_functions(&mut self) -> Result<Vec<Function>, ValidationError> {
         let mut funcs = vec![];
         let mut func_idx: usize = 0;
         // let mut import_map = HashMap::new::<>();
@@ -32,10 +45,18 @@ impl Parser {
             }
         }
 
-        let mut code_index = 0;
-        for func_sig_idx in &self.module_binary.function_section {
+        // Export names are used as labels as-is, so other labels must not clash with them.
+        let mut labels: HashSet<String> = self
+            .module_binary
+            .export_section
+            .iter()
+            .filter(|export| matches!(export.desc, ExportDesc::Func(_)))
+            .map(|export| export.name.clone())
+            .collect();
+
+        for (code_index, func_sig_idx) in self.module_binary.function_section.iter().enumerate() {
             let func_type = &self.module_binary.type_section[*func_sig_idx as usize];
-            let func_body = &self.module_binary.code_section[code_index as usize];
+            let func_body = &self.module_binary.code_section[code_index];
             let mut params_locals = func_type.params.clone();
             params_locals.append(&mut func_body.locals.clone());
             let export_name = self
@@ -44,23 +65,67 @@ impl Parser {
                 .iter()
                 .find(|a| matches!(a.desc, ExportDesc::Func(f) if f == func_idx as u32))
                 .map(|a| a.name.clone());
+            let name = self
+                .module_binary
+                .name_section
+                .as_ref()
+                .and_then(|names| names.function_name(func_idx as u32))
+                .map(str::to_owned);
+
+            let label = match &export_name {
+                Some(export_name) => export_name.clone(),
+                None => {
+                    let mut label = match &name {
+                        Some(name) => to_label(name),
+                        None => format!("func_{}", func_idx),
+                    };
+                    if labels.contains(&label) {
+                        label = format!("{}_{}", label, func_idx);
+                    }
+                    labels.insert(label.clone());
+                    label
+                }
+            };
 
             let func = Function {
                 index: func_idx,
-                label: export_name.clone().unwrap_or(format!("func_{}", func_idx)),
+                label,
                 export_name,
+                name,
                 params: func_type.params.clone(),
                 results: func_type.results.clone(),
                 locals: func_body.locals.clone(),
                 params_locals,
                 raw_body: Some(func_body.code.clone()),
+                body: build_body(
+                    &func_body.code,
+                    &self.module_binary.type_section,
+                    func_idx as u32,
+                )?,
             };
             funcs.push(func);
 
             func_idx += 1;
-            code_index += 1;
         }
 
-        funcs
+        Ok(funcs)
     }
 }
+
+/// Turns a function name from the name section (e.g. `module/gpio_write`) into
+/// a valid assembly label (e.g. `module_gpio_write`).
+fn to_label(name: &str) -> String {
+    let mut label: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+
+    if label.is_empty() || label.starts_with(|c: char| c.is_ascii_digit()) {
+        label.insert(0, '_');
+    }
+
+    label
+}
@@ -0,0 +1,1120 @@
+use crate::decoder::instructions::{Block, Catch, Instruction, MemArg};
+
+use super::{lexer::Token, number, parser::Space, Result, TextErrorKind, WatParser};
+
+impl<'a> WatParser<'a> {
+    /// Parses plain and folded instructions up to a `)`, `end` or `else`.
+    pub(crate) fn instructions(&mut self, out: &mut Vec<Instruction>) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(Token::LParen) => self.folded_instruction(out)?,
+                Some(Token::Keyword("end" | "else")) => return Ok(()),
+                Some(Token::Keyword(_)) => self.plain_instruction(out)?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn plain_instruction(&mut self, out: &mut Vec<Instruction>) -> Result<()> {
+        let offset = self.offset();
+        let name = self.keyword()?;
+
+        let Some(instr) = self.block_instruction(name)? else {
+            let instr = self.instruction(name, offset)?;
+            out.push(instr);
+
+            return Ok(());
+        };
+        out.push(instr);
+        self.instructions(out)?;
+
+        if name == "if" && self.eat_keyword("else") {
+            self.end_label()?;
+            out.push(Instruction::Else);
+            self.instructions(out)?;
+        }
+        self.expect_keyword("end")?;
+        self.end_label()?;
+        self.labels.pop();
+        out.push(Instruction::End);
+
+        Ok(())
+    }
+
+    /// Parses `(instr folded*)`, `(block ...)`, `(loop ...)`, `(try_table ...)`
+    /// or `(if ... (then ...) (else ...))`.
+    pub(crate) fn folded_instruction(&mut self, out: &mut Vec<Instruction>) -> Result<()> {
+        self.expect_lparen()?;
+        let offset = self.offset();
+        let name = self.keyword()?;
+
+        if name == "if" {
+            let label = self.maybe_id();
+            let block_type = self.block_type()?;
+            while self.peek() == Some(&Token::LParen) && !self.peek_list("then") {
+                self.folded_instruction(out)?;
+            }
+            out.push(Instruction::If {
+                block: Block { block_type },
+            });
+            self.labels.push(label);
+
+            if !self.eat_list("then") {
+                return Err(self.expected("`(then`"));
+            }
+            self.instructions(out)?;
+            self.expect_rparen()?;
+
+            if self.eat_list("else") {
+                out.push(Instruction::Else);
+                self.instructions(out)?;
+                self.expect_rparen()?;
+            }
+            self.labels.pop();
+            out.push(Instruction::End);
+        } else if let Some(instr) = self.block_instruction(name)? {
+            out.push(instr);
+            self.instructions(out)?;
+            self.labels.pop();
+            out.push(Instruction::End);
+        } else {
+            let instr = self.instruction(name, offset)?;
+            self.instructions(out)?;
+            out.push(instr);
+        }
+
+        self.expect_rparen()
+    }
+
+    /// Parses the label and immediates of a block instruction and enters the
+    /// block. Returns `None` for other instructions.
+    fn block_instruction(&mut self, name: &str) -> Result<Option<Instruction>> {
+        if !matches!(name, "block" | "loop" | "if" | "try_table") {
+            return Ok(None);
+        }
+        let label = self.maybe_id();
+        let block = Block {
+            block_type: self.block_type()?,
+        };
+
+        let instr = match name {
+            "block" => Instruction::Block { block },
+            "loop" => Instruction::Loop { block },
+            "if" => Instruction::If { block },
+            _ => Instruction::TryTable {
+                block,
+                catches: self.catches()?,
+            },
+        };
+        self.labels.push(label);
+
+        Ok(Some(instr))
+    }
+
+    /// Parses the optional label repeated after `else` and `end`, which must
+    /// match the block's label.
+    fn end_label(&mut self) -> Result<()> {
+        let offset = self.offset();
+
+        match self.maybe_id() {
+            Some(id) if self.labels.last() != Some(&Some(id)) => {
+                Err(self.error(offset, TextErrorKind::MismatchedLabel(id.to_owned())))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses the catch clauses of a `try_table`. Their labels are resolved
+    /// outside of the `try_table` block.
+    fn catches(&mut self) -> Result<Vec<Catch>> {
+        let mut catches = vec![];
+
+        loop {
+            let catch = if self.eat_list("catch") {
+                let tag_index = self.index(Space::Tag)?;
+                Catch::Catch {
+                    tag_index,
+                    label: self.label()?,
+                }
+            } else if self.eat_list("catch_ref") {
+                let tag_index = self.index(Space::Tag)?;
+                Catch::CatchRef {
+                    tag_index,
+                    label: self.label()?,
+                }
+            } else if self.eat_list("catch_all") {
+                Catch::CatchAll {
+                    label: self.label()?,
+                }
+            } else if self.eat_list("catch_all_ref") {
+                Catch::CatchAllRef {
+                    label: self.label()?,
+                }
+            } else {
+                return Ok(catches);
+            };
+            self.expect_rparen()?;
+
+            catches.push(catch);
+        }
+    }
+
+    /// Parses `memidx? offset=<u64>? align=<u32>?`.
+    fn mem_arg(&mut self, natural_align: u32) -> Result<MemArg> {
+        let memory_index = self.optional_index(Space::Memory)?.unwrap_or(0);
+
+        self.offset_and_align(memory_index, natural_align)
+    }
+
+    fn offset_and_align(&mut self, memory_index: u32, natural_align: u32) -> Result<MemArg> {
+        let offset = match self
+            .peek_keyword()
+            .is_some_and(|keyword| keyword.starts_with("offset="))
+        {
+            true => self.number(|text| number::parse_u64(&text["offset=".len()..]))?,
+            false => 0,
+        };
+
+        let align_offset = self.offset();
+        let align = match self
+            .peek_keyword()
+            .is_some_and(|keyword| keyword.starts_with("align="))
+        {
+            true => {
+                let align = self.number(|text| number::parse_u32(&text["align=".len()..]))?;
+                if !align.is_power_of_two() {
+                    return Err(self.error(align_offset, TextErrorKind::InvalidAlignment));
+                }
+
+                align.trailing_zeros()
+            }
+            false => natural_align,
+        };
+
+        Ok(MemArg {
+            align,
+            offset,
+            memory_index,
+        })
+    }
+
+    /// Parses the immediates of `v128.load*_lane` and `v128.store*_lane`. A
+    /// lone number is the lane, not a memory index.
+    fn lane_mem_arg(&mut self, natural_align: u32) -> Result<(MemArg, u8)> {
+        let has_memory_index = matches!(self.peek(), Some(Token::Id(_)))
+            || self.peek_index()
+                && matches!(self.peek_at(1), Some(Token::Keyword(keyword))
+                    if keyword.starts_with(|c: char| c.is_ascii_digit())
+                        || keyword.starts_with("offset=")
+                        || keyword.starts_with("align="));
+
+        let memory_index = match has_memory_index {
+            true => self.index(Space::Memory)?,
+            false => 0,
+        };
+        let mem_arg = self.offset_and_align(memory_index, natural_align)?;
+
+        Ok((mem_arg, self.lane()?))
+    }
+
+    fn lane(&mut self) -> Result<u8> {
+        self.number(|text| number::parse_u32(text)?.try_into().ok())
+    }
+
+    fn shuffle_lanes(&mut self) -> Result<[u8; 16]> {
+        let mut lanes = [0; 16];
+        for lane in &mut lanes {
+            *lane = self.lane()?;
+        }
+
+        Ok(lanes)
+    }
+
+    /// Parses the shape and lanes of a `v128.const`.
+    fn v128(&mut self) -> Result<u128> {
+        let (lanes, width): (usize, u32) = match self.peek_keyword() {
+            Some("i8x16") => (16, 8),
+            Some("i16x8") => (8, 16),
+            Some("i32x4" | "f32x4") => (4, 32),
+            Some("i64x2" | "f64x2") => (2, 64),
+            _ => return Err(self.expected("vector shape")),
+        };
+        let shape = self.keyword()?;
+
+        let mut value = 0;
+        for lane in 0..lanes {
+            let bits = match shape {
+                "i8x16" => self.number(|text| {
+                    let value = number::parse_i32(text)?;
+                    (-128..=255).contains(&value).then_some(value as u8 as u128)
+                })?,
+                "i16x8" => self.number(|text| {
+                    let value = number::parse_i32(text)?;
+                    (-32768..=65535)
+                        .contains(&value)
+                        .then_some(value as u16 as u128)
+                })?,
+                "i32x4" => self.number(number::parse_i32)? as u32 as u128,
+                "i64x2" => self.number(number::parse_i64)? as u64 as u128,
+                "f32x4" => self.number(number::parse_f32)?.to_bits() as u128,
+                _ => self.number(number::parse_f64)?.to_bits() as u128,
+            };
+            value |= bits << (lane as u32 * width);
+        }
+
+        Ok(value)
+    }
+
+    /// Parses a plain instruction with its immediates, after its name.
+    fn instruction(&mut self, name: &str, offset: usize) -> Result<Instruction> {
+        let instr = match name {
+            "br_table" => {
+                let mut label_indexes = vec![self.label()?];
+                while self.peek_index() {
+                    label_indexes.push(self.label()?);
+                }
+                let default_index = label_indexes.pop().unwrap();
+
+                Instruction::BrTable {
+                    label_indexes,
+                    default_index,
+                }
+            }
+            "select" => {
+                let result_types = match self.peek_list("result") {
+                    true => Some(self.results()?),
+                    false => None,
+                };
+
+                Instruction::Select { result_types }
+            }
+            "unreachable" => Instruction::Unreachable,
+            "nop" => Instruction::Nop,
+            "throw" => Instruction::Throw {
+                tag_index: self.index(Space::Tag)?,
+            },
+            "throw_ref" => Instruction::ThrowRef,
+            "br" => Instruction::Br {
+                level: self.label()?,
+            },
+            "br_if" => Instruction::BrIf {
+                level: self.label()?,
+            },
+            "return" => Instruction::Return,
+            "call" => Instruction::Call {
+                func_index: self.index(Space::Func)?,
+            },
+            "call_indirect" => {
+                let table_index = self.optional_index(Space::Table)?.unwrap_or(0);
+                Instruction::CallIndirect {
+                    type_index: self.type_index_use()?,
+                    table_index,
+                }
+            }
+            "return_call" => Instruction::ReturnCall {
+                func_index: self.index(Space::Func)?,
+            },
+            "return_call_indirect" => {
+                let table_index = self.optional_index(Space::Table)?.unwrap_or(0);
+                Instruction::ReturnCallIndirect {
+                    type_index: self.type_index_use()?,
+                    table_index,
+                }
+            }
+            "ref.null" => Instruction::RefNull {
+                ref_type: self.heap_type()?,
+            },
+            "ref.is_null" => Instruction::RefIsNull,
+            "ref.func" => Instruction::RefFunc {
+                func_index: self.index(Space::Func)?,
+            },
+            "drop" => Instruction::Drop,
+            "local.get" => Instruction::LocalGet {
+                local_index: self.local_index()?,
+            },
+            "local.set" => Instruction::LocalSet {
+                local_index: self.local_index()?,
+            },
+            "local.tee" => Instruction::LocalTee {
+                local_index: self.local_index()?,
+            },
+            "global.get" => Instruction::GlobalGet {
+                global_index: self.index(Space::Global)?,
+            },
+            "global.set" => Instruction::GlobalSet {
+                global_index: self.index(Space::Global)?,
+            },
+            "table.get" => Instruction::TableGet {
+                table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+            },
+            "table.set" => Instruction::TableSet {
+                table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+            },
+            "i32.load" => Instruction::I32Load {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.load" => Instruction::I64Load {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "f32.load" => Instruction::F32Load {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "f64.load" => Instruction::F64Load {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.load8_s" => Instruction::I32Load8S {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.load8_u" => Instruction::I32Load8U {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.load16_s" => Instruction::I32Load16S {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i32.load16_u" => Instruction::I32Load16U {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.load8_s" => Instruction::I64Load8S {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.load8_u" => Instruction::I64Load8U {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.load16_s" => Instruction::I64Load16S {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.load16_u" => Instruction::I64Load16U {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.load32_s" => Instruction::I64Load32S {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.load32_u" => Instruction::I64Load32U {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.store" => Instruction::I32Store {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.store" => Instruction::I64Store {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "f32.store" => Instruction::F32Store {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "f64.store" => Instruction::F64Store {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.store8" => Instruction::I32Store8 {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.store16" => Instruction::I32Store16 {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.store8" => Instruction::I64Store8 {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.store16" => Instruction::I64Store16 {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.store32" => Instruction::I64Store32 {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "memory.size" => Instruction::MemorySize {
+                memory_index: self.optional_index(Space::Memory)?.unwrap_or(0),
+            },
+            "memory.grow" => Instruction::MemoryGrow {
+                memory_index: self.optional_index(Space::Memory)?.unwrap_or(0),
+            },
+            "i32.const" => Instruction::I32Const {
+                value: self.number(number::parse_i32)?,
+            },
+            "i64.const" => Instruction::I64Const {
+                value: self.number(number::parse_i64)?,
+            },
+            "f32.const" => Instruction::F32Const {
+                value: self.number(number::parse_f32)?,
+            },
+            "f64.const" => Instruction::F64Const {
+                value: self.number(number::parse_f64)?,
+            },
+            "i32.eqz" => Instruction::I32Eqz,
+            "i32.eq" => Instruction::I32Eq,
+            "i32.ne" => Instruction::I32Ne,
+            "i32.lt_s" => Instruction::I32LtS,
+            "i32.lt_u" => Instruction::I32LtU,
+            "i32.gt_s" => Instruction::I32GtS,
+            "i32.gt_u" => Instruction::I32GtU,
+            "i32.le_s" => Instruction::I32LeS,
+            "i32.le_u" => Instruction::I32LeU,
+            "i32.ge_s" => Instruction::I32GeS,
+            "i32.ge_u" => Instruction::I32GeU,
+            "i64.eqz" => Instruction::I64Eqz,
+            "i64.eq" => Instruction::I64Eq,
+            "i64.ne" => Instruction::I64Ne,
+            "i64.lt_s" => Instruction::I64LtS,
+            "i64.lt_u" => Instruction::I64LtU,
+            "i64.gt_s" => Instruction::I64GtS,
+            "i64.gt_u" => Instruction::I64GtU,
+            "i64.le_s" => Instruction::I64LeS,
+            "i64.le_u" => Instruction::I64LeU,
+            "i64.ge_s" => Instruction::I64GeS,
+            "i64.ge_u" => Instruction::I64GeU,
+            "f32.eq" => Instruction::F32Eq,
+            "f32.ne" => Instruction::F32Ne,
+            "f32.lt" => Instruction::F32Lt,
+            "f32.gt" => Instruction::F32Gt,
+            "f32.le" => Instruction::F32Le,
+            "f32.ge" => Instruction::F32Ge,
+            "f64.eq" => Instruction::F64Eq,
+            "f64.ne" => Instruction::F64Ne,
+            "f64.lt" => Instruction::F64Lt,
+            "f64.gt" => Instruction::F64Gt,
+            "f64.le" => Instruction::F64Le,
+            "f64.ge" => Instruction::F64Ge,
+            "i32.clz" => Instruction::I32Clz,
+            "i32.ctz" => Instruction::I32Ctz,
+            "i32.popcnt" => Instruction::I32Popcnt,
+            "i32.add" => Instruction::I32Add,
+            "i32.sub" => Instruction::I32Sub,
+            "i32.mul" => Instruction::I32Mul,
+            "i32.div_s" => Instruction::I32DivS,
+            "i32.div_u" => Instruction::I32DivU,
+            "i32.rem_s" => Instruction::I32RemS,
+            "i32.rem_u" => Instruction::I32RemU,
+            "i32.and" => Instruction::I32And,
+            "i32.or" => Instruction::I32Or,
+            "i32.xor" => Instruction::I32Xor,
+            "i32.shl" => Instruction::I32Shl,
+            "i32.shr_s" => Instruction::I32ShrS,
+            "i32.shr_u" => Instruction::I32ShrU,
+            "i32.rotl" => Instruction::I32Rotl,
+            "i32.rotr" => Instruction::I32Rotr,
+            "i64.clz" => Instruction::I64Clz,
+            "i64.ctz" => Instruction::I64Ctz,
+            "i64.popcnt" => Instruction::I64Popcnt,
+            "i64.add" => Instruction::I64Add,
+            "i64.sub" => Instruction::I64Sub,
+            "i64.mul" => Instruction::I64Mul,
+            "i64.div_s" => Instruction::I64DivS,
+            "i64.div_u" => Instruction::I64DivU,
+            "i64.rem_s" => Instruction::I64RemS,
+            "i64.rem_u" => Instruction::I64RemU,
+            "i64.and" => Instruction::I64And,
+            "i64.or" => Instruction::I64Or,
+            "i64.xor" => Instruction::I64Xor,
+            "i64.shl" => Instruction::I64Shl,
+            "i64.shr_s" => Instruction::I64ShrS,
+            "i64.shr_u" => Instruction::I64ShrU,
+            "i64.rotl" => Instruction::I64Rotl,
+            "i64.rotr" => Instruction::I64Rotr,
+            "f32.abs" => Instruction::F32Abs,
+            "f32.neg" => Instruction::F32Neg,
+            "f32.ceil" => Instruction::F32Ceil,
+            "f32.floor" => Instruction::F32Floor,
+            "f32.trunc" => Instruction::F32Trunc,
+            "f32.nearest" => Instruction::F32Nearest,
+            "f32.sqrt" => Instruction::F32Sqrt,
+            "f32.add" => Instruction::F32Add,
+            "f32.sub" => Instruction::F32Sub,
+            "f32.mul" => Instruction::F32Mul,
+            "f32.div" => Instruction::F32Div,
+            "f32.min" => Instruction::F32Min,
+            "f32.max" => Instruction::F32Max,
+            "f32.copysign" => Instruction::F32Copysign,
+            "f64.abs" => Instruction::F64Abs,
+            "f64.neg" => Instruction::F64Neg,
+            "f64.ceil" => Instruction::F64Ceil,
+            "f64.floor" => Instruction::F64Floor,
+            "f64.trunc" => Instruction::F64Trunc,
+            "f64.nearest" => Instruction::F64Nearest,
+            "f64.sqrt" => Instruction::F64Sqrt,
+            "f64.add" => Instruction::F64Add,
+            "f64.sub" => Instruction::F64Sub,
+            "f64.mul" => Instruction::F64Mul,
+            "f64.div" => Instruction::F64Div,
+            "f64.min" => Instruction::F64Min,
+            "f64.max" => Instruction::F64Max,
+            "f64.copysign" => Instruction::F64Copysign,
+            "i32.wrap_i64" => Instruction::I32WrapI64,
+            "i32.trunc_f32_s" => Instruction::I32TruncF32S,
+            "i32.trunc_f32_u" => Instruction::I32TruncF32U,
+            "i32.trunc_f64_s" => Instruction::I32TruncF64S,
+            "i32.trunc_f64_u" => Instruction::I32TruncF64U,
+            "i64.extend_i32_s" => Instruction::I64ExtendI32S,
+            "i64.extend_i32_u" => Instruction::I64ExtendI32U,
+            "i64.trunc_f32_s" => Instruction::I64TruncF32S,
+            "i64.trunc_f32_u" => Instruction::I64TruncF32U,
+            "i64.trunc_f64_s" => Instruction::I64TruncF64S,
+            "i64.trunc_f64_u" => Instruction::I64TruncF64U,
+            "f32.convert_i32_s" => Instruction::F32ConvertI32S,
+            "f32.convert_i32_u" => Instruction::F32ConvertI32U,
+            "f32.convert_i64_s" => Instruction::F32ConvertI64S,
+            "f32.convert_i64_u" => Instruction::F32ConvertI64U,
+            "f32.demote_f64" => Instruction::F32DemoteF64,
+            "f64.convert_i32_s" => Instruction::F64ConvertI32S,
+            "f64.convert_i32_u" => Instruction::F64ConvertI32U,
+            "f64.convert_i64_s" => Instruction::F64ConvertI64S,
+            "f64.convert_i64_u" => Instruction::F64ConvertI64U,
+            "f64.promote_f32" => Instruction::F64PromoteF32,
+            "i32.reinterpret_f32" => Instruction::I32ReinterpretF32,
+            "i64.reinterpret_f64" => Instruction::I64ReinterpretF64,
+            "f32.reinterpret_i32" => Instruction::F32ReinterpretI32,
+            "f64.reinterpret_i64" => Instruction::F64ReinterpretI64,
+            "i32.extend8_s" => Instruction::I32Extend8S,
+            "i32.extend16_s" => Instruction::I32Extend16S,
+            "i64.extend8_s" => Instruction::I64Extend8S,
+            "i64.extend16_s" => Instruction::I64Extend16S,
+            "i64.extend32_s" => Instruction::I64Extend32S,
+            "i32.trunc_sat_f32_s" => Instruction::I32TruncSatF32S,
+            "i32.trunc_sat_f32_u" => Instruction::I32TruncSatF32U,
+            "i32.trunc_sat_f64_s" => Instruction::I32TruncSatF64S,
+            "i32.trunc_sat_f64_u" => Instruction::I32TruncSatF64U,
+            "i64.trunc_sat_f32_s" => Instruction::I64TruncSatF32S,
+            "i64.trunc_sat_f32_u" => Instruction::I64TruncSatF32U,
+            "i64.trunc_sat_f64_s" => Instruction::I64TruncSatF64S,
+            "i64.trunc_sat_f64_u" => Instruction::I64TruncSatF64U,
+            "memory.init" => {
+                let memory_index = match self.peek_index_at(1) {
+                    true => self.index(Space::Memory)?,
+                    false => 0,
+                };
+                Instruction::MemoryInit {
+                    data_index: self.index(Space::Data)?,
+                    memory_index,
+                }
+            }
+            "data.drop" => Instruction::DataDrop {
+                data_index: self.index(Space::Data)?,
+            },
+            "memory.copy" => Instruction::MemoryCopy {
+                dst_memory_index: self.optional_index(Space::Memory)?.unwrap_or(0),
+                src_memory_index: self.optional_index(Space::Memory)?.unwrap_or(0),
+            },
+            "memory.fill" => Instruction::MemoryFill {
+                memory_index: self.optional_index(Space::Memory)?.unwrap_or(0),
+            },
+            "table.init" => {
+                let table_index = match self.peek_index_at(1) {
+                    true => self.index(Space::Table)?,
+                    false => 0,
+                };
+                Instruction::TableInit {
+                    element_index: self.index(Space::Elem)?,
+                    table_index,
+                }
+            }
+            "elem.drop" => Instruction::ElemDrop {
+                element_index: self.index(Space::Elem)?,
+            },
+            "table.copy" => Instruction::TableCopy {
+                dst_table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+                src_table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+            },
+            "table.grow" => Instruction::TableGrow {
+                table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+            },
+            "table.size" => Instruction::TableSize {
+                table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+            },
+            "table.fill" => Instruction::TableFill {
+                table_index: self.optional_index(Space::Table)?.unwrap_or(0),
+            },
+            "v128.load" => Instruction::V128Load {
+                mem_arg: self.mem_arg(4)?,
+            },
+            "v128.load8x8_s" => Instruction::V128Load8x8S {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.load8x8_u" => Instruction::V128Load8x8U {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.load16x4_s" => Instruction::V128Load16x4S {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.load16x4_u" => Instruction::V128Load16x4U {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.load32x2_s" => Instruction::V128Load32x2S {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.load32x2_u" => Instruction::V128Load32x2U {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.load8_splat" => Instruction::V128Load8Splat {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "v128.load16_splat" => Instruction::V128Load16Splat {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "v128.load32_splat" => Instruction::V128Load32Splat {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "v128.load64_splat" => Instruction::V128Load64Splat {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "v128.store" => Instruction::V128Store {
+                mem_arg: self.mem_arg(4)?,
+            },
+            "v128.const" => Instruction::V128Const {
+                value: self.v128()?,
+            },
+            "i8x16.shuffle" => Instruction::I8x16Shuffle {
+                lanes: self.shuffle_lanes()?,
+            },
+            "i8x16.swizzle" => Instruction::I8x16Swizzle,
+            "i8x16.splat" => Instruction::I8x16Splat,
+            "i16x8.splat" => Instruction::I16x8Splat,
+            "i32x4.splat" => Instruction::I32x4Splat,
+            "i64x2.splat" => Instruction::I64x2Splat,
+            "f32x4.splat" => Instruction::F32x4Splat,
+            "f64x2.splat" => Instruction::F64x2Splat,
+            "i8x16.extract_lane_s" => Instruction::I8x16ExtractLaneS { lane: self.lane()? },
+            "i8x16.extract_lane_u" => Instruction::I8x16ExtractLaneU { lane: self.lane()? },
+            "i8x16.replace_lane" => Instruction::I8x16ReplaceLane { lane: self.lane()? },
+            "i16x8.extract_lane_s" => Instruction::I16x8ExtractLaneS { lane: self.lane()? },
+            "i16x8.extract_lane_u" => Instruction::I16x8ExtractLaneU { lane: self.lane()? },
+            "i16x8.replace_lane" => Instruction::I16x8ReplaceLane { lane: self.lane()? },
+            "i32x4.extract_lane" => Instruction::I32x4ExtractLane { lane: self.lane()? },
+            "i32x4.replace_lane" => Instruction::I32x4ReplaceLane { lane: self.lane()? },
+            "i64x2.extract_lane" => Instruction::I64x2ExtractLane { lane: self.lane()? },
+            "i64x2.replace_lane" => Instruction::I64x2ReplaceLane { lane: self.lane()? },
+            "f32x4.extract_lane" => Instruction::F32x4ExtractLane { lane: self.lane()? },
+            "f32x4.replace_lane" => Instruction::F32x4ReplaceLane { lane: self.lane()? },
+            "f64x2.extract_lane" => Instruction::F64x2ExtractLane { lane: self.lane()? },
+            "f64x2.replace_lane" => Instruction::F64x2ReplaceLane { lane: self.lane()? },
+            "i8x16.eq" => Instruction::I8x16Eq,
+            "i8x16.ne" => Instruction::I8x16Ne,
+            "i8x16.lt_s" => Instruction::I8x16LtS,
+            "i8x16.lt_u" => Instruction::I8x16LtU,
+            "i8x16.gt_s" => Instruction::I8x16GtS,
+            "i8x16.gt_u" => Instruction::I8x16GtU,
+            "i8x16.le_s" => Instruction::I8x16LeS,
+            "i8x16.le_u" => Instruction::I8x16LeU,
+            "i8x16.ge_s" => Instruction::I8x16GeS,
+            "i8x16.ge_u" => Instruction::I8x16GeU,
+            "i16x8.eq" => Instruction::I16x8Eq,
+            "i16x8.ne" => Instruction::I16x8Ne,
+            "i16x8.lt_s" => Instruction::I16x8LtS,
+            "i16x8.lt_u" => Instruction::I16x8LtU,
+            "i16x8.gt_s" => Instruction::I16x8GtS,
+            "i16x8.gt_u" => Instruction::I16x8GtU,
+            "i16x8.le_s" => Instruction::I16x8LeS,
+            "i16x8.le_u" => Instruction::I16x8LeU,
+            "i16x8.ge_s" => Instruction::I16x8GeS,
+            "i16x8.ge_u" => Instruction::I16x8GeU,
+            "i32x4.eq" => Instruction::I32x4Eq,
+            "i32x4.ne" => Instruction::I32x4Ne,
+            "i32x4.lt_s" => Instruction::I32x4LtS,
+            "i32x4.lt_u" => Instruction::I32x4LtU,
+            "i32x4.gt_s" => Instruction::I32x4GtS,
+            "i32x4.gt_u" => Instruction::I32x4GtU,
+            "i32x4.le_s" => Instruction::I32x4LeS,
+            "i32x4.le_u" => Instruction::I32x4LeU,
+            "i32x4.ge_s" => Instruction::I32x4GeS,
+            "i32x4.ge_u" => Instruction::I32x4GeU,
+            "f32x4.eq" => Instruction::F32x4Eq,
+            "f32x4.ne" => Instruction::F32x4Ne,
+            "f32x4.lt" => Instruction::F32x4Lt,
+            "f32x4.gt" => Instruction::F32x4Gt,
+            "f32x4.le" => Instruction::F32x4Le,
+            "f32x4.ge" => Instruction::F32x4Ge,
+            "f64x2.eq" => Instruction::F64x2Eq,
+            "f64x2.ne" => Instruction::F64x2Ne,
+            "f64x2.lt" => Instruction::F64x2Lt,
+            "f64x2.gt" => Instruction::F64x2Gt,
+            "f64x2.le" => Instruction::F64x2Le,
+            "f64x2.ge" => Instruction::F64x2Ge,
+            "v128.not" => Instruction::V128Not,
+            "v128.and" => Instruction::V128And,
+            "v128.andnot" => Instruction::V128Andnot,
+            "v128.or" => Instruction::V128Or,
+            "v128.xor" => Instruction::V128Xor,
+            "v128.bitselect" => Instruction::V128Bitselect,
+            "v128.any_true" => Instruction::V128AnyTrue,
+            "v128.load8_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(0)?;
+                Instruction::V128Load8Lane { mem_arg, lane }
+            }
+            "v128.load16_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(1)?;
+                Instruction::V128Load16Lane { mem_arg, lane }
+            }
+            "v128.load32_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(2)?;
+                Instruction::V128Load32Lane { mem_arg, lane }
+            }
+            "v128.load64_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(3)?;
+                Instruction::V128Load64Lane { mem_arg, lane }
+            }
+            "v128.store8_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(0)?;
+                Instruction::V128Store8Lane { mem_arg, lane }
+            }
+            "v128.store16_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(1)?;
+                Instruction::V128Store16Lane { mem_arg, lane }
+            }
+            "v128.store32_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(2)?;
+                Instruction::V128Store32Lane { mem_arg, lane }
+            }
+            "v128.store64_lane" => {
+                let (mem_arg, lane) = self.lane_mem_arg(3)?;
+                Instruction::V128Store64Lane { mem_arg, lane }
+            }
+            "v128.load32_zero" => Instruction::V128Load32Zero {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "v128.load64_zero" => Instruction::V128Load64Zero {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "f32x4.demote_f64x2_zero" => Instruction::F32x4DemoteF64x2Zero,
+            "f64x2.promote_low_f32x4" => Instruction::F64x2PromoteLowF32x4,
+            "i8x16.abs" => Instruction::I8x16Abs,
+            "i8x16.neg" => Instruction::I8x16Neg,
+            "i8x16.popcnt" => Instruction::I8x16Popcnt,
+            "i8x16.all_true" => Instruction::I8x16AllTrue,
+            "i8x16.bitmask" => Instruction::I8x16Bitmask,
+            "i8x16.narrow_i16x8_s" => Instruction::I8x16NarrowI16x8S,
+            "i8x16.narrow_i16x8_u" => Instruction::I8x16NarrowI16x8U,
+            "f32x4.ceil" => Instruction::F32x4Ceil,
+            "f32x4.floor" => Instruction::F32x4Floor,
+            "f32x4.trunc" => Instruction::F32x4Trunc,
+            "f32x4.nearest" => Instruction::F32x4Nearest,
+            "i8x16.shl" => Instruction::I8x16Shl,
+            "i8x16.shr_s" => Instruction::I8x16ShrS,
+            "i8x16.shr_u" => Instruction::I8x16ShrU,
+            "i8x16.add" => Instruction::I8x16Add,
+            "i8x16.add_sat_s" => Instruction::I8x16AddSatS,
+            "i8x16.add_sat_u" => Instruction::I8x16AddSatU,
+            "i8x16.sub" => Instruction::I8x16Sub,
+            "i8x16.sub_sat_s" => Instruction::I8x16SubSatS,
+            "i8x16.sub_sat_u" => Instruction::I8x16SubSatU,
+            "f64x2.ceil" => Instruction::F64x2Ceil,
+            "f64x2.floor" => Instruction::F64x2Floor,
+            "i8x16.min_s" => Instruction::I8x16MinS,
+            "i8x16.min_u" => Instruction::I8x16MinU,
+            "i8x16.max_s" => Instruction::I8x16MaxS,
+            "i8x16.max_u" => Instruction::I8x16MaxU,
+            "f64x2.trunc" => Instruction::F64x2Trunc,
+            "i8x16.avgr_u" => Instruction::I8x16AvgrU,
+            "i16x8.extadd_pairwise_i8x16_s" => Instruction::I16x8ExtaddPairwiseI8x16S,
+            "i16x8.extadd_pairwise_i8x16_u" => Instruction::I16x8ExtaddPairwiseI8x16U,
+            "i32x4.extadd_pairwise_i16x8_s" => Instruction::I32x4ExtaddPairwiseI16x8S,
+            "i32x4.extadd_pairwise_i16x8_u" => Instruction::I32x4ExtaddPairwiseI16x8U,
+            "i16x8.abs" => Instruction::I16x8Abs,
+            "i16x8.neg" => Instruction::I16x8Neg,
+            "i16x8.q15mulr_sat_s" => Instruction::I16x8Q15mulrSatS,
+            "i16x8.all_true" => Instruction::I16x8AllTrue,
+            "i16x8.bitmask" => Instruction::I16x8Bitmask,
+            "i16x8.narrow_i32x4_s" => Instruction::I16x8NarrowI32x4S,
+            "i16x8.narrow_i32x4_u" => Instruction::I16x8NarrowI32x4U,
+            "i16x8.extend_low_i8x16_s" => Instruction::I16x8ExtendLowI8x16S,
+            "i16x8.extend_high_i8x16_s" => Instruction::I16x8ExtendHighI8x16S,
+            "i16x8.extend_low_i8x16_u" => Instruction::I16x8ExtendLowI8x16U,
+            "i16x8.extend_high_i8x16_u" => Instruction::I16x8ExtendHighI8x16U,
+            "i16x8.shl" => Instruction::I16x8Shl,
+            "i16x8.shr_s" => Instruction::I16x8ShrS,
+            "i16x8.shr_u" => Instruction::I16x8ShrU,
+            "i16x8.add" => Instruction::I16x8Add,
+            "i16x8.add_sat_s" => Instruction::I16x8AddSatS,
+            "i16x8.add_sat_u" => Instruction::I16x8AddSatU,
+            "i16x8.sub" => Instruction::I16x8Sub,
+            "i16x8.sub_sat_s" => Instruction::I16x8SubSatS,
+            "i16x8.sub_sat_u" => Instruction::I16x8SubSatU,
+            "f64x2.nearest" => Instruction::F64x2Nearest,
+            "i16x8.mul" => Instruction::I16x8Mul,
+            "i16x8.min_s" => Instruction::I16x8MinS,
+            "i16x8.min_u" => Instruction::I16x8MinU,
+            "i16x8.max_s" => Instruction::I16x8MaxS,
+            "i16x8.max_u" => Instruction::I16x8MaxU,
+            "i16x8.avgr_u" => Instruction::I16x8AvgrU,
+            "i16x8.extmul_low_i8x16_s" => Instruction::I16x8ExtmulLowI8x16S,
+            "i16x8.extmul_high_i8x16_s" => Instruction::I16x8ExtmulHighI8x16S,
+            "i16x8.extmul_low_i8x16_u" => Instruction::I16x8ExtmulLowI8x16U,
+            "i16x8.extmul_high_i8x16_u" => Instruction::I16x8ExtmulHighI8x16U,
+            "i32x4.abs" => Instruction::I32x4Abs,
+            "i32x4.neg" => Instruction::I32x4Neg,
+            "i32x4.all_true" => Instruction::I32x4AllTrue,
+            "i32x4.bitmask" => Instruction::I32x4Bitmask,
+            "i32x4.extend_low_i16x8_s" => Instruction::I32x4ExtendLowI16x8S,
+            "i32x4.extend_high_i16x8_s" => Instruction::I32x4ExtendHighI16x8S,
+            "i32x4.extend_low_i16x8_u" => Instruction::I32x4ExtendLowI16x8U,
+            "i32x4.extend_high_i16x8_u" => Instruction::I32x4ExtendHighI16x8U,
+            "i32x4.shl" => Instruction::I32x4Shl,
+            "i32x4.shr_s" => Instruction::I32x4ShrS,
+            "i32x4.shr_u" => Instruction::I32x4ShrU,
+            "i32x4.add" => Instruction::I32x4Add,
+            "i32x4.sub" => Instruction::I32x4Sub,
+            "i32x4.mul" => Instruction::I32x4Mul,
+            "i32x4.min_s" => Instruction::I32x4MinS,
+            "i32x4.min_u" => Instruction::I32x4MinU,
+            "i32x4.max_s" => Instruction::I32x4MaxS,
+            "i32x4.max_u" => Instruction::I32x4MaxU,
+            "i32x4.dot_i16x8_s" => Instruction::I32x4DotI16x8S,
+            "i32x4.extmul_low_i16x8_s" => Instruction::I32x4ExtmulLowI16x8S,
+            "i32x4.extmul_high_i16x8_s" => Instruction::I32x4ExtmulHighI16x8S,
+            "i32x4.extmul_low_i16x8_u" => Instruction::I32x4ExtmulLowI16x8U,
+            "i32x4.extmul_high_i16x8_u" => Instruction::I32x4ExtmulHighI16x8U,
+            "i64x2.abs" => Instruction::I64x2Abs,
+            "i64x2.neg" => Instruction::I64x2Neg,
+            "i64x2.all_true" => Instruction::I64x2AllTrue,
+            "i64x2.bitmask" => Instruction::I64x2Bitmask,
+            "i64x2.extend_low_i32x4_s" => Instruction::I64x2ExtendLowI32x4S,
+            "i64x2.extend_high_i32x4_s" => Instruction::I64x2ExtendHighI32x4S,
+            "i64x2.extend_low_i32x4_u" => Instruction::I64x2ExtendLowI32x4U,
+            "i64x2.extend_high_i32x4_u" => Instruction::I64x2ExtendHighI32x4U,
+            "i64x2.shl" => Instruction::I64x2Shl,
+            "i64x2.shr_s" => Instruction::I64x2ShrS,
+            "i64x2.shr_u" => Instruction::I64x2ShrU,
+            "i64x2.add" => Instruction::I64x2Add,
+            "i64x2.sub" => Instruction::I64x2Sub,
+            "i64x2.mul" => Instruction::I64x2Mul,
+            "i64x2.eq" => Instruction::I64x2Eq,
+            "i64x2.ne" => Instruction::I64x2Ne,
+            "i64x2.lt_s" => Instruction::I64x2LtS,
+            "i64x2.gt_s" => Instruction::I64x2GtS,
+            "i64x2.le_s" => Instruction::I64x2LeS,
+            "i64x2.ge_s" => Instruction::I64x2GeS,
+            "i64x2.extmul_low_i32x4_s" => Instruction::I64x2ExtmulLowI32x4S,
+            "i64x2.extmul_high_i32x4_s" => Instruction::I64x2ExtmulHighI32x4S,
+            "i64x2.extmul_low_i32x4_u" => Instruction::I64x2ExtmulLowI32x4U,
+            "i64x2.extmul_high_i32x4_u" => Instruction::I64x2ExtmulHighI32x4U,
+            "f32x4.abs" => Instruction::F32x4Abs,
+            "f32x4.neg" => Instruction::F32x4Neg,
+            "f32x4.sqrt" => Instruction::F32x4Sqrt,
+            "f32x4.add" => Instruction::F32x4Add,
+            "f32x4.sub" => Instruction::F32x4Sub,
+            "f32x4.mul" => Instruction::F32x4Mul,
+            "f32x4.div" => Instruction::F32x4Div,
+            "f32x4.min" => Instruction::F32x4Min,
+            "f32x4.max" => Instruction::F32x4Max,
+            "f32x4.pmin" => Instruction::F32x4Pmin,
+            "f32x4.pmax" => Instruction::F32x4Pmax,
+            "f64x2.abs" => Instruction::F64x2Abs,
+            "f64x2.neg" => Instruction::F64x2Neg,
+            "f64x2.sqrt" => Instruction::F64x2Sqrt,
+            "f64x2.add" => Instruction::F64x2Add,
+            "f64x2.sub" => Instruction::F64x2Sub,
+            "f64x2.mul" => Instruction::F64x2Mul,
+            "f64x2.div" => Instruction::F64x2Div,
+            "f64x2.min" => Instruction::F64x2Min,
+            "f64x2.max" => Instruction::F64x2Max,
+            "f64x2.pmin" => Instruction::F64x2Pmin,
+            "f64x2.pmax" => Instruction::F64x2Pmax,
+            "i32x4.trunc_sat_f32x4_s" => Instruction::I32x4TruncSatF32x4S,
+            "i32x4.trunc_sat_f32x4_u" => Instruction::I32x4TruncSatF32x4U,
+            "f32x4.convert_i32x4_s" => Instruction::F32x4ConvertI32x4S,
+            "f32x4.convert_i32x4_u" => Instruction::F32x4ConvertI32x4U,
+            "i32x4.trunc_sat_f64x2_s_zero" => Instruction::I32x4TruncSatF64x2SZero,
+            "i32x4.trunc_sat_f64x2_u_zero" => Instruction::I32x4TruncSatF64x2UZero,
+            "f64x2.convert_low_i32x4_s" => Instruction::F64x2ConvertLowI32x4S,
+            "f64x2.convert_low_i32x4_u" => Instruction::F64x2ConvertLowI32x4U,
+            "memory.atomic.notify" => Instruction::MemoryAtomicNotify {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "memory.atomic.wait32" => Instruction::MemoryAtomicWait32 {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "memory.atomic.wait64" => Instruction::MemoryAtomicWait64 {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.load" => Instruction::I32AtomicLoad {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.load" => Instruction::I64AtomicLoad {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.load8_u" => Instruction::I32AtomicLoad8U {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.load16_u" => Instruction::I32AtomicLoad16U {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.load8_u" => Instruction::I64AtomicLoad8U {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.load16_u" => Instruction::I64AtomicLoad16U {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.load32_u" => Instruction::I64AtomicLoad32U {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.store" => Instruction::I32AtomicStore {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.store" => Instruction::I64AtomicStore {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.store8" => Instruction::I32AtomicStore8 {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.store16" => Instruction::I32AtomicStore16 {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.store8" => Instruction::I64AtomicStore8 {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.store16" => Instruction::I64AtomicStore16 {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.store32" => Instruction::I64AtomicStore32 {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.add" => Instruction::I32AtomicRmwAdd {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.add" => Instruction::I64AtomicRmwAdd {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.add_u" => Instruction::I32AtomicRmw8AddU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.add_u" => Instruction::I32AtomicRmw16AddU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.add_u" => Instruction::I64AtomicRmw8AddU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.add_u" => Instruction::I64AtomicRmw16AddU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.add_u" => Instruction::I64AtomicRmw32AddU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.sub" => Instruction::I32AtomicRmwSub {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.sub" => Instruction::I64AtomicRmwSub {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.sub_u" => Instruction::I32AtomicRmw8SubU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.sub_u" => Instruction::I32AtomicRmw16SubU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.sub_u" => Instruction::I64AtomicRmw8SubU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.sub_u" => Instruction::I64AtomicRmw16SubU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.sub_u" => Instruction::I64AtomicRmw32SubU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.and" => Instruction::I32AtomicRmwAnd {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.and" => Instruction::I64AtomicRmwAnd {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.and_u" => Instruction::I32AtomicRmw8AndU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.and_u" => Instruction::I32AtomicRmw16AndU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.and_u" => Instruction::I64AtomicRmw8AndU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.and_u" => Instruction::I64AtomicRmw16AndU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.and_u" => Instruction::I64AtomicRmw32AndU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.or" => Instruction::I32AtomicRmwOr {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.or" => Instruction::I64AtomicRmwOr {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.or_u" => Instruction::I32AtomicRmw8OrU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.or_u" => Instruction::I32AtomicRmw16OrU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.or_u" => Instruction::I64AtomicRmw8OrU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.or_u" => Instruction::I64AtomicRmw16OrU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.or_u" => Instruction::I64AtomicRmw32OrU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.xor" => Instruction::I32AtomicRmwXor {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.xor" => Instruction::I64AtomicRmwXor {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.xor_u" => Instruction::I32AtomicRmw8XorU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.xor_u" => Instruction::I32AtomicRmw16XorU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.xor_u" => Instruction::I64AtomicRmw8XorU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.xor_u" => Instruction::I64AtomicRmw16XorU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.xor_u" => Instruction::I64AtomicRmw32XorU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.xchg" => Instruction::I32AtomicRmwXchg {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.xchg" => Instruction::I64AtomicRmwXchg {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.xchg_u" => Instruction::I32AtomicRmw8XchgU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.xchg_u" => Instruction::I32AtomicRmw16XchgU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.xchg_u" => Instruction::I64AtomicRmw8XchgU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.xchg_u" => Instruction::I64AtomicRmw16XchgU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.xchg_u" => Instruction::I64AtomicRmw32XchgU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i32.atomic.rmw.cmpxchg" => Instruction::I32AtomicRmwCmpxchg {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "i64.atomic.rmw.cmpxchg" => Instruction::I64AtomicRmwCmpxchg {
+                mem_arg: self.mem_arg(3)?,
+            },
+            "i32.atomic.rmw8.cmpxchg_u" => Instruction::I32AtomicRmw8CmpxchgU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i32.atomic.rmw16.cmpxchg_u" => Instruction::I32AtomicRmw16CmpxchgU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw8.cmpxchg_u" => Instruction::I64AtomicRmw8CmpxchgU {
+                mem_arg: self.mem_arg(0)?,
+            },
+            "i64.atomic.rmw16.cmpxchg_u" => Instruction::I64AtomicRmw16CmpxchgU {
+                mem_arg: self.mem_arg(1)?,
+            },
+            "i64.atomic.rmw32.cmpxchg_u" => Instruction::I64AtomicRmw32CmpxchgU {
+                mem_arg: self.mem_arg(2)?,
+            },
+            "atomic.fence" => Instruction::AtomicFence,
+            name => {
+                return Err(self.error(offset, TextErrorKind::UnknownInstruction(name.to_owned())))
+            }
+        };
+
+        Ok(instr)
+    }
+}
@@ -0,0 +1,181 @@
+use super::{Result, TextError, TextErrorKind};
+
+/// Token of the text format.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/text/lexical.html#tokens
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token<'a> {
+    LParen,
+    RParen,
+    /// Keyword, number or other reserved word, e.g. `i32.add`, `offset=8` or `-0x1p3`.
+    Keyword(&'a str),
+    /// Symbolic identifier, without the leading `$`.
+    Id(&'a str),
+    String(Vec<u8>),
+}
+
+pub(crate) struct Lexer<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
+        Lexer { source, pos: 0 }
+    }
+
+    /// Splits the whole source into tokens, each with its byte offset.
+    pub(crate) fn tokenize(mut self) -> Result<Vec<(usize, Token<'a>)>> {
+        let mut tokens = vec![];
+
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+
+    fn error(&self, offset: usize, kind: TextErrorKind) -> TextError {
+        TextError::new(self.source, offset, kind)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.source.as_bytes()[self.pos..]
+    }
+
+    fn next_token(&mut self) -> Result<Option<(usize, Token<'a>)>> {
+        self.skip_whitespace()?;
+
+        let start = self.pos;
+        let Some(&byte) = self.rest().first() else {
+            return Ok(None);
+        };
+
+        let token = match byte {
+            b'(' => {
+                self.pos += 1;
+                Token::LParen
+            }
+            b')' => {
+                self.pos += 1;
+                Token::RParen
+            }
+            b'"' => Token::String(self.read_string()?),
+            byte if is_idchar(byte) => {
+                let len = self.rest().iter().take_while(|&&b| is_idchar(b)).count();
+                self.pos += len;
+
+                match &self.source[start..self.pos] {
+                    "$" => return Err(self.error(start, TextErrorKind::UnexpectedChar('$'))),
+                    word if word.starts_with('$') => Token::Id(&word[1..]),
+                    word => Token::Keyword(word),
+                }
+            }
+            _ => {
+                let c = self.source[start..].chars().next().unwrap();
+                return Err(self.error(start, TextErrorKind::UnexpectedChar(c)));
+            }
+        };
+
+        Ok(Some((start, token)))
+    }
+
+    /// Skips whitespace, line comments and (nested) block comments.
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            match self.rest() {
+                [b' ' | b'\t' | b'\n' | b'\r', ..] => self.pos += 1,
+                [b';', b';', ..] => {
+                    let len = self.rest().iter().take_while(|&&b| b != b'\n').count();
+                    self.pos += len;
+                }
+                [b'(', b';', ..] => {
+                    let start = self.pos;
+                    let mut depth = 0;
+
+                    loop {
+                        match self.rest() {
+                            [b'(', b';', ..] => {
+                                depth += 1;
+                                self.pos += 2;
+                            }
+                            [b';', b')', ..] => {
+                                depth -= 1;
+                                self.pos += 2;
+
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            [_, ..] => self.pos += 1,
+                            [] => return Err(self.error(start, TextErrorKind::UnterminatedComment)),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>> {
+        let start = self.pos;
+        let mut bytes = vec![];
+        self.pos += 1;
+
+        loop {
+            let escape = self.pos;
+            match self.rest() {
+                [b'"', ..] => {
+                    self.pos += 1;
+                    return Ok(bytes);
+                }
+                [b'\\', rest @ ..] => {
+                    let (byte, consumed) = match rest {
+                        [b't', ..] => (Some(b'\t'), 1),
+                        [b'n', ..] => (Some(b'\n'), 1),
+                        [b'r', ..] => (Some(b'\r'), 1),
+                        [b'"', ..] => (Some(b'"'), 1),
+                        [b'\'', ..] => (Some(b'\''), 1),
+                        [b'\\', ..] => (Some(b'\\'), 1),
+                        [b'u', b'{', ..] => {
+                            let digits = rest[2..].iter().take_while(|&&b| b != b'}').count();
+                            let c = std::str::from_utf8(&rest[2..2 + digits])
+                                .ok()
+                                .and_then(|digits| {
+                                    u32::from_str_radix(&digits.replace('_', ""), 16).ok()
+                                })
+                                .and_then(char::from_u32)
+                                .filter(|_| rest.get(2 + digits) == Some(&b'}'))
+                                .ok_or_else(|| self.error(escape, TextErrorKind::InvalidEscape))?;
+
+                            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                            (None, 3 + digits)
+                        }
+                        [hi, lo, ..] if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                            let digits = std::str::from_utf8(&rest[..2]).unwrap();
+                            (Some(u8::from_str_radix(digits, 16).unwrap()), 2)
+                        }
+                        _ => return Err(self.error(escape, TextErrorKind::InvalidEscape)),
+                    };
+
+                    bytes.extend(byte);
+                    self.pos += 1 + consumed;
+                }
+                [b'\n', ..] | [] => {
+                    return Err(self.error(start, TextErrorKind::UnterminatedString))
+                }
+                [byte, ..] => {
+                    bytes.push(*byte);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Characters allowed in keywords, numbers and identifiers.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/text/values.html#text-idchar
+fn is_idchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&byte)
+}
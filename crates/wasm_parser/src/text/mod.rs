@@ -0,0 +1,11 @@
+mod instructions;
+mod lexer;
+mod number;
+mod parser;
+mod print_instructions;
+mod printer;
+mod result;
+
+pub use parser::*;
+pub use printer::*;
+pub use result::*;
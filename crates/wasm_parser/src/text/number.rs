@@ -0,0 +1,236 @@
+//! Numeric literals of the text format.
+//!
+//! WebAssembly specification: https://webassembly.github.io/spec/core/text/values.html
+
+pub(crate) fn parse_u32(text: &str) -> Option<u32> {
+    match parse_int(text)? {
+        (false, value) => value.try_into().ok(),
+        (true, _) => None,
+    }
+}
+
+pub(crate) fn parse_u64(text: &str) -> Option<u64> {
+    match parse_int(text)? {
+        (false, value) => value.try_into().ok(),
+        (true, _) => None,
+    }
+}
+
+/// Parses an `i32` literal, which may be written signed or unsigned.
+pub(crate) fn parse_i32(text: &str) -> Option<i32> {
+    match parse_int(text)? {
+        (false, value) => u32::try_from(value).ok().map(|value| value as i32),
+        (true, value) if value <= 1 << 31 => Some((value as i64).wrapping_neg() as i32),
+        (true, _) => None,
+    }
+}
+
+/// Parses an `i64` literal, which may be written signed or unsigned.
+pub(crate) fn parse_i64(text: &str) -> Option<i64> {
+    match parse_int(text)? {
+        (false, value) => u64::try_from(value).ok().map(|value| value as i64),
+        (true, value) if value <= 1 << 63 => Some((value as i128).wrapping_neg() as i64),
+        (true, _) => None,
+    }
+}
+
+pub(crate) fn parse_f32(text: &str) -> Option<f32> {
+    let bits = parse_float(text, 23, 8, |text| {
+        text.parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(|value| value.to_bits() as u64)
+    })?;
+
+    Some(f32::from_bits(bits as u32))
+}
+
+pub(crate) fn parse_f64(text: &str) -> Option<f64> {
+    let bits = parse_float(text, 52, 11, |text| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(f64::to_bits)
+    })?;
+
+    Some(f64::from_bits(bits))
+}
+
+fn split_sign(text: &str) -> (bool, &str) {
+    match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    }
+}
+
+/// Removes the `_` separators, which are only allowed between two digits.
+fn strip_underscores(digits: &str, is_digit: fn(&u8) -> bool) -> Option<String> {
+    let bytes = digits.as_bytes();
+    let valid = !bytes.is_empty()
+        && bytes.iter().enumerate().all(|(i, byte)| {
+            is_digit(byte)
+                || *byte == b'_'
+                    && i > 0
+                    && bytes.get(i + 1).is_some_and(is_digit)
+                    && is_digit(&bytes[i - 1])
+        });
+
+    valid.then(|| digits.replace('_', ""))
+}
+
+/// Returns the sign and the magnitude of an integer literal.
+fn parse_int(text: &str) -> Option<(bool, u128)> {
+    let (negative, text) = split_sign(text);
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(&strip_underscores(hex, u8::is_ascii_hexdigit)?, 16),
+        None => strip_underscores(text, u8::is_ascii_digit)?.parse(),
+    };
+
+    Some((
+        negative,
+        value.ok().filter(|value| *value <= u64::MAX as u128)?,
+    ))
+}
+
+/// Parses a float literal into the bits of a float with `mant_bits` fraction
+/// bits and `exp_bits` exponent bits. `decimal` parses unsigned decimal literals.
+fn parse_float(
+    text: &str,
+    mant_bits: u32,
+    exp_bits: u32,
+    decimal: fn(&str) -> Option<u64>,
+) -> Option<u64> {
+    let (negative, text) = split_sign(text);
+    let sign = (negative as u64) << (mant_bits + exp_bits);
+    let infinity = ((1 << exp_bits) - 1) << mant_bits;
+
+    let bits = match text {
+        "inf" => infinity,
+        "nan" => infinity | 1 << (mant_bits - 1),
+        _ if text.starts_with("nan:0x") => {
+            let payload =
+                u64::from_str_radix(&strip_underscores(&text[6..], u8::is_ascii_hexdigit)?, 16)
+                    .ok()?;
+            if payload == 0 || payload >> mant_bits != 0 {
+                return None;
+            }
+
+            infinity | payload
+        }
+        _ if text.starts_with("0x") => parse_hex_float(&text[2..], mant_bits, exp_bits)?,
+        _ if text.starts_with(|c: char| c.is_ascii_digit()) => {
+            let valid = text
+                .bytes()
+                .all(|byte| byte.is_ascii_digit() || b"._eE+-".contains(&byte));
+            if !valid {
+                return None;
+            }
+
+            let (mantissa, exponent) = match text.find(['e', 'E']) {
+                Some(i) => (&text[..i], Some(split_sign(&text[i + 1..]).1)),
+                None => (text, None),
+            };
+            let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+            strip_underscores(int, u8::is_ascii_digit)?;
+            if !frac.is_empty() {
+                strip_underscores(frac, u8::is_ascii_digit)?;
+            }
+            if let Some(exponent) = exponent {
+                strip_underscores(exponent, u8::is_ascii_digit)?;
+            }
+
+            decimal(&text.replace('_', ""))?
+        }
+        _ => return None,
+    };
+
+    Some(sign | bits)
+}
+
+/// Converts a hexadecimal float (without sign and `0x`), rounding to nearest even.
+fn parse_hex_float(text: &str, mant_bits: u32, exp_bits: u32) -> Option<u64> {
+    let (mantissa, mut exponent) = match text.find(['p', 'P']) {
+        Some(i) => {
+            let (negative, digits) = split_sign(&text[i + 1..]);
+            let exponent: i64 = strip_underscores(digits, u8::is_ascii_digit)?
+                .parse()
+                .ok()?;
+            (&text[..i], if negative { -exponent } else { exponent })
+        }
+        None => (text, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let int = strip_underscores(int, u8::is_ascii_hexdigit)?;
+    let frac = match frac {
+        "" => String::new(),
+        frac => strip_underscores(frac, u8::is_ascii_hexdigit)?,
+    };
+
+    // Significand with room to spare; digits that don't fit only matter as a
+    // sticky bit for rounding.
+    let mut significand: u128 = 0;
+    let mut sticky = false;
+    for (i, digit) in int.chars().chain(frac.chars()).enumerate() {
+        let digit = digit.to_digit(16).unwrap() as u128;
+        let fractional = i >= int.len();
+
+        if significand >> 120 != 0 {
+            sticky |= digit != 0;
+            if !fractional {
+                exponent += 4;
+            }
+        } else {
+            significand = significand << 4 | digit;
+            if fractional {
+                exponent -= 4;
+            }
+        }
+    }
+    significand |= sticky as u128;
+
+    if significand == 0 {
+        return Some(0);
+    }
+
+    let bias = (1i64 << (exp_bits - 1)) - 1;
+    let msb = 127 - significand.leading_zeros() as i64;
+    let mut exponent = msb + exponent;
+
+    // Number of low bits to round off, more for subnormals.
+    let mut shift = msb - mant_bits as i64;
+    if exponent < 1 - bias {
+        shift += 1 - bias - exponent;
+    }
+
+    let mut mantissa = match shift {
+        ..=0 => significand << -shift,
+        128.. => 0,
+        _ => {
+            let half = 1 << (shift - 1);
+            let rest = significand & ((1 << shift) - 1);
+            let truncated = significand >> shift;
+
+            if rest > half || rest == half && truncated & 1 == 1 {
+                truncated + 1
+            } else {
+                truncated
+            }
+        }
+    };
+
+    if exponent < 1 - bias {
+        // Subnormal, or the smallest normal if it rounded up.
+        return Some(mantissa as u64);
+    }
+    if mantissa >> (mant_bits + 1) != 0 {
+        mantissa >>= 1;
+        exponent += 1;
+    }
+    if exponent > bias {
+        return None;
+    }
+
+    Some(((exponent + bias) as u64) << mant_bits | (mantissa as u64 & ((1 << mant_bits) - 1)))
+}
@@ -0,0 +1,1269 @@
+use std::collections::HashMap;
+
+use crate::decoder::{
+    const_expr::ConstExpr,
+    instructions::{BlockType, Instruction},
+    module::Module,
+    section::{Code, NameSection},
+    types::{
+        Data, DataMode, Element, ElementInit, ElementMode, Export, ExportDesc, FuncType, Global,
+        GlobalType, Import, ImportDesc, Limits, MemoryType, RefType, TableType, TagType, ValueType,
+    },
+};
+
+use super::{
+    lexer::{Lexer, Token},
+    number, Result, TextError, TextErrorKind,
+};
+
+/// Index space that identifiers are bound in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Space {
+    Type,
+    Func,
+    Table,
+    Memory,
+    Global,
+    Tag,
+    Elem,
+    Data,
+}
+
+impl Space {
+    const COUNT: usize = 8;
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Func => "function",
+            Self::Table => "table",
+            Self::Memory => "memory",
+            Self::Global => "global",
+            Self::Tag => "tag",
+            Self::Elem => "elem segment",
+            Self::Data => "data segment",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct IndexSpace<'a> {
+    names: HashMap<&'a str, u32>,
+    len: u32,
+}
+
+/// Function type written inline, possibly together with a `(type x)`.
+struct TypeUse<'a> {
+    offset: usize,
+    index: Option<u32>,
+    func_type: FuncType,
+    param_names: Vec<Option<&'a str>>,
+}
+
+/// Parser for the WebAssembly text format, producing the same `Module` as the
+/// binary decoder.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/text/index.html
+pub struct WatParser<'a> {
+    source: &'a str,
+    tokens: Vec<(usize, Token<'a>)>,
+    pos: usize,
+    module: Module,
+    names: NameSection,
+    spaces: [IndexSpace<'a>; Space::COUNT],
+    /// Indices handed out so far while building the module.
+    next_index: [u32; Space::COUNT],
+    /// Named params and locals of the function being parsed.
+    pub(crate) locals: HashMap<&'a str, u32>,
+    /// Labels of the enclosing blocks, innermost last.
+    pub(crate) labels: Vec<Option<&'a str>>,
+}
+
+impl<'a> WatParser<'a> {
+    pub fn new(source: &'a str) -> Self {
+        WatParser {
+            source,
+            tokens: vec![],
+            pos: 0,
+            module: Module::default(),
+            names: NameSection::default(),
+            spaces: Default::default(),
+            next_index: [0; Space::COUNT],
+            locals: HashMap::new(),
+            labels: vec![],
+        }
+    }
+
+    /// Parses a `(module ...)`, or a bare list of module fields.
+    pub fn parse(mut self) -> Result<Module> {
+        self.tokens = Lexer::new(self.source).tokenize()?;
+
+        let wrapped = self.peek_list("module");
+        if wrapped {
+            self.pos += 2;
+            self.names.module_name = self.maybe_id().map(str::to_owned);
+        }
+
+        let fields = self.collect_fields()?;
+        if wrapped {
+            self.expect_rparen()?;
+        }
+        if self.pos < self.tokens.len() {
+            return Err(self.expected("end of input"));
+        }
+
+        for field in fields {
+            self.pos = field;
+            self.parse_field()?;
+        }
+
+        let uses_data_count = self.module.code_section.iter().any(|code| {
+            code.code.iter().any(|instr| {
+                matches!(
+                    instr,
+                    Instruction::MemoryInit { .. } | Instruction::DataDrop { .. }
+                )
+            })
+        });
+        if uses_data_count {
+            self.module.data_count_section = Some(self.module.data_section.len() as u32);
+        }
+
+        let has_names = self.names.module_name.is_some()
+            || !self.names.function_names.is_empty()
+            || !self.names.local_names.is_empty();
+        if has_names {
+            self.module.name_section = Some(self.names);
+        }
+        self.module.version = 1;
+
+        Ok(self.module)
+    }
+
+    /* Tokens */
+
+    pub(crate) fn error(&self, offset: usize, kind: TextErrorKind) -> TextError {
+        TextError::new(self.source, offset, kind)
+    }
+
+    pub(crate) fn expected(&self, what: &str) -> TextError {
+        let kind = match self.peek() {
+            Some(_) => TextErrorKind::Expected(what.to_owned()),
+            None => TextErrorKind::UnexpectedEof,
+        };
+
+        self.error(self.offset(), kind)
+    }
+
+    /// Offset of the next token.
+    pub(crate) fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.source.len(), |(offset, _)| *offset)
+    }
+
+    pub(crate) fn peek(&self) -> Option<&Token<'a>> {
+        self.peek_at(0)
+    }
+
+    pub(crate) fn peek_at(&self, n: usize) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos + n).map(|(_, token)| token)
+    }
+
+    pub(crate) fn peek_keyword(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) => Some(keyword),
+            _ => None,
+        }
+    }
+
+    /// Whether the next tokens open a list starting with `keyword`.
+    pub(crate) fn peek_list(&self, keyword: &str) -> bool {
+        self.peek() == Some(&Token::LParen) && self.peek_at(1) == Some(&Token::Keyword(keyword))
+    }
+
+    /// Consumes `(keyword` if the next tokens open such a list.
+    pub(crate) fn eat_list(&mut self, keyword: &str) -> bool {
+        let found = self.peek_list(keyword);
+        if found {
+            self.pos += 2;
+        }
+
+        found
+    }
+
+    pub(crate) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword() == Some(keyword);
+        if found {
+            self.pos += 1;
+        }
+
+        found
+    }
+
+    pub(crate) fn keyword(&mut self) -> Result<&'a str> {
+        let keyword = self
+            .peek_keyword()
+            .ok_or_else(|| self.expected("keyword"))?;
+        self.pos += 1;
+
+        Ok(keyword)
+    }
+
+    pub(crate) fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if !self.eat_keyword(keyword) {
+            return Err(self.expected(&format!("`{}`", keyword)));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn expect_lparen(&mut self) -> Result<()> {
+        if self.peek() != Some(&Token::LParen) {
+            return Err(self.expected("`(`"));
+        }
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn expect_rparen(&mut self) -> Result<()> {
+        if self.peek() != Some(&Token::RParen) {
+            return Err(self.expected("`)`"));
+        }
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn maybe_id(&mut self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Token::Id(id)) => {
+                let id = *id;
+                self.pos += 1;
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn string(&mut self) -> Result<Vec<u8>> {
+        match self.peek() {
+            Some(Token::String(bytes)) => {
+                let bytes = bytes.clone();
+                self.pos += 1;
+                Ok(bytes)
+            }
+            _ => Err(self.expected("string")),
+        }
+    }
+
+    pub(crate) fn name(&mut self) -> Result<String> {
+        let offset = self.offset();
+
+        String::from_utf8(self.string()?)
+            .map_err(|_| self.error(offset, TextErrorKind::InvalidUtf8Name))
+    }
+
+    /// Skips tokens up to and including the `)` closing the current list.
+    fn skip_rest(&mut self) -> Result<()> {
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                Some(Token::LParen) => depth += 1,
+                Some(Token::RParen) if depth == 0 => break,
+                Some(Token::RParen) => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.expected("`)`")),
+            }
+            self.pos += 1;
+        }
+
+        self.expect_rparen()
+    }
+
+    /* Numbers */
+
+    /// Parses the next keyword as a number with `parse`.
+    pub(crate) fn number<T>(&mut self, parse: fn(&str) -> Option<T>) -> Result<T> {
+        let offset = self.offset();
+        let text = self.peek_keyword().ok_or_else(|| self.expected("number"))?;
+        self.pos += 1;
+
+        parse(text).ok_or_else(|| self.error(offset, TextErrorKind::InvalidNumber(text.to_owned())))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        self.number(number::parse_u32)
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        self.number(number::parse_u64)
+    }
+
+    /* Indices */
+
+    /// Whether the next token is an index, i.e. a number or an identifier.
+    pub(crate) fn peek_index(&self) -> bool {
+        self.peek_index_at(0)
+    }
+
+    pub(crate) fn peek_index_at(&self, n: usize) -> bool {
+        match self.peek_at(n) {
+            Some(Token::Id(_)) => true,
+            Some(Token::Keyword(keyword)) => keyword.starts_with(|c: char| c.is_ascii_digit()),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn index(&mut self, space: Space) -> Result<u32> {
+        let offset = self.offset();
+
+        match self.peek() {
+            Some(Token::Id(id)) => {
+                let id = *id;
+                self.pos += 1;
+
+                self.spaces[space as usize]
+                    .names
+                    .get(id)
+                    .copied()
+                    .ok_or_else(|| {
+                        self.error(
+                            offset,
+                            TextErrorKind::UnknownName {
+                                space: space.name(),
+                                name: id.to_owned(),
+                            },
+                        )
+                    })
+            }
+            _ => self.u32(),
+        }
+    }
+
+    pub(crate) fn optional_index(&mut self, space: Space) -> Result<Option<u32>> {
+        match self.peek_index() {
+            true => self.index(space).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    pub(crate) fn local_index(&mut self) -> Result<u32> {
+        let offset = self.offset();
+
+        match self.maybe_id() {
+            Some(id) => self.locals.get(id).copied().ok_or_else(|| {
+                self.error(
+                    offset,
+                    TextErrorKind::UnknownName {
+                        space: "local",
+                        name: id.to_owned(),
+                    },
+                )
+            }),
+            None => self.u32(),
+        }
+    }
+
+    /// Parses a label, returning its depth relative to the innermost block.
+    pub(crate) fn label(&mut self) -> Result<u32> {
+        let offset = self.offset();
+
+        match self.maybe_id() {
+            Some(id) => self
+                .labels
+                .iter()
+                .rev()
+                .position(|label| *label == Some(id))
+                .map(|depth| depth as u32)
+                .ok_or_else(|| {
+                    self.error(
+                        offset,
+                        TextErrorKind::UnknownName {
+                            space: "label",
+                            name: id.to_owned(),
+                        },
+                    )
+                }),
+            None => self.u32(),
+        }
+    }
+
+    /// Binds `id` to the next index of `space` while collecting the fields.
+    fn define(&mut self, space: Space, id: Option<&'a str>, offset: usize) -> Result<()> {
+        let space_names = &mut self.spaces[space as usize];
+
+        if let Some(id) = id {
+            if space_names.names.insert(id, space_names.len).is_some() {
+                let kind = TextErrorKind::DuplicateName {
+                    space: space.name(),
+                    name: id.to_owned(),
+                };
+                return Err(self.error(offset, kind));
+            }
+        }
+        space_names.len += 1;
+
+        Ok(())
+    }
+
+    /// Hands out the next index of `space` while building the module.
+    fn next_index(&mut self, space: Space) -> u32 {
+        let index = self.next_index[space as usize];
+        self.next_index[space as usize] += 1;
+
+        index
+    }
+
+    /* Types */
+
+    pub(crate) fn value_type(&mut self) -> Result<ValueType> {
+        let value_type = match self.peek_keyword() {
+            Some("i32") => ValueType::I32,
+            Some("i64") => ValueType::I64,
+            Some("f32") => ValueType::F32,
+            Some("f64") => ValueType::F64,
+            Some("v128") => ValueType::V128,
+            _ => {
+                return Ok(match self.ref_type()? {
+                    RefType::FuncRef => ValueType::FuncRef,
+                    RefType::ExternRef => ValueType::ExternRef,
+                    RefType::ExnRef => ValueType::ExnRef,
+                })
+            }
+        };
+        self.pos += 1;
+
+        Ok(value_type)
+    }
+
+    pub(crate) fn peek_ref_type(&self) -> bool {
+        matches!(
+            self.peek_keyword(),
+            Some("funcref" | "externref" | "exnref")
+        ) || self.peek_list("ref")
+    }
+
+    /// Parses `funcref`, `externref`, `exnref` or `(ref null <heap type>)`.
+    pub(crate) fn ref_type(&mut self) -> Result<RefType> {
+        if self.eat_list("ref") {
+            self.expect_keyword("null")?;
+            let ref_type = self.heap_type()?;
+            self.expect_rparen()?;
+
+            return Ok(ref_type);
+        }
+
+        let ref_type = match self.peek_keyword() {
+            Some("funcref") => RefType::FuncRef,
+            Some("externref") => RefType::ExternRef,
+            Some("exnref") => RefType::ExnRef,
+            _ => return Err(self.expected("value type")),
+        };
+        self.pos += 1;
+
+        Ok(ref_type)
+    }
+
+    pub(crate) fn heap_type(&mut self) -> Result<RefType> {
+        let ref_type = match self.peek_keyword() {
+            Some("func") => RefType::FuncRef,
+            Some("extern") => RefType::ExternRef,
+            Some("exn") => RefType::ExnRef,
+            _ => return Err(self.expected("heap type")),
+        };
+        self.pos += 1;
+
+        Ok(ref_type)
+    }
+
+    /// Parses `(param ...)*` lists, returning the types and their names.
+    fn params(&mut self) -> Result<(Vec<ValueType>, Vec<Option<&'a str>>)> {
+        let mut params = vec![];
+        let mut names = vec![];
+
+        while self.eat_list("param") {
+            if let Some(id) = self.maybe_id() {
+                params.push(self.value_type()?);
+                names.push(Some(id));
+            } else {
+                while self.peek() != Some(&Token::RParen) {
+                    params.push(self.value_type()?);
+                    names.push(None);
+                }
+            }
+            self.expect_rparen()?;
+        }
+
+        Ok((params, names))
+    }
+
+    pub(crate) fn results(&mut self) -> Result<Vec<ValueType>> {
+        let mut results = vec![];
+
+        while self.eat_list("result") {
+            while self.peek() != Some(&Token::RParen) {
+                results.push(self.value_type()?);
+            }
+            self.expect_rparen()?;
+        }
+
+        Ok(results)
+    }
+
+    /// Parses the `(func ...)` of a type definition.
+    fn func_type(&mut self) -> Result<FuncType> {
+        if !self.eat_list("func") {
+            return Err(self.expected("`(func`"));
+        }
+        let (params, _) = self.params()?;
+        let results = self.results()?;
+        self.expect_rparen()?;
+
+        Ok(FuncType { params, results })
+    }
+
+    fn type_use(&mut self) -> Result<TypeUse<'a>> {
+        let offset = self.offset();
+        let index = match self.eat_list("type") {
+            true => {
+                let index = self.index(Space::Type)?;
+                self.expect_rparen()?;
+                Some(index)
+            }
+            false => None,
+        };
+        let (params, param_names) = self.params()?;
+        let results = self.results()?;
+
+        Ok(TypeUse {
+            offset,
+            index,
+            func_type: FuncType { params, results },
+            param_names,
+        })
+    }
+
+    /// Returns the type index a type use refers to. Without `(type x)`, that's
+    /// the first matching type, which is appended to the type section if needed.
+    fn resolve_type_use(&mut self, type_use: &mut TypeUse<'a>) -> Result<u32> {
+        let Some(index) = type_use.index else {
+            return Ok(self.add_type(type_use.func_type.clone()));
+        };
+        let func_type = self
+            .module
+            .type_section
+            .get(index as usize)
+            .ok_or_else(|| {
+                self.error(
+                    type_use.offset,
+                    TextErrorKind::UnknownIndex {
+                        space: Space::Type.name(),
+                        index,
+                    },
+                )
+            })?;
+
+        let inline = &type_use.func_type;
+        if inline.params.is_empty() && inline.results.is_empty() {
+            type_use.param_names = vec![None; func_type.params.len()];
+        } else if inline != func_type {
+            return Err(self.error(type_use.offset, TextErrorKind::TypeUseMismatch(index)));
+        }
+
+        Ok(index)
+    }
+
+    fn add_type(&mut self, func_type: FuncType) -> u32 {
+        let types = &mut self.module.type_section;
+
+        match types.iter().position(|ty| *ty == func_type) {
+            Some(index) => index as u32,
+            None => {
+                types.push(func_type);
+                types.len() as u32 - 1
+            }
+        }
+    }
+
+    /// Parses a type use and returns just the type index.
+    pub(crate) fn type_index_use(&mut self) -> Result<u32> {
+        let mut type_use = self.type_use()?;
+        self.resolve_type_use(&mut type_use)
+    }
+
+    /// Parses a block type, which is either empty, a single result or a type use.
+    pub(crate) fn block_type(&mut self) -> Result<BlockType> {
+        let mut type_use = self.type_use()?;
+        let FuncType { params, results } = &type_use.func_type;
+
+        if type_use.index.is_none() && params.is_empty() && results.len() <= 1 {
+            return Ok(match results.first() {
+                Some(result) => BlockType::Value(vec![result.clone()]),
+                None => BlockType::Empty,
+            });
+        }
+
+        Ok(BlockType::TypeIndex(self.resolve_type_use(&mut type_use)?))
+    }
+
+    fn limits(&mut self, index64: bool) -> Result<Limits> {
+        let min = self.u64()?;
+        let max = match self.peek_index() {
+            true => Some(self.u64()?),
+            false => None,
+        };
+
+        Ok(Limits {
+            min,
+            max,
+            shared: false,
+            index64,
+        })
+    }
+
+    fn table_type(&mut self) -> Result<TableType> {
+        let index64 = self.eat_keyword("i64");
+        let limits = self.limits(index64)?;
+        let element_type = self.ref_type()?;
+
+        Ok(TableType {
+            element_type,
+            limits,
+        })
+    }
+
+    fn memory_type(&mut self) -> Result<Limits> {
+        let index64 = self.eat_keyword("i64");
+        if !index64 {
+            self.eat_keyword("i32");
+        }
+        let mut limits = self.limits(index64)?;
+        limits.shared = self.eat_keyword("shared");
+        if !limits.shared {
+            self.eat_keyword("unshared");
+        }
+
+        Ok(limits)
+    }
+
+    fn global_type(&mut self) -> Result<GlobalType> {
+        if self.eat_list("mut") {
+            let value_type = self.value_type()?;
+            self.expect_rparen()?;
+
+            return Ok(GlobalType {
+                value_type,
+                mutable: true,
+            });
+        }
+
+        Ok(GlobalType {
+            value_type: self.value_type()?,
+            mutable: false,
+        })
+    }
+
+    /* Module fields */
+
+    /// First pass over the module fields: binds identifiers to indices and
+    /// collects explicit type definitions, which come before any type added by
+    /// an inline type use. Returns the position of each field.
+    fn collect_fields(&mut self) -> Result<Vec<usize>> {
+        let mut fields = vec![];
+        let mut defined = false;
+
+        while self.peek().is_some_and(|token| *token != Token::RParen) {
+            let start = self.pos;
+            let offset = self.offset();
+            self.expect_lparen()?;
+            let keyword = self.keyword()?;
+
+            let imported = match keyword {
+                "type" => {
+                    let id = self.maybe_id();
+                    self.define(Space::Type, id, offset)?;
+                    let func_type = self.func_type()?;
+                    self.module.type_section.push(func_type);
+
+                    false
+                }
+                "import" => {
+                    self.name()?;
+                    self.name()?;
+                    self.expect_lparen()?;
+                    let space = self.import_space()?;
+                    let id = self.maybe_id();
+                    self.define(space, id, offset)?;
+
+                    true
+                }
+                "func" | "table" | "memory" | "global" | "tag" => {
+                    let space = match keyword {
+                        "func" => Space::Func,
+                        "table" => Space::Table,
+                        "memory" => Space::Memory,
+                        "global" => Space::Global,
+                        _ => Space::Tag,
+                    };
+                    let id = self.maybe_id();
+                    self.define(space, id, offset)?;
+
+                    while self.eat_list("export") {
+                        self.skip_rest()?;
+                    }
+                    let imported = self.peek_list("import");
+
+                    // Inline `(elem ...)` and `(data ...)` define a segment too.
+                    let segment = match keyword {
+                        "table" => Some((Space::Elem, "elem")),
+                        "memory" => Some((Space::Data, "data")),
+                        _ => None,
+                    };
+                    if let Some((space, keyword)) = segment {
+                        self.eat_keyword("i64");
+                        if self.peek_ref_type() {
+                            self.ref_type()?;
+                        }
+                        if self.peek_list(keyword) {
+                            self.define(space, None, offset)?;
+                        }
+                    }
+
+                    if !imported {
+                        defined = true;
+                    }
+                    imported
+                }
+                "elem" => {
+                    let id = self.maybe_id();
+                    self.define(Space::Elem, id, offset)?;
+
+                    false
+                }
+                "data" => {
+                    let id = self.maybe_id();
+                    self.define(Space::Data, id, offset)?;
+
+                    false
+                }
+                "export" | "start" => false,
+                keyword => {
+                    return Err(self.error(offset, TextErrorKind::UnknownField(keyword.to_owned())));
+                }
+            };
+
+            if imported && defined {
+                return Err(self.error(offset, TextErrorKind::ImportAfterDefinition));
+            }
+
+            self.pos = start + 1;
+            self.skip_rest()?;
+            fields.push(start);
+        }
+
+        Ok(fields)
+    }
+
+    fn import_space(&mut self) -> Result<Space> {
+        let space = match self.peek_keyword() {
+            Some("func") => Space::Func,
+            Some("table") => Space::Table,
+            Some("memory") => Space::Memory,
+            Some("global") => Space::Global,
+            Some("tag") => Space::Tag,
+            _ => return Err(self.expected("import kind")),
+        };
+        self.pos += 1;
+
+        Ok(space)
+    }
+
+    fn parse_field(&mut self) -> Result<()> {
+        self.expect_lparen()?;
+
+        match self.keyword()? {
+            "type" => self.skip_rest(),
+            "import" => self.import(),
+            "func" => self.func(),
+            "table" => self.table(),
+            "memory" => self.memory(),
+            "global" => self.global(),
+            "tag" => self.tag(),
+            "export" => self.export(),
+            "start" => self.start(),
+            "elem" => self.elem(),
+            "data" => self.data(),
+            _ => unreachable!("unknown fields are rejected by collect_fields"),
+        }
+    }
+
+    /// Parses `(export "name")*` of a definition.
+    fn inline_exports(&mut self, desc: ExportDesc) -> Result<()> {
+        while self.eat_list("export") {
+            let name = self.name()?;
+            self.expect_rparen()?;
+
+            self.module.export_section.push(Export {
+                name,
+                desc: desc.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Parses the `(import "module" "field")` of a definition.
+    fn inline_import(&mut self) -> Result<Option<(String, String)>> {
+        if !self.eat_list("import") {
+            return Ok(None);
+        }
+        let module = self.name()?;
+        let field = self.name()?;
+        self.expect_rparen()?;
+
+        Ok(Some((module, field)))
+    }
+
+    fn import(&mut self) -> Result<()> {
+        let module = self.name()?;
+        let field = self.name()?;
+        self.expect_lparen()?;
+        let space = self.import_space()?;
+        let id = self.maybe_id();
+        let desc = self.import_desc(space, id)?;
+        self.expect_rparen()?;
+        self.expect_rparen()?;
+
+        self.module.import_section.push(Import {
+            module,
+            field,
+            desc,
+        });
+
+        Ok(())
+    }
+
+    /// Parses what's imported once the kind and identifier are known.
+    fn import_desc(&mut self, space: Space, id: Option<&'a str>) -> Result<ImportDesc> {
+        let index = self.next_index(space);
+
+        Ok(match space {
+            Space::Func => {
+                if let Some(id) = id {
+                    self.names.function_names.insert(index, id.to_owned());
+                }
+                ImportDesc::Func(self.type_index_use()?)
+            }
+            Space::Table => ImportDesc::Table(self.table_type()?),
+            Space::Memory => ImportDesc::Memory(self.memory_type()?),
+            Space::Global => ImportDesc::Global(self.global_type()?),
+            Space::Tag => ImportDesc::Tag(TagType {
+                type_index: self.type_index_use()?,
+            }),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Parses a definition that may be an inline import, in which case it's
+    /// added to the import section and `true` is returned.
+    fn import_or_define(
+        &mut self,
+        space: Space,
+        id: Option<&'a str>,
+        export: fn(u32) -> ExportDesc,
+    ) -> Result<bool> {
+        self.inline_exports(export(self.next_index[space as usize]))?;
+
+        let Some((module, field)) = self.inline_import()? else {
+            return Ok(false);
+        };
+        let desc = self.import_desc(space, id)?;
+        self.expect_rparen()?;
+
+        self.module.import_section.push(Import {
+            module,
+            field,
+            desc,
+        });
+
+        Ok(true)
+    }
+
+    fn func(&mut self) -> Result<()> {
+        let id = self.maybe_id();
+        if self.import_or_define(Space::Func, id, ExportDesc::Func)? {
+            return Ok(());
+        }
+        let index = self.next_index(Space::Func);
+
+        let mut type_use = self.type_use()?;
+        let type_index = self.resolve_type_use(&mut type_use)?;
+        let mut local_names = type_use.param_names;
+
+        let mut locals = vec![];
+        while self.eat_list("local") {
+            if let Some(id) = self.maybe_id() {
+                locals.push(self.value_type()?);
+                local_names.push(Some(id));
+            } else {
+                while self.peek() != Some(&Token::RParen) {
+                    locals.push(self.value_type()?);
+                    local_names.push(None);
+                }
+            }
+            self.expect_rparen()?;
+        }
+
+        self.locals.clear();
+        for (local_index, name) in local_names.iter().enumerate() {
+            if let Some(name) = name {
+                if self.locals.insert(name, local_index as u32).is_some() {
+                    let kind = TextErrorKind::DuplicateName {
+                        space: "local",
+                        name: name.to_string(),
+                    };
+                    return Err(self.error(type_use.offset, kind));
+                }
+            }
+        }
+
+        let mut code = vec![];
+        self.labels.clear();
+        self.instructions(&mut code)?;
+        self.expect_rparen()?;
+        code.push(Instruction::End);
+
+        if let Some(id) = id {
+            self.names.function_names.insert(index, id.to_owned());
+        }
+        let named_locals: std::collections::BTreeMap<_, _> = local_names
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| Some((i as u32, name.as_ref()?.to_string())))
+            .collect();
+        if !named_locals.is_empty() {
+            self.names.local_names.insert(index, named_locals);
+        }
+
+        self.module.function_section.push(type_index);
+        self.module.code_section.push(Code { locals, code });
+
+        Ok(())
+    }
+
+    fn table(&mut self) -> Result<()> {
+        let id = self.maybe_id();
+        if self.import_or_define(Space::Table, id, ExportDesc::Table)? {
+            return Ok(());
+        }
+        let index = self.next_index(Space::Table);
+
+        let start = self.pos;
+        let index64 = self.eat_keyword("i64");
+        if !self.peek_ref_type() {
+            self.pos = start;
+            let table_type = self.table_type()?;
+            self.module.table_section.push(table_type);
+
+            return self.expect_rparen();
+        }
+
+        // `(table reftype (elem ...))` sizes the table to fit the segment.
+        let ref_type = self.ref_type()?;
+        if !self.eat_list("elem") {
+            return Err(self.expected("`(elem`"));
+        }
+        let init = match self.peek() {
+            Some(Token::LParen) => ElementInit::Expressions(self.element_expressions()?),
+            _ => ElementInit::Functions(self.func_indices()?),
+        };
+        self.expect_rparen()?;
+        self.expect_rparen()?;
+
+        let len = match &init {
+            ElementInit::Functions(indices) => indices.len(),
+            ElementInit::Expressions(exprs) => exprs.len(),
+        } as u64;
+        self.module.table_section.push(TableType {
+            element_type: ref_type,
+            limits: Limits {
+                min: len,
+                max: Some(len),
+                shared: false,
+                index64,
+            },
+        });
+        self.next_index(Space::Elem);
+        self.module.element_section.push(Element {
+            mode: ElementMode::Active {
+                table_index: index,
+                offset: zero_offset(index64),
+            },
+            ref_type,
+            init,
+        });
+
+        Ok(())
+    }
+
+    fn memory(&mut self) -> Result<()> {
+        let id = self.maybe_id();
+        if self.import_or_define(Space::Memory, id, ExportDesc::Mem)? {
+            return Ok(());
+        }
+        let index = self.next_index(Space::Memory);
+
+        let start = self.pos;
+        let index64 = self.eat_keyword("i64");
+        if !self.eat_list("data") {
+            self.pos = start;
+            let limits = self.memory_type()?;
+            self.module.memory_section.push(MemoryType { limits });
+
+            return self.expect_rparen();
+        }
+
+        // `(memory (data ...))` sizes the memory to fit the segment.
+        let mut bytes = vec![];
+        while self.peek() != Some(&Token::RParen) {
+            bytes.extend(self.string()?);
+        }
+        self.expect_rparen()?;
+        self.expect_rparen()?;
+
+        let pages = (bytes.len() as u64).div_ceil(65536);
+        self.module.memory_section.push(MemoryType {
+            limits: Limits {
+                min: pages,
+                max: Some(pages),
+                shared: false,
+                index64,
+            },
+        });
+        self.next_index(Space::Data);
+        self.module.data_section.push(Data {
+            mode: DataMode::Active {
+                memory_index: index,
+                offset: zero_offset(index64),
+            },
+            bytes,
+        });
+
+        Ok(())
+    }
+
+    fn global(&mut self) -> Result<()> {
+        let id = self.maybe_id();
+        if self.import_or_define(Space::Global, id, ExportDesc::Global)? {
+            return Ok(());
+        }
+        self.next_index(Space::Global);
+
+        let global_type = self.global_type()?;
+        let init_expr = self.const_expr()?;
+        self.expect_rparen()?;
+
+        self.module.global_section.push(Global {
+            global_type,
+            init_expr,
+        });
+
+        Ok(())
+    }
+
+    fn tag(&mut self) -> Result<()> {
+        let id = self.maybe_id();
+        if self.import_or_define(Space::Tag, id, ExportDesc::Tag)? {
+            return Ok(());
+        }
+        self.next_index(Space::Tag);
+
+        let type_index = self.type_index_use()?;
+        self.expect_rparen()?;
+
+        self.module.tag_section.push(TagType { type_index });
+
+        Ok(())
+    }
+
+    fn export(&mut self) -> Result<()> {
+        let name = self.name()?;
+        self.expect_lparen()?;
+        let desc = match self.keyword()? {
+            "func" => ExportDesc::Func(self.index(Space::Func)?),
+            "table" => ExportDesc::Table(self.index(Space::Table)?),
+            "memory" => ExportDesc::Mem(self.index(Space::Memory)?),
+            "global" => ExportDesc::Global(self.index(Space::Global)?),
+            "tag" => ExportDesc::Tag(self.index(Space::Tag)?),
+            _ => {
+                self.pos -= 1;
+                return Err(self.expected("export kind"));
+            }
+        };
+        self.expect_rparen()?;
+        self.expect_rparen()?;
+
+        self.module.export_section.push(Export { name, desc });
+
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<()> {
+        self.module.start_section = Some(self.index(Space::Func)?);
+
+        self.expect_rparen()
+    }
+
+    fn elem(&mut self) -> Result<()> {
+        self.maybe_id();
+        self.next_index(Space::Elem);
+
+        let mode = if self.eat_keyword("declare") {
+            ElementMode::Declarative
+        } else if self.peek_list("table") || self.peek_index() || self.peek_offset() {
+            let table_index = if self.eat_list("table") {
+                let index = self.index(Space::Table)?;
+                self.expect_rparen()?;
+                index
+            } else {
+                self.optional_index(Space::Table)?.unwrap_or(0)
+            };
+
+            ElementMode::Active {
+                table_index,
+                offset: self.offset_expr()?,
+            }
+        } else {
+            ElementMode::Passive
+        };
+
+        let (ref_type, init) = if self.peek_ref_type() {
+            let ref_type = self.ref_type()?;
+            (
+                ref_type,
+                ElementInit::Expressions(self.element_expressions()?),
+            )
+        } else {
+            self.eat_keyword("func");
+            (
+                RefType::FuncRef,
+                ElementInit::Functions(self.func_indices()?),
+            )
+        };
+        self.expect_rparen()?;
+
+        self.module.element_section.push(Element {
+            mode,
+            ref_type,
+            init,
+        });
+
+        Ok(())
+    }
+
+    fn data(&mut self) -> Result<()> {
+        self.maybe_id();
+        self.next_index(Space::Data);
+
+        let mode = if self.peek_list("memory") || self.peek_index() || self.peek_offset() {
+            let memory_index = if self.eat_list("memory") {
+                let index = self.index(Space::Memory)?;
+                self.expect_rparen()?;
+                index
+            } else {
+                self.optional_index(Space::Memory)?.unwrap_or(0)
+            };
+
+            DataMode::Active {
+                memory_index,
+                offset: self.offset_expr()?,
+            }
+        } else {
+            DataMode::Passive
+        };
+
+        let mut bytes = vec![];
+        while self.peek() != Some(&Token::RParen) {
+            bytes.extend(self.string()?);
+        }
+        self.expect_rparen()?;
+
+        self.module.data_section.push(Data { mode, bytes });
+
+        Ok(())
+    }
+
+    fn func_indices(&mut self) -> Result<Vec<u32>> {
+        let mut indices = vec![];
+        while self.peek_index() {
+            indices.push(self.index(Space::Func)?);
+        }
+
+        Ok(indices)
+    }
+
+    /// Parses `(item instr*)` or `(instr)` element expressions.
+    fn element_expressions(&mut self) -> Result<Vec<ConstExpr>> {
+        let mut exprs = vec![];
+
+        while self.peek() == Some(&Token::LParen) {
+            if self.eat_list("item") {
+                exprs.push(self.const_expr()?);
+                self.expect_rparen()?;
+            } else {
+                let mut instructions = vec![];
+                self.folded_instruction(&mut instructions)?;
+                exprs.push(ConstExpr { instructions });
+            }
+        }
+
+        Ok(exprs)
+    }
+
+    /// Whether the next tokens start a segment offset: `(offset ...)` or a
+    /// folded instruction.
+    fn peek_offset(&self) -> bool {
+        self.peek() == Some(&Token::LParen)
+            && match self.peek_at(1) {
+                Some(Token::Keyword(keyword)) => {
+                    !matches!(*keyword, "item" | "ref" | "memory" | "table")
+                }
+                _ => false,
+            }
+    }
+
+    fn offset_expr(&mut self) -> Result<ConstExpr> {
+        if self.eat_list("offset") {
+            let expr = self.const_expr()?;
+            self.expect_rparen()?;
+
+            return Ok(expr);
+        }
+
+        let mut instructions = vec![];
+        self.folded_instruction(&mut instructions)?;
+
+        Ok(ConstExpr { instructions })
+    }
+
+    fn const_expr(&mut self) -> Result<ConstExpr> {
+        let mut instructions = vec![];
+        self.instructions(&mut instructions)?;
+
+        Ok(ConstExpr { instructions })
+    }
+}
+
+fn zero_offset(index64: bool) -> ConstExpr {
+    let instruction = match index64 {
+        true => Instruction::I64Const { value: 0 },
+        false => Instruction::I32Const { value: 0 },
+    };
+
+    ConstExpr {
+        instructions: vec![instruction],
+    }
+}
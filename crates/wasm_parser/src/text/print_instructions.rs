@@ -0,0 +1,927 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+};
+
+use crate::decoder::instructions::{BlockType, Catch, Instruction, MemArg};
+
+use super::printer::{heap_type, value_types};
+
+/// Prints instructions of one function, with identifiers in place of function
+/// and local indices where names are known.
+pub(crate) struct InstructionPrinter<'a> {
+    pub(crate) funcs: &'a HashMap<u32, String>,
+    pub(crate) locals: HashMap<u32, String>,
+}
+
+impl InstructionPrinter<'_> {
+    /// Writes a plain instruction, without the body of blocks.
+    pub(crate) fn instruction(&self, line: &mut String, instr: &Instruction) -> fmt::Result {
+        match instr {
+            Instruction::Block { block } => block_instruction(line, "block", &block.block_type),
+            Instruction::Loop { block } => block_instruction(line, "loop", &block.block_type),
+            Instruction::If { block } => block_instruction(line, "if", &block.block_type),
+            Instruction::TryTable { block, catches } => {
+                block_instruction(line, "try_table", &block.block_type)?;
+                for catch in catches {
+                    match catch {
+                        Catch::Catch { tag_index, label } => {
+                            write!(line, " (catch {} {})", tag_index, label)
+                        }
+                        Catch::CatchRef { tag_index, label } => {
+                            write!(line, " (catch_ref {} {})", tag_index, label)
+                        }
+                        Catch::CatchAll { label } => write!(line, " (catch_all {})", label),
+                        Catch::CatchAllRef { label } => write!(line, " (catch_all_ref {})", label),
+                    }?;
+                }
+                Ok(())
+            }
+            Instruction::Else => line.write_str("else"),
+            Instruction::End => line.write_str("end"),
+            Instruction::BrTable {
+                label_indexes,
+                default_index,
+            } => {
+                line.write_str("br_table")?;
+                for label in label_indexes.iter().chain([default_index]) {
+                    write!(line, " {}", label)?;
+                }
+                Ok(())
+            }
+            Instruction::Select { result_types: None } => line.write_str("select"),
+            Instruction::Select {
+                result_types: Some(result_types),
+            } => write!(line, "select (result{})", value_types(result_types)),
+            Instruction::SelectResult { result_type } => write!(
+                line,
+                "select (result{})",
+                value_types(std::slice::from_ref(result_type))
+            ),
+            Instruction::Unreachable => line.write_str("unreachable"),
+            Instruction::Nop => line.write_str("nop"),
+            Instruction::Throw { tag_index } => write!(line, "throw {}", tag_index),
+            Instruction::ThrowRef => line.write_str("throw_ref"),
+            Instruction::Br { level } => write!(line, "br {}", level),
+            Instruction::BrIf { level } => write!(line, "br_if {}", level),
+            Instruction::Return => line.write_str("return"),
+            Instruction::Call { func_index } => self.func(line, "call", *func_index),
+            Instruction::CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                write!(line, "call_indirect {} (type {})", table_index, type_index)
+            }
+            Instruction::ReturnCall { func_index } => self.func(line, "return_call", *func_index),
+            Instruction::ReturnCallIndirect {
+                type_index,
+                table_index,
+            } => {
+                write!(
+                    line,
+                    "return_call_indirect {} (type {})",
+                    table_index, type_index
+                )
+            }
+            Instruction::RefNull { ref_type } => write!(line, "ref.null {}", heap_type(*ref_type)),
+            Instruction::RefIsNull => line.write_str("ref.is_null"),
+            Instruction::RefFunc { func_index } => self.func(line, "ref.func", *func_index),
+            Instruction::Drop => line.write_str("drop"),
+            Instruction::LocalGet { local_index } => self.local(line, "local.get", *local_index),
+            Instruction::LocalSet { local_index } => self.local(line, "local.set", *local_index),
+            Instruction::LocalTee { local_index } => self.local(line, "local.tee", *local_index),
+            Instruction::GlobalGet { global_index } => write!(line, "global.get {}", global_index),
+            Instruction::GlobalSet { global_index } => write!(line, "global.set {}", global_index),
+            Instruction::TableGet { table_index } => {
+                optional_index(line, "table.get", *table_index)
+            }
+            Instruction::TableSet { table_index } => {
+                optional_index(line, "table.set", *table_index)
+            }
+            Instruction::I32Load { mem_arg } => self.mem_arg(line, "i32.load", mem_arg, 2),
+            Instruction::I64Load { mem_arg } => self.mem_arg(line, "i64.load", mem_arg, 3),
+            Instruction::F32Load { mem_arg } => self.mem_arg(line, "f32.load", mem_arg, 2),
+            Instruction::F64Load { mem_arg } => self.mem_arg(line, "f64.load", mem_arg, 3),
+            Instruction::I32Load8S { mem_arg } => self.mem_arg(line, "i32.load8_s", mem_arg, 0),
+            Instruction::I32Load8U { mem_arg } => self.mem_arg(line, "i32.load8_u", mem_arg, 0),
+            Instruction::I32Load16S { mem_arg } => self.mem_arg(line, "i32.load16_s", mem_arg, 1),
+            Instruction::I32Load16U { mem_arg } => self.mem_arg(line, "i32.load16_u", mem_arg, 1),
+            Instruction::I64Load8S { mem_arg } => self.mem_arg(line, "i64.load8_s", mem_arg, 0),
+            Instruction::I64Load8U { mem_arg } => self.mem_arg(line, "i64.load8_u", mem_arg, 0),
+            Instruction::I64Load16S { mem_arg } => self.mem_arg(line, "i64.load16_s", mem_arg, 1),
+            Instruction::I64Load16U { mem_arg } => self.mem_arg(line, "i64.load16_u", mem_arg, 1),
+            Instruction::I64Load32S { mem_arg } => self.mem_arg(line, "i64.load32_s", mem_arg, 2),
+            Instruction::I64Load32U { mem_arg } => self.mem_arg(line, "i64.load32_u", mem_arg, 2),
+            Instruction::I32Store { mem_arg } => self.mem_arg(line, "i32.store", mem_arg, 2),
+            Instruction::I64Store { mem_arg } => self.mem_arg(line, "i64.store", mem_arg, 3),
+            Instruction::F32Store { mem_arg } => self.mem_arg(line, "f32.store", mem_arg, 2),
+            Instruction::F64Store { mem_arg } => self.mem_arg(line, "f64.store", mem_arg, 3),
+            Instruction::I32Store8 { mem_arg } => self.mem_arg(line, "i32.store8", mem_arg, 0),
+            Instruction::I32Store16 { mem_arg } => self.mem_arg(line, "i32.store16", mem_arg, 1),
+            Instruction::I64Store8 { mem_arg } => self.mem_arg(line, "i64.store8", mem_arg, 0),
+            Instruction::I64Store16 { mem_arg } => self.mem_arg(line, "i64.store16", mem_arg, 1),
+            Instruction::I64Store32 { mem_arg } => self.mem_arg(line, "i64.store32", mem_arg, 2),
+            Instruction::MemorySize { memory_index } => {
+                optional_index(line, "memory.size", *memory_index)
+            }
+            Instruction::MemoryGrow { memory_index } => {
+                optional_index(line, "memory.grow", *memory_index)
+            }
+            Instruction::I32Const { value } => write!(line, "i32.const {}", value),
+            Instruction::I64Const { value } => write!(line, "i64.const {}", value),
+            Instruction::F32Const { value } => write!(line, "f32.const {}", Float32(*value)),
+            Instruction::F64Const { value } => write!(line, "f64.const {}", Float64(*value)),
+            Instruction::I32Eqz => line.write_str("i32.eqz"),
+            Instruction::I32Eq => line.write_str("i32.eq"),
+            Instruction::I32Ne => line.write_str("i32.ne"),
+            Instruction::I32LtS => line.write_str("i32.lt_s"),
+            Instruction::I32LtU => line.write_str("i32.lt_u"),
+            Instruction::I32GtS => line.write_str("i32.gt_s"),
+            Instruction::I32GtU => line.write_str("i32.gt_u"),
+            Instruction::I32LeS => line.write_str("i32.le_s"),
+            Instruction::I32LeU => line.write_str("i32.le_u"),
+            Instruction::I32GeS => line.write_str("i32.ge_s"),
+            Instruction::I32GeU => line.write_str("i32.ge_u"),
+            Instruction::I64Eqz => line.write_str("i64.eqz"),
+            Instruction::I64Eq => line.write_str("i64.eq"),
+            Instruction::I64Ne => line.write_str("i64.ne"),
+            Instruction::I64LtS => line.write_str("i64.lt_s"),
+            Instruction::I64LtU => line.write_str("i64.lt_u"),
+            Instruction::I64GtS => line.write_str("i64.gt_s"),
+            Instruction::I64GtU => line.write_str("i64.gt_u"),
+            Instruction::I64LeS => line.write_str("i64.le_s"),
+            Instruction::I64LeU => line.write_str("i64.le_u"),
+            Instruction::I64GeS => line.write_str("i64.ge_s"),
+            Instruction::I64GeU => line.write_str("i64.ge_u"),
+            Instruction::F32Eq => line.write_str("f32.eq"),
+            Instruction::F32Ne => line.write_str("f32.ne"),
+            Instruction::F32Lt => line.write_str("f32.lt"),
+            Instruction::F32Gt => line.write_str("f32.gt"),
+            Instruction::F32Le => line.write_str("f32.le"),
+            Instruction::F32Ge => line.write_str("f32.ge"),
+            Instruction::F64Eq => line.write_str("f64.eq"),
+            Instruction::F64Ne => line.write_str("f64.ne"),
+            Instruction::F64Lt => line.write_str("f64.lt"),
+            Instruction::F64Gt => line.write_str("f64.gt"),
+            Instruction::F64Le => line.write_str("f64.le"),
+            Instruction::F64Ge => line.write_str("f64.ge"),
+            Instruction::I32Clz => line.write_str("i32.clz"),
+            Instruction::I32Ctz => line.write_str("i32.ctz"),
+            Instruction::I32Popcnt => line.write_str("i32.popcnt"),
+            Instruction::I32Add => line.write_str("i32.add"),
+            Instruction::I32Sub => line.write_str("i32.sub"),
+            Instruction::I32Mul => line.write_str("i32.mul"),
+            Instruction::I32DivS => line.write_str("i32.div_s"),
+            Instruction::I32DivU => line.write_str("i32.div_u"),
+            Instruction::I32RemS => line.write_str("i32.rem_s"),
+            Instruction::I32RemU => line.write_str("i32.rem_u"),
+            Instruction::I32And => line.write_str("i32.and"),
+            Instruction::I32Or => line.write_str("i32.or"),
+            Instruction::I32Xor => line.write_str("i32.xor"),
+            Instruction::I32Shl => line.write_str("i32.shl"),
+            Instruction::I32ShrS => line.write_str("i32.shr_s"),
+            Instruction::I32ShrU => line.write_str("i32.shr_u"),
+            Instruction::I32Rotl => line.write_str("i32.rotl"),
+            Instruction::I32Rotr => line.write_str("i32.rotr"),
+            Instruction::I64Clz => line.write_str("i64.clz"),
+            Instruction::I64Ctz => line.write_str("i64.ctz"),
+            Instruction::I64Popcnt => line.write_str("i64.popcnt"),
+            Instruction::I64Add => line.write_str("i64.add"),
+            Instruction::I64Sub => line.write_str("i64.sub"),
+            Instruction::I64Mul => line.write_str("i64.mul"),
+            Instruction::I64DivS => line.write_str("i64.div_s"),
+            Instruction::I64DivU => line.write_str("i64.div_u"),
+            Instruction::I64RemS => line.write_str("i64.rem_s"),
+            Instruction::I64RemU => line.write_str("i64.rem_u"),
+            Instruction::I64And => line.write_str("i64.and"),
+            Instruction::I64Or => line.write_str("i64.or"),
+            Instruction::I64Xor => line.write_str("i64.xor"),
+            Instruction::I64Shl => line.write_str("i64.shl"),
+            Instruction::I64ShrS => line.write_str("i64.shr_s"),
+            Instruction::I64ShrU => line.write_str("i64.shr_u"),
+            Instruction::I64Rotl => line.write_str("i64.rotl"),
+            Instruction::I64Rotr => line.write_str("i64.rotr"),
+            Instruction::F32Abs => line.write_str("f32.abs"),
+            Instruction::F32Neg => line.write_str("f32.neg"),
+            Instruction::F32Ceil => line.write_str("f32.ceil"),
+            Instruction::F32Floor => line.write_str("f32.floor"),
+            Instruction::F32Trunc => line.write_str("f32.trunc"),
+            Instruction::F32Nearest => line.write_str("f32.nearest"),
+            Instruction::F32Sqrt => line.write_str("f32.sqrt"),
+            Instruction::F32Add => line.write_str("f32.add"),
+            Instruction::F32Sub => line.write_str("f32.sub"),
+            Instruction::F32Mul => line.write_str("f32.mul"),
+            Instruction::F32Div => line.write_str("f32.div"),
+            Instruction::F32Min => line.write_str("f32.min"),
+            Instruction::F32Max => line.write_str("f32.max"),
+            Instruction::F32Copysign => line.write_str("f32.copysign"),
+            Instruction::F64Abs => line.write_str("f64.abs"),
+            Instruction::F64Neg => line.write_str("f64.neg"),
+            Instruction::F64Ceil => line.write_str("f64.ceil"),
+            Instruction::F64Floor => line.write_str("f64.floor"),
+            Instruction::F64Trunc => line.write_str("f64.trunc"),
+            Instruction::F64Nearest => line.write_str("f64.nearest"),
+            Instruction::F64Sqrt => line.write_str("f64.sqrt"),
+            Instruction::F64Add => line.write_str("f64.add"),
+            Instruction::F64Sub => line.write_str("f64.sub"),
+            Instruction::F64Mul => line.write_str("f64.mul"),
+            Instruction::F64Div => line.write_str("f64.div"),
+            Instruction::F64Min => line.write_str("f64.min"),
+            Instruction::F64Max => line.write_str("f64.max"),
+            Instruction::F64Copysign => line.write_str("f64.copysign"),
+            Instruction::I32WrapI64 => line.write_str("i32.wrap_i64"),
+            Instruction::I32TruncF32S => line.write_str("i32.trunc_f32_s"),
+            Instruction::I32TruncF32U => line.write_str("i32.trunc_f32_u"),
+            Instruction::I32TruncF64S => line.write_str("i32.trunc_f64_s"),
+            Instruction::I32TruncF64U => line.write_str("i32.trunc_f64_u"),
+            Instruction::I64ExtendI32S => line.write_str("i64.extend_i32_s"),
+            Instruction::I64ExtendI32U => line.write_str("i64.extend_i32_u"),
+            Instruction::I64TruncF32S => line.write_str("i64.trunc_f32_s"),
+            Instruction::I64TruncF32U => line.write_str("i64.trunc_f32_u"),
+            Instruction::I64TruncF64S => line.write_str("i64.trunc_f64_s"),
+            Instruction::I64TruncF64U => line.write_str("i64.trunc_f64_u"),
+            Instruction::F32ConvertI32S => line.write_str("f32.convert_i32_s"),
+            Instruction::F32ConvertI32U => line.write_str("f32.convert_i32_u"),
+            Instruction::F32ConvertI64S => line.write_str("f32.convert_i64_s"),
+            Instruction::F32ConvertI64U => line.write_str("f32.convert_i64_u"),
+            Instruction::F32DemoteF64 => line.write_str("f32.demote_f64"),
+            Instruction::F64ConvertI32S => line.write_str("f64.convert_i32_s"),
+            Instruction::F64ConvertI32U => line.write_str("f64.convert_i32_u"),
+            Instruction::F64ConvertI64S => line.write_str("f64.convert_i64_s"),
+            Instruction::F64ConvertI64U => line.write_str("f64.convert_i64_u"),
+            Instruction::F64PromoteF32 => line.write_str("f64.promote_f32"),
+            Instruction::I32ReinterpretF32 => line.write_str("i32.reinterpret_f32"),
+            Instruction::I64ReinterpretF64 => line.write_str("i64.reinterpret_f64"),
+            Instruction::F32ReinterpretI32 => line.write_str("f32.reinterpret_i32"),
+            Instruction::F64ReinterpretI64 => line.write_str("f64.reinterpret_i64"),
+            Instruction::I32Extend8S => line.write_str("i32.extend8_s"),
+            Instruction::I32Extend16S => line.write_str("i32.extend16_s"),
+            Instruction::I64Extend8S => line.write_str("i64.extend8_s"),
+            Instruction::I64Extend16S => line.write_str("i64.extend16_s"),
+            Instruction::I64Extend32S => line.write_str("i64.extend32_s"),
+            Instruction::I32TruncSatF32S => line.write_str("i32.trunc_sat_f32_s"),
+            Instruction::I32TruncSatF32U => line.write_str("i32.trunc_sat_f32_u"),
+            Instruction::I32TruncSatF64S => line.write_str("i32.trunc_sat_f64_s"),
+            Instruction::I32TruncSatF64U => line.write_str("i32.trunc_sat_f64_u"),
+            Instruction::I64TruncSatF32S => line.write_str("i64.trunc_sat_f32_s"),
+            Instruction::I64TruncSatF32U => line.write_str("i64.trunc_sat_f32_u"),
+            Instruction::I64TruncSatF64S => line.write_str("i64.trunc_sat_f64_s"),
+            Instruction::I64TruncSatF64U => line.write_str("i64.trunc_sat_f64_u"),
+            Instruction::MemoryInit {
+                data_index,
+                memory_index,
+            } => {
+                write!(line, "memory.init {} {}", memory_index, data_index)
+            }
+            Instruction::DataDrop { data_index } => write!(line, "data.drop {}", data_index),
+            Instruction::MemoryCopy {
+                dst_memory_index,
+                src_memory_index,
+            } => {
+                write!(
+                    line,
+                    "memory.copy {} {}",
+                    dst_memory_index, src_memory_index
+                )
+            }
+            Instruction::MemoryFill { memory_index } => {
+                optional_index(line, "memory.fill", *memory_index)
+            }
+            Instruction::TableInit {
+                element_index,
+                table_index,
+            } => {
+                write!(line, "table.init {} {}", table_index, element_index)
+            }
+            Instruction::ElemDrop { element_index } => write!(line, "elem.drop {}", element_index),
+            Instruction::TableCopy {
+                dst_table_index,
+                src_table_index,
+            } => {
+                write!(line, "table.copy {} {}", dst_table_index, src_table_index)
+            }
+            Instruction::TableGrow { table_index } => {
+                optional_index(line, "table.grow", *table_index)
+            }
+            Instruction::TableSize { table_index } => {
+                optional_index(line, "table.size", *table_index)
+            }
+            Instruction::TableFill { table_index } => {
+                optional_index(line, "table.fill", *table_index)
+            }
+            Instruction::V128Load { mem_arg } => self.mem_arg(line, "v128.load", mem_arg, 4),
+            Instruction::V128Load8x8S { mem_arg } => {
+                self.mem_arg(line, "v128.load8x8_s", mem_arg, 3)
+            }
+            Instruction::V128Load8x8U { mem_arg } => {
+                self.mem_arg(line, "v128.load8x8_u", mem_arg, 3)
+            }
+            Instruction::V128Load16x4S { mem_arg } => {
+                self.mem_arg(line, "v128.load16x4_s", mem_arg, 3)
+            }
+            Instruction::V128Load16x4U { mem_arg } => {
+                self.mem_arg(line, "v128.load16x4_u", mem_arg, 3)
+            }
+            Instruction::V128Load32x2S { mem_arg } => {
+                self.mem_arg(line, "v128.load32x2_s", mem_arg, 3)
+            }
+            Instruction::V128Load32x2U { mem_arg } => {
+                self.mem_arg(line, "v128.load32x2_u", mem_arg, 3)
+            }
+            Instruction::V128Load8Splat { mem_arg } => {
+                self.mem_arg(line, "v128.load8_splat", mem_arg, 0)
+            }
+            Instruction::V128Load16Splat { mem_arg } => {
+                self.mem_arg(line, "v128.load16_splat", mem_arg, 1)
+            }
+            Instruction::V128Load32Splat { mem_arg } => {
+                self.mem_arg(line, "v128.load32_splat", mem_arg, 2)
+            }
+            Instruction::V128Load64Splat { mem_arg } => {
+                self.mem_arg(line, "v128.load64_splat", mem_arg, 3)
+            }
+            Instruction::V128Store { mem_arg } => self.mem_arg(line, "v128.store", mem_arg, 4),
+            Instruction::V128Const { value } => {
+                write!(line, "v128.const i32x4")?;
+                for lane in 0..4 {
+                    write!(line, " {:#010x}", (value >> (lane * 32)) as u32)?;
+                }
+                Ok(())
+            }
+            Instruction::I8x16Shuffle { lanes } => {
+                write!(line, "i8x16.shuffle")?;
+                for lane in lanes {
+                    write!(line, " {}", lane)?;
+                }
+                Ok(())
+            }
+            Instruction::I8x16Swizzle => line.write_str("i8x16.swizzle"),
+            Instruction::I8x16Splat => line.write_str("i8x16.splat"),
+            Instruction::I16x8Splat => line.write_str("i16x8.splat"),
+            Instruction::I32x4Splat => line.write_str("i32x4.splat"),
+            Instruction::I64x2Splat => line.write_str("i64x2.splat"),
+            Instruction::F32x4Splat => line.write_str("f32x4.splat"),
+            Instruction::F64x2Splat => line.write_str("f64x2.splat"),
+            Instruction::I8x16ExtractLaneS { lane } => {
+                write!(line, "i8x16.extract_lane_s {}", lane)
+            }
+            Instruction::I8x16ExtractLaneU { lane } => {
+                write!(line, "i8x16.extract_lane_u {}", lane)
+            }
+            Instruction::I8x16ReplaceLane { lane } => write!(line, "i8x16.replace_lane {}", lane),
+            Instruction::I16x8ExtractLaneS { lane } => {
+                write!(line, "i16x8.extract_lane_s {}", lane)
+            }
+            Instruction::I16x8ExtractLaneU { lane } => {
+                write!(line, "i16x8.extract_lane_u {}", lane)
+            }
+            Instruction::I16x8ReplaceLane { lane } => write!(line, "i16x8.replace_lane {}", lane),
+            Instruction::I32x4ExtractLane { lane } => write!(line, "i32x4.extract_lane {}", lane),
+            Instruction::I32x4ReplaceLane { lane } => write!(line, "i32x4.replace_lane {}", lane),
+            Instruction::I64x2ExtractLane { lane } => write!(line, "i64x2.extract_lane {}", lane),
+            Instruction::I64x2ReplaceLane { lane } => write!(line, "i64x2.replace_lane {}", lane),
+            Instruction::F32x4ExtractLane { lane } => write!(line, "f32x4.extract_lane {}", lane),
+            Instruction::F32x4ReplaceLane { lane } => write!(line, "f32x4.replace_lane {}", lane),
+            Instruction::F64x2ExtractLane { lane } => write!(line, "f64x2.extract_lane {}", lane),
+            Instruction::F64x2ReplaceLane { lane } => write!(line, "f64x2.replace_lane {}", lane),
+            Instruction::I8x16Eq => line.write_str("i8x16.eq"),
+            Instruction::I8x16Ne => line.write_str("i8x16.ne"),
+            Instruction::I8x16LtS => line.write_str("i8x16.lt_s"),
+            Instruction::I8x16LtU => line.write_str("i8x16.lt_u"),
+            Instruction::I8x16GtS => line.write_str("i8x16.gt_s"),
+            Instruction::I8x16GtU => line.write_str("i8x16.gt_u"),
+            Instruction::I8x16LeS => line.write_str("i8x16.le_s"),
+            Instruction::I8x16LeU => line.write_str("i8x16.le_u"),
+            Instruction::I8x16GeS => line.write_str("i8x16.ge_s"),
+            Instruction::I8x16GeU => line.write_str("i8x16.ge_u"),
+            Instruction::I16x8Eq => line.write_str("i16x8.eq"),
+            Instruction::I16x8Ne => line.write_str("i16x8.ne"),
+            Instruction::I16x8LtS => line.write_str("i16x8.lt_s"),
+            Instruction::I16x8LtU => line.write_str("i16x8.lt_u"),
+            Instruction::I16x8GtS => line.write_str("i16x8.gt_s"),
+            Instruction::I16x8GtU => line.write_str("i16x8.gt_u"),
+            Instruction::I16x8LeS => line.write_str("i16x8.le_s"),
+            Instruction::I16x8LeU => line.write_str("i16x8.le_u"),
+            Instruction::I16x8GeS => line.write_str("i16x8.ge_s"),
+            Instruction::I16x8GeU => line.write_str("i16x8.ge_u"),
+            Instruction::I32x4Eq => line.write_str("i32x4.eq"),
+            Instruction::I32x4Ne => line.write_str("i32x4.ne"),
+            Instruction::I32x4LtS => line.write_str("i32x4.lt_s"),
+            Instruction::I32x4LtU => line.write_str("i32x4.lt_u"),
+            Instruction::I32x4GtS => line.write_str("i32x4.gt_s"),
+            Instruction::I32x4GtU => line.write_str("i32x4.gt_u"),
+            Instruction::I32x4LeS => line.write_str("i32x4.le_s"),
+            Instruction::I32x4LeU => line.write_str("i32x4.le_u"),
+            Instruction::I32x4GeS => line.write_str("i32x4.ge_s"),
+            Instruction::I32x4GeU => line.write_str("i32x4.ge_u"),
+            Instruction::F32x4Eq => line.write_str("f32x4.eq"),
+            Instruction::F32x4Ne => line.write_str("f32x4.ne"),
+            Instruction::F32x4Lt => line.write_str("f32x4.lt"),
+            Instruction::F32x4Gt => line.write_str("f32x4.gt"),
+            Instruction::F32x4Le => line.write_str("f32x4.le"),
+            Instruction::F32x4Ge => line.write_str("f32x4.ge"),
+            Instruction::F64x2Eq => line.write_str("f64x2.eq"),
+            Instruction::F64x2Ne => line.write_str("f64x2.ne"),
+            Instruction::F64x2Lt => line.write_str("f64x2.lt"),
+            Instruction::F64x2Gt => line.write_str("f64x2.gt"),
+            Instruction::F64x2Le => line.write_str("f64x2.le"),
+            Instruction::F64x2Ge => line.write_str("f64x2.ge"),
+            Instruction::V128Not => line.write_str("v128.not"),
+            Instruction::V128And => line.write_str("v128.and"),
+            Instruction::V128Andnot => line.write_str("v128.andnot"),
+            Instruction::V128Or => line.write_str("v128.or"),
+            Instruction::V128Xor => line.write_str("v128.xor"),
+            Instruction::V128Bitselect => line.write_str("v128.bitselect"),
+            Instruction::V128AnyTrue => line.write_str("v128.any_true"),
+            Instruction::V128Load8Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.load8_lane", mem_arg, 0)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Load16Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.load16_lane", mem_arg, 1)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Load32Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.load32_lane", mem_arg, 2)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Load64Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.load64_lane", mem_arg, 3)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Store8Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.store8_lane", mem_arg, 0)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Store16Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.store16_lane", mem_arg, 1)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Store32Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.store32_lane", mem_arg, 2)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Store64Lane { mem_arg, lane } => {
+                self.mem_arg(line, "v128.store64_lane", mem_arg, 3)?;
+                write!(line, " {}", lane)
+            }
+            Instruction::V128Load32Zero { mem_arg } => {
+                self.mem_arg(line, "v128.load32_zero", mem_arg, 2)
+            }
+            Instruction::V128Load64Zero { mem_arg } => {
+                self.mem_arg(line, "v128.load64_zero", mem_arg, 3)
+            }
+            Instruction::F32x4DemoteF64x2Zero => line.write_str("f32x4.demote_f64x2_zero"),
+            Instruction::F64x2PromoteLowF32x4 => line.write_str("f64x2.promote_low_f32x4"),
+            Instruction::I8x16Abs => line.write_str("i8x16.abs"),
+            Instruction::I8x16Neg => line.write_str("i8x16.neg"),
+            Instruction::I8x16Popcnt => line.write_str("i8x16.popcnt"),
+            Instruction::I8x16AllTrue => line.write_str("i8x16.all_true"),
+            Instruction::I8x16Bitmask => line.write_str("i8x16.bitmask"),
+            Instruction::I8x16NarrowI16x8S => line.write_str("i8x16.narrow_i16x8_s"),
+            Instruction::I8x16NarrowI16x8U => line.write_str("i8x16.narrow_i16x8_u"),
+            Instruction::F32x4Ceil => line.write_str("f32x4.ceil"),
+            Instruction::F32x4Floor => line.write_str("f32x4.floor"),
+            Instruction::F32x4Trunc => line.write_str("f32x4.trunc"),
+            Instruction::F32x4Nearest => line.write_str("f32x4.nearest"),
+            Instruction::I8x16Shl => line.write_str("i8x16.shl"),
+            Instruction::I8x16ShrS => line.write_str("i8x16.shr_s"),
+            Instruction::I8x16ShrU => line.write_str("i8x16.shr_u"),
+            Instruction::I8x16Add => line.write_str("i8x16.add"),
+            Instruction::I8x16AddSatS => line.write_str("i8x16.add_sat_s"),
+            Instruction::I8x16AddSatU => line.write_str("i8x16.add_sat_u"),
+            Instruction::I8x16Sub => line.write_str("i8x16.sub"),
+            Instruction::I8x16SubSatS => line.write_str("i8x16.sub_sat_s"),
+            Instruction::I8x16SubSatU => line.write_str("i8x16.sub_sat_u"),
+            Instruction::F64x2Ceil => line.write_str("f64x2.ceil"),
+            Instruction::F64x2Floor => line.write_str("f64x2.floor"),
+            Instruction::I8x16MinS => line.write_str("i8x16.min_s"),
+            Instruction::I8x16MinU => line.write_str("i8x16.min_u"),
+            Instruction::I8x16MaxS => line.write_str("i8x16.max_s"),
+            Instruction::I8x16MaxU => line.write_str("i8x16.max_u"),
+            Instruction::F64x2Trunc => line.write_str("f64x2.trunc"),
+            Instruction::I8x16AvgrU => line.write_str("i8x16.avgr_u"),
+            Instruction::I16x8ExtaddPairwiseI8x16S => {
+                line.write_str("i16x8.extadd_pairwise_i8x16_s")
+            }
+            Instruction::I16x8ExtaddPairwiseI8x16U => {
+                line.write_str("i16x8.extadd_pairwise_i8x16_u")
+            }
+            Instruction::I32x4ExtaddPairwiseI16x8S => {
+                line.write_str("i32x4.extadd_pairwise_i16x8_s")
+            }
+            Instruction::I32x4ExtaddPairwiseI16x8U => {
+                line.write_str("i32x4.extadd_pairwise_i16x8_u")
+            }
+            Instruction::I16x8Abs => line.write_str("i16x8.abs"),
+            Instruction::I16x8Neg => line.write_str("i16x8.neg"),
+            Instruction::I16x8Q15mulrSatS => line.write_str("i16x8.q15mulr_sat_s"),
+            Instruction::I16x8AllTrue => line.write_str("i16x8.all_true"),
+            Instruction::I16x8Bitmask => line.write_str("i16x8.bitmask"),
+            Instruction::I16x8NarrowI32x4S => line.write_str("i16x8.narrow_i32x4_s"),
+            Instruction::I16x8NarrowI32x4U => line.write_str("i16x8.narrow_i32x4_u"),
+            Instruction::I16x8ExtendLowI8x16S => line.write_str("i16x8.extend_low_i8x16_s"),
+            Instruction::I16x8ExtendHighI8x16S => line.write_str("i16x8.extend_high_i8x16_s"),
+            Instruction::I16x8ExtendLowI8x16U => line.write_str("i16x8.extend_low_i8x16_u"),
+            Instruction::I16x8ExtendHighI8x16U => line.write_str("i16x8.extend_high_i8x16_u"),
+            Instruction::I16x8Shl => line.write_str("i16x8.shl"),
+            Instruction::I16x8ShrS => line.write_str("i16x8.shr_s"),
+            Instruction::I16x8ShrU => line.write_str("i16x8.shr_u"),
+            Instruction::I16x8Add => line.write_str("i16x8.add"),
+            Instruction::I16x8AddSatS => line.write_str("i16x8.add_sat_s"),
+            Instruction::I16x8AddSatU => line.write_str("i16x8.add_sat_u"),
+            Instruction::I16x8Sub => line.write_str("i16x8.sub"),
+            Instruction::I16x8SubSatS => line.write_str("i16x8.sub_sat_s"),
+            Instruction::I16x8SubSatU => line.write_str("i16x8.sub_sat_u"),
+            Instruction::F64x2Nearest => line.write_str("f64x2.nearest"),
+            Instruction::I16x8Mul => line.write_str("i16x8.mul"),
+            Instruction::I16x8MinS => line.write_str("i16x8.min_s"),
+            Instruction::I16x8MinU => line.write_str("i16x8.min_u"),
+            Instruction::I16x8MaxS => line.write_str("i16x8.max_s"),
+            Instruction::I16x8MaxU => line.write_str("i16x8.max_u"),
+            Instruction::I16x8AvgrU => line.write_str("i16x8.avgr_u"),
+            Instruction::I16x8ExtmulLowI8x16S => line.write_str("i16x8.extmul_low_i8x16_s"),
+            Instruction::I16x8ExtmulHighI8x16S => line.write_str("i16x8.extmul_high_i8x16_s"),
+            Instruction::I16x8ExtmulLowI8x16U => line.write_str("i16x8.extmul_low_i8x16_u"),
+            Instruction::I16x8ExtmulHighI8x16U => line.write_str("i16x8.extmul_high_i8x16_u"),
+            Instruction::I32x4Abs => line.write_str("i32x4.abs"),
+            Instruction::I32x4Neg => line.write_str("i32x4.neg"),
+            Instruction::I32x4AllTrue => line.write_str("i32x4.all_true"),
+            Instruction::I32x4Bitmask => line.write_str("i32x4.bitmask"),
+            Instruction::I32x4ExtendLowI16x8S => line.write_str("i32x4.extend_low_i16x8_s"),
+            Instruction::I32x4ExtendHighI16x8S => line.write_str("i32x4.extend_high_i16x8_s"),
+            Instruction::I32x4ExtendLowI16x8U => line.write_str("i32x4.extend_low_i16x8_u"),
+            Instruction::I32x4ExtendHighI16x8U => line.write_str("i32x4.extend_high_i16x8_u"),
+            Instruction::I32x4Shl => line.write_str("i32x4.shl"),
+            Instruction::I32x4ShrS => line.write_str("i32x4.shr_s"),
+            Instruction::I32x4ShrU => line.write_str("i32x4.shr_u"),
+            Instruction::I32x4Add => line.write_str("i32x4.add"),
+            Instruction::I32x4Sub => line.write_str("i32x4.sub"),
+            Instruction::I32x4Mul => line.write_str("i32x4.mul"),
+            Instruction::I32x4MinS => line.write_str("i32x4.min_s"),
+            Instruction::I32x4MinU => line.write_str("i32x4.min_u"),
+            Instruction::I32x4MaxS => line.write_str("i32x4.max_s"),
+            Instruction::I32x4MaxU => line.write_str("i32x4.max_u"),
+            Instruction::I32x4DotI16x8S => line.write_str("i32x4.dot_i16x8_s"),
+            Instruction::I32x4ExtmulLowI16x8S => line.write_str("i32x4.extmul_low_i16x8_s"),
+            Instruction::I32x4ExtmulHighI16x8S => line.write_str("i32x4.extmul_high_i16x8_s"),
+            Instruction::I32x4ExtmulLowI16x8U => line.write_str("i32x4.extmul_low_i16x8_u"),
+            Instruction::I32x4ExtmulHighI16x8U => line.write_str("i32x4.extmul_high_i16x8_u"),
+            Instruction::I64x2Abs => line.write_str("i64x2.abs"),
+            Instruction::I64x2Neg => line.write_str("i64x2.neg"),
+            Instruction::I64x2AllTrue => line.write_str("i64x2.all_true"),
+            Instruction::I64x2Bitmask => line.write_str("i64x2.bitmask"),
+            Instruction::I64x2ExtendLowI32x4S => line.write_str("i64x2.extend_low_i32x4_s"),
+            Instruction::I64x2ExtendHighI32x4S => line.write_str("i64x2.extend_high_i32x4_s"),
+            Instruction::I64x2ExtendLowI32x4U => line.write_str("i64x2.extend_low_i32x4_u"),
+            Instruction::I64x2ExtendHighI32x4U => line.write_str("i64x2.extend_high_i32x4_u"),
+            Instruction::I64x2Shl => line.write_str("i64x2.shl"),
+            Instruction::I64x2ShrS => line.write_str("i64x2.shr_s"),
+            Instruction::I64x2ShrU => line.write_str("i64x2.shr_u"),
+            Instruction::I64x2Add => line.write_str("i64x2.add"),
+            Instruction::I64x2Sub => line.write_str("i64x2.sub"),
+            Instruction::I64x2Mul => line.write_str("i64x2.mul"),
+            Instruction::I64x2Eq => line.write_str("i64x2.eq"),
+            Instruction::I64x2Ne => line.write_str("i64x2.ne"),
+            Instruction::I64x2LtS => line.write_str("i64x2.lt_s"),
+            Instruction::I64x2GtS => line.write_str("i64x2.gt_s"),
+            Instruction::I64x2LeS => line.write_str("i64x2.le_s"),
+            Instruction::I64x2GeS => line.write_str("i64x2.ge_s"),
+            Instruction::I64x2ExtmulLowI32x4S => line.write_str("i64x2.extmul_low_i32x4_s"),
+            Instruction::I64x2ExtmulHighI32x4S => line.write_str("i64x2.extmul_high_i32x4_s"),
+            Instruction::I64x2ExtmulLowI32x4U => line.write_str("i64x2.extmul_low_i32x4_u"),
+            Instruction::I64x2ExtmulHighI32x4U => line.write_str("i64x2.extmul_high_i32x4_u"),
+            Instruction::F32x4Abs => line.write_str("f32x4.abs"),
+            Instruction::F32x4Neg => line.write_str("f32x4.neg"),
+            Instruction::F32x4Sqrt => line.write_str("f32x4.sqrt"),
+            Instruction::F32x4Add => line.write_str("f32x4.add"),
+            Instruction::F32x4Sub => line.write_str("f32x4.sub"),
+            Instruction::F32x4Mul => line.write_str("f32x4.mul"),
+            Instruction::F32x4Div => line.write_str("f32x4.div"),
+            Instruction::F32x4Min => line.write_str("f32x4.min"),
+            Instruction::F32x4Max => line.write_str("f32x4.max"),
+            Instruction::F32x4Pmin => line.write_str("f32x4.pmin"),
+            Instruction::F32x4Pmax => line.write_str("f32x4.pmax"),
+            Instruction::F64x2Abs => line.write_str("f64x2.abs"),
+            Instruction::F64x2Neg => line.write_str("f64x2.neg"),
+            Instruction::F64x2Sqrt => line.write_str("f64x2.sqrt"),
+            Instruction::F64x2Add => line.write_str("f64x2.add"),
+            Instruction::F64x2Sub => line.write_str("f64x2.sub"),
+            Instruction::F64x2Mul => line.write_str("f64x2.mul"),
+            Instruction::F64x2Div => line.write_str("f64x2.div"),
+            Instruction::F64x2Min => line.write_str("f64x2.min"),
+            Instruction::F64x2Max => line.write_str("f64x2.max"),
+            Instruction::F64x2Pmin => line.write_str("f64x2.pmin"),
+            Instruction::F64x2Pmax => line.write_str("f64x2.pmax"),
+            Instruction::I32x4TruncSatF32x4S => line.write_str("i32x4.trunc_sat_f32x4_s"),
+            Instruction::I32x4TruncSatF32x4U => line.write_str("i32x4.trunc_sat_f32x4_u"),
+            Instruction::F32x4ConvertI32x4S => line.write_str("f32x4.convert_i32x4_s"),
+            Instruction::F32x4ConvertI32x4U => line.write_str("f32x4.convert_i32x4_u"),
+            Instruction::I32x4TruncSatF64x2SZero => line.write_str("i32x4.trunc_sat_f64x2_s_zero"),
+            Instruction::I32x4TruncSatF64x2UZero => line.write_str("i32x4.trunc_sat_f64x2_u_zero"),
+            Instruction::F64x2ConvertLowI32x4S => line.write_str("f64x2.convert_low_i32x4_s"),
+            Instruction::F64x2ConvertLowI32x4U => line.write_str("f64x2.convert_low_i32x4_u"),
+            Instruction::MemoryAtomicNotify { mem_arg } => {
+                self.mem_arg(line, "memory.atomic.notify", mem_arg, 2)
+            }
+            Instruction::MemoryAtomicWait32 { mem_arg } => {
+                self.mem_arg(line, "memory.atomic.wait32", mem_arg, 2)
+            }
+            Instruction::MemoryAtomicWait64 { mem_arg } => {
+                self.mem_arg(line, "memory.atomic.wait64", mem_arg, 3)
+            }
+            Instruction::I32AtomicLoad { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.load", mem_arg, 2)
+            }
+            Instruction::I64AtomicLoad { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.load", mem_arg, 3)
+            }
+            Instruction::I32AtomicLoad8U { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.load8_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicLoad16U { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.load16_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicLoad8U { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.load8_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicLoad16U { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.load16_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicLoad32U { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.load32_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicStore { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.store", mem_arg, 2)
+            }
+            Instruction::I64AtomicStore { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.store", mem_arg, 3)
+            }
+            Instruction::I32AtomicStore8 { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.store8", mem_arg, 0)
+            }
+            Instruction::I32AtomicStore16 { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.store16", mem_arg, 1)
+            }
+            Instruction::I64AtomicStore8 { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.store8", mem_arg, 0)
+            }
+            Instruction::I64AtomicStore16 { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.store16", mem_arg, 1)
+            }
+            Instruction::I64AtomicStore32 { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.store32", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwAdd { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.add", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwAdd { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.add", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8AddU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.add_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16AddU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.add_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8AddU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.add_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16AddU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.add_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32AddU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.add_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwSub { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.sub", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwSub { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.sub", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8SubU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.sub_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16SubU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.sub_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8SubU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.sub_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16SubU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.sub_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32SubU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.sub_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwAnd { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.and", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwAnd { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.and", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8AndU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.and_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16AndU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.and_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8AndU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.and_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16AndU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.and_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32AndU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.and_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwOr { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.or", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwOr { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.or", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8OrU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.or_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16OrU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.or_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8OrU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.or_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16OrU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.or_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32OrU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.or_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwXor { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.xor", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwXor { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.xor", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8XorU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.xor_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16XorU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.xor_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8XorU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.xor_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16XorU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.xor_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32XorU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.xor_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwXchg { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.xchg", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwXchg { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.xchg", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8XchgU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.xchg_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16XchgU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.xchg_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8XchgU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.xchg_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16XchgU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.xchg_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32XchgU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.xchg_u", mem_arg, 2)
+            }
+            Instruction::I32AtomicRmwCmpxchg { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw.cmpxchg", mem_arg, 2)
+            }
+            Instruction::I64AtomicRmwCmpxchg { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw.cmpxchg", mem_arg, 3)
+            }
+            Instruction::I32AtomicRmw8CmpxchgU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw8.cmpxchg_u", mem_arg, 0)
+            }
+            Instruction::I32AtomicRmw16CmpxchgU { mem_arg } => {
+                self.mem_arg(line, "i32.atomic.rmw16.cmpxchg_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw8CmpxchgU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw8.cmpxchg_u", mem_arg, 0)
+            }
+            Instruction::I64AtomicRmw16CmpxchgU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw16.cmpxchg_u", mem_arg, 1)
+            }
+            Instruction::I64AtomicRmw32CmpxchgU { mem_arg } => {
+                self.mem_arg(line, "i64.atomic.rmw32.cmpxchg_u", mem_arg, 2)
+            }
+            Instruction::AtomicFence => line.write_str("atomic.fence"),
+        }
+    }
+
+    pub(crate) fn func_ref(&self, line: &mut String, func_index: u32) -> fmt::Result {
+        match self.funcs.get(&func_index) {
+            Some(id) => write!(line, "${}", id),
+            None => write!(line, "{}", func_index),
+        }
+    }
+
+    fn func(&self, line: &mut String, name: &str, func_index: u32) -> fmt::Result {
+        write!(line, "{} ", name)?;
+        self.func_ref(line, func_index)
+    }
+
+    fn local(&self, line: &mut String, name: &str, local_index: u32) -> fmt::Result {
+        match self.locals.get(&local_index) {
+            Some(id) => write!(line, "{} ${}", name, id),
+            None => write!(line, "{} {}", name, local_index),
+        }
+    }
+
+    /// Writes the memory argument, leaving out the defaults.
+    fn mem_arg(
+        &self,
+        line: &mut String,
+        name: &str,
+        mem_arg: &MemArg,
+        natural_align: u32,
+    ) -> fmt::Result {
+        optional_index(line, name, mem_arg.memory_index)?;
+        if mem_arg.offset != 0 {
+            write!(line, " offset={}", mem_arg.offset)?;
+        }
+        if mem_arg.align != natural_align {
+            match 1u64.checked_shl(mem_arg.align) {
+                Some(align) => write!(line, " align={}", align)?,
+                None => write!(line, " (;align=2**{};)", mem_arg.align)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn block_instruction(line: &mut String, name: &str, block_type: &BlockType) -> fmt::Result {
+    line.write_str(name)?;
+    match block_type {
+        BlockType::Empty => Ok(()),
+        BlockType::Value(results) => write!(line, " (result{})", value_types(results)),
+        BlockType::TypeIndex(type_index) => write!(line, " (type {})", type_index),
+    }
+}
+
+/// Writes a memory or table index, leaving it out if it's the default 0.
+fn optional_index(line: &mut String, name: &str, index: u32) -> fmt::Result {
+    line.write_str(name)?;
+    if index != 0 {
+        write!(line, " {}", index)?;
+    }
+    Ok(())
+}
+
+/// `f32` literal that parses back to the same bits, NaN payloads included.
+struct Float32(f32);
+
+impl fmt::Display for Float32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        float(f, self.0.to_bits() as u64, 23, 8, || {
+            format!("{:?}", self.0)
+        })
+    }
+}
+
+struct Float64(f64);
+
+impl fmt::Display for Float64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        float(f, self.0.to_bits(), 52, 11, || format!("{:?}", self.0))
+    }
+}
+
+/// Writes `inf` and `nan` the text format way, and other values in Rust's
+/// shortest round-tripping notation.
+fn float(
+    f: &mut fmt::Formatter<'_>,
+    bits: u64,
+    mant_bits: u32,
+    exp_bits: u32,
+    finite: impl FnOnce() -> String,
+) -> fmt::Result {
+    let sign = if bits >> (mant_bits + exp_bits) & 1 == 1 {
+        "-"
+    } else {
+        ""
+    };
+    let exponent = bits >> mant_bits & ((1 << exp_bits) - 1);
+    let payload = bits & ((1 << mant_bits) - 1);
+
+    match (exponent == (1 << exp_bits) - 1, payload) {
+        (false, _) => f.write_str(&finite()),
+        (true, 0) => write!(f, "{}inf", sign),
+        (true, payload) if payload == 1 << (mant_bits - 1) => write!(f, "{}nan", sign),
+        (true, payload) => write!(f, "{}nan:{:#x}", sign, payload),
+    }
+}
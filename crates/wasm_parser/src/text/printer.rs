@@ -0,0 +1,772 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::{
+    decoder::{
+        self,
+        const_expr::ConstExpr,
+        instructions::Instruction,
+        module::Module,
+        section::NameSection,
+        types::{
+            Data, DataMode, ElementInit, ElementMode, ExportDesc, GlobalType, ImportDesc, Limits,
+            RefType, TableType, ValueType,
+        },
+        Decoder,
+    },
+    parser,
+};
+
+use super::print_instructions::InstructionPrinter;
+
+/// Width of the `(;@1a2b;)` offset annotations at the start of lines.
+const OFFSET_WIDTH: usize = 12;
+
+/// Renders modules in the text format, with the names from the name section
+/// as identifiers. Instructions are printed in the flat (non-folded) form.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/text/modules.html
+#[derive(Debug, Clone, Default)]
+pub struct WatPrinter {
+    /// Byte offsets of function bodies, by function index.
+    offsets: HashMap<u32, FunctionOffsets>,
+}
+
+#[derive(Debug, Clone)]
+struct FunctionOffsets {
+    body: usize,
+    instructions: Vec<usize>,
+}
+
+impl WatPrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Annotates functions and their instructions with their byte offsets in
+    /// `binary`, which must be the binary the printed module was decoded from.
+    pub fn with_offsets(mut self, binary: &[u8]) -> decoder::Result<Self> {
+        let module = Decoder::new(binary).decode_borrowed()?;
+
+        for body in &module.code_section {
+            let mut iter = body.instructions()?;
+            let mut instructions = vec![];
+
+            loop {
+                let offset = iter.offset();
+                match iter.next() {
+                    Some(instr) => {
+                        instr?;
+                        instructions.push(offset);
+                    }
+                    None => break,
+                }
+            }
+
+            self.offsets.insert(
+                body.func_index,
+                FunctionOffsets {
+                    body: body.offset,
+                    instructions,
+                },
+            );
+        }
+
+        Ok(self)
+    }
+
+    pub fn print(&self, module: &Module) -> String {
+        let names = module.name_section.as_ref();
+        let imported_funcs = module
+            .import_section
+            .iter()
+            .filter(|import| matches!(import.desc, ImportDesc::Func(_)))
+            .count() as u32;
+        let funcs = func_ids(
+            (0..imported_funcs + module.function_section.len() as u32)
+                .map(|index| (index, names.and_then(|names| names.function_name(index)))),
+        );
+        let mut out = Output::new(!self.offsets.is_empty());
+
+        match names.and_then(|names| names.module_name.as_deref()) {
+            Some(name) => out.line(None, format!("(module ${}", id(name))),
+            None => out.line(None, "(module"),
+        }
+        out.indent += 1;
+
+        for (index, func_type) in module.type_section.iter().enumerate() {
+            out.line(
+                None,
+                format!(
+                    "(type (;{};) (func{}))",
+                    index,
+                    signature(&func_type.params, &func_type.results)
+                ),
+            );
+        }
+
+        let mut counts = [0u32; 5];
+        for import in &module.import_section {
+            let (space, desc) = match &import.desc {
+                ImportDesc::Func(type_index) => {
+                    let id = funcs
+                        .get(&counts[0])
+                        .map_or(String::new(), |id| format!(" ${}", id));
+                    (
+                        0,
+                        format!("func{} (;{};) (type {})", id, counts[0], type_index),
+                    )
+                }
+                ImportDesc::Table(table_type) => {
+                    (1, format!("table (;{};) {}", counts[1], table(table_type)))
+                }
+                ImportDesc::Memory(memory) => {
+                    (2, format!("memory (;{};) {}", counts[2], limits(memory)))
+                }
+                ImportDesc::Global(global_type) => (
+                    3,
+                    format!("global (;{};) {}", counts[3], global(global_type)),
+                ),
+                ImportDesc::Tag(tag_type) => (
+                    4,
+                    format!("tag (;{};) (type {})", counts[4], tag_type.type_index),
+                ),
+            };
+            counts[space] += 1;
+
+            out.line(
+                None,
+                format!(
+                    "(import {} {} ({}))",
+                    string(import.module.as_bytes()),
+                    string(import.field.as_bytes()),
+                    desc
+                ),
+            );
+        }
+        let [_, imported_tables, imported_memories, imported_globals, imported_tags] = counts;
+
+        for (code_index, (type_index, code)) in module
+            .function_section
+            .iter()
+            .zip(&module.code_section)
+            .enumerate()
+        {
+            let func_index = imported_funcs + code_index as u32;
+            let Some(func_type) = module.type_section.get(*type_index as usize) else {
+                out.line(None, format!(";; function {} has unknown type", func_index));
+                continue;
+            };
+            let locals = local_ids(names, func_index);
+
+            let mut header = String::from("(func");
+            if let Some(id) = funcs.get(&func_index) {
+                write!(header, " ${}", id).unwrap();
+            }
+            write!(header, " (;{};) (type {})", func_index, type_index).unwrap();
+            locals_decl(&mut header, "param", &func_type.params, 0, &locals);
+            if !func_type.results.is_empty() {
+                write!(header, " (result{})", value_types(&func_type.results)).unwrap();
+            }
+
+            function(
+                &mut out,
+                header,
+                self.offsets.get(&func_index),
+                func_type.params.len(),
+                &code.locals,
+                &code.code,
+                InstructionPrinter {
+                    funcs: &funcs,
+                    locals,
+                },
+            );
+        }
+
+        for (index, table_type) in module.table_section.iter().enumerate() {
+            let index = imported_tables as usize + index;
+            out.line(None, format!("(table (;{};) {})", index, table(table_type)));
+        }
+        for (index, memory_type) in module.memory_section.iter().enumerate() {
+            let index = imported_memories as usize + index;
+            out.line(
+                None,
+                format!("(memory (;{};) {})", index, limits(&memory_type.limits)),
+            );
+        }
+        for (index, tag_type) in module.tag_section.iter().enumerate() {
+            let index = imported_tags as usize + index;
+            out.line(
+                None,
+                format!("(tag (;{};) (type {}))", index, tag_type.type_index),
+            );
+        }
+
+        let printer = InstructionPrinter {
+            funcs: &funcs,
+            locals: HashMap::new(),
+        };
+        for (index, global_) in module.global_section.iter().enumerate() {
+            let index = imported_globals as usize + index;
+            out.line(
+                None,
+                format!(
+                    "(global (;{};) {} {})",
+                    index,
+                    global(&global_.global_type),
+                    init_expr(&printer, &global_.init_expr)
+                ),
+            );
+        }
+
+        for export in &module.export_section {
+            let mut desc = String::new();
+            match export.desc {
+                ExportDesc::Func(index) => {
+                    desc.push_str("func ");
+                    printer.func_ref(&mut desc, index).unwrap();
+                }
+                ExportDesc::Table(index) => write!(desc, "table {}", index).unwrap(),
+                ExportDesc::Mem(index) => write!(desc, "memory {}", index).unwrap(),
+                ExportDesc::Global(index) => write!(desc, "global {}", index).unwrap(),
+                ExportDesc::Tag(index) => write!(desc, "tag {}", index).unwrap(),
+            }
+            out.line(
+                None,
+                format!("(export {} ({}))", string(export.name.as_bytes()), desc),
+            );
+        }
+
+        if let Some(func_index) = module.start_section {
+            let mut line = String::from("(start ");
+            printer.func_ref(&mut line, func_index).unwrap();
+            line.push(')');
+            out.line(None, line);
+        }
+
+        for (index, element) in module.element_section.iter().enumerate() {
+            let mut line = format!("(elem (;{};)", index);
+            match &element.mode {
+                ElementMode::Passive => {}
+                ElementMode::Active {
+                    table_index,
+                    offset,
+                } => write!(
+                    line,
+                    " (table {}) {}",
+                    table_index,
+                    offset_expr(&printer, offset)
+                )
+                .unwrap(),
+                ElementMode::Declarative => line.push_str(" declare"),
+            }
+            match &element.init {
+                ElementInit::Functions(func_indices) => {
+                    line.push_str(" func");
+                    for func_index in func_indices {
+                        line.push(' ');
+                        printer.func_ref(&mut line, *func_index).unwrap();
+                    }
+                }
+                ElementInit::Expressions(exprs) => {
+                    write!(line, " {}", ref_type(element.ref_type)).unwrap();
+                    for expr in exprs {
+                        write!(line, " (item {})", const_expr(&printer, expr)).unwrap();
+                    }
+                }
+            }
+            line.push(')');
+            out.line(None, line);
+        }
+
+        for (index, data) in module.data_section.iter().enumerate() {
+            out.line(None, data_segment(&printer, index, data));
+        }
+
+        for custom in &module.custom_section {
+            out.line(
+                None,
+                format!(
+                    ";; custom section {} ({} bytes)",
+                    string(custom.name.as_bytes()),
+                    custom.bytes.len()
+                ),
+            );
+        }
+
+        out.indent -= 1;
+        out.line(None, ")");
+        out.text
+    }
+
+    /// Prints the module as the compiler sees it: imports, the functions with
+    /// their export names, tables, memories, globals and data segments.
+    pub fn print_parsed(&self, module: &parser::module::Module) -> String {
+        let funcs = func_ids(
+            module
+                .functions
+                .iter()
+                .map(|func| (func.index as u32, func.name.as_deref())),
+        );
+        let printer = InstructionPrinter {
+            funcs: &funcs,
+            locals: HashMap::new(),
+        };
+        let mut out = Output::new(!self.offsets.is_empty());
+
+        out.line(None, "(module");
+        out.indent += 1;
+
+        for (index, func_type) in module.types.iter().enumerate() {
+            out.line(
+                None,
+                format!(
+                    "(type (;{};) (func{}))",
+                    index,
+                    signature(&func_type.params, &func_type.results)
+                ),
+            );
+        }
+
+        let mut imported_funcs = 0;
+        for import in &module.imports {
+            let desc = match &import.desc {
+                ImportDesc::Func(type_index) => {
+                    imported_funcs += 1;
+                    format!("func (;{};) (type {})", imported_funcs - 1, type_index)
+                }
+                ImportDesc::Table(table_type) => format!("table {}", table(table_type)),
+                ImportDesc::Memory(memory) => format!("memory {}", limits(memory)),
+                ImportDesc::Global(global_type) => format!("global {}", global(global_type)),
+                ImportDesc::Tag(tag_type) => format!("tag (type {})", tag_type.type_index),
+            };
+            out.line(
+                None,
+                format!(
+                    "(import {} {} ({}))",
+                    string(import.module.as_bytes()),
+                    string(import.field.as_bytes()),
+                    desc
+                ),
+            );
+        }
+
+        for func in &module.functions {
+            let func_index = func.index as u32;
+            let mut header = String::from("(func");
+            if let Some(id) = funcs.get(&func_index) {
+                write!(header, " ${}", id).unwrap();
+            }
+            write!(header, " (;{};)", func_index).unwrap();
+            if let Some(export_name) = &func.export_name {
+                write!(header, " (export {})", string(export_name.as_bytes())).unwrap();
+            }
+            write!(header, "{}", signature(&func.params, &func.results)).unwrap();
+
+            function(
+                &mut out,
+                header,
+                self.offsets.get(&func_index),
+                func.params.len(),
+                &func.locals,
+                func.raw_body.as_deref().unwrap_or_default(),
+                InstructionPrinter {
+                    funcs: &funcs,
+                    locals: HashMap::new(),
+                },
+            );
+        }
+
+        let imported = |is_kind: fn(&ImportDesc) -> bool| {
+            module
+                .imports
+                .iter()
+                .filter(|import| is_kind(&import.desc))
+                .count()
+        };
+        let imported_tables = imported(|desc| matches!(desc, ImportDesc::Table(_)));
+        let imported_memories = imported(|desc| matches!(desc, ImportDesc::Memory(_)));
+        let imported_globals = imported(|desc| matches!(desc, ImportDesc::Global(_)));
+
+        for (index, table_type) in module.tables.iter().enumerate() {
+            let index = imported_tables + index;
+            out.line(None, format!("(table (;{};) {})", index, table(table_type)));
+        }
+        for (index, memory_type) in module.memories.iter().enumerate() {
+            let index = imported_memories + index;
+            out.line(
+                None,
+                format!("(memory (;{};) {})", index, limits(&memory_type.limits)),
+            );
+        }
+        for (index, global_) in module.globals.iter().enumerate() {
+            out.line(
+                None,
+                format!(
+                    "(global (;{};) {} {})",
+                    imported_globals + index,
+                    global(&global_.global_type),
+                    init_expr(&printer, &global_.init_expr)
+                ),
+            );
+        }
+
+        for (index, data) in module.datas.iter().enumerate() {
+            out.line(None, data_segment(&printer, index, data));
+        }
+
+        out.indent -= 1;
+        out.line(None, ")");
+        out.text
+    }
+}
+
+/// Prints a function from its `(func ...` header on, closing it with the
+/// body's final `end`.
+fn function(
+    out: &mut Output,
+    header: String,
+    offsets: Option<&FunctionOffsets>,
+    param_count: usize,
+    locals: &[ValueType],
+    code: &[Instruction],
+    printer: InstructionPrinter,
+) {
+    out.line(offsets.map(|offsets| offsets.body), header);
+    out.indent += 1;
+
+    if !locals.is_empty() {
+        let mut line = String::new();
+        locals_decl(&mut line, "local", locals, param_count, &printer.locals);
+        out.line(None, line.trim_start());
+    }
+
+    let base = out.indent;
+    for (index, instr) in code.iter().enumerate() {
+        let offset = offsets.and_then(|offsets| offsets.instructions.get(index).copied());
+
+        if matches!(instr, Instruction::Else | Instruction::End) {
+            out.indent = (out.indent - 1).max(base - 1);
+        }
+        if out.indent < base {
+            // The `end` of the function body.
+            out.line(offset, ")");
+            out.indent = base;
+            continue;
+        }
+
+        let mut line = String::new();
+        printer.instruction(&mut line, instr).unwrap();
+        out.line(offset, line);
+
+        if matches!(
+            instr,
+            Instruction::Block { .. }
+                | Instruction::Loop { .. }
+                | Instruction::If { .. }
+                | Instruction::Else
+                | Instruction::TryTable { .. }
+        ) {
+            out.indent += 1;
+        }
+    }
+
+    if code.last() != Some(&Instruction::End) {
+        out.indent = base - 1;
+        out.line(None, ")");
+    }
+    out.indent = base - 1;
+}
+
+struct Output {
+    text: String,
+    annotate: bool,
+    indent: usize,
+}
+
+impl Output {
+    fn new(annotate: bool) -> Self {
+        Output {
+            text: String::new(),
+            annotate,
+            indent: 0,
+        }
+    }
+
+    fn line(&mut self, offset: Option<usize>, line: impl AsRef<str>) {
+        if self.annotate {
+            match offset {
+                Some(offset) => {
+                    let annotation = format!("(;@{:x};)", offset);
+                    write!(self.text, "{:<width$}", annotation, width = OFFSET_WIDTH).unwrap();
+                }
+                None => write!(self.text, "{:width$}", "", width = OFFSET_WIDTH).unwrap(),
+            }
+        }
+
+        for _ in 0..self.indent {
+            self.text.push_str("  ");
+        }
+        self.text.push_str(line.as_ref());
+        self.text.push('\n');
+    }
+}
+
+/// Turns a name into an identifier, replacing the characters identifiers
+/// can't contain.
+fn id(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '0'..='9' | 'a'..='z' | 'A'..='Z' => c,
+            c if "!#$%&'*+-./:<=>?@\\^_`|~".contains(c) => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Makes unique identifiers from the names of functions, suffixing
+/// duplicates with their index.
+fn func_ids<'n>(names: impl Iterator<Item = (u32, Option<&'n str>)>) -> HashMap<u32, String> {
+    unique_ids(names.filter_map(|(index, name)| Some((index, name?))))
+}
+
+fn local_ids(names: Option<&NameSection>, func_index: u32) -> HashMap<u32, String> {
+    let locals = names.and_then(|names| names.local_names.get(&func_index));
+
+    unique_ids(
+        locals
+            .into_iter()
+            .flatten()
+            .map(|(index, name)| (*index, name.as_str())),
+    )
+}
+
+fn unique_ids<'n>(names: impl Iterator<Item = (u32, &'n str)>) -> HashMap<u32, String> {
+    let mut used = HashSet::new();
+    let mut ids = HashMap::new();
+
+    for (index, name) in names {
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut id = id(name);
+        while used.contains(&id) {
+            id = format!("{}.{}", id, index);
+        }
+        used.insert(id.clone());
+        ids.insert(index, id);
+    }
+
+    ids
+}
+
+/// Writes `(param ...)` or `(local ...)` declarations, one per named local
+/// and grouped otherwise.
+fn locals_decl(
+    out: &mut String,
+    keyword: &str,
+    types: &[ValueType],
+    first_index: usize,
+    names: &HashMap<u32, String>,
+) {
+    let mut group = vec![];
+
+    for (index, value_type) in types.iter().enumerate() {
+        match names.get(&((first_index + index) as u32)) {
+            Some(id) => {
+                if !group.is_empty() {
+                    write!(out, " ({}{})", keyword, value_types(&group)).unwrap();
+                    group.clear();
+                }
+                write!(
+                    out,
+                    " ({} ${} {})",
+                    keyword,
+                    id,
+                    value_type_name(value_type)
+                )
+                .unwrap();
+            }
+            None => group.push(value_type.clone()),
+        }
+    }
+
+    if !group.is_empty() {
+        write!(out, " ({}{})", keyword, value_types(&group)).unwrap();
+    }
+}
+
+fn signature(params: &[ValueType], results: &[ValueType]) -> String {
+    let mut signature = String::new();
+    if !params.is_empty() {
+        write!(signature, " (param{})", value_types(params)).unwrap();
+    }
+    if !results.is_empty() {
+        write!(signature, " (result{})", value_types(results)).unwrap();
+    }
+
+    signature
+}
+
+fn limits(limits: &Limits) -> String {
+    let mut text = String::new();
+    if limits.index64 {
+        text.push_str("i64 ");
+    }
+    write!(text, "{}", limits.min).unwrap();
+    if let Some(max) = limits.max {
+        write!(text, " {}", max).unwrap();
+    }
+    if limits.shared {
+        text.push_str(" shared");
+    }
+
+    text
+}
+
+fn table(table_type: &TableType) -> String {
+    format!(
+        "{} {}",
+        limits(&table_type.limits),
+        ref_type(table_type.element_type)
+    )
+}
+
+fn global(global_type: &GlobalType) -> String {
+    match global_type.mutable {
+        true => format!("(mut {})", value_type_name(&global_type.value_type)),
+        false => value_type_name(&global_type.value_type).to_owned(),
+    }
+}
+
+fn const_expr(printer: &InstructionPrinter, expr: &ConstExpr) -> String {
+    let mut text = String::new();
+    for instr in &expr.instructions {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        printer.instruction(&mut text, instr).unwrap();
+    }
+
+    text
+}
+
+/// Global initializer, folded when it's a single instruction.
+fn init_expr(printer: &InstructionPrinter, expr: &ConstExpr) -> String {
+    match expr.instructions.len() {
+        1 => format!("({})", const_expr(printer, expr)),
+        _ => const_expr(printer, expr),
+    }
+}
+
+/// Segment offset, folded when it's a single instruction.
+fn offset_expr(printer: &InstructionPrinter, expr: &ConstExpr) -> String {
+    match expr.instructions.len() {
+        1 => format!("({})", const_expr(printer, expr)),
+        _ => format!("(offset {})", const_expr(printer, expr)),
+    }
+}
+
+/// Prints a `(data ...)` segment with the data index `index`.
+fn data_segment(printer: &InstructionPrinter, index: usize, data: &Data) -> String {
+    let mut line = format!("(data (;{};)", index);
+    if let DataMode::Active {
+        memory_index,
+        offset,
+    } = &data.mode
+    {
+        write!(
+            line,
+            " (memory {}) {}",
+            memory_index,
+            offset_expr(printer, offset)
+        )
+        .unwrap();
+    }
+    write!(line, " {})", string(&data.bytes)).unwrap();
+    line
+}
+
+/// Writes a string literal, escaping quotes, backslashes, control characters
+/// and bytes that aren't valid UTF-8.
+fn string(bytes: &[u8]) -> String {
+    let mut text = String::from("\"");
+
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => text.push_str("\\\""),
+                '\\' => text.push_str("\\\\"),
+                '\t' => text.push_str("\\t"),
+                '\n' => text.push_str("\\n"),
+                c if c.is_control() => {
+                    let mut utf8 = [0; 4];
+                    for byte in c.encode_utf8(&mut utf8).bytes() {
+                        write!(text, "\\{:02x}", byte).unwrap();
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            write!(text, "\\{:02x}", byte).unwrap();
+        }
+    }
+    text.push('"');
+
+    text
+}
+
+/// Prints a single instruction, with numeric indices. Blocks print as their
+/// opening instruction only.
+pub fn print_instruction(instr: &Instruction) -> String {
+    let funcs = HashMap::new();
+    let printer = InstructionPrinter {
+        funcs: &funcs,
+        locals: HashMap::new(),
+    };
+    let mut line = String::new();
+    printer.instruction(&mut line, instr).unwrap();
+
+    line
+}
+
+pub fn value_type_name(value_type: &ValueType) -> &'static str {
+    match value_type {
+        ValueType::I32 => "i32",
+        ValueType::I64 => "i64",
+        ValueType::F32 => "f32",
+        ValueType::F64 => "f64",
+        ValueType::V128 => "v128",
+        ValueType::FuncRef => "funcref",
+        ValueType::ExternRef => "externref",
+        ValueType::ExnRef => "exnref",
+    }
+}
+
+/// Space-separated value types, each with a leading space.
+pub(crate) fn value_types(value_types: &[ValueType]) -> String {
+    value_types
+        .iter()
+        .map(|value_type| format!(" {}", value_type_name(value_type)))
+        .collect()
+}
+
+fn ref_type(ref_type: RefType) -> &'static str {
+    match ref_type {
+        RefType::FuncRef => "funcref",
+        RefType::ExternRef => "externref",
+        RefType::ExnRef => "exnref",
+    }
+}
+
+pub(crate) fn heap_type(ref_type: RefType) -> &'static str {
+    match ref_type {
+        RefType::FuncRef => "func",
+        RefType::ExternRef => "extern",
+        RefType::ExnRef => "exn",
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+/// Error returned by the text format parser, together with where in the
+/// source it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextError {
+    /// Byte offset in the source text.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, in characters.
+    pub column: usize,
+    pub kind: TextErrorKind,
+}
+
+impl TextError {
+    pub(crate) fn new(source: &str, offset: usize, kind: TextErrorKind) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        TextError {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for TextError {}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TextErrorKind {
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("unexpected character {0:?}")]
+    UnexpectedChar(char),
+    #[error("unterminated string")]
+    UnterminatedString,
+    #[error("unterminated block comment")]
+    UnterminatedComment,
+    #[error("invalid escape sequence in string")]
+    InvalidEscape,
+    #[error("invalid UTF-8 in name")]
+    InvalidUtf8Name,
+    #[error("expected {0}")]
+    Expected(String),
+
+    #[error("unknown module field `{0}`")]
+    UnknownField(String),
+    #[error("unknown instruction `{0}`")]
+    UnknownInstruction(String),
+    #[error("unknown {space} ${name}")]
+    UnknownName { space: &'static str, name: String },
+    #[error("unknown {space} {index}")]
+    UnknownIndex { space: &'static str, index: u32 },
+    #[error("duplicate {space} ${name}")]
+    DuplicateName { space: &'static str, name: String },
+    #[error("mismatched label ${0}")]
+    MismatchedLabel(String),
+
+    #[error("invalid number `{0}`")]
+    InvalidNumber(String),
+    #[error("alignment must be a power of two")]
+    InvalidAlignment,
+    #[error("imports must come before function, table, memory, global and tag definitions")]
+    ImportAfterDefinition,
+    #[error("inline function type doesn't match type {0}")]
+    TypeUseMismatch(u32),
+}
+
+pub type Result<T> = std::result::Result<T, TextError>;
@@ -0,0 +1,616 @@
+use crate::decoder::{
+    instructions::{BlockType, Catch, Instruction},
+    types::{RefType, ValueType},
+};
+
+use super::{
+    operators::{lane, memory_access, operator_signature},
+    validator::{addr_type, ref_value_type, Context, KindResult},
+    ValidationErrorKind,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+    TryTable,
+}
+
+/// Entry of the control stack.
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    params: Vec<ValueType>,
+    results: Vec<ValueType>,
+    /// Height of the operand stack when the block was entered.
+    height: usize,
+    /// Whether the rest of the block is unreachable, which makes the operand
+    /// stack polymorphic.
+    unreachable: bool,
+}
+
+/// Validates instructions one at a time, following the validation algorithm
+/// from the appendix of the specification.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/appendix/algorithm.html
+pub(crate) struct FuncValidator<'c, 'm> {
+    context: &'c Context<'m>,
+    locals: Vec<ValueType>,
+    results: Vec<ValueType>,
+    /// Operand stack; `None` is a value of unknown type, popped from the
+    /// polymorphic stack of unreachable code.
+    operands: Vec<Option<ValueType>>,
+    frames: Vec<Frame>,
+}
+
+impl<'c, 'm> FuncValidator<'c, 'm> {
+    /// `locals` includes the parameters.
+    pub(crate) fn new(
+        context: &'c Context<'m>,
+        locals: Vec<ValueType>,
+        results: Vec<ValueType>,
+    ) -> Self {
+        let mut validator = FuncValidator {
+            context,
+            locals,
+            results: results.clone(),
+            operands: vec![],
+            frames: vec![],
+        };
+        validator.push_frame(FrameKind::Function, vec![], results);
+
+        validator
+    }
+
+    /// Whether the `end` of the function has been reached.
+    pub(crate) fn is_done(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Checks the implicit `end` of a constant expression.
+    pub(crate) fn end(&mut self) -> KindResult<()> {
+        self.pop_frame().map(drop)
+    }
+
+    fn push(&mut self, value_type: ValueType) {
+        self.operands.push(Some(value_type));
+    }
+
+    fn push_all(&mut self, value_types: &[ValueType]) {
+        self.operands.extend(value_types.iter().cloned().map(Some));
+    }
+
+    fn pop_any(&mut self) -> KindResult<Option<ValueType>> {
+        let frame = self.frames.last().unwrap();
+
+        if self.operands.len() == frame.height {
+            return match frame.unreachable {
+                true => Ok(None),
+                false => Err(ValidationErrorKind::EmptyOperandStack),
+            };
+        }
+
+        Ok(self.operands.pop().unwrap())
+    }
+
+    fn pop(&mut self, expected: &ValueType) -> KindResult<Option<ValueType>> {
+        match self.pop_any()? {
+            Some(actual) if actual != *expected => Err(ValidationErrorKind::TypeMismatch {
+                expected: expected.clone(),
+                actual,
+            }),
+            actual => Ok(actual),
+        }
+    }
+
+    /// Pops values of the given types, returning them in stack order.
+    fn pop_all(&mut self, value_types: &[ValueType]) -> KindResult<Vec<Option<ValueType>>> {
+        let mut values = value_types
+            .iter()
+            .rev()
+            .map(|value_type| self.pop(value_type))
+            .collect::<KindResult<Vec<_>>>()?;
+        values.reverse();
+
+        Ok(values)
+    }
+
+    fn push_frame(&mut self, kind: FrameKind, params: Vec<ValueType>, results: Vec<ValueType>) {
+        let height = self.operands.len();
+        self.push_all(&params);
+        self.frames.push(Frame {
+            kind,
+            params,
+            results,
+            height,
+            unreachable: false,
+        });
+    }
+
+    fn pop_frame(&mut self) -> KindResult<Frame> {
+        let results = self.frames.last().unwrap().results.clone();
+        self.pop_all(&results)?;
+
+        let frame = self.frames.pop().unwrap();
+        if self.operands.len() != frame.height {
+            return Err(ValidationErrorKind::UnexpectedOperands(
+                self.operands.len() - frame.height,
+            ));
+        }
+
+        Ok(frame)
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    /// Types of the values a branch to the label passes.
+    fn label_types(&self, level: u32) -> KindResult<Vec<ValueType>> {
+        let frame = self
+            .frames
+            .len()
+            .checked_sub(level as usize + 1)
+            .map(|index| &self.frames[index])
+            .ok_or(ValidationErrorKind::Unknown {
+                space: "label",
+                index: level,
+            })?;
+
+        Ok(match frame.kind {
+            FrameKind::Loop => frame.params.clone(),
+            _ => frame.results.clone(),
+        })
+    }
+
+    fn block_type(&self, block_type: &BlockType) -> KindResult<(Vec<ValueType>, Vec<ValueType>)> {
+        let func_type = block_type.func_type(self.context.types).ok_or_else(|| {
+            let BlockType::TypeIndex(index) = block_type else {
+                unreachable!()
+            };
+            ValidationErrorKind::Unknown {
+                space: "type",
+                index: *index,
+            }
+        })?;
+
+        Ok((func_type.params, func_type.results))
+    }
+
+    /// Enters a block, popping its parameters.
+    fn block(&mut self, kind: FrameKind, block_type: &BlockType) -> KindResult<()> {
+        let (params, results) = self.block_type(block_type)?;
+        self.pop_all(&params)?;
+        self.push_frame(kind, params, results);
+
+        Ok(())
+    }
+
+    fn local(&self, local_index: u32) -> KindResult<ValueType> {
+        self.locals
+            .get(local_index as usize)
+            .cloned()
+            .ok_or(ValidationErrorKind::Unknown {
+                space: "local",
+                index: local_index,
+            })
+    }
+
+    /// Pops the arguments of a call and pushes its results.
+    fn call(&mut self, params: &[ValueType], results: &[ValueType]) -> KindResult<()> {
+        self.pop_all(params)?;
+        self.push_all(results);
+
+        Ok(())
+    }
+
+    fn return_call(&mut self, params: &[ValueType], results: &[ValueType]) -> KindResult<()> {
+        if results != self.results {
+            return Err(ValidationErrorKind::ReturnCallMismatch);
+        }
+        self.pop_all(params)?;
+        self.set_unreachable();
+
+        Ok(())
+    }
+
+    /// Pops the table index operand of `call_indirect`.
+    fn call_indirect_table(&mut self, table_index: u32) -> KindResult<()> {
+        let table = self.context.table(table_index)?;
+        if table.element_type != RefType::FuncRef {
+            return Err(ValidationErrorKind::TableNotFuncRef(table_index));
+        }
+        self.pop(&addr_type(&table.limits))?;
+
+        Ok(())
+    }
+
+    fn table_addr_type(&self, table_index: u32) -> KindResult<ValueType> {
+        Ok(addr_type(&self.context.table(table_index)?.limits))
+    }
+
+    fn memory_addr_type(&self, memory_index: u32) -> KindResult<ValueType> {
+        Ok(addr_type(self.context.memory(memory_index)?))
+    }
+
+    fn data(&self, data_index: u32) -> KindResult<()> {
+        let data_count = self
+            .context
+            .data_count
+            .ok_or(ValidationErrorKind::DataCountRequired)?;
+
+        match data_index < data_count {
+            true => Ok(()),
+            false => Err(ValidationErrorKind::Unknown {
+                space: "data segment",
+                index: data_index,
+            }),
+        }
+    }
+
+    fn catch(&self, catch: &Catch) -> KindResult<()> {
+        let (label, types) = match catch {
+            Catch::Catch { tag_index, label } => {
+                (label, self.context.tag(*tag_index)?.params.clone())
+            }
+            Catch::CatchRef { tag_index, label } => {
+                let mut types = self.context.tag(*tag_index)?.params.clone();
+                types.push(ValueType::ExnRef);
+                (label, types)
+            }
+            Catch::CatchAll { label } => (label, vec![]),
+            Catch::CatchAllRef { label } => (label, vec![ValueType::ExnRef]),
+        };
+
+        match self.label_types(*label)? == types {
+            true => Ok(()),
+            false => Err(ValidationErrorKind::CatchMismatch(*label)),
+        }
+    }
+
+    pub(crate) fn instruction(&mut self, instr: &Instruction) -> KindResult<()> {
+        if let Some((lane, count)) = lane(instr) {
+            if lane >= count {
+                return Err(ValidationErrorKind::InvalidLane { lane, count });
+            }
+        }
+
+        if let Some((params, results)) = operator_signature(instr) {
+            self.pop_all(params)?;
+            self.push_all(results);
+
+            return Ok(());
+        }
+
+        if let Some(access) = memory_access(instr) {
+            let mem_arg = access.mem_arg;
+            let addr_type = self.memory_addr_type(mem_arg.memory_index)?;
+
+            if access.atomic && mem_arg.align != access.natural_align {
+                return Err(ValidationErrorKind::UnalignedAtomic {
+                    align: mem_arg.align,
+                    natural: access.natural_align,
+                });
+            }
+            if mem_arg.align > access.natural_align {
+                return Err(ValidationErrorKind::InvalidAlignment {
+                    align: mem_arg.align,
+                    natural: access.natural_align,
+                });
+            }
+
+            let (params, results) = access.signature;
+            self.pop_all(params)?;
+            self.pop(&addr_type)?;
+            self.push_all(results);
+
+            return Ok(());
+        }
+
+        match instr {
+            Instruction::Unreachable => self.set_unreachable(),
+            Instruction::Nop | Instruction::AtomicFence => {}
+            Instruction::Block { block } => self.block(FrameKind::Block, &block.block_type)?,
+            Instruction::Loop { block } => self.block(FrameKind::Loop, &block.block_type)?,
+            Instruction::If { block } => {
+                self.pop(&ValueType::I32)?;
+                self.block(FrameKind::If, &block.block_type)?;
+            }
+            Instruction::TryTable { block, catches } => {
+                for catch in catches {
+                    self.catch(catch)?;
+                }
+                self.block(FrameKind::TryTable, &block.block_type)?;
+            }
+            Instruction::Else => {
+                if self.frames.last().unwrap().kind != FrameKind::If {
+                    return Err(ValidationErrorKind::ElseWithoutIf);
+                }
+                let frame = self.pop_frame()?;
+                self.push_frame(FrameKind::Else, frame.params, frame.results);
+            }
+            Instruction::End => {
+                let frame = self.pop_frame()?;
+                if frame.kind == FrameKind::If && frame.params != frame.results {
+                    return Err(ValidationErrorKind::IfWithoutElse);
+                }
+                if frame.kind != FrameKind::Function {
+                    self.push_all(&frame.results);
+                }
+            }
+            Instruction::Br { level } => {
+                let types = self.label_types(*level)?;
+                self.pop_all(&types)?;
+                self.set_unreachable();
+            }
+            Instruction::BrIf { level } => {
+                self.pop(&ValueType::I32)?;
+                let types = self.label_types(*level)?;
+                self.pop_all(&types)?;
+                self.push_all(&types);
+            }
+            Instruction::BrTable {
+                label_indexes,
+                default_index,
+            } => {
+                self.pop(&ValueType::I32)?;
+                let default_types = self.label_types(*default_index)?;
+
+                for level in label_indexes {
+                    let types = self.label_types(*level)?;
+                    if types.len() != default_types.len() {
+                        return Err(ValidationErrorKind::BrTableArityMismatch);
+                    }
+
+                    let values = self.pop_all(&types)?;
+                    self.operands.extend(values);
+                }
+                self.pop_all(&default_types)?;
+                self.set_unreachable();
+            }
+            Instruction::Return => {
+                self.pop_all(&self.results.clone())?;
+                self.set_unreachable();
+            }
+            Instruction::Call { func_index } => {
+                let func_type = self.context.func_type(*func_index)?;
+                self.call(&func_type.params, &func_type.results)?;
+            }
+            Instruction::CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                self.call_indirect_table(*table_index)?;
+                let func_type = self.context.ty(*type_index)?;
+                self.call(&func_type.params, &func_type.results)?;
+            }
+            Instruction::ReturnCall { func_index } => {
+                let func_type = self.context.func_type(*func_index)?;
+                self.return_call(&func_type.params, &func_type.results)?;
+            }
+            Instruction::ReturnCallIndirect {
+                type_index,
+                table_index,
+            } => {
+                self.call_indirect_table(*table_index)?;
+                let func_type = self.context.ty(*type_index)?;
+                self.return_call(&func_type.params, &func_type.results)?;
+            }
+            Instruction::Throw { tag_index } => {
+                let params = self.context.tag(*tag_index)?.params.clone();
+                self.pop_all(&params)?;
+                self.set_unreachable();
+            }
+            Instruction::ThrowRef => {
+                self.pop(&ValueType::ExnRef)?;
+                self.set_unreachable();
+            }
+            Instruction::RefNull { ref_type } => self.push(ref_value_type(*ref_type)),
+            Instruction::RefIsNull => {
+                if let Some(value_type) = self.pop_any()? {
+                    if !is_reference(&value_type) {
+                        return Err(ValidationErrorKind::ExpectedReference(value_type));
+                    }
+                }
+                self.push(ValueType::I32);
+            }
+            Instruction::RefFunc { func_index } => {
+                self.context.func_type(*func_index)?;
+                if !self.context.refs.contains(func_index) {
+                    return Err(ValidationErrorKind::UndeclaredFunctionReference(
+                        *func_index,
+                    ));
+                }
+                self.push(ValueType::FuncRef);
+            }
+            Instruction::Drop => {
+                self.pop_any()?;
+            }
+            Instruction::Select { result_types: None } => {
+                self.pop(&ValueType::I32)?;
+                let first = self.pop_any()?;
+                let second = self.pop_any()?;
+
+                for value_type in first.iter().chain(&second) {
+                    if is_reference(value_type) {
+                        return Err(ValidationErrorKind::ExpectedNumericOperand(
+                            value_type.clone(),
+                        ));
+                    }
+                }
+                if let (Some(expected), Some(actual)) = (&first, &second) {
+                    if expected != actual {
+                        return Err(ValidationErrorKind::TypeMismatch {
+                            expected: expected.clone(),
+                            actual: actual.clone(),
+                        });
+                    }
+                }
+                self.operands.push(first.or(second));
+            }
+            Instruction::Select {
+                result_types: Some(result_types),
+            } => {
+                let [value_type] = result_types.as_slice() else {
+                    return Err(ValidationErrorKind::InvalidSelectArity(result_types.len()));
+                };
+                self.select(value_type)?;
+            }
+            Instruction::SelectResult { result_type } => self.select(result_type)?,
+            Instruction::LocalGet { local_index } => {
+                let value_type = self.local(*local_index)?;
+                self.push(value_type);
+            }
+            Instruction::LocalSet { local_index } => {
+                let value_type = self.local(*local_index)?;
+                self.pop(&value_type)?;
+            }
+            Instruction::LocalTee { local_index } => {
+                let value_type = self.local(*local_index)?;
+                self.pop(&value_type)?;
+                self.push(value_type);
+            }
+            Instruction::GlobalGet { global_index } => {
+                let global = self.context.global(*global_index)?;
+                self.push(global.value_type.clone());
+            }
+            Instruction::GlobalSet { global_index } => {
+                let global = self.context.global(*global_index)?;
+                if !global.mutable {
+                    return Err(ValidationErrorKind::ImmutableGlobal(*global_index));
+                }
+                self.pop(&global.value_type)?;
+            }
+            Instruction::TableGet { table_index } => {
+                let table = self.context.table(*table_index)?;
+                self.pop(&addr_type(&table.limits))?;
+                self.push(ref_value_type(table.element_type));
+            }
+            Instruction::TableSet { table_index } => {
+                let table = self.context.table(*table_index)?;
+                self.pop(&ref_value_type(table.element_type))?;
+                self.pop(&addr_type(&table.limits))?;
+            }
+            Instruction::TableSize { table_index } => {
+                let addr_type = self.table_addr_type(*table_index)?;
+                self.push(addr_type);
+            }
+            Instruction::TableGrow { table_index } => {
+                let table = self.context.table(*table_index)?;
+                let addr_type = addr_type(&table.limits);
+                self.pop(&addr_type)?;
+                self.pop(&ref_value_type(table.element_type))?;
+                self.push(addr_type);
+            }
+            Instruction::TableFill { table_index } => {
+                let table = self.context.table(*table_index)?;
+                let addr_type = addr_type(&table.limits);
+                self.pop(&addr_type)?;
+                self.pop(&ref_value_type(table.element_type))?;
+                self.pop(&addr_type)?;
+            }
+            Instruction::TableCopy {
+                dst_table_index,
+                src_table_index,
+            } => {
+                let dst = self.context.table(*dst_table_index)?;
+                let src = self.context.table(*src_table_index)?;
+                if dst.element_type != src.element_type {
+                    return Err(ValidationErrorKind::TypeMismatch {
+                        expected: ref_value_type(dst.element_type),
+                        actual: ref_value_type(src.element_type),
+                    });
+                }
+                self.copy(addr_type(&dst.limits), addr_type(&src.limits))?;
+            }
+            Instruction::TableInit {
+                element_index,
+                table_index,
+            } => {
+                let table = self.context.table(*table_index)?;
+                let element_type = self.context.elem(*element_index)?;
+                if table.element_type != element_type {
+                    return Err(ValidationErrorKind::TypeMismatch {
+                        expected: ref_value_type(table.element_type),
+                        actual: ref_value_type(element_type),
+                    });
+                }
+                self.pop_all(&[ValueType::I32, ValueType::I32])?;
+                self.pop(&addr_type(&table.limits))?;
+            }
+            Instruction::ElemDrop { element_index } => {
+                self.context.elem(*element_index)?;
+            }
+            Instruction::MemorySize { memory_index } => {
+                let addr_type = self.memory_addr_type(*memory_index)?;
+                self.push(addr_type);
+            }
+            Instruction::MemoryGrow { memory_index } => {
+                let addr_type = self.memory_addr_type(*memory_index)?;
+                self.pop(&addr_type)?;
+                self.push(addr_type);
+            }
+            Instruction::MemoryFill { memory_index } => {
+                let addr_type = self.memory_addr_type(*memory_index)?;
+                self.pop(&addr_type)?;
+                self.pop(&ValueType::I32)?;
+                self.pop(&addr_type)?;
+            }
+            Instruction::MemoryCopy {
+                dst_memory_index,
+                src_memory_index,
+            } => {
+                let dst = self.memory_addr_type(*dst_memory_index)?;
+                let src = self.memory_addr_type(*src_memory_index)?;
+                self.copy(dst, src)?;
+            }
+            Instruction::MemoryInit {
+                data_index,
+                memory_index,
+            } => {
+                let addr_type = self.memory_addr_type(*memory_index)?;
+                self.data(*data_index)?;
+                self.pop_all(&[ValueType::I32, ValueType::I32])?;
+                self.pop(&addr_type)?;
+            }
+            Instruction::DataDrop { data_index } => self.data(*data_index)?,
+            instr => unreachable!("instruction without a signature: {:?}", instr),
+        }
+
+        Ok(())
+    }
+
+    fn select(&mut self, value_type: &ValueType) -> KindResult<()> {
+        self.pop(&ValueType::I32)?;
+        self.pop(value_type)?;
+        self.pop(value_type)?;
+        self.push(value_type.clone());
+
+        Ok(())
+    }
+
+    /// Pops the destination, source and length of `memory.copy` or `table.copy`.
+    fn copy(&mut self, dst: ValueType, src: ValueType) -> KindResult<()> {
+        let len = match (&dst, &src) {
+            (ValueType::I64, ValueType::I64) => ValueType::I64,
+            _ => ValueType::I32,
+        };
+        self.pop(&len)?;
+        self.pop(&src)?;
+        self.pop(&dst)?;
+
+        Ok(())
+    }
+}
+
+fn is_reference(value_type: &ValueType) -> bool {
+    matches!(
+        value_type,
+        ValueType::FuncRef | ValueType::ExternRef | ValueType::ExnRef
+    )
+}
@@ -0,0 +1,9 @@
+mod func;
+mod operators;
+mod result;
+#[allow(clippy::module_inception)]
+mod validator;
+
+pub use operators::{accessed_memory, operand_types};
+pub use result::*;
+pub use validator::*;
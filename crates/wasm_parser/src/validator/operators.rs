@@ -0,0 +1,562 @@
+//! Typing of the instructions that don't depend on the module context.
+//!
+//! WebAssembly specification: https://webassembly.github.io/spec/core/valid/instructions.html
+
+use crate::decoder::{
+    instructions::{Instruction, MemArg},
+    types::ValueType::{self, F32, F64, I32, I64, V128},
+};
+
+/// Operand and result types of an instruction.
+pub(crate) type Signature = (&'static [ValueType], &'static [ValueType]);
+
+/// Memory access of a load, store or atomic instruction.
+pub(crate) struct MemoryAccess<'a> {
+    pub(crate) mem_arg: &'a MemArg,
+    /// Natural alignment, as a power of two.
+    pub(crate) natural_align: u32,
+    /// Atomic accesses must be naturally aligned.
+    pub(crate) atomic: bool,
+    /// Operand types after the address, and result types.
+    pub(crate) signature: Signature,
+}
+
+/// Returns the operand and result types of a numeric, vector or memory access
+/// instruction, or `None` for other instructions. The operands of a memory
+/// access don't include its address.
+pub fn operand_types(instr: &Instruction) -> Option<(&'static [ValueType], &'static [ValueType])> {
+    operator_signature(instr).or_else(|| memory_access(instr).map(|access| access.signature))
+}
+
+/// Returns the index of the memory accessed by a load, store or atomic
+/// instruction.
+pub fn accessed_memory(instr: &Instruction) -> Option<u32> {
+    memory_access(instr).map(|access| access.mem_arg.memory_index)
+}
+
+/// Returns the signature of numeric and vector instructions other than memory
+/// accesses.
+pub(crate) fn operator_signature(instr: &Instruction) -> Option<Signature> {
+    let signature: Signature = match instr {
+        Instruction::I32Const { .. } => (&[], &[I32]),
+        Instruction::I64Const { .. } => (&[], &[I64]),
+        Instruction::F32Const { .. } => (&[], &[F32]),
+        Instruction::F64Const { .. } => (&[], &[F64]),
+        Instruction::I32Eqz
+        | Instruction::I32Clz
+        | Instruction::I32Ctz
+        | Instruction::I32Popcnt
+        | Instruction::I32Extend8S
+        | Instruction::I32Extend16S => (&[I32], &[I32]),
+        Instruction::I32Eq
+        | Instruction::I32Ne
+        | Instruction::I32LtS
+        | Instruction::I32LtU
+        | Instruction::I32GtS
+        | Instruction::I32GtU
+        | Instruction::I32LeS
+        | Instruction::I32LeU
+        | Instruction::I32GeS
+        | Instruction::I32GeU
+        | Instruction::I32Add
+        | Instruction::I32Sub
+        | Instruction::I32Mul
+        | Instruction::I32DivS
+        | Instruction::I32DivU
+        | Instruction::I32RemS
+        | Instruction::I32RemU
+        | Instruction::I32And
+        | Instruction::I32Or
+        | Instruction::I32Xor
+        | Instruction::I32Shl
+        | Instruction::I32ShrS
+        | Instruction::I32ShrU
+        | Instruction::I32Rotl
+        | Instruction::I32Rotr => (&[I32, I32], &[I32]),
+        Instruction::I64Eqz | Instruction::I32WrapI64 => (&[I64], &[I32]),
+        Instruction::I64Eq
+        | Instruction::I64Ne
+        | Instruction::I64LtS
+        | Instruction::I64LtU
+        | Instruction::I64GtS
+        | Instruction::I64GtU
+        | Instruction::I64LeS
+        | Instruction::I64LeU
+        | Instruction::I64GeS
+        | Instruction::I64GeU => (&[I64, I64], &[I32]),
+        Instruction::F32Eq
+        | Instruction::F32Ne
+        | Instruction::F32Lt
+        | Instruction::F32Gt
+        | Instruction::F32Le
+        | Instruction::F32Ge => (&[F32, F32], &[I32]),
+        Instruction::F64Eq
+        | Instruction::F64Ne
+        | Instruction::F64Lt
+        | Instruction::F64Gt
+        | Instruction::F64Le
+        | Instruction::F64Ge => (&[F64, F64], &[I32]),
+        Instruction::I64Clz
+        | Instruction::I64Ctz
+        | Instruction::I64Popcnt
+        | Instruction::I64Extend8S
+        | Instruction::I64Extend16S
+        | Instruction::I64Extend32S => (&[I64], &[I64]),
+        Instruction::I64Add
+        | Instruction::I64Sub
+        | Instruction::I64Mul
+        | Instruction::I64DivS
+        | Instruction::I64DivU
+        | Instruction::I64RemS
+        | Instruction::I64RemU
+        | Instruction::I64And
+        | Instruction::I64Or
+        | Instruction::I64Xor
+        | Instruction::I64Shl
+        | Instruction::I64ShrS
+        | Instruction::I64ShrU
+        | Instruction::I64Rotl
+        | Instruction::I64Rotr => (&[I64, I64], &[I64]),
+        Instruction::F32Abs
+        | Instruction::F32Neg
+        | Instruction::F32Ceil
+        | Instruction::F32Floor
+        | Instruction::F32Trunc
+        | Instruction::F32Nearest
+        | Instruction::F32Sqrt => (&[F32], &[F32]),
+        Instruction::F32Add
+        | Instruction::F32Sub
+        | Instruction::F32Mul
+        | Instruction::F32Div
+        | Instruction::F32Min
+        | Instruction::F32Max
+        | Instruction::F32Copysign => (&[F32, F32], &[F32]),
+        Instruction::F64Abs
+        | Instruction::F64Neg
+        | Instruction::F64Ceil
+        | Instruction::F64Floor
+        | Instruction::F64Trunc
+        | Instruction::F64Nearest
+        | Instruction::F64Sqrt => (&[F64], &[F64]),
+        Instruction::F64Add
+        | Instruction::F64Sub
+        | Instruction::F64Mul
+        | Instruction::F64Div
+        | Instruction::F64Min
+        | Instruction::F64Max
+        | Instruction::F64Copysign => (&[F64, F64], &[F64]),
+        Instruction::I32TruncF32S
+        | Instruction::I32TruncF32U
+        | Instruction::I32ReinterpretF32
+        | Instruction::I32TruncSatF32S
+        | Instruction::I32TruncSatF32U => (&[F32], &[I32]),
+        Instruction::I32TruncF64S
+        | Instruction::I32TruncF64U
+        | Instruction::I32TruncSatF64S
+        | Instruction::I32TruncSatF64U => (&[F64], &[I32]),
+        Instruction::I64ExtendI32S | Instruction::I64ExtendI32U => (&[I32], &[I64]),
+        Instruction::I64TruncF32S
+        | Instruction::I64TruncF32U
+        | Instruction::I64TruncSatF32S
+        | Instruction::I64TruncSatF32U => (&[F32], &[I64]),
+        Instruction::I64TruncF64S
+        | Instruction::I64TruncF64U
+        | Instruction::I64ReinterpretF64
+        | Instruction::I64TruncSatF64S
+        | Instruction::I64TruncSatF64U => (&[F64], &[I64]),
+        Instruction::F32ConvertI32S
+        | Instruction::F32ConvertI32U
+        | Instruction::F32ReinterpretI32 => (&[I32], &[F32]),
+        Instruction::F32ConvertI64S | Instruction::F32ConvertI64U => (&[I64], &[F32]),
+        Instruction::F32DemoteF64 => (&[F64], &[F32]),
+        Instruction::F64ConvertI32S | Instruction::F64ConvertI32U => (&[I32], &[F64]),
+        Instruction::F64ConvertI64S
+        | Instruction::F64ConvertI64U
+        | Instruction::F64ReinterpretI64 => (&[I64], &[F64]),
+        Instruction::F64PromoteF32 => (&[F32], &[F64]),
+        Instruction::V128Const { .. } => (&[], &[V128]),
+        Instruction::I8x16Shuffle { .. }
+        | Instruction::I8x16Swizzle
+        | Instruction::I8x16Eq
+        | Instruction::I8x16Ne
+        | Instruction::I8x16LtS
+        | Instruction::I8x16LtU
+        | Instruction::I8x16GtS
+        | Instruction::I8x16GtU
+        | Instruction::I8x16LeS
+        | Instruction::I8x16LeU
+        | Instruction::I8x16GeS
+        | Instruction::I8x16GeU
+        | Instruction::I16x8Eq
+        | Instruction::I16x8Ne
+        | Instruction::I16x8LtS
+        | Instruction::I16x8LtU
+        | Instruction::I16x8GtS
+        | Instruction::I16x8GtU
+        | Instruction::I16x8LeS
+        | Instruction::I16x8LeU
+        | Instruction::I16x8GeS
+        | Instruction::I16x8GeU
+        | Instruction::I32x4Eq
+        | Instruction::I32x4Ne
+        | Instruction::I32x4LtS
+        | Instruction::I32x4LtU
+        | Instruction::I32x4GtS
+        | Instruction::I32x4GtU
+        | Instruction::I32x4LeS
+        | Instruction::I32x4LeU
+        | Instruction::I32x4GeS
+        | Instruction::I32x4GeU
+        | Instruction::F32x4Eq
+        | Instruction::F32x4Ne
+        | Instruction::F32x4Lt
+        | Instruction::F32x4Gt
+        | Instruction::F32x4Le
+        | Instruction::F32x4Ge
+        | Instruction::F64x2Eq
+        | Instruction::F64x2Ne
+        | Instruction::F64x2Lt
+        | Instruction::F64x2Gt
+        | Instruction::F64x2Le
+        | Instruction::F64x2Ge
+        | Instruction::V128And
+        | Instruction::V128Andnot
+        | Instruction::V128Or
+        | Instruction::V128Xor
+        | Instruction::I8x16NarrowI16x8S
+        | Instruction::I8x16NarrowI16x8U
+        | Instruction::I8x16Add
+        | Instruction::I8x16AddSatS
+        | Instruction::I8x16AddSatU
+        | Instruction::I8x16Sub
+        | Instruction::I8x16SubSatS
+        | Instruction::I8x16SubSatU
+        | Instruction::I8x16MinS
+        | Instruction::I8x16MinU
+        | Instruction::I8x16MaxS
+        | Instruction::I8x16MaxU
+        | Instruction::I8x16AvgrU
+        | Instruction::I16x8Q15mulrSatS
+        | Instruction::I16x8NarrowI32x4S
+        | Instruction::I16x8NarrowI32x4U
+        | Instruction::I16x8Add
+        | Instruction::I16x8AddSatS
+        | Instruction::I16x8AddSatU
+        | Instruction::I16x8Sub
+        | Instruction::I16x8SubSatS
+        | Instruction::I16x8SubSatU
+        | Instruction::I16x8Mul
+        | Instruction::I16x8MinS
+        | Instruction::I16x8MinU
+        | Instruction::I16x8MaxS
+        | Instruction::I16x8MaxU
+        | Instruction::I16x8AvgrU
+        | Instruction::I16x8ExtmulLowI8x16S
+        | Instruction::I16x8ExtmulHighI8x16S
+        | Instruction::I16x8ExtmulLowI8x16U
+        | Instruction::I16x8ExtmulHighI8x16U
+        | Instruction::I32x4Add
+        | Instruction::I32x4Sub
+        | Instruction::I32x4Mul
+        | Instruction::I32x4MinS
+        | Instruction::I32x4MinU
+        | Instruction::I32x4MaxS
+        | Instruction::I32x4MaxU
+        | Instruction::I32x4DotI16x8S
+        | Instruction::I32x4ExtmulLowI16x8S
+        | Instruction::I32x4ExtmulHighI16x8S
+        | Instruction::I32x4ExtmulLowI16x8U
+        | Instruction::I32x4ExtmulHighI16x8U
+        | Instruction::I64x2Add
+        | Instruction::I64x2Sub
+        | Instruction::I64x2Mul
+        | Instruction::I64x2Eq
+        | Instruction::I64x2Ne
+        | Instruction::I64x2LtS
+        | Instruction::I64x2GtS
+        | Instruction::I64x2LeS
+        | Instruction::I64x2GeS
+        | Instruction::I64x2ExtmulLowI32x4S
+        | Instruction::I64x2ExtmulHighI32x4S
+        | Instruction::I64x2ExtmulLowI32x4U
+        | Instruction::I64x2ExtmulHighI32x4U
+        | Instruction::F32x4Add
+        | Instruction::F32x4Sub
+        | Instruction::F32x4Mul
+        | Instruction::F32x4Div
+        | Instruction::F32x4Min
+        | Instruction::F32x4Max
+        | Instruction::F32x4Pmin
+        | Instruction::F32x4Pmax
+        | Instruction::F64x2Add
+        | Instruction::F64x2Sub
+        | Instruction::F64x2Mul
+        | Instruction::F64x2Div
+        | Instruction::F64x2Min
+        | Instruction::F64x2Max
+        | Instruction::F64x2Pmin
+        | Instruction::F64x2Pmax => (&[V128, V128], &[V128]),
+        Instruction::I8x16Splat | Instruction::I16x8Splat | Instruction::I32x4Splat => {
+            (&[I32], &[V128])
+        }
+        Instruction::I64x2Splat => (&[I64], &[V128]),
+        Instruction::F32x4Splat => (&[F32], &[V128]),
+        Instruction::F64x2Splat => (&[F64], &[V128]),
+        Instruction::I8x16ExtractLaneS { .. }
+        | Instruction::I8x16ExtractLaneU { .. }
+        | Instruction::I16x8ExtractLaneS { .. }
+        | Instruction::I16x8ExtractLaneU { .. }
+        | Instruction::I32x4ExtractLane { .. }
+        | Instruction::V128AnyTrue
+        | Instruction::I8x16AllTrue
+        | Instruction::I8x16Bitmask
+        | Instruction::I16x8AllTrue
+        | Instruction::I16x8Bitmask
+        | Instruction::I32x4AllTrue
+        | Instruction::I32x4Bitmask
+        | Instruction::I64x2AllTrue
+        | Instruction::I64x2Bitmask => (&[V128], &[I32]),
+        Instruction::I8x16ReplaceLane { .. }
+        | Instruction::I16x8ReplaceLane { .. }
+        | Instruction::I32x4ReplaceLane { .. }
+        | Instruction::I8x16Shl
+        | Instruction::I8x16ShrS
+        | Instruction::I8x16ShrU
+        | Instruction::I16x8Shl
+        | Instruction::I16x8ShrS
+        | Instruction::I16x8ShrU
+        | Instruction::I32x4Shl
+        | Instruction::I32x4ShrS
+        | Instruction::I32x4ShrU
+        | Instruction::I64x2Shl
+        | Instruction::I64x2ShrS
+        | Instruction::I64x2ShrU => (&[V128, I32], &[V128]),
+        Instruction::I64x2ExtractLane { .. } => (&[V128], &[I64]),
+        Instruction::I64x2ReplaceLane { .. } => (&[V128, I64], &[V128]),
+        Instruction::F32x4ExtractLane { .. } => (&[V128], &[F32]),
+        Instruction::F32x4ReplaceLane { .. } => (&[V128, F32], &[V128]),
+        Instruction::F64x2ExtractLane { .. } => (&[V128], &[F64]),
+        Instruction::F64x2ReplaceLane { .. } => (&[V128, F64], &[V128]),
+        Instruction::V128Not
+        | Instruction::F32x4DemoteF64x2Zero
+        | Instruction::F64x2PromoteLowF32x4
+        | Instruction::I8x16Abs
+        | Instruction::I8x16Neg
+        | Instruction::I8x16Popcnt
+        | Instruction::F32x4Ceil
+        | Instruction::F32x4Floor
+        | Instruction::F32x4Trunc
+        | Instruction::F32x4Nearest
+        | Instruction::F64x2Ceil
+        | Instruction::F64x2Floor
+        | Instruction::F64x2Trunc
+        | Instruction::I16x8ExtaddPairwiseI8x16S
+        | Instruction::I16x8ExtaddPairwiseI8x16U
+        | Instruction::I32x4ExtaddPairwiseI16x8S
+        | Instruction::I32x4ExtaddPairwiseI16x8U
+        | Instruction::I16x8Abs
+        | Instruction::I16x8Neg
+        | Instruction::I16x8ExtendLowI8x16S
+        | Instruction::I16x8ExtendHighI8x16S
+        | Instruction::I16x8ExtendLowI8x16U
+        | Instruction::I16x8ExtendHighI8x16U
+        | Instruction::F64x2Nearest
+        | Instruction::I32x4Abs
+        | Instruction::I32x4Neg
+        | Instruction::I32x4ExtendLowI16x8S
+        | Instruction::I32x4ExtendHighI16x8S
+        | Instruction::I32x4ExtendLowI16x8U
+        | Instruction::I32x4ExtendHighI16x8U
+        | Instruction::I64x2Abs
+        | Instruction::I64x2Neg
+        | Instruction::I64x2ExtendLowI32x4S
+        | Instruction::I64x2ExtendHighI32x4S
+        | Instruction::I64x2ExtendLowI32x4U
+        | Instruction::I64x2ExtendHighI32x4U
+        | Instruction::F32x4Abs
+        | Instruction::F32x4Neg
+        | Instruction::F32x4Sqrt
+        | Instruction::F64x2Abs
+        | Instruction::F64x2Neg
+        | Instruction::F64x2Sqrt
+        | Instruction::I32x4TruncSatF32x4S
+        | Instruction::I32x4TruncSatF32x4U
+        | Instruction::F32x4ConvertI32x4S
+        | Instruction::F32x4ConvertI32x4U
+        | Instruction::I32x4TruncSatF64x2SZero
+        | Instruction::I32x4TruncSatF64x2UZero
+        | Instruction::F64x2ConvertLowI32x4S
+        | Instruction::F64x2ConvertLowI32x4U => (&[V128], &[V128]),
+        Instruction::V128Bitselect => (&[V128, V128, V128], &[V128]),
+        _ => return None,
+    };
+
+    Some(signature)
+}
+
+pub(crate) fn memory_access(instr: &Instruction) -> Option<MemoryAccess<'_>> {
+    let (mem_arg, natural_align, atomic, signature): (_, _, _, Signature) = match instr {
+        Instruction::I32Load { mem_arg } => (mem_arg, 2, false, (&[], &[I32])),
+        Instruction::I64Load { mem_arg } => (mem_arg, 3, false, (&[], &[I64])),
+        Instruction::F32Load { mem_arg } => (mem_arg, 2, false, (&[], &[F32])),
+        Instruction::F64Load { mem_arg } => (mem_arg, 3, false, (&[], &[F64])),
+        Instruction::I32Load8S { mem_arg } | Instruction::I32Load8U { mem_arg } => {
+            (mem_arg, 0, false, (&[], &[I32]))
+        }
+        Instruction::I32Load16S { mem_arg } | Instruction::I32Load16U { mem_arg } => {
+            (mem_arg, 1, false, (&[], &[I32]))
+        }
+        Instruction::I64Load8S { mem_arg } | Instruction::I64Load8U { mem_arg } => {
+            (mem_arg, 0, false, (&[], &[I64]))
+        }
+        Instruction::I64Load16S { mem_arg } | Instruction::I64Load16U { mem_arg } => {
+            (mem_arg, 1, false, (&[], &[I64]))
+        }
+        Instruction::I64Load32S { mem_arg } | Instruction::I64Load32U { mem_arg } => {
+            (mem_arg, 2, false, (&[], &[I64]))
+        }
+        Instruction::I32Store { mem_arg } => (mem_arg, 2, false, (&[I32], &[])),
+        Instruction::I64Store { mem_arg } => (mem_arg, 3, false, (&[I64], &[])),
+        Instruction::F32Store { mem_arg } => (mem_arg, 2, false, (&[F32], &[])),
+        Instruction::F64Store { mem_arg } => (mem_arg, 3, false, (&[F64], &[])),
+        Instruction::I32Store8 { mem_arg } => (mem_arg, 0, false, (&[I32], &[])),
+        Instruction::I32Store16 { mem_arg } => (mem_arg, 1, false, (&[I32], &[])),
+        Instruction::I64Store8 { mem_arg } => (mem_arg, 0, false, (&[I64], &[])),
+        Instruction::I64Store16 { mem_arg } => (mem_arg, 1, false, (&[I64], &[])),
+        Instruction::I64Store32 { mem_arg } => (mem_arg, 2, false, (&[I64], &[])),
+        Instruction::V128Load { mem_arg } => (mem_arg, 4, false, (&[], &[V128])),
+        Instruction::V128Load8x8S { mem_arg }
+        | Instruction::V128Load8x8U { mem_arg }
+        | Instruction::V128Load16x4S { mem_arg }
+        | Instruction::V128Load16x4U { mem_arg }
+        | Instruction::V128Load32x2S { mem_arg }
+        | Instruction::V128Load32x2U { mem_arg }
+        | Instruction::V128Load64Splat { mem_arg }
+        | Instruction::V128Load64Zero { mem_arg } => (mem_arg, 3, false, (&[], &[V128])),
+        Instruction::V128Load8Splat { mem_arg } => (mem_arg, 0, false, (&[], &[V128])),
+        Instruction::V128Load16Splat { mem_arg } => (mem_arg, 1, false, (&[], &[V128])),
+        Instruction::V128Load32Splat { mem_arg } | Instruction::V128Load32Zero { mem_arg } => {
+            (mem_arg, 2, false, (&[], &[V128]))
+        }
+        Instruction::V128Store { mem_arg } => (mem_arg, 4, false, (&[V128], &[])),
+        Instruction::V128Load8Lane { mem_arg, .. } => (mem_arg, 0, false, (&[V128], &[V128])),
+        Instruction::V128Load16Lane { mem_arg, .. } => (mem_arg, 1, false, (&[V128], &[V128])),
+        Instruction::V128Load32Lane { mem_arg, .. } => (mem_arg, 2, false, (&[V128], &[V128])),
+        Instruction::V128Load64Lane { mem_arg, .. } => (mem_arg, 3, false, (&[V128], &[V128])),
+        Instruction::V128Store8Lane { mem_arg, .. } => (mem_arg, 0, false, (&[V128], &[])),
+        Instruction::V128Store16Lane { mem_arg, .. } => (mem_arg, 1, false, (&[V128], &[])),
+        Instruction::V128Store32Lane { mem_arg, .. } => (mem_arg, 2, false, (&[V128], &[])),
+        Instruction::V128Store64Lane { mem_arg, .. } => (mem_arg, 3, false, (&[V128], &[])),
+        Instruction::MemoryAtomicNotify { mem_arg }
+        | Instruction::I32AtomicRmwAdd { mem_arg }
+        | Instruction::I32AtomicRmwSub { mem_arg }
+        | Instruction::I32AtomicRmwAnd { mem_arg }
+        | Instruction::I32AtomicRmwOr { mem_arg }
+        | Instruction::I32AtomicRmwXor { mem_arg }
+        | Instruction::I32AtomicRmwXchg { mem_arg } => (mem_arg, 2, true, (&[I32], &[I32])),
+        Instruction::MemoryAtomicWait32 { mem_arg } => (mem_arg, 2, true, (&[I32, I64], &[I32])),
+        Instruction::MemoryAtomicWait64 { mem_arg } => (mem_arg, 3, true, (&[I64, I64], &[I32])),
+        Instruction::I32AtomicLoad { mem_arg } => (mem_arg, 2, true, (&[], &[I32])),
+        Instruction::I64AtomicLoad { mem_arg } => (mem_arg, 3, true, (&[], &[I64])),
+        Instruction::I32AtomicLoad8U { mem_arg } => (mem_arg, 0, true, (&[], &[I32])),
+        Instruction::I32AtomicLoad16U { mem_arg } => (mem_arg, 1, true, (&[], &[I32])),
+        Instruction::I64AtomicLoad8U { mem_arg } => (mem_arg, 0, true, (&[], &[I64])),
+        Instruction::I64AtomicLoad16U { mem_arg } => (mem_arg, 1, true, (&[], &[I64])),
+        Instruction::I64AtomicLoad32U { mem_arg } => (mem_arg, 2, true, (&[], &[I64])),
+        Instruction::I32AtomicStore { mem_arg } => (mem_arg, 2, true, (&[I32], &[])),
+        Instruction::I64AtomicStore { mem_arg } => (mem_arg, 3, true, (&[I64], &[])),
+        Instruction::I32AtomicStore8 { mem_arg } => (mem_arg, 0, true, (&[I32], &[])),
+        Instruction::I32AtomicStore16 { mem_arg } => (mem_arg, 1, true, (&[I32], &[])),
+        Instruction::I64AtomicStore8 { mem_arg } => (mem_arg, 0, true, (&[I64], &[])),
+        Instruction::I64AtomicStore16 { mem_arg } => (mem_arg, 1, true, (&[I64], &[])),
+        Instruction::I64AtomicStore32 { mem_arg } => (mem_arg, 2, true, (&[I64], &[])),
+        Instruction::I64AtomicRmwAdd { mem_arg }
+        | Instruction::I64AtomicRmwSub { mem_arg }
+        | Instruction::I64AtomicRmwAnd { mem_arg }
+        | Instruction::I64AtomicRmwOr { mem_arg }
+        | Instruction::I64AtomicRmwXor { mem_arg }
+        | Instruction::I64AtomicRmwXchg { mem_arg } => (mem_arg, 3, true, (&[I64], &[I64])),
+        Instruction::I32AtomicRmw8AddU { mem_arg }
+        | Instruction::I32AtomicRmw8SubU { mem_arg }
+        | Instruction::I32AtomicRmw8AndU { mem_arg }
+        | Instruction::I32AtomicRmw8OrU { mem_arg }
+        | Instruction::I32AtomicRmw8XorU { mem_arg }
+        | Instruction::I32AtomicRmw8XchgU { mem_arg } => (mem_arg, 0, true, (&[I32], &[I32])),
+        Instruction::I32AtomicRmw16AddU { mem_arg }
+        | Instruction::I32AtomicRmw16SubU { mem_arg }
+        | Instruction::I32AtomicRmw16AndU { mem_arg }
+        | Instruction::I32AtomicRmw16OrU { mem_arg }
+        | Instruction::I32AtomicRmw16XorU { mem_arg }
+        | Instruction::I32AtomicRmw16XchgU { mem_arg } => (mem_arg, 1, true, (&[I32], &[I32])),
+        Instruction::I64AtomicRmw8AddU { mem_arg }
+        | Instruction::I64AtomicRmw8SubU { mem_arg }
+        | Instruction::I64AtomicRmw8AndU { mem_arg }
+        | Instruction::I64AtomicRmw8OrU { mem_arg }
+        | Instruction::I64AtomicRmw8XorU { mem_arg }
+        | Instruction::I64AtomicRmw8XchgU { mem_arg } => (mem_arg, 0, true, (&[I64], &[I64])),
+        Instruction::I64AtomicRmw16AddU { mem_arg }
+        | Instruction::I64AtomicRmw16SubU { mem_arg }
+        | Instruction::I64AtomicRmw16AndU { mem_arg }
+        | Instruction::I64AtomicRmw16OrU { mem_arg }
+        | Instruction::I64AtomicRmw16XorU { mem_arg }
+        | Instruction::I64AtomicRmw16XchgU { mem_arg } => (mem_arg, 1, true, (&[I64], &[I64])),
+        Instruction::I64AtomicRmw32AddU { mem_arg }
+        | Instruction::I64AtomicRmw32SubU { mem_arg }
+        | Instruction::I64AtomicRmw32AndU { mem_arg }
+        | Instruction::I64AtomicRmw32OrU { mem_arg }
+        | Instruction::I64AtomicRmw32XorU { mem_arg }
+        | Instruction::I64AtomicRmw32XchgU { mem_arg } => (mem_arg, 2, true, (&[I64], &[I64])),
+        Instruction::I32AtomicRmwCmpxchg { mem_arg } => (mem_arg, 2, true, (&[I32, I32], &[I32])),
+        Instruction::I64AtomicRmwCmpxchg { mem_arg } => (mem_arg, 3, true, (&[I64, I64], &[I64])),
+        Instruction::I32AtomicRmw8CmpxchgU { mem_arg } => (mem_arg, 0, true, (&[I32, I32], &[I32])),
+        Instruction::I32AtomicRmw16CmpxchgU { mem_arg } => {
+            (mem_arg, 1, true, (&[I32, I32], &[I32]))
+        }
+        Instruction::I64AtomicRmw8CmpxchgU { mem_arg } => (mem_arg, 0, true, (&[I64, I64], &[I64])),
+        Instruction::I64AtomicRmw16CmpxchgU { mem_arg } => {
+            (mem_arg, 1, true, (&[I64, I64], &[I64]))
+        }
+        Instruction::I64AtomicRmw32CmpxchgU { mem_arg } => {
+            (mem_arg, 2, true, (&[I64, I64], &[I64]))
+        }
+        _ => return None,
+    };
+
+    Some(MemoryAccess {
+        mem_arg,
+        natural_align,
+        atomic,
+        signature,
+    })
+}
+
+/// Returns the lane index immediate and the number of lanes it selects from.
+pub(crate) fn lane(instr: &Instruction) -> Option<(u8, u8)> {
+    let (lane, count) = match instr {
+        Instruction::I8x16ExtractLaneS { lane }
+        | Instruction::I8x16ExtractLaneU { lane }
+        | Instruction::I8x16ReplaceLane { lane }
+        | Instruction::V128Load8Lane { lane, .. }
+        | Instruction::V128Store8Lane { lane, .. } => (lane, 16),
+        Instruction::I16x8ExtractLaneS { lane }
+        | Instruction::I16x8ExtractLaneU { lane }
+        | Instruction::I16x8ReplaceLane { lane }
+        | Instruction::V128Load16Lane { lane, .. }
+        | Instruction::V128Store16Lane { lane, .. } => (lane, 8),
+        Instruction::I32x4ExtractLane { lane }
+        | Instruction::I32x4ReplaceLane { lane }
+        | Instruction::F32x4ExtractLane { lane }
+        | Instruction::F32x4ReplaceLane { lane }
+        | Instruction::V128Load32Lane { lane, .. }
+        | Instruction::V128Store32Lane { lane, .. } => (lane, 4),
+        Instruction::I64x2ExtractLane { lane }
+        | Instruction::I64x2ReplaceLane { lane }
+        | Instruction::F64x2ExtractLane { lane }
+        | Instruction::F64x2ReplaceLane { lane }
+        | Instruction::V128Load64Lane { lane, .. }
+        | Instruction::V128Store64Lane { lane, .. } => (lane, 2),
+        _ => return None,
+    };
+
+    Some((*lane, count))
+}
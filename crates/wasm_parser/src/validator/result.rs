@@ -0,0 +1,109 @@
+use std::fmt;
+
+use crate::decoder::{section::SectionId, types::ValueType};
+
+/// Error returned by the validator, together with where in the module it
+/// happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Section being validated.
+    pub section: Option<SectionId>,
+    /// Function index (imported functions included) of the body being validated.
+    pub func_index: Option<u32>,
+    /// Index of the instruction within the function body or constant expression.
+    pub instr_index: Option<u32>,
+    pub kind: ValidationErrorKind,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+
+        if let Some(section) = self.section {
+            write!(f, "{} section", section)?;
+            separator = ", ";
+        }
+        if let Some(func_index) = self.func_index {
+            write!(f, "{}func {}", separator, func_index)?;
+            separator = ", ";
+        }
+        if let Some(instr_index) = self.instr_index {
+            write!(f, "{}instr {}", separator, instr_index)?;
+        }
+
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    #[error("type mismatch: expected {expected:?}, found {actual:?}")]
+    TypeMismatch {
+        expected: ValueType,
+        actual: ValueType,
+    },
+    #[error("type mismatch: operand stack is empty")]
+    EmptyOperandStack,
+    #[error("type mismatch: {0} values left on the operand stack at the end of the block")]
+    UnexpectedOperands(usize),
+    #[error("type mismatch: expected a reference, found {0:?}")]
+    ExpectedReference(ValueType),
+    #[error("type mismatch: select needs numeric or vector operands, found {0:?}")]
+    ExpectedNumericOperand(ValueType),
+    #[error("unknown {space} {index}")]
+    Unknown { space: &'static str, index: u32 },
+
+    #[error("else without a matching if")]
+    ElseWithoutIf,
+    #[error("if without else must have the same parameter and result types")]
+    IfWithoutElse,
+    #[error("function body must end with `end`")]
+    MissingEnd,
+    #[error("instructions after the end of the function")]
+    InstructionsAfterEnd,
+    #[error("br_table labels have different arities")]
+    BrTableArityMismatch,
+    #[error("catch clause doesn't match the types of label {0}")]
+    CatchMismatch(u32),
+    #[error("select must have exactly one result type, got {0}")]
+    InvalidSelectArity(usize),
+    #[error("table {0} doesn't hold funcref")]
+    TableNotFuncRef(u32),
+    #[error("tail call results don't match the results of the caller")]
+    ReturnCallMismatch,
+    #[error("global {0} is immutable")]
+    ImmutableGlobal(u32),
+    #[error("alignment 2**{align} exceeds the natural alignment 2**{natural}")]
+    InvalidAlignment { align: u32, natural: u32 },
+    #[error("atomic access must be naturally aligned to 2**{natural}, got 2**{align}")]
+    UnalignedAtomic { align: u32, natural: u32 },
+    #[error("lane {lane} out of range for {count} lanes")]
+    InvalidLane { lane: u8, count: u8 },
+    #[error("data count section is required")]
+    DataCountRequired,
+    #[error("function {0} is referenced but not declared")]
+    UndeclaredFunctionReference(u32),
+
+    #[error("instruction is not constant")]
+    NonConstantInstruction,
+    #[error("constant expression reads mutable global {0}")]
+    MutableGlobalInConstExpr(u32),
+    #[error("minimum {min} exceeds maximum {max}")]
+    InvalidLimits { min: u64, max: u64 },
+    #[error("limit {limit} exceeds {bound}")]
+    LimitTooLarge { limit: u64, bound: u64 },
+    #[error("shared memory must have a maximum")]
+    SharedMemoryWithoutMax,
+    #[error("tag type {0} must not have results")]
+    TagWithResults(u32),
+    #[error("start function {0} must take no parameters and return no results")]
+    InvalidStartFunction(u32),
+    #[error("function and code section have inconsistent lengths: {functions} functions, {bodies} bodies")]
+    FunctionCodeMismatch { functions: usize, bodies: usize },
+    #[error("duplicate export name `{0}`")]
+    DuplicateExport(String),
+}
+
+pub type Result<T> = std::result::Result<T, ValidationError>;
@@ -0,0 +1,445 @@
+use std::collections::HashSet;
+
+use crate::decoder::{
+    const_expr::ConstExpr,
+    instructions::Instruction,
+    module::Module,
+    section::SectionId,
+    types::{
+        DataMode, ElementInit, ElementMode, ExportDesc, FuncType, GlobalType, ImportDesc, Limits,
+        RefType, TableType, ValueType,
+    },
+};
+
+use super::{func::FuncValidator, Result, ValidationError, ValidationErrorKind};
+
+pub(crate) type KindResult<T> = std::result::Result<T, ValidationErrorKind>;
+
+/// Maximum number of pages of a 32-bit memory.
+const MAX_PAGES_32: u64 = 1 << 16;
+/// Maximum number of pages of a 64-bit memory.
+const MAX_PAGES_64: u64 = 1 << 48;
+
+/// Validates the module: types of all instructions, index bounds, constant
+/// expressions, limits and export names.
+///
+/// WebAssembly specification: https://webassembly.github.io/spec/core/valid/modules.html
+pub fn validate(module: &Module) -> Result<()> {
+    Validator {
+        module,
+        context: Context {
+            types: &module.type_section,
+            funcs: vec![],
+            tables: vec![],
+            memories: vec![],
+            globals: vec![],
+            tags: vec![],
+            elems: vec![],
+            data_count: module.data_count_section,
+            refs: HashSet::new(),
+        },
+        section: None,
+        func_index: None,
+    }
+    .validate()
+}
+
+/// Index spaces of the module, imports included.
+pub(crate) struct Context<'m> {
+    pub(crate) types: &'m [FuncType],
+    /// Type index of every function.
+    pub(crate) funcs: Vec<u32>,
+    pub(crate) tables: Vec<&'m TableType>,
+    pub(crate) memories: Vec<&'m Limits>,
+    pub(crate) globals: Vec<&'m GlobalType>,
+    /// Type index of every tag.
+    pub(crate) tags: Vec<u32>,
+    /// Reference type of every element segment.
+    pub(crate) elems: Vec<RefType>,
+    pub(crate) data_count: Option<u32>,
+    /// Functions that may be referenced with `ref.func` in function bodies.
+    pub(crate) refs: HashSet<u32>,
+}
+
+impl<'m> Context<'m> {
+    pub(crate) fn ty(&self, type_index: u32) -> KindResult<&'m FuncType> {
+        self.types
+            .get(type_index as usize)
+            .ok_or(ValidationErrorKind::Unknown {
+                space: "type",
+                index: type_index,
+            })
+    }
+
+    pub(crate) fn func_type(&self, func_index: u32) -> KindResult<&'m FuncType> {
+        let type_index = lookup(&self.funcs, "function", func_index)?;
+        self.ty(*type_index)
+    }
+
+    pub(crate) fn table(&self, table_index: u32) -> KindResult<&'m TableType> {
+        lookup(&self.tables, "table", table_index).copied()
+    }
+
+    pub(crate) fn memory(&self, memory_index: u32) -> KindResult<&'m Limits> {
+        lookup(&self.memories, "memory", memory_index).copied()
+    }
+
+    pub(crate) fn global(&self, global_index: u32) -> KindResult<&'m GlobalType> {
+        lookup(&self.globals, "global", global_index).copied()
+    }
+
+    pub(crate) fn tag(&self, tag_index: u32) -> KindResult<&'m FuncType> {
+        let type_index = lookup(&self.tags, "tag", tag_index)?;
+        self.ty(*type_index)
+    }
+
+    pub(crate) fn elem(&self, element_index: u32) -> KindResult<RefType> {
+        lookup(&self.elems, "element segment", element_index).copied()
+    }
+}
+
+fn lookup<'a, T>(items: &'a [T], space: &'static str, index: u32) -> KindResult<&'a T> {
+    items
+        .get(index as usize)
+        .ok_or(ValidationErrorKind::Unknown { space, index })
+}
+
+/// Type of the addresses of a memory or table.
+pub(crate) fn addr_type(limits: &Limits) -> ValueType {
+    match limits.index64 {
+        true => ValueType::I64,
+        false => ValueType::I32,
+    }
+}
+
+pub(crate) fn ref_value_type(ref_type: RefType) -> ValueType {
+    match ref_type {
+        RefType::FuncRef => ValueType::FuncRef,
+        RefType::ExternRef => ValueType::ExternRef,
+        RefType::ExnRef => ValueType::ExnRef,
+    }
+}
+
+struct Validator<'m> {
+    module: &'m Module,
+    context: Context<'m>,
+    section: Option<SectionId>,
+    func_index: Option<u32>,
+}
+
+impl<'m> Validator<'m> {
+    fn error(&self, instr_index: Option<u32>, kind: ValidationErrorKind) -> ValidationError {
+        ValidationError {
+            section: self.section,
+            func_index: self.func_index,
+            instr_index,
+            kind,
+        }
+    }
+
+    fn check<T>(&self, result: KindResult<T>) -> Result<T> {
+        result.map_err(|kind| self.error(None, kind))
+    }
+
+    fn validate(mut self) -> Result<()> {
+        let module = self.module;
+
+        self.section = Some(SectionId::Import);
+        for import in &module.import_section {
+            match &import.desc {
+                ImportDesc::Func(type_index) => {
+                    self.check(self.context.ty(*type_index))?;
+                    self.context.funcs.push(*type_index);
+                }
+                ImportDesc::Table(table_type) => {
+                    self.check(table_limits(&table_type.limits))?;
+                    self.context.tables.push(table_type);
+                }
+                ImportDesc::Memory(limits) => {
+                    self.check(memory_limits(limits))?;
+                    self.context.memories.push(limits);
+                }
+                ImportDesc::Global(global_type) => self.context.globals.push(global_type),
+                ImportDesc::Tag(tag_type) => {
+                    self.check(self.tag_type(tag_type.type_index))?;
+                    self.context.tags.push(tag_type.type_index);
+                }
+            }
+        }
+        let imported_funcs = self.context.funcs.len();
+
+        self.section = Some(SectionId::Function);
+        for type_index in &module.function_section {
+            self.check(self.context.ty(*type_index))?;
+            self.context.funcs.push(*type_index);
+        }
+
+        self.section = Some(SectionId::Table);
+        for table_type in &module.table_section {
+            self.check(table_limits(&table_type.limits))?;
+            self.context.tables.push(table_type);
+        }
+
+        self.section = Some(SectionId::Memory);
+        for memory_type in &module.memory_section {
+            self.check(memory_limits(&memory_type.limits))?;
+            self.context.memories.push(&memory_type.limits);
+        }
+
+        self.section = Some(SectionId::Tag);
+        for tag_type in &module.tag_section {
+            self.check(self.tag_type(tag_type.type_index))?;
+            self.context.tags.push(tag_type.type_index);
+        }
+
+        self.context.refs = declared_refs(module);
+
+        self.section = Some(SectionId::Global);
+        for global in &module.global_section {
+            // Only imported and previously defined globals are visible.
+            let visible_globals = self.context.globals.len();
+            self.const_expr(
+                &global.init_expr,
+                global.global_type.value_type.clone(),
+                visible_globals,
+            )?;
+            self.context.globals.push(&global.global_type);
+        }
+
+        self.section = Some(SectionId::Export);
+        let mut names = HashSet::new();
+        for export in &module.export_section {
+            let result = match export.desc {
+                ExportDesc::Func(index) => self.context.func_type(index).map(drop),
+                ExportDesc::Table(index) => self.context.table(index).map(drop),
+                ExportDesc::Mem(index) => self.context.memory(index).map(drop),
+                ExportDesc::Global(index) => self.context.global(index).map(drop),
+                ExportDesc::Tag(index) => self.context.tag(index).map(drop),
+            };
+            self.check(result)?;
+
+            if !names.insert(export.name.as_str()) {
+                return Err(self.error(
+                    None,
+                    ValidationErrorKind::DuplicateExport(export.name.clone()),
+                ));
+            }
+        }
+
+        self.section = Some(SectionId::Start);
+        if let Some(func_index) = module.start_section {
+            let func_type = self.check(self.context.func_type(func_index))?;
+            if !func_type.params.is_empty() || !func_type.results.is_empty() {
+                return Err(self.error(None, ValidationErrorKind::InvalidStartFunction(func_index)));
+            }
+        }
+
+        self.section = Some(SectionId::Element);
+        let all_globals = self.context.globals.len();
+        for element in &module.element_section {
+            if let ElementMode::Active {
+                table_index,
+                offset,
+            } = &element.mode
+            {
+                let table = self.check(self.context.table(*table_index))?;
+                if table.element_type != element.ref_type {
+                    return Err(self.error(
+                        None,
+                        ValidationErrorKind::TypeMismatch {
+                            expected: ref_value_type(table.element_type),
+                            actual: ref_value_type(element.ref_type),
+                        },
+                    ));
+                }
+                self.const_expr(offset, addr_type(&table.limits), all_globals)?;
+            }
+
+            match &element.init {
+                ElementInit::Functions(func_indices) => {
+                    for func_index in func_indices {
+                        self.check(self.context.func_type(*func_index))?;
+                    }
+                }
+                ElementInit::Expressions(exprs) => {
+                    for expr in exprs {
+                        self.const_expr(expr, ref_value_type(element.ref_type), all_globals)?;
+                    }
+                }
+            }
+            self.context.elems.push(element.ref_type);
+        }
+
+        self.section = Some(SectionId::Data);
+        for data in &module.data_section {
+            if let DataMode::Active {
+                memory_index,
+                offset,
+            } = &data.mode
+            {
+                let memory = self.check(self.context.memory(*memory_index))?;
+                self.const_expr(offset, addr_type(memory), all_globals)?;
+            }
+        }
+
+        self.section = Some(SectionId::Code);
+        if module.function_section.len() != module.code_section.len() {
+            return Err(self.error(
+                None,
+                ValidationErrorKind::FunctionCodeMismatch {
+                    functions: module.function_section.len(),
+                    bodies: module.code_section.len(),
+                },
+            ));
+        }
+        for (code_index, code) in module.code_section.iter().enumerate() {
+            let func_index = (imported_funcs + code_index) as u32;
+            self.func_index = Some(func_index);
+
+            let func_type = self.check(self.context.func_type(func_index))?;
+            let mut locals = func_type.params.clone();
+            locals.extend(code.locals.iter().cloned());
+            let mut func = FuncValidator::new(&self.context, locals, func_type.results.clone());
+
+            for (index, instr) in code.code.iter().enumerate() {
+                let result = match func.is_done() {
+                    true => Err(ValidationErrorKind::InstructionsAfterEnd),
+                    false => func.instruction(instr),
+                };
+                result.map_err(|kind| self.error(Some(index as u32), kind))?;
+            }
+            if !func.is_done() {
+                return Err(self.error(
+                    Some(code.code.len() as u32),
+                    ValidationErrorKind::MissingEnd,
+                ));
+            }
+        }
+        self.func_index = None;
+
+        Ok(())
+    }
+
+    fn tag_type(&self, type_index: u32) -> KindResult<()> {
+        match self.context.ty(type_index)?.results.is_empty() {
+            true => Ok(()),
+            false => Err(ValidationErrorKind::TagWithResults(type_index)),
+        }
+    }
+
+    /// Checks that the expression is constant and produces a single value of
+    /// type `expected`. It may only read the first `visible_globals` globals.
+    fn const_expr(
+        &self,
+        expr: &ConstExpr,
+        expected: ValueType,
+        visible_globals: usize,
+    ) -> Result<()> {
+        let mut func = FuncValidator::new(&self.context, vec![], vec![expected]);
+
+        for (index, instr) in expr.instructions.iter().enumerate() {
+            let result = self
+                .const_instruction(instr, visible_globals)
+                .and_then(|()| func.instruction(instr));
+            result.map_err(|kind| self.error(Some(index as u32), kind))?;
+        }
+
+        func.end()
+            .map_err(|kind| self.error(Some(expr.instructions.len() as u32), kind))
+    }
+
+    fn const_instruction(&self, instr: &Instruction, visible_globals: usize) -> KindResult<()> {
+        match instr {
+            Instruction::I32Const { .. }
+            | Instruction::I64Const { .. }
+            | Instruction::F32Const { .. }
+            | Instruction::F64Const { .. }
+            | Instruction::V128Const { .. }
+            | Instruction::RefNull { .. }
+            | Instruction::RefFunc { .. }
+            | Instruction::I32Add
+            | Instruction::I32Sub
+            | Instruction::I32Mul
+            | Instruction::I64Add
+            | Instruction::I64Sub
+            | Instruction::I64Mul => Ok(()),
+            Instruction::GlobalGet { global_index } => {
+                let global = lookup(
+                    &self.context.globals[..visible_globals],
+                    "global",
+                    *global_index,
+                )?;
+
+                match global.mutable {
+                    true => Err(ValidationErrorKind::MutableGlobalInConstExpr(*global_index)),
+                    false => Ok(()),
+                }
+            }
+            _ => Err(ValidationErrorKind::NonConstantInstruction),
+        }
+    }
+}
+
+fn limits(limits: &Limits, bound: u64) -> KindResult<()> {
+    for limit in [Some(limits.min), limits.max].into_iter().flatten() {
+        if limit > bound {
+            return Err(ValidationErrorKind::LimitTooLarge { limit, bound });
+        }
+    }
+
+    match limits.max {
+        Some(max) if limits.min > max => Err(ValidationErrorKind::InvalidLimits {
+            min: limits.min,
+            max,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn table_limits(table_limits: &Limits) -> KindResult<()> {
+    match table_limits.index64 {
+        true => limits(table_limits, u64::MAX),
+        false => limits(table_limits, u32::MAX as u64),
+    }
+}
+
+fn memory_limits(memory_limits: &Limits) -> KindResult<()> {
+    if memory_limits.shared && memory_limits.max.is_none() {
+        return Err(ValidationErrorKind::SharedMemoryWithoutMax);
+    }
+
+    match memory_limits.index64 {
+        true => limits(memory_limits, MAX_PAGES_64),
+        false => limits(memory_limits, MAX_PAGES_32),
+    }
+}
+
+/// Functions referenced outside of function bodies, which function bodies may
+/// take references to.
+fn declared_refs(module: &Module) -> HashSet<u32> {
+    let mut refs = HashSet::new();
+    let mut exprs = vec![];
+
+    for export in &module.export_section {
+        if let ExportDesc::Func(func_index) = export.desc {
+            refs.insert(func_index);
+        }
+    }
+    for element in &module.element_section {
+        match &element.init {
+            ElementInit::Functions(func_indices) => refs.extend(func_indices),
+            ElementInit::Expressions(init_exprs) => exprs.extend(init_exprs),
+        }
+    }
+    exprs.extend(module.global_section.iter().map(|global| &global.init_expr));
+
+    for expr in exprs {
+        for instr in &expr.instructions {
+            if let Instruction::RefFunc { func_index } = instr {
+                refs.insert(*func_index);
+            }
+        }
+    }
+
+    refs
+}
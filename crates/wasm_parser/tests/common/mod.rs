@@ -0,0 +1,42 @@
+//! Module generation shared by the tests that run on random modules.
+
+use arbitrary::Unstructured;
+
+/// Features enabled in every generated module.
+pub fn wasm_smith_config() -> wasm_smith::Config {
+    wasm_smith::Config {
+        bulk_memory_enabled: true,
+        reference_types_enabled: true,
+        simd_enabled: true,
+        threads_enabled: true,
+        memory64_enabled: true,
+        max_memories: 4,
+        tail_call_enabled: true,
+        ..wasm_smith::Config::default()
+    }
+}
+
+/// Generates the module of `seed` with `config`, or `None` if the random bytes
+/// run out before it is complete.
+pub fn generate_module(seed: u64, config: wasm_smith::Config) -> Option<Vec<u8>> {
+    let data = random_bytes(seed, 16 * 1024);
+    let mut u = Unstructured::new(&data);
+
+    wasm_smith::Module::new(config, &mut u)
+        .ok()
+        .map(|module| module.to_bytes())
+}
+
+/// Deterministic pseudo-random bytes (xorshift64), so failures are reproducible.
+fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
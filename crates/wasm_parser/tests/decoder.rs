@@ -0,0 +1,461 @@
+use wasm_parser::{
+    decoder::{
+        const_expr::{ConstExpr, ConstExprError, ConstValue},
+        instructions::{Block, BlockType, Instruction, MemArg},
+        section::SectionId,
+        types::{Element, ElementInit, ElementMode, RefType},
+        DecodeError, DecodeErrorKind, Decoder,
+    },
+    text::WatParser,
+    validator::{validate, ValidationErrorKind},
+};
+
+/// Module binary with the given `(id, contents)` sections.
+fn module(sections: &[(u8, &[u8])]) -> Vec<u8> {
+    let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+    for (id, contents) in sections {
+        assert!(contents.len() < 0x80);
+        bytes.push(*id);
+        bytes.push(contents.len() as u8);
+        bytes.extend_from_slice(contents);
+    }
+    bytes
+}
+
+/// Module importing one `[] -> []` function and defining another with `body`,
+/// which starts at byte 31.
+fn module_with_body(body: &[u8]) -> Vec<u8> {
+    let mut code = vec![0x01, body.len() as u8];
+    code.extend_from_slice(body);
+
+    module(&[
+        (1, &[0x01, 0x60, 0x00, 0x00]),
+        (2, &[0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00]),
+        (3, &[0x01, 0x00]),
+        (10, &code),
+    ])
+}
+
+/// Decodes `body` as the body of the second function of [`module_with_body`].
+fn decode_body(body: &[u8]) -> Vec<Instruction> {
+    let mut module = Decoder::new(&module_with_body(body)).decode().unwrap();
+    module.code_section.remove(0).code
+}
+
+/// Decodes an element section holding the single `segment`.
+fn decode_element(segment: &[u8]) -> Element {
+    let mut section = vec![0x01];
+    section.extend_from_slice(segment);
+
+    let mut module = Decoder::new(&module(&[(9, &section)])).decode().unwrap();
+    module.element_section.remove(0)
+}
+
+/// `i32.const 1`.
+fn offset_1() -> ConstExpr {
+    ConstExpr {
+        instructions: vec![Instruction::I32Const { value: 1 }],
+    }
+}
+
+/// `ref.func 0`.
+fn ref_func_0() -> ConstExpr {
+    ConstExpr {
+        instructions: vec![Instruction::RefFunc { func_index: 0 }],
+    }
+}
+
+fn decode_err(bytes: &[u8]) -> DecodeError {
+    Decoder::new(bytes).decode().unwrap_err()
+}
+
+#[test]
+fn rejects_shared_tables() {
+    // One funcref table with shared limits 1..1.
+    let err = decode_err(&module(&[(4, &[0x01, 0x70, 0x03, 0x01, 0x01])]));
+
+    assert_eq!(err.kind, DecodeErrorKind::InvalidLimitsKind);
+    assert_eq!(err.section, Some(SectionId::Table));
+    assert_eq!(err.offset, 12);
+}
+
+#[test]
+fn reports_where_bodies_are_malformed() {
+    // No locals, then nop, i32.const 0, drop and an unknown opcode.
+    let err = decode_err(&module_with_body(&[
+        0x00, 0x01, 0x41, 0x00, 0x1a, 0xff, 0x0b,
+    ]));
+
+    assert_eq!(
+        err,
+        DecodeError {
+            offset: 36,
+            section: Some(SectionId::Code),
+            func_index: Some(1),
+            instr_index: Some(3),
+            kind: DecodeErrorKind::UnknownOpcode(0xff),
+        }
+    );
+}
+
+#[test]
+fn reports_where_bodies_are_truncated() {
+    // The body ends before the immediate of i32.const.
+    let err = decode_err(&module_with_body(&[0x00, 0x01, 0x41]));
+
+    assert_eq!(err.offset, 34);
+    assert_eq!(err.section, Some(SectionId::Code));
+    assert_eq!(err.func_index, Some(1));
+    assert_eq!(err.instr_index, Some(1));
+}
+
+#[test]
+fn rejects_sections_out_of_order() {
+    // Function section, then type section, which starts at byte 11.
+    let err = decode_err(&module(&[(3, &[0x00]), (1, &[0x00])]));
+    assert_eq!(
+        err.kind,
+        DecodeErrorKind::SectionOutOfOrder(SectionId::Type)
+    );
+    assert_eq!(err.offset, 11);
+
+    // The data count section goes before the code section, despite its id.
+    let err = decode_err(&module(&[(10, &[0x00]), (12, &[0x00])]));
+    assert_eq!(
+        err.kind,
+        DecodeErrorKind::SectionOutOfOrder(SectionId::DataCount)
+    );
+}
+
+#[test]
+fn rejects_duplicate_sections() {
+    let err = decode_err(&module(&[(1, &[0x00]), (1, &[0x00])]));
+
+    assert_eq!(err.kind, DecodeErrorKind::DuplicateSection(SectionId::Type));
+    assert_eq!(err.offset, 11);
+}
+
+#[test]
+fn accepts_custom_sections_anywhere() {
+    let module = Decoder::new(&module(&[
+        (0, &[0x01, b'a']),
+        (1, &[0x00]),
+        (0, &[0x01, b'b']),
+        (3, &[0x00]),
+    ]))
+    .decode()
+    .unwrap();
+
+    assert_eq!(module.custom_section.len(), 2);
+}
+
+#[test]
+fn rejects_section_size_mismatches() {
+    // One `[] -> []` type takes 4 of the 5 declared bytes.
+    let err = decode_err(&module(&[(1, &[0x01, 0x60, 0x00, 0x00, 0x00])]));
+
+    assert_eq!(
+        err,
+        DecodeError {
+            offset: 14,
+            section: Some(SectionId::Type),
+            func_index: None,
+            instr_index: None,
+            kind: DecodeErrorKind::SectionSizeMismatch {
+                expected: 5,
+                actual: 4
+            },
+        }
+    );
+}
+
+#[test]
+fn decodes_name_sections() {
+    // Function names subsection naming function 0 `f`.
+    let module = Decoder::new(&module(&[(
+        0,
+        &[
+            0x04, b'n', b'a', b'm', b'e', 0x01, 0x04, 0x01, 0x00, 0x01, b'f',
+        ],
+    )]))
+    .decode()
+    .unwrap();
+
+    let names = module.name_section.unwrap();
+    assert_eq!(names.function_names.get(&0).map(String::as_str), Some("f"));
+}
+
+#[test]
+fn ignores_malformed_name_sections() {
+    // The function names subsection claims more bytes than the section has.
+    let module = Decoder::new(&module(&[(
+        0,
+        &[
+            0x04, b'n', b'a', b'm', b'e', 0x01, 0x09, 0x01, 0x00, 0x01, b'f',
+        ],
+    )]))
+    .decode()
+    .unwrap();
+
+    assert_eq!(module.name_section, None);
+    assert_eq!(module.custom_section.len(), 1);
+}
+
+#[test]
+fn decodes_element_prefix_0() {
+    // Offset, function indices.
+    assert_eq!(
+        decode_element(&[0x00, 0x41, 0x01, 0x0b, 0x01, 0x00]),
+        Element {
+            mode: ElementMode::Active {
+                table_index: 0,
+                offset: offset_1()
+            },
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Functions(vec![0]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_1() {
+    // Element kind, function indices.
+    assert_eq!(
+        decode_element(&[0x01, 0x00, 0x01, 0x00]),
+        Element {
+            mode: ElementMode::Passive,
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Functions(vec![0]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_2() {
+    // Table index, offset, element kind, function indices.
+    assert_eq!(
+        decode_element(&[0x02, 0x01, 0x41, 0x01, 0x0b, 0x00, 0x01, 0x00]),
+        Element {
+            mode: ElementMode::Active {
+                table_index: 1,
+                offset: offset_1()
+            },
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Functions(vec![0]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_3() {
+    // Element kind, function indices.
+    assert_eq!(
+        decode_element(&[0x03, 0x00, 0x01, 0x00]),
+        Element {
+            mode: ElementMode::Declarative,
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Functions(vec![0]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_4() {
+    // Offset, expressions.
+    assert_eq!(
+        decode_element(&[0x04, 0x41, 0x01, 0x0b, 0x01, 0xd2, 0x00, 0x0b]),
+        Element {
+            mode: ElementMode::Active {
+                table_index: 0,
+                offset: offset_1()
+            },
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Expressions(vec![ref_func_0()]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_5() {
+    // Reference type, expressions.
+    assert_eq!(
+        decode_element(&[0x05, 0x6f, 0x01, 0xd0, 0x6f, 0x0b]),
+        Element {
+            mode: ElementMode::Passive,
+            ref_type: RefType::ExternRef,
+            init: ElementInit::Expressions(vec![ConstExpr {
+                instructions: vec![Instruction::RefNull {
+                    ref_type: RefType::ExternRef
+                }],
+            }]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_6() {
+    // Table index, offset, reference type, expressions.
+    assert_eq!(
+        decode_element(&[0x06, 0x01, 0x41, 0x01, 0x0b, 0x70, 0x01, 0xd2, 0x00, 0x0b]),
+        Element {
+            mode: ElementMode::Active {
+                table_index: 1,
+                offset: offset_1()
+            },
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Expressions(vec![ref_func_0()]),
+        }
+    );
+}
+
+#[test]
+fn decodes_element_prefix_7() {
+    // Reference type, expressions.
+    assert_eq!(
+        decode_element(&[0x07, 0x70, 0x01, 0xd2, 0x00, 0x0b]),
+        Element {
+            mode: ElementMode::Declarative,
+            ref_type: RefType::FuncRef,
+            init: ElementInit::Expressions(vec![ref_func_0()]),
+        }
+    );
+}
+
+#[test]
+fn rejects_unknown_element_prefixes() {
+    let err = decode_err(&module(&[(9, &[0x01, 0x08])]));
+
+    assert_eq!(err.kind, DecodeErrorKind::UnsupportedElementPrefix);
+}
+
+#[test]
+fn decodes_block_type_indices_as_s33() {
+    // Two byte s33 type index 200.
+    let code = decode_body(&[0x00, 0x02, 0xc8, 0x01, 0x0b, 0x0b]);
+    assert_eq!(
+        code[0],
+        Instruction::Block {
+            block: Block {
+                block_type: BlockType::TypeIndex(200)
+            }
+        }
+    );
+
+    // Type index 0 isn't the empty block type 0x40.
+    let code = decode_body(&[0x00, 0x03, 0x00, 0x0b, 0x0b]);
+    assert_eq!(
+        code[0],
+        Instruction::Loop {
+            block: Block {
+                block_type: BlockType::TypeIndex(0)
+            }
+        }
+    );
+}
+
+#[test]
+fn rejects_negative_block_type_indices() {
+    // 0x60 is -32 as an s33, and not a value type.
+    let err = decode_err(&module_with_body(&[0x00, 0x02, 0x60, 0x0b, 0x0b]));
+
+    assert_eq!(err.kind, DecodeErrorKind::InvalidBlockType);
+    assert_eq!(err.offset, 33);
+    assert_eq!(err.instr_index, Some(0));
+}
+
+#[test]
+fn evaluates_global_get_of_imported_globals() {
+    let module = WatParser::new(
+        r#"
+        (module
+          (import "env" "base" (global i32))
+          (global i32 (i32.add (global.get 0) (i32.const 8))))
+        "#,
+    )
+    .parse()
+    .unwrap();
+    let init_expr = &module.global_section[0].init_expr;
+
+    // The embedder provides the value of the import.
+    let value = init_expr.eval(|index| (index == 0).then_some(ConstValue::I32(16)));
+    assert_eq!(value, Ok(ConstValue::I32(24)));
+
+    let value = init_expr.eval(|_| None);
+    assert_eq!(value, Err(ConstExprError::UnknownGlobal(0)));
+}
+
+#[test]
+fn decodes_simd_lane_instructions() {
+    // i8x16.extract_lane_s 3, v128.load8_lane offset=4 15.
+    let code = decode_body(&[0x00, 0xfd, 0x15, 0x03, 0xfd, 0x54, 0x00, 0x04, 0x0f, 0x0b]);
+
+    assert_eq!(code[0], Instruction::I8x16ExtractLaneS { lane: 3 });
+    assert_eq!(
+        code[1],
+        Instruction::V128Load8Lane {
+            mem_arg: MemArg {
+                align: 0,
+                offset: 4,
+                memory_index: 0
+            },
+            lane: 15
+        }
+    );
+}
+
+#[test]
+fn rejects_misaligned_atomic_instructions() {
+    // Shared memory 1..1 and `i32.const 0, i32.atomic.load align=1, drop`.
+    let module = Decoder::new(&module(&[
+        (1, &[0x01, 0x60, 0x00, 0x00]),
+        (3, &[0x01, 0x00]),
+        (5, &[0x01, 0x03, 0x01, 0x01]),
+        (
+            10,
+            &[
+                0x01, 0x09, 0x00, 0x41, 0x00, 0xfe, 0x10, 0x00, 0x00, 0x1a, 0x0b,
+            ],
+        ),
+    ]))
+    .decode()
+    .unwrap();
+
+    assert_eq!(
+        module.code_section[0].code[1],
+        Instruction::I32AtomicLoad {
+            mem_arg: MemArg {
+                align: 0,
+                offset: 0,
+                memory_index: 0
+            }
+        }
+    );
+
+    // Atomic accesses must be naturally aligned.
+    let err = validate(&module).unwrap_err();
+    assert_eq!(
+        err.kind,
+        ValidationErrorKind::UnalignedAtomic {
+            align: 0,
+            natural: 2
+        }
+    );
+    assert_eq!(err.instr_index, Some(1));
+}
+
+#[test]
+fn decodes_explicit_memory_indices() {
+    // i32.load with bit 6 of the alignment set, then memory 1, offset 4.
+    let code = decode_body(&[0x00, 0x41, 0x00, 0x28, 0x42, 0x01, 0x04, 0x1a, 0x0b]);
+
+    assert_eq!(
+        code[1],
+        Instruction::I32Load {
+            mem_arg: MemArg {
+                align: 2,
+                offset: 4,
+                memory_index: 1
+            }
+        }
+    );
+}
@@ -0,0 +1,51 @@
+use std::{fs, path::PathBuf};
+
+mod common;
+
+use wasm_parser::{decoder::Decoder, encoder::Encoder};
+
+/// Number of random modules checked by `round_trips_generated_modules`.
+const GENERATED_MODULES: u64 = 300;
+
+/// Decodes `bytes`, encodes the module and decodes the result again, checking
+/// that the module didn't change.
+fn assert_round_trip(bytes: &[u8]) {
+    let module = Decoder::new(bytes).decode().unwrap();
+    let encoded = Encoder::new(&module).encode();
+    let decoded = Decoder::new(&encoded)
+        .decode()
+        .unwrap_or_else(|err| panic!("re-encoded module doesn't decode: {}", err));
+
+    // NaN constants never compare equal, so compare the debug output, and the
+    // bytes of a second encoding, which keep NaN payloads.
+    assert_eq!(format!("{:?}", decoded), format!("{:?}", module));
+    assert_eq!(Encoder::new(&decoded).encode(), encoded);
+}
+
+#[test]
+fn round_trips_examples() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "wasm") {
+            assert_round_trip(&fs::read(&path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn round_trips_generated_modules() {
+    for seed in 0..GENERATED_MODULES {
+        let config = wasm_smith::Config {
+            exceptions_enabled: true,
+            generate_custom_sections: true,
+            ..common::wasm_smith_config()
+        };
+        let Some(bytes) = common::generate_module(seed, config) else {
+            continue;
+        };
+
+        assert_round_trip(&bytes);
+    }
+}
@@ -0,0 +1,91 @@
+use wasm_parser::{
+    decoder::{
+        instructions::Instruction,
+        types::{FuncType, ValueType},
+    },
+    parser::{module::Node, Parser},
+    text::WatParser,
+    validator::ValidationErrorKind,
+};
+
+#[test]
+fn builds_structured_bodies() {
+    let module = WatParser::new(
+        r#"
+        (module
+          (type $pair (func (param i32) (result i32 i32)))
+          (func (param i32) (result i32)
+            block
+              loop (result i32)
+                local.get 0
+                br_if 1
+                i32.const 1
+              end
+              drop
+            end
+            local.get 0
+            local.get 0
+            if (type $pair)
+              i32.const 2
+            else
+              i32.const 3
+            end
+            i32.add))
+        "#,
+    )
+    .parse()
+    .unwrap();
+    let module = Parser::new(module).parse().unwrap();
+
+    let block_type = |params: &[ValueType], results: &[ValueType]| FuncType {
+        params: params.to_vec(),
+        results: results.to_vec(),
+    };
+    assert_eq!(
+        module.functions[0].body,
+        vec![
+            Node::Block {
+                block_type: block_type(&[], &[]),
+                depth: 1,
+                body: vec![
+                    Node::Loop {
+                        block_type: block_type(&[], &[ValueType::I32]),
+                        depth: 2,
+                        body: vec![
+                            Node::Instruction(Instruction::LocalGet { local_index: 0 }),
+                            Node::Instruction(Instruction::BrIf { level: 1 }),
+                            Node::Instruction(Instruction::I32Const { value: 1 }),
+                        ],
+                    },
+                    Node::Instruction(Instruction::Drop),
+                ],
+            },
+            Node::Instruction(Instruction::LocalGet { local_index: 0 }),
+            Node::Instruction(Instruction::LocalGet { local_index: 0 }),
+            Node::If {
+                block_type: block_type(&[ValueType::I32], &[ValueType::I32, ValueType::I32]),
+                depth: 1,
+                then_body: vec![Node::Instruction(Instruction::I32Const { value: 2 })],
+                else_body: Some(vec![Node::Instruction(Instruction::I32Const { value: 3 })]),
+            },
+            Node::Instruction(Instruction::I32Add),
+        ]
+    );
+}
+
+#[test]
+fn rejects_invalid_modules() {
+    let mut module = WatParser::new("(module (type (func)) (func (type 0)))")
+        .parse()
+        .unwrap();
+    module.type_section.clear();
+
+    let err = Parser::new(module).parse().unwrap_err();
+    assert_eq!(
+        err.kind,
+        ValidationErrorKind::Unknown {
+            space: "type",
+            index: 0
+        }
+    );
+}
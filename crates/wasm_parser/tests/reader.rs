@@ -0,0 +1,100 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use wasm_parser::decoder::{DecodeErrorKind, Decoder, StreamingDecoder};
+
+/// Reader that returns at most one byte per `read` call, like a slow serial
+/// link, and is interrupted before every byte.
+struct TrickleReader<'a> {
+    bytes: &'a [u8],
+    interrupt: bool,
+}
+
+impl Read for TrickleReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+
+        match (self.bytes.split_first(), buf.first_mut()) {
+            (Some((byte, rest)), Some(out)) => {
+                *out = *byte;
+                self.bytes = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+fn examples() -> Vec<(PathBuf, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+    let mut examples: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .map(|path| {
+            let bytes = fs::read(&path).unwrap();
+            (path, bytes)
+        })
+        .collect();
+    examples.sort();
+
+    assert!(!examples.is_empty());
+    examples
+}
+
+#[test]
+fn decodes_examples_one_byte_at_a_time() {
+    for (path, bytes) in examples() {
+        let expected = Decoder::new(&bytes).decode().unwrap();
+        let reader = TrickleReader {
+            bytes: &bytes,
+            interrupt: false,
+        };
+
+        let module = StreamingDecoder::decode_reader(reader)
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+
+        assert_eq!(module, expected, "{}", path.display());
+    }
+}
+
+#[test]
+fn reports_truncated_examples() {
+    for (path, bytes) in examples() {
+        let truncated = &bytes[..bytes.len() - 1];
+        let reader = TrickleReader {
+            bytes: truncated,
+            interrupt: false,
+        };
+
+        assert!(
+            StreamingDecoder::decode_reader(reader).is_err(),
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn reports_read_errors() {
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::ConnectionReset.into())
+        }
+    }
+
+    let err = StreamingDecoder::decode_reader(FailingReader).unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        DecodeErrorKind::Io(io::ErrorKind::ConnectionReset)
+    );
+}
@@ -0,0 +1,336 @@
+use std::{fs, path::PathBuf};
+
+use wasm_parser::{
+    decoder::{
+        instructions::{BlockType, Instruction},
+        types::{DataMode, ElementInit, ElementMode, ExportDesc, ImportDesc},
+        Decoder,
+    },
+    encoder::Encoder,
+    parser::Parser,
+    text::{TextErrorKind, WatParser, WatPrinter},
+};
+
+#[test]
+fn parses_examples_like_their_binaries() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        // led.wasm was built from an older, unoptimized revision of led.wat.
+        if path.extension().is_none_or(|ext| ext != "wat") || path.ends_with("led.wat") {
+            continue;
+        }
+
+        let text = fs::read_to_string(&path).unwrap();
+        let mut module = WatParser::new(&text)
+            .parse()
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let wasm = fs::read(path.with_extension("wasm")).unwrap();
+
+        // The binaries were built without debug names.
+        module.name_section = None;
+        assert_eq!(
+            module,
+            Decoder::new(&wasm).decode().unwrap(),
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn folded_and_flat_forms_agree() {
+    let folded = r#"
+        (module
+          (func $max (param $a i32) (param $b i32) (result i32)
+            (if (result i32) (i32.gt_s (local.get $a) (local.get $b))
+              (then (local.get $a))
+              (else (local.get $b))))
+          (func $count (param $n i32) (local $i i32)
+            (block $done
+              (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))))
+    "#;
+    let flat = r#"
+        (module
+          (func $max (param $a i32) (param $b i32) (result i32)
+            local.get $a
+            local.get $b
+            i32.gt_s
+            if (result i32)
+              local.get $a
+            else
+              local.get $b
+            end)
+          (func $count (param $n i32) (local $i i32)
+            block $done
+              loop $next
+                local.get $i
+                local.get $n
+                i32.ge_u
+                br_if $done
+                local.get $i
+                i32.const 1
+                i32.add
+                local.set $i
+                br $next
+              end $next
+            end))
+    "#;
+
+    let folded = WatParser::new(folded).parse().unwrap();
+    assert_eq!(folded, WatParser::new(flat).parse().unwrap());
+
+    let count = &folded.code_section[1].code;
+    assert_eq!(count[5], Instruction::BrIf { level: 1 });
+    assert_eq!(count[10], Instruction::Br { level: 0 });
+
+    let names = folded.name_section.unwrap();
+    assert_eq!(names.function_name(1), Some("count"));
+    assert_eq!(names.local_name(1, 1), Some("i"));
+}
+
+#[test]
+fn resolves_inline_imports_exports_and_segments() {
+    let text = r#"
+        (module
+          (type $binop (func (param i32 i32) (result i32)))
+          (func $log (import "env" "log") (param i32))
+          (memory $mem (export "memory") (data "hi" "\00\ff"))
+          (table $funcs funcref (elem $add $log))
+          (global $counter (mut i32) (i32.const -1))
+          (func $add (export "add") (export "plus") (type $binop)
+            (call $log (local.get 0))
+            (i32.add (local.get 0) (local.get 1)))
+          (func (param f64) (result i32 i32)
+            (block (result i32 i32) (i32.const 1) (i32.const 2)))
+          (elem (table $funcs) (offset (i32.const 1)) func $add)
+          (elem declare func $log)
+          (data $passive "abc")
+          (start $add))
+    "#;
+    let module = WatParser::new(text).parse().unwrap();
+
+    assert_eq!(module.import_section[0].desc, ImportDesc::Func(1));
+    assert_eq!(module.function_section, vec![0, 2]);
+    assert_eq!(module.type_section.len(), 4);
+
+    let exports: Vec<_> = module
+        .export_section
+        .iter()
+        .map(|export| (export.name.as_str(), export.desc.clone()))
+        .collect();
+    assert_eq!(
+        exports,
+        vec![
+            ("memory", ExportDesc::Mem(0)),
+            ("add", ExportDesc::Func(1)),
+            ("plus", ExportDesc::Func(1)),
+        ]
+    );
+
+    assert_eq!(module.memory_section[0].limits.min, 1);
+    assert_eq!(module.data_section[0].bytes, b"hi\x00\xff");
+    assert_eq!(module.data_section[1].mode, DataMode::Passive);
+
+    assert_eq!(module.table_section[0].limits.max, Some(2));
+    assert_eq!(
+        module.element_section[0].init,
+        ElementInit::Functions(vec![1, 0])
+    );
+    assert!(matches!(
+        module.element_section[1].mode,
+        ElementMode::Active { table_index: 0, .. }
+    ));
+    assert_eq!(module.element_section[2].mode, ElementMode::Declarative);
+
+    let Instruction::Block { block } = &module.code_section[1].code[0] else {
+        panic!("expected a block");
+    };
+    assert_eq!(block.block_type, BlockType::TypeIndex(3));
+    assert_eq!(module.start_section, Some(1));
+}
+
+#[test]
+fn parses_numeric_literals() {
+    let text = r#"
+        (func
+          i32.const 0xffff_ffff
+          i32.const -0x8000_0000
+          i64.const 18446744073709551615
+          f32.const 0x1.8p1
+          f32.const -inf
+          f32.const nan:0x200000
+          f64.const 1e-3
+          f64.const 0x1p-1074
+          v128.const i16x8 -1 0 1 2 3 4 5 65535
+          drop drop drop drop drop drop drop drop drop)
+    "#;
+    let module = WatParser::new(text).parse().unwrap();
+    let code = &module.code_section[0].code;
+
+    assert_eq!(code[0], Instruction::I32Const { value: -1 });
+    assert_eq!(code[1], Instruction::I32Const { value: i32::MIN });
+    assert_eq!(code[2], Instruction::I64Const { value: -1 });
+    assert_eq!(code[3], Instruction::F32Const { value: 3.0 });
+    assert_eq!(
+        code[4],
+        Instruction::F32Const {
+            value: f32::NEG_INFINITY
+        }
+    );
+    let Instruction::F32Const { value } = code[5] else {
+        panic!("expected f32.const");
+    };
+    assert_eq!(value.to_bits(), 0x7fa0_0000);
+    assert_eq!(code[6], Instruction::F64Const { value: 0.001 });
+    assert_eq!(
+        code[7],
+        Instruction::F64Const {
+            value: f64::from_bits(1)
+        }
+    );
+    assert_eq!(
+        code[8],
+        Instruction::V128Const {
+            value: 0xffff_0005_0004_0003_0002_0001_0000_ffff
+        }
+    );
+}
+
+#[test]
+fn reports_errors_with_line_and_column() {
+    let err = WatParser::new("(module\n  (func\n    local.get $x))")
+        .parse()
+        .unwrap_err();
+    assert_eq!((err.line, err.column), (3, 15));
+    assert_eq!(
+        err.kind,
+        TextErrorKind::UnknownName {
+            space: "local",
+            name: "x".to_owned()
+        }
+    );
+
+    let err = WatParser::new("(func i32.const 1 i32.frobnicate)")
+        .parse()
+        .unwrap_err();
+    assert_eq!(
+        err.kind,
+        TextErrorKind::UnknownInstruction("i32.frobnicate".to_owned())
+    );
+    assert_eq!(
+        err.to_string(),
+        "1:19: unknown instruction `i32.frobnicate`"
+    );
+
+    let err = WatParser::new("(func) (import \"a\" \"b\" (func))")
+        .parse()
+        .unwrap_err();
+    assert_eq!(err.kind, TextErrorKind::ImportAfterDefinition);
+
+    let err = WatParser::new("(func i32.const 4294967296)")
+        .parse()
+        .unwrap_err();
+    assert_eq!(
+        err.kind,
+        TextErrorKind::InvalidNumber("4294967296".to_owned())
+    );
+}
+
+#[test]
+fn printed_examples_parse_back() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let mut module = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("wat") => WatParser::new(&fs::read_to_string(&path).unwrap())
+                .parse()
+                .unwrap(),
+            Some("wasm") => Decoder::new(&fs::read(&path).unwrap()).decode().unwrap(),
+            _ => continue,
+        };
+
+        let text = WatPrinter::new().print(&module);
+        let printed = WatParser::new(&text)
+            .parse()
+            .unwrap_or_else(|err| panic!("{}: {}\n{}", path.display(), err, text));
+
+        // Custom sections are printed as comments, and the data count section
+        // is only emitted when the code needs it.
+        module.custom_section.clear();
+        module.data_count_section = printed.data_count_section;
+        assert_eq!(printed, module, "{}", path.display());
+    }
+}
+
+#[test]
+fn prints_names_and_offsets() {
+    let text = r#"
+        (module $demo
+          (import "env" "log" (func $log (param i32)))
+          (func $count (export "count") (param $n i32) (result i32) (local $i i32)
+            block $done
+              local.get $n
+              br_if $done
+              f32.const nan:0x1
+              drop
+            end
+            local.get $i
+            call $log
+            local.get $i))
+    "#;
+    let module = WatParser::new(text).parse().unwrap();
+    let wasm = Encoder::new(&module).encode();
+
+    let printed = WatPrinter::new().print(&module);
+    assert!(printed.starts_with("(module $demo\n"));
+    assert!(printed.contains("(func $count (;1;) (type 1) (param $n i32) (result i32)\n"));
+    assert!(printed.contains("\n    (local $i i32)\n"));
+    assert!(printed.contains("\n      local.get $n\n      br_if 0\n      f32.const nan:0x1\n"));
+    assert!(printed.contains("\n    call $log\n"));
+    assert!(printed.contains("(export \"count\" (func $count))"));
+
+    let annotated = WatPrinter::new()
+        .with_offsets(&wasm)
+        .unwrap()
+        .print(&module);
+    let body = Decoder::new(&wasm).decode_borrowed().unwrap().code_section[0].offset;
+    assert!(annotated.contains(&format!("(;@{:x};)", body)));
+    assert!(annotated.contains(&format!(
+        "\n{:<12}    block\n",
+        format!("(;@{:x};)", body + 3)
+    )));
+}
+
+#[test]
+fn prints_parsed_memories_tables_and_data() {
+    let text = r#"
+        (module
+          (import "env" "memory" (memory 1))
+          (memory 1 4)
+          (table 2 funcref)
+          (data (memory 1) (i32.const 16) "hi")
+          (data "x"))
+    "#;
+    let module = Parser::new(WatParser::new(text).parse().unwrap())
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        WatPrinter::new().print_parsed(&module),
+        "\
+(module
+  (import \"env\" \"memory\" (memory 1))
+  (table (;0;) 2 funcref)
+  (memory (;1;) 1 4)
+  (data (;0;) (memory 1) (i32.const 16) \"hi\")
+  (data (;1;) \"x\")
+)
+"
+    );
+}
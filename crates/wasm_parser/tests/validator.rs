@@ -0,0 +1,218 @@
+use std::{fs, path::PathBuf};
+
+use wasm_parser::{
+    decoder::{section::SectionId, types::ValueType, Decoder},
+    text::WatParser,
+    validator::{validate, ValidationError, ValidationErrorKind},
+};
+
+fn validate_wat(text: &str) -> Result<(), ValidationError> {
+    validate(&WatParser::new(text).parse().unwrap())
+}
+
+#[test]
+fn accepts_examples() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "wasm") {
+            continue;
+        }
+
+        let module = Decoder::new(&fs::read(&path).unwrap()).decode().unwrap();
+        validate(&module).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    }
+}
+
+#[test]
+fn accepts_polymorphic_stacks_and_multi_value_blocks() {
+    validate_wat(
+        r#"
+        (module
+          (table 1 funcref)
+          (func $swap (param i32 i64) (result i64 i32)
+            local.get 1
+            local.get 0)
+          (func (param i32) (result i32)
+            block (result i32)
+              i32.const 7
+              local.get 0
+              br_table 0 0
+              i64.add
+              drop
+            end
+            i32.const 1
+            i64.const 2
+            call $swap
+            drop
+            i32.wrap_i64
+            i32.add
+            local.get 0
+            i32.const 0
+            call_indirect (param i32) (result i32)
+            i32.add)
+          (func (result i32)
+            unreachable
+            select))
+        "#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn reports_the_function_and_instruction() {
+    let err = validate_wat(
+        r#"
+        (module
+          (import "env" "log" (func (param i32)))
+          (func (result i32)
+            i32.const 1
+            f64.const 2
+            i32.add))
+        "#,
+    )
+    .unwrap_err();
+
+    assert_eq!(err.section, Some(SectionId::Code));
+    assert_eq!(err.func_index, Some(1));
+    assert_eq!(err.instr_index, Some(2));
+    assert_eq!(
+        err.kind,
+        ValidationErrorKind::TypeMismatch {
+            expected: ValueType::I32,
+            actual: ValueType::F64
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "code section, func 1, instr 2: type mismatch: expected I32, found F64"
+    );
+}
+
+#[test]
+fn rejects_invalid_function_bodies() {
+    let cases = [
+        (
+            "(func (result i32) i32.const 1 i32.const 2)",
+            ValidationErrorKind::UnexpectedOperands(1),
+        ),
+        ("(func drop)", ValidationErrorKind::EmptyOperandStack),
+        (
+            "(func local.get 1)",
+            ValidationErrorKind::Unknown {
+                space: "local",
+                index: 1,
+            },
+        ),
+        (
+            "(global i32 (i32.const 0)) (func i32.const 1 global.set 0)",
+            ValidationErrorKind::ImmutableGlobal(0),
+        ),
+        (
+            "(func (result i32) i32.const 0 if (result i32) i32.const 1 end)",
+            ValidationErrorKind::IfWithoutElse,
+        ),
+        (
+            "(memory 1) (func i32.const 0 i32.load align=8 drop)",
+            ValidationErrorKind::InvalidAlignment {
+                align: 3,
+                natural: 2,
+            },
+        ),
+        (
+            "(memory 1 1 shared) (func i32.const 0 i32.atomic.load align=1 drop)",
+            ValidationErrorKind::UnalignedAtomic {
+                align: 0,
+                natural: 2,
+            },
+        ),
+        (
+            "(func v128.const i64x2 0 0 i64x2.extract_lane 2 drop)",
+            ValidationErrorKind::InvalidLane { lane: 2, count: 2 },
+        ),
+        (
+            "(func (result i32) block i32.const 0 br_table 0 1 end i32.const 0)",
+            ValidationErrorKind::BrTableArityMismatch,
+        ),
+        (
+            "(func ref.func 0 drop)",
+            ValidationErrorKind::UndeclaredFunctionReference(0),
+        ),
+        (
+            "(func (result i32) i32.const 0 return_call 1) (func)",
+            ValidationErrorKind::ReturnCallMismatch,
+        ),
+    ];
+
+    for (text, kind) in cases {
+        let err = validate_wat(text).unwrap_err();
+        assert_eq!(err.kind, kind, "{}", text);
+        assert_eq!(err.func_index, Some(0), "{}", text);
+    }
+}
+
+#[test]
+fn rejects_invalid_module_fields() {
+    let cases = [
+        (
+            "(global (mut i32) (i32.const 0)) (global i32 (global.get 0))",
+            SectionId::Global,
+            ValidationErrorKind::MutableGlobalInConstExpr(0),
+        ),
+        (
+            "(global i32 (i32.const 0) (i32.const 1) drop)",
+            SectionId::Global,
+            ValidationErrorKind::NonConstantInstruction,
+        ),
+        (
+            "(memory 2 1)",
+            SectionId::Memory,
+            ValidationErrorKind::InvalidLimits { min: 2, max: 1 },
+        ),
+        (
+            "(memory 65537)",
+            SectionId::Memory,
+            ValidationErrorKind::LimitTooLarge {
+                limit: 65537,
+                bound: 65536,
+            },
+        ),
+        (
+            "(memory 1 shared)",
+            SectionId::Memory,
+            ValidationErrorKind::SharedMemoryWithoutMax,
+        ),
+        (
+            r#"(func (export "f")) (func (export "f"))"#,
+            SectionId::Export,
+            ValidationErrorKind::DuplicateExport("f".to_owned()),
+        ),
+        (
+            "(func (param i32)) (start 0)",
+            SectionId::Start,
+            ValidationErrorKind::InvalidStartFunction(0),
+        ),
+        (
+            "(table 1 externref) (func) (elem (i32.const 0) func 0)",
+            SectionId::Element,
+            ValidationErrorKind::TypeMismatch {
+                expected: ValueType::ExternRef,
+                actual: ValueType::FuncRef,
+            },
+        ),
+        (
+            "(memory 1) (data (i64.const 0) \"\")",
+            SectionId::Data,
+            ValidationErrorKind::TypeMismatch {
+                expected: ValueType::I32,
+                actual: ValueType::I64,
+            },
+        ),
+    ];
+
+    for (text, section, kind) in cases {
+        let err = validate_wat(text).unwrap_err();
+        assert_eq!((err.section, err.kind), (Some(section), kind), "{}", text);
+    }
+}
@@ -1,10 +1,29 @@
 #![no_main]
 
-use libfuzzer_sys::fuzz_target;
-use wasm_parser::decoder::Decoder;
+use libfuzzer_sys::{
+    arbitrary::{Arbitrary, Unstructured},
+    fuzz_target,
+};
+use wasm_parser::{decoder::Decoder, validator::validate};
 use wasm_smith::Module;
 
-fuzz_target!(|module: Module| {
+/// `\0asm` followed by version 1.
+const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+fuzz_target!(|data: &[u8]| {
+    // Arbitrary bytes must never make the decoder panic, only fail.
+    let _ = Decoder::new(data).decode();
+
+    // Same again behind a valid header, so the fuzzer reaches the sections.
+    let prefixed = [&HEADER[..], data].concat();
+    if let Ok(module) = Decoder::new(prefixed.as_slice()).decode() {
+        // Nor the validator, whatever the decoder accepted.
+        let _ = validate(&module);
+    }
+
+    let Ok(module) = Module::arbitrary(&mut Unstructured::new(data)) else {
+        return;
+    };
     let wasm_bytes = module.to_bytes();
 
     // println!("------------------------------WASM------------------------------");
@@ -16,5 +35,8 @@ fuzz_target!(|module: Module| {
 
     let mut decoder = Decoder::new(wasm_bytes.as_slice());
 
-    decoder.decode().unwrap();
+    let module = decoder.decode().unwrap();
+
+    // wasm-smith only generates valid modules.
+    validate(&module).unwrap();
 });
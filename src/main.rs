@@ -1,27 +1,120 @@
-use std::{env::args, fs, process};
+use std::{env::args, fs, path::Path, process};
 
 use compiler::xtensa_esp32;
 use wasm_parser::{
-    decoder::{instructions::Instruction, Decoder},
+    decoder::Decoder,
     parser::Parser,
+    text::{WatParser, WatPrinter},
 };
 
+const USAGE: &str =
+    "usage: wasmicon <module.wasm|module.wat> [--wat | --ir] [--bounds-checks] [--max-memory-pages <n>]\n       wasmicon --trap-runtime";
+
+/// Command line options.
+#[derive(Default)]
+struct Options {
+    path: Option<String>,
+    /// `--trap-runtime` prints the default trap handler, to build with the firmware.
+    trap_runtime: bool,
+    /// `--wat` prints what the compiler receives instead of compiling it.
+    print_wat: bool,
+    /// `--ir` prints the SSA form of the functions instead.
+    print_ir: bool,
+    /// `--bounds-checks` traps on out of bounds memory accesses.
+    bounds_checks: bool,
+    /// `--max-memory-pages <n>` sets how many pages the linear memory may use.
+    max_memory_pages: Option<u32>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trap-runtime" => options.trap_runtime = true,
+                "--wat" => options.print_wat = true,
+                "--ir" => options.print_ir = true,
+                "--bounds-checks" => options.bounds_checks = true,
+                "--max-memory-pages" => {
+                    let pages = args
+                        .next()
+                        .and_then(|pages| pages.parse().ok())
+                        .ok_or("--max-memory-pages needs a number of pages")?;
+                    options.max_memory_pages = Some(pages);
+                }
+                flag if flag.starts_with('-') => return Err(format!("unknown flag {}", flag)),
+                _ if options.path.is_some() => return Err(format!("unexpected argument {}", arg)),
+                _ => options.path = Some(arg),
+            }
+        }
+
+        if options.print_wat && options.print_ir {
+            return Err("--wat and --ir can't be combined".to_string());
+        }
+        if options.path.is_none() && !options.trap_runtime {
+            return Err("missing module path".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
 fn main() {
     // let wasm = fs::read("examples/reg.wasm").unwrap();
     // let wasm = fs::read("examples/led.wasm").unwrap();
     // let wasm = fs::read("examples/add_two.wasm").unwrap();
-    let wasm = fs::read(args().nth(1).unwrap()).unwrap();
+    let options = Options::parse(args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(2);
+    });
+    if options.trap_runtime {
+        print!("{}", xtensa_esp32::trap_runtime());
+        return;
+    }
+    let path = options.path.expect("Options::parse requires a path");
+    let mut printer = WatPrinter::new();
     // let wasm = fs::read("examples/sandbox.wasm").unwrap();
 
-    let mut decoder = Decoder::new(&wasm[..]);
-    let module = decoder.decode().unwrap();
+    let module = if Path::new(&path).extension().is_some_and(|ext| ext == "wat") {
+        let text = fs::read_to_string(&path).unwrap();
+        WatParser::new(&text)
+            .parse()
+            .unwrap_or_else(|err| panic!("{}:{}", path, err))
+    } else {
+        let wasm = fs::read(&path).unwrap();
+        if options.print_wat {
+            printer = printer.with_offsets(&wasm).unwrap();
+        }
+        let mut decoder = Decoder::new(&wasm[..]);
+        decoder.decode().unwrap()
+    };
 
     let mut parser = Parser::new(module);
-    let module = parser.parse();
+    let module = parser
+        .parse()
+        .unwrap_or_else(|err| panic!("{}: {}", path, err));
+
+    if options.print_wat {
+        print!("{}", printer.print_parsed(&module));
+        return;
+    }
+    if options.print_ir {
+        let functions = ir::lower_module(&module).unwrap_or_else(|err| panic!("{}: {}", path, err));
+        for func in functions {
+            print!("{}", func);
+        }
+        return;
+    }
 
-    let mut compiler = xtensa_esp32::XtensaEsp32::new();
-    let result = compiler.compile(module);
-    println!("{}", &result);
+    let mut compiler = xtensa_esp32::XtensaEsp32::new().with_bounds_checks(options.bounds_checks);
+    if let Some(pages) = options.max_memory_pages {
+        compiler = compiler.with_max_memory_pages(pages);
+    }
+    let result = compiler
+        .compile(module)
+        .unwrap_or_else(|err| panic!("{}: {}", path, err));
+    println!("{}", result);
 
     // fs::write(
     //     "/Users/hota1024/GitHub/hota1024/esp32_nolib_c/src/main.s",